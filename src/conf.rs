@@ -1,12 +1,23 @@
 use num::FromPrimitive;
 use serde::{Deserialize, Serialize};
 
+use std::collections::HashMap;
+
 #[derive(Clone, Copy)]
 pub struct AudioConfig {
     pub sample_rate: f32,
     pub max_block_size: usize,
+
+    // how many channels the host runs the board with (1 = mono, 2 = stereo)
+    // effects that keep per-channel state size it from this
+    pub num_channels: usize,
+
+    // the host's tempo, for tempo synced effects. DEFAULT_TEMPO_BPM if the host has none
+    pub tempo_bpm: f32,
 }
 
+pub const DEFAULT_TEMPO_BPM: f32 = 120.0f32;
+
 #[derive(Serialize, Deserialize, Clone, Copy, Debug)]
 pub enum BoardEffectConfigParameterValue {
     N(i32),
@@ -41,10 +52,28 @@ impl BoardEffectConfigParameterValue {
     }
 }
 
+// multi-value parameters (e.g. eq band gains) that have to be set all at once
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum BoardEffectConfigParameterArrayValue {
+    FArray(Vec<f32>),
+}
+
+// what a parameter can be set to in a board config
+// e.g. {"F": 1.0} or {"FArray": [1.0, 2.0]}
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum BoardEffectConfigValue {
+    Single(BoardEffectConfigParameterValue),
+    Array(BoardEffectConfigParameterArrayValue),
+}
+
 #[derive(Copy, Clone, Serialize)]
 pub enum ParameterRange {
     N(i32, i32),
     F(f32, f32),
+
+    // number of elements, min, max
+    FArray(usize, f32, f32),
 }
 
 #[derive(Copy, Clone, Serialize)]
@@ -54,13 +83,13 @@ pub struct AdvertisedParameter {
     pub default_value: BoardEffectConfigParameterValue,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct BoardEffectConfigParameter {
     pub name: String,
-    pub value: BoardEffectConfigParameterValue,
+    pub value: BoardEffectConfigValue,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct BoardEffectDeclaration {
     pub effect_name: String,
     pub bind_name: String,
@@ -68,16 +97,117 @@ pub struct BoardEffectDeclaration {
     pub enabled: bool,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct BoardConnectionDeclaration {
     pub effect: String,
     pub reads: Vec<String>,
     pub writes: Vec<String>,
 }
 
-#[derive(Serialize, Deserialize)]
+// shorthand for a chain of effects. the first effect reads `reads`, the last writes `writes`
+// and scratch buffers are generated for everything in between
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BoardSeriesDeclaration {
+    pub effects: Vec<String>,
+    pub reads: Vec<String>,
+    pub writes: Vec<String>,
+}
+
+// turns the target effect's outputs down by amount_db while the key buffer is over threshold_db
+// attack_ms / release_ms are how fast the ducking goes in and comes back out
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BoardDuckingDeclaration {
+    pub key: String,
+    pub target: String,
+    pub amount_db: f32,
+
+    #[serde(default = "default_ducking_threshold_db")]
+    pub threshold_db: f32,
+    #[serde(default = "default_ducking_attack_ms")]
+    pub attack_ms: f32,
+    #[serde(default = "default_ducking_release_ms")]
+    pub release_ms: f32,
+}
+
+fn default_ducking_threshold_db() -> f32 {
+    -30.0f32
+}
+
+fn default_ducking_attack_ms() -> f32 {
+    10.0f32
+}
+
+fn default_ducking_release_ms() -> f32 {
+    250.0f32
+}
+
+#[derive(Serialize, Deserialize, Clone)]
 pub struct BoardConfig {
     pub buffers: Vec<String>,
     pub effects: Vec<BoardEffectDeclaration>,
     pub connections: Vec<BoardConnectionDeclaration>,
+
+    #[serde(default)]
+    pub series: Vec<BoardSeriesDeclaration>,
+
+    // nested boards that effects on this board can use as "SubBoard/<name>"
+    // a sub board's sources and sinks are the reads and writes of the connection using it
+    #[serde(default)]
+    pub sub_boards: HashMap<String, BoardConfig>,
+
+    #[serde(default)]
+    pub ducking: Vec<BoardDuckingDeclaration>,
+}
+
+impl BoardConfig {
+    // rewrites each series as plain buffers + connections
+    // each series goes in as one block, just before the first connection reading what it
+    // writes (and after whatever writes what it reads), so nothing waits a block for its output
+    pub fn expand_series(&mut self) {
+        let series = std::mem::replace(&mut self.series, Vec::new());
+
+        for (series_idx, series_decl) in series.into_iter().enumerate() {
+            let num_effects = series_decl.effects.len();
+            let num_channels = series_decl.writes.len().max(1);
+
+            let earliest = self
+                .connections
+                .iter()
+                .rposition(|c| c.writes.iter().any(|w| series_decl.reads.contains(w)))
+                .map_or(0, |idx| idx + 1);
+            let insert_at = self
+                .connections
+                .iter()
+                .position(|c| c.reads.iter().any(|r| series_decl.writes.contains(r)))
+                .unwrap_or(self.connections.len())
+                .max(earliest);
+
+            let mut expanded = Vec::with_capacity(num_effects);
+            let mut reads = series_decl.reads;
+            for (effect_idx, effect) in series_decl.effects.into_iter().enumerate() {
+                let writes = if effect_idx + 1 == num_effects {
+                    series_decl.writes.clone()
+                } else {
+                    let scratch_bufs: Vec<String> = (0..num_channels)
+                        .map(|channel| {
+                            format!("__series_{}_{}_{}", series_idx, effect_idx, channel)
+                        })
+                        .collect();
+
+                    self.buffers.extend(scratch_bufs.iter().cloned());
+                    scratch_bufs
+                };
+
+                expanded.push(BoardConnectionDeclaration {
+                    effect,
+                    reads,
+                    writes: writes.clone(),
+                });
+
+                reads = writes;
+            }
+
+            self.connections.splice(insert_at..insert_at, expanded);
+        }
+    }
 }
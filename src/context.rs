@@ -1,28 +1,37 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::collections::{HashMap, HashSet};
 use std::fmt;
 
 use super::conf::AudioConfig;
-use super::conf::{BoardConfig, BoardConnectionDeclaration};
+use super::conf::{BoardConfig, BoardConnectionDeclaration, BoardDuckingDeclaration};
 use super::errors::ContextInitError;
 use super::otters::LoadedEffects;
 use super::utils::buf_rw::{AudioBufferReader, AudioBufferWriter};
+use super::utils::envelope::EnvelopeDetector;
+use super::utils::mathutils;
 use super::utils::ringbuf::SimpleFloatBuffer;
+use super::utils::smoothed_param::{RampCurve, SmoothedParam};
 
 const MAX_ALLOWABLE_BUF_DECLS: usize = 1024;
 pub const MAX_ALLOWABLE_INPUTS: usize = 10;
 pub const MAX_ALLOWABLE_OUTPUTS: usize = 10;
 
-const MAX_EXTERNAL_INS: usize = 6;
-const MAX_EXTERNAL_OUTS: usize = 6;
-
 const FIRST_INPUT_IDX: usize = 1024;
 const FIRST_OUTPUT_IDX: usize = 2048;
 
+// how the ducking key is followed. only decides whether the key is over the threshold, the
+// ducking's own attack and release shape the gain
+const DUCKING_KEY_ATTACK_MS: f32 = 1.0f32;
+const DUCKING_KEY_RELEASE_MS: f32 = 50.0f32;
+
+// @CONST_<value> declares a read-only buffer that always holds <value> (e.g. @CONST_0.5)
+const CONST_BUF_PREFIX: &str = "@CONST_";
+
 enum BufferUsageError<'a> {
     NoError,
     NoSuchBuffer(&'a String),
     BufferAlreadyUsed(&'a String),
+    ConstantBufferWritten(&'a String),
 }
 
 impl<'a> fmt::Display for BufferUsageError<'a> {
@@ -31,6 +40,9 @@ impl<'a> fmt::Display for BufferUsageError<'a> {
             BufferUsageError::NoError => write!(f, "ok"),
             BufferUsageError::NoSuchBuffer(buf) => write!(f, "No such buffer {}", buf),
             BufferUsageError::BufferAlreadyUsed(buf) => write!(f, "Buffer already used {}", buf),
+            BufferUsageError::ConstantBufferWritten(buf) => {
+                write!(f, "Constant buffer {} can't be written to", buf)
+            }
         }
     }
 }
@@ -77,23 +89,87 @@ impl BoardContextConstructionState {
             } else {
                 return None;
             }
+        } else if requested_buf_name.starts_with(CONST_BUF_PREFIX) {
+            // constants are ordinary internal buffers that get filled once and never written
+            if let None = parse_const_buf_value(requested_buf_name) {
+                return None;
+            }
+
+            return Some(self.buf_name_to_idx.len() - self.num_external_buffers);
         } else {
             return Some(self.buf_name_to_idx.len() - self.num_external_buffers);
         }
     }
 }
 
+fn parse_const_buf_value(buf_name: &str) -> Option<f32> {
+    if !buf_name.starts_with(CONST_BUF_PREFIX) {
+        return None;
+    }
+
+    match buf_name[CONST_BUF_PREFIX.len()..].parse::<f32>() {
+        Ok(value) if value.is_finite() => Some(value),
+        _ => None,
+    }
+}
+
 pub struct BoardConnection {
     pub ordinal: usize,
     pub inputs_idxs: Vec<usize>,
     pub output_idxs: Vec<usize>,
 }
 
+// when set, effects writing @SINK_n write into scratch buffers instead of the bound outputs
+// and the sinks are mixed into the outputs after all connections have run
+struct OutputRouting {
+    // gains[output][sink]
+    gains: Vec<Vec<f32>>,
+    sink_bufs: Vec<Vec<f32>>,
+    sink_ptrs: Vec<*mut f32>,
+}
+
+// turns down every output of the target's connections while the key buffer is loud
+// the gains are worked out from the key once per block, the first time the target runs
+struct Ducker {
+    key_idx: usize,
+    target_ordinal: usize,
+    amount_db: f32,
+    threshold_db: f32,
+
+    key_detector: EnvelopeDetector,
+    // 0 = not ducked, 1 = ducked by the full amount
+    duck_detector: EnvelopeDetector,
+
+    gains: RefCell<Vec<f32>>,
+    gains_ready: Cell<bool>,
+}
+
+// what blend_with_dry keeps between blocks for one connection
+pub struct DryBlend {
+    // each input, delayed to line up with the (latent) output it's blended into.
+    // one longer than the effect's max latency, since the current sample is written first
+    dry_lines: Vec<Vec<f32>>,
+    write_idx: usize,
+
+    // wet_amount changes ramp across a block instead of stepping
+    wet_amount: SmoothedParam,
+    wet_gains: Vec<f32>,
+}
+
 pub struct BoardContext {
     buffers: Vec<RefCell<SimpleFloatBuffer>>,
     connections: Vec<BoardConnection>,
+    duckers: Vec<Ducker>,
     external_ins: Vec<*const f32>,
     external_outs: Vec<*mut f32>,
+    output_routing: Option<OutputRouting>,
+
+    // host sources put aside while substitute_sources is in effect
+    saved_external_ins: Vec<*const f32>,
+
+    // set by Otters when it's running over its cpu budget. heavy effects check it
+    // and do a cheaper (worse sounding) version of their work for the block
+    degraded: Cell<bool>,
 }
 
 impl BoardContext {
@@ -111,23 +187,33 @@ impl BoardContext {
 
         let connections =
             create_effect_connections(&mut construction_state, &board_config.connections, effects)?;
+        let duckers = create_duckers(
+            &construction_state,
+            &board_config.ducking,
+            effects,
+            audio_config,
+        )?;
 
         let mut external_ins = Vec::new();
         let mut external_outs = Vec::new();
 
-        for _ in 0..MAX_EXTERNAL_INS {
+        for _ in 0..MAX_ALLOWABLE_INPUTS {
             external_ins.push(0 as *const f32);
         }
 
-        for _ in 0..MAX_EXTERNAL_OUTS {
+        for _ in 0..MAX_ALLOWABLE_OUTPUTS {
             external_outs.push(0 as *mut f32);
         }
 
         Ok(BoardContext {
             buffers,
             connections,
+            duckers,
+            saved_external_ins: external_ins.clone(),
             external_ins,
             external_outs,
+            output_routing: None,
+            degraded: Cell::new(false),
         })
     }
 
@@ -147,6 +233,82 @@ impl BoardContext {
         self.external_ins[source_idx] = source_ptr;
     }
 
+    // points every source at source_ptr until restore_sources is called
+    pub fn substitute_sources(&mut self, source_ptr: *const f32) {
+        self.saved_external_ins.copy_from_slice(&self.external_ins);
+        for external_in in self.external_ins.iter_mut() {
+            *external_in = source_ptr;
+        }
+    }
+
+    pub fn restore_sources(&mut self) {
+        self.external_ins.copy_from_slice(&self.saved_external_ins);
+    }
+
+    pub fn should_degrade(&self) -> bool {
+        self.degraded.get()
+    }
+
+    pub fn set_degraded(&self, degraded: bool) {
+        self.degraded.set(degraded);
+    }
+
+    // gains[output][sink] is how much of @SINK_sink reaches external output `output`
+    // an empty matrix turns routing off (sink n goes straight to output n)
+    pub fn set_output_routing(&mut self, gains: &[Vec<f32>], max_block_size: usize) {
+        if gains.is_empty() {
+            self.output_routing = None;
+            return;
+        }
+
+        let mut routed_gains = Vec::with_capacity(MAX_ALLOWABLE_OUTPUTS);
+        for output_idx in 0..MAX_ALLOWABLE_OUTPUTS {
+            let mut row = vec![0.0f32; MAX_ALLOWABLE_OUTPUTS];
+            if let Some(requested_row) = gains.get(output_idx) {
+                for (sink_idx, gain) in requested_row.iter().take(MAX_ALLOWABLE_OUTPUTS).enumerate()
+                {
+                    row[sink_idx] = *gain;
+                }
+            }
+
+            routed_gains.push(row);
+        }
+
+        let mut sink_bufs: Vec<Vec<f32>> = (0..MAX_ALLOWABLE_OUTPUTS)
+            .map(|_| vec![0.0f32; max_block_size])
+            .collect();
+        let sink_ptrs = sink_bufs.iter_mut().map(|b| b.as_mut_ptr()).collect();
+
+        self.output_routing = Some(OutputRouting {
+            gains: routed_gains,
+            sink_bufs,
+            sink_ptrs,
+        });
+    }
+
+    // mixes the routed sinks into the bound outputs. does nothing when routing is off
+    pub fn route_outputs(&self, num_samples: usize) {
+        let routing = match &self.output_routing {
+            Some(routing) => routing,
+            None => return,
+        };
+
+        for (output_idx, row) in routing.gains.iter().enumerate() {
+            let mut write_buf = AudioBufferWriter::External(self.external_outs[output_idx]);
+
+            for i in 0..num_samples {
+                let mut value = 0.0f32;
+                for (sink_idx, gain) in row.iter().enumerate() {
+                    if *gain != 0.0f32 {
+                        value += gain * routing.sink_bufs[sink_idx][i];
+                    }
+                }
+
+                write_buf.buf_write(i, value);
+            }
+        }
+    }
+
     pub fn get_buffer_for_read<'a>(&'a self, buf_idx: usize) -> AudioBufferReader<'a> {
         if buf_idx >= FIRST_INPUT_IDX {
             if buf_idx >= FIRST_INPUT_IDX + MAX_ALLOWABLE_INPUTS {
@@ -175,11 +337,18 @@ impl BoardContext {
             }
 
             let norm_idx = buf_idx - FIRST_OUTPUT_IDX;
+            if let Some(routing) = &self.output_routing {
+                return match routing.sink_ptrs.get(norm_idx) {
+                    Some(ptr) => AudioBufferWriter::External(*ptr),
+                    None => AudioBufferWriter::Null,
+                };
+            }
+
             if self.external_outs[norm_idx] == (0 as *mut f32) {
                 return AudioBufferWriter::Null;
             }
 
-            return AudioBufferWriter::External(self.external_outs[buf_idx - FIRST_OUTPUT_IDX]);
+            return AudioBufferWriter::External(self.external_outs[norm_idx]);
         } else {
             if buf_idx >= self.buffers.len() {
                 return AudioBufferWriter::Null;
@@ -200,6 +369,179 @@ impl BoardContext {
     pub fn get_connections<'a>(&'a self) -> &'a Vec<BoardConnection> {
         return &self.connections;
     }
+
+    // whether running the connections in this order gives the same result as the declared one
+    // order has to list every connection index once. two connections that share a buffer, with
+    // at least one of them writing it, have to stay in their declared order
+    pub fn order_keeps_dependencies(&self, order: &[usize]) -> bool {
+        let num_connections = self.connections.len();
+        if order.len() != num_connections {
+            return false;
+        }
+
+        let mut positions = vec![num_connections; num_connections];
+        for (position, connection_idx) in order.iter().enumerate() {
+            if *connection_idx >= num_connections || positions[*connection_idx] != num_connections {
+                return false;
+            }
+
+            positions[*connection_idx] = position;
+        }
+
+        for first in 0..num_connections {
+            for second in first + 1..num_connections {
+                if positions[first] > positions[second]
+                    && connections_depend(&self.connections[first], &self.connections[second])
+                {
+                    return false;
+                }
+            }
+        }
+
+        true
+    }
+
+    // the latency at the sinks, following the connections in execution order
+    // effect_latencies is indexed by effect ordinal. a buffer is as late as the latest input of
+    // the effect that wrote it plus that effect's own latency. connection_latencies is scratch
+    // with one entry per connection, so this doesn't allocate
+    pub fn latency_at_sinks(
+        &self,
+        effect_latencies: &[usize],
+        connection_latencies: &mut [usize],
+    ) -> usize {
+        let mut sink_latency = 0;
+
+        for (i, connection) in self.connections.iter().enumerate() {
+            let input_latency = connection
+                .inputs_idxs
+                .iter()
+                .map(|idx| self.latency_of_buffer(*idx, &connection_latencies[..i]))
+                .max()
+                .unwrap_or(0);
+            let output_latency = input_latency + effect_latencies[connection.ordinal];
+            connection_latencies[i] = output_latency;
+
+            if connection
+                .output_idxs
+                .iter()
+                .any(|idx| *idx >= FIRST_OUTPUT_IDX)
+            {
+                sink_latency = sink_latency.max(output_latency);
+            }
+        }
+
+        sink_latency
+    }
+
+    // the latency of whatever was last written to buffer idx by the connections so far
+    // sources (and buffers nothing has written yet) aren't late at all
+    fn latency_of_buffer(&self, idx: usize, connection_latencies: &[usize]) -> usize {
+        self.connections[..connection_latencies.len()]
+            .iter()
+            .zip(connection_latencies.iter())
+            .rev()
+            .find(|(connection, _)| connection.output_idxs.contains(&idx))
+            .map_or(0, |(_, latency)| *latency)
+    }
+
+    // crossfades each output of a connection with the matching input (output k <- input k)
+    // outputs without a matching input are faded towards silence. the input is delayed by
+    // latency_samples first, so a latent effect doesn't comb filter against its own dry signal
+    // has to run every block the effect does, even at a wet_amount of 1, to keep the lines full
+    pub fn blend_with_dry(
+        &self,
+        connection_idx: usize,
+        dry: &mut DryBlend,
+        wet_amount: f32,
+        latency_samples: usize,
+        num_samples: usize,
+    ) {
+        let fully_wet = dry.prepare_wet_gains(wet_amount, num_samples);
+        if fully_wet && latency_samples == 0 {
+            return;
+        }
+
+        let inputs = &self.connections[connection_idx].inputs_idxs;
+        let outputs = &self.connections[connection_idx].output_idxs;
+        let write_idx = dry.write_idx;
+
+        for (i, output) in outputs.iter().enumerate() {
+            let read_buf = match inputs.get(i) {
+                Some(input) => self.get_buffer_for_read(*input),
+                None => AudioBufferReader::Null,
+            };
+            let mut write_buf = self.get_buffer_for_write(*output);
+
+            let line = &mut dry.dry_lines[i];
+            let line_len = line.len();
+            let delay_samples = latency_samples.min(line_len - 1);
+            let mut line_idx = write_idx;
+
+            for j in 0..num_samples {
+                line[line_idx] = read_buf.buf_read(j);
+                let delayed = line[(line_idx + line_len - delay_samples) % line_len];
+                line_idx = (line_idx + 1) % line_len;
+
+                if !fully_wet {
+                    write_buf.buf_blend(j, num_samples, dry.wet_gains[j], delayed);
+                }
+            }
+        }
+
+        dry.write_idx = (write_idx + num_samples) % dry.dry_lines[0].len();
+    }
+
+    // the next block works the ducking gains out from the key again
+    pub fn begin_ducking_block(&self) {
+        for ducker in self.duckers.iter() {
+            ducker.gains_ready.set(false);
+        }
+    }
+
+    // applies every ducker aimed at the connection's effect to its outputs
+    // the key is read as it is at this point in the block
+    pub fn apply_ducking(&self, connection_idx: usize, num_samples: usize) {
+        let ordinal = self.connections[connection_idx].ordinal;
+
+        for ducker in self.duckers.iter() {
+            if ducker.target_ordinal != ordinal {
+                continue;
+            }
+
+            let mut gains = ducker.gains.borrow_mut();
+            if !ducker.gains_ready.get() {
+                let key_buf = self.get_buffer_for_read(ducker.key_idx);
+                for i in 0..num_samples {
+                    let key_db = ducker.key_detector.process(key_buf.buf_read(i));
+                    let over = if key_db >= ducker.threshold_db {
+                        1.0f32
+                    } else {
+                        0.0f32
+                    };
+
+                    let duck = ducker.duck_detector.process(over);
+                    gains[i] = mathutils::db_to_linear(-ducker.amount_db * duck);
+                }
+
+                ducker.gains_ready.set(true);
+            }
+
+            for output in self.connections[connection_idx].output_idxs.iter() {
+                let mut write_buf = self.get_buffer_for_write(*output);
+                for i in 0..num_samples {
+                    write_buf.buf_blend(i, num_samples, gains[i], 0.0f32);
+                }
+            }
+        }
+    }
+}
+
+fn connections_depend(a: &BoardConnection, b: &BoardConnection) -> bool {
+    a.output_idxs
+        .iter()
+        .any(|idx| b.inputs_idxs.contains(idx) || b.output_idxs.contains(idx))
+        || b.output_idxs.iter().any(|idx| a.inputs_idxs.contains(idx))
 }
 
 fn create_construction_intermediate() -> BoardContextConstructionState {
@@ -248,9 +590,12 @@ fn create_mem_buffers(
         // external ones have special indexes and are backed by a buffer unknown to
         // the context
         if next_idx < MAX_ALLOWABLE_BUF_DECLS {
-            result.push(RefCell::new(SimpleFloatBuffer::with_max_capacity(
-                max_block_size,
-            )));
+            let mut buffer = SimpleFloatBuffer::with_max_capacity(max_block_size);
+            if let Some(value) = parse_const_buf_value(buf_name) {
+                buffer.fill(value);
+            }
+
+            result.push(RefCell::new(buffer));
         }
     }
 
@@ -296,6 +641,7 @@ fn create_effect_connections(
         // find read buffers
         find_buffer_targets(
             &connection_info.reads,
+            false,
             &mut input_target_idxs,
             &mut used_buffer_tracker,
             &construction_helper,
@@ -305,6 +651,7 @@ fn create_effect_connections(
         // find write buffers
         find_buffer_targets(
             &connection_info.writes,
+            true,
             &mut output_target_idxs,
             &mut used_buffer_tracker,
             &construction_helper,
@@ -331,14 +678,19 @@ fn create_effect_connections(
 
 fn find_buffer_targets(
     targets: &Vec<String>,
+    is_write: bool,
     result_vec: &mut Vec<usize>,
     used_buffer_tracker: &mut HashSet<String>,
     helper: &BoardContextConstructionState,
     errors_acc: &mut Vec<String>,
 ) {
     for input_target in targets {
-        let buffer_usage =
-            is_valid_buffer(&helper.buf_name_to_idx, &used_buffer_tracker, input_target);
+        let buffer_usage = is_valid_buffer(
+            &helper.buf_name_to_idx,
+            &used_buffer_tracker,
+            input_target,
+            is_write,
+        );
         if buffer_usage.is_err() {
             errors_acc.push(buffer_usage.to_string());
             continue;
@@ -354,12 +706,125 @@ fn is_valid_buffer<'a>(
     buf_name_to_idx: &HashMap<String, usize>,
     used_buffers: &HashSet<String>,
     requested_buf: &'a String,
+    is_write: bool,
 ) -> BufferUsageError<'a> {
     if !buf_name_to_idx.contains_key(requested_buf) {
         BufferUsageError::NoSuchBuffer(requested_buf)
+    } else if is_write && requested_buf.starts_with(CONST_BUF_PREFIX) {
+        BufferUsageError::ConstantBufferWritten(requested_buf)
     } else if used_buffers.contains(requested_buf) {
         BufferUsageError::BufferAlreadyUsed(requested_buf)
     } else {
         BufferUsageError::NoError
     }
 }
+
+fn create_duckers(
+    construction_helper: &BoardContextConstructionState,
+    ducking_infos: &Vec<BoardDuckingDeclaration>,
+    effects: &LoadedEffects,
+    audio_config: &AudioConfig,
+) -> Result<Vec<Ducker>, ContextInitError> {
+    let mut errors: Vec<String> = Vec::new();
+    let mut duckers: Vec<Ducker> = Vec::new();
+
+    for ducking_info in ducking_infos {
+        let key_idx = match construction_helper.buf_name_to_idx.get(&ducking_info.key) {
+            // sinks can't be read back
+            Some(idx) if *idx < FIRST_OUTPUT_IDX => *idx,
+            _ => {
+                errors.push(format!("Can't duck from buffer {}", &ducking_info.key));
+                continue;
+            }
+        };
+
+        let target_ordinal = match effects.get(&ducking_info.target) {
+            Some((ordinal, _, _)) => *ordinal,
+            None => {
+                errors.push(format!(
+                    "Trying to duck nonexistent node {}",
+                    &ducking_info.target
+                ));
+                continue;
+            }
+        };
+
+        let mut key_detector = EnvelopeDetector::new(audio_config.sample_rate);
+        key_detector.set_attack_time_ms(DUCKING_KEY_ATTACK_MS);
+        key_detector.set_release_time_ms(DUCKING_KEY_RELEASE_MS);
+
+        let mut duck_detector = EnvelopeDetector::new(audio_config.sample_rate);
+        duck_detector.should_return_db = false;
+        duck_detector.set_attack_time_ms(ducking_info.attack_ms);
+        duck_detector.set_release_time_ms(ducking_info.release_ms);
+
+        println!(
+            "Ducking Manager: Buffer {} ducks effect ordinal {} by {} dB",
+            key_idx, target_ordinal, ducking_info.amount_db
+        );
+
+        duckers.push(Ducker {
+            key_idx,
+            target_ordinal,
+            amount_db: ducking_info.amount_db.max(0.0f32),
+            threshold_db: ducking_info.threshold_db,
+            key_detector,
+            duck_detector,
+            gains: RefCell::new(vec![1.0f32; audio_config.max_block_size]),
+            gains_ready: Cell::new(false),
+        });
+    }
+
+    if errors.len() > 0 {
+        Err(ContextInitError(errors))
+    } else {
+        Ok(duckers)
+    }
+}
+
+impl DryBlend {
+    pub fn new(
+        num_outputs: usize,
+        max_latency_samples: usize,
+        max_block_size: usize,
+        wet_amount: f32,
+    ) -> DryBlend {
+        DryBlend {
+            dry_lines: vec![vec![0.0f32; max_latency_samples + 1]; num_outputs.max(1)],
+            write_idx: 0,
+            wet_amount: SmoothedParam::new(wet_amount),
+            wet_gains: vec![wet_amount; max_block_size],
+        }
+    }
+
+    pub fn clear(&mut self) {
+        for line in self.dry_lines.iter_mut() {
+            for sample in line.iter_mut() {
+                *sample = 0.0f32;
+            }
+        }
+    }
+
+    // ramps from where the last block ended to wet_amount over this block
+    // returns true if the whole block is fully wet, i.e. there's nothing to blend
+    fn prepare_wet_gains(&mut self, wet_amount: f32, num_samples: usize) -> bool {
+        let num_samples = num_samples.min(self.wet_gains.len());
+
+        // the last block's ramp always finishes, so this is where it left off
+        if self.wet_amount.next() == wet_amount {
+            for gain in self.wet_gains[..num_samples].iter_mut() {
+                *gain = wet_amount;
+            }
+
+            return wet_amount >= 1.0f32;
+        }
+
+        self.wet_amount
+            .ramp_to(wet_amount, num_samples, RampCurve::Linear);
+        for gain in self.wet_gains[..num_samples].iter_mut() {
+            *gain = self.wet_amount.next();
+        }
+
+        false
+    }
+}
@@ -5,11 +5,12 @@ use crate::context::BoardContext;
 use crate::traits::AudioEffect;
 use crate::utils::{
     biquad::{Biquad, BiquadCoefficients, IIRFilterType},
+    buf_rw::process_block,
+    smoothed_param::ParamSmoother,
+    state::{StateReader, StateWriter},
 };
 
-use crate::effects::basic_single_in_single_out;
-
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 
 const PARAMS: &'static [AdvertisedParameter] = &[
     AdvertisedParameter {
@@ -33,17 +34,33 @@ const PARAMS: &'static [AdvertisedParameter] = &[
         name: "q",
         range: ParameterRange::F(0.707f32, 20.0f32),
         default_value: BoardEffectConfigParameterValue::F(0.707f32),
-    }
+    },
+    AdvertisedParameter {
+        // run the filter math in f64. costs a little more, helps very resonant or very low filters
+        name: "double_precision?",
+        range: ParameterRange::N(0, 1),
+        default_value: BoardEffectConfigParameterValue::N(0),
+    },
 ];
 
 const PARAM_FILTER_TYPE: usize = 0;
 const PARAM_CORNER_FREQ_HZ: usize = 1;
 const PARAM_BOOST_CUT_DB: usize = 2;
 const PARAM_Q: usize = 3;
+const PARAM_DOUBLE_PRECISION: usize = 4;
+
+// cutoff sweeps glide over about this long, so they don't zipper
+const CUTOFF_SMOOTHING_MS: f32 = 10.0f32;
 
+// one filter per channel of the audio config, all with the same settings
+// reads = [ch 0, ch 1, ...], writes = [ch 0, ch 1, ...]. a mono board just uses channel 0
 pub struct BiquadFilter {
     params: Vec<BoardEffectConfigParameterValue>,
-    biquad: RefCell<Biquad>,
+    biquads: RefCell<Vec<Biquad>>,
+
+    // the coefficients are recomputed every sample while this is moving
+    corner_freq_hz: RefCell<ParamSmoother>,
+    has_processed_audio: Cell<bool>,
 }
 
 impl BiquadFilter {
@@ -53,18 +70,43 @@ impl BiquadFilter {
             params.push(PARAMS[i].default_value);
         }
 
+        let coeff = BiquadCoefficients::first_order_lpf(
+            PARAMS[PARAM_CORNER_FREQ_HZ].default_value.as_flt(),
+            ac.sample_rate,
+        );
+
+        let mut biquads = Vec::with_capacity(ac.num_channels.max(1));
+        for _ in 0..ac.num_channels.max(1) {
+            biquads.push(Biquad::new(coeff.clone()));
+        }
+
+        let corner_freq_hz = ParamSmoother::new(
+            PARAMS[PARAM_CORNER_FREQ_HZ].default_value.as_flt(),
+            CUTOFF_SMOOTHING_MS,
+            ac.sample_rate,
+        );
+
         BiquadFilter {
             params,
-            biquad: RefCell::new(Biquad::new(BiquadCoefficients::first_order_lpf(
-                PARAMS[PARAM_CORNER_FREQ_HZ].default_value.as_flt(),
-                ac.sample_rate,
-            ))),
+            biquads: RefCell::new(biquads),
+            corner_freq_hz: RefCell::new(corner_freq_hz),
+            has_processed_audio: Cell::new(false),
         }
     }
 
     pub fn info() -> &'static [AdvertisedParameter] {
         PARAMS
     }
+
+    fn finish_cutoff_glide(&mut self) {
+        let corner_freq_hz = self.corner_freq_hz.get_mut();
+        let target = corner_freq_hz.target();
+        corner_freq_hz.set_immediate(target);
+
+        for biquad in self.biquads.get_mut().iter_mut() {
+            biquad.change_cutoff(target);
+        }
+    }
 }
 
 impl AudioEffect for BiquadFilter {
@@ -73,7 +115,53 @@ impl AudioEffect for BiquadFilter {
     }
 
     fn set_audio_parameters(&mut self, new_config: &AudioConfig) {
-        self.biquad.borrow_mut().change_sample_rate(new_config.sample_rate);
+        let double_precision = self.params[PARAM_DOUBLE_PRECISION].as_int() != 0;
+        self.corner_freq_hz
+            .get_mut()
+            .set_time_constant(CUTOFF_SMOOTHING_MS, new_config.sample_rate);
+
+        let biquads = self.biquads.get_mut();
+
+        for biquad in biquads.iter_mut() {
+            biquad.change_sample_rate(new_config.sample_rate);
+        }
+
+        // new channels start out with the same settings (and silence)
+        let coeff = biquads[0].coefficients().clone();
+        biquads.resize_with(new_config.num_channels.max(1), || {
+            let mut biquad = Biquad::new(coeff.clone());
+            biquad.set_double_precision(double_precision);
+            biquad
+        });
+    }
+
+    fn reset(&mut self) {
+        self.finish_cutoff_glide();
+
+        for biquad in self.biquads.get_mut().iter_mut() {
+            biquad.clear();
+        }
+    }
+
+    fn serialize_state(&self, writer: &mut StateWriter) -> bool {
+        let biquads = self.biquads.borrow();
+
+        writer.write_usize(biquads.len());
+        for biquad in biquads.iter() {
+            biquad.save_state(writer);
+        }
+
+        true
+    }
+
+    fn deserialize_state(&mut self, reader: &mut StateReader) -> bool {
+        self.finish_cutoff_glide();
+        let biquads = self.biquads.get_mut();
+
+        reader.read_usize() == Some(biquads.len())
+            && biquads
+                .iter_mut()
+                .all(|biquad| biquad.load_state(reader).is_some())
     }
 
     fn set_effect_parameter(
@@ -84,29 +172,78 @@ impl AudioEffect for BiquadFilter {
         self.params[param_idx] = param_value;
 
         if param_idx == PARAM_CORNER_FREQ_HZ {
-            self.biquad.borrow_mut().change_cutoff(param_value.as_flt());
-        } else if param_idx == PARAM_FILTER_TYPE {
-            self.biquad.borrow_mut().change_type(param_value.as_enum::<IIRFilterType>());
-        } else if param_idx == PARAM_BOOST_CUT_DB {
-            self.biquad.borrow_mut().change_shelf_gain(param_value.as_flt());
-        } else if param_idx == PARAM_Q {
-            self.biquad.borrow_mut().change_q(param_value.as_flt());
+            let corner_freq_hz = self.corner_freq_hz.get_mut();
+            corner_freq_hz.set_target(param_value.as_flt());
+
+            // nothing has been heard yet, so there's nothing to glide from
+            if !self.has_processed_audio.get() {
+                self.finish_cutoff_glide();
+            }
+
+            return;
+        }
+
+        for biquad in self.biquads.get_mut().iter_mut() {
+            if param_idx == PARAM_FILTER_TYPE {
+                biquad.change_type(param_value.as_enum::<IIRFilterType>());
+            } else if param_idx == PARAM_BOOST_CUT_DB {
+                biquad.change_shelf_gain(param_value.as_flt());
+            } else if param_idx == PARAM_Q {
+                biquad.change_q(param_value.as_flt());
+            } else if param_idx == PARAM_DOUBLE_PRECISION {
+                biquad.set_double_precision(param_value.as_int() != 0);
+            }
         }
     }
 
     fn execute(&self, context: &BoardContext, connection_idx: usize, num_samples: usize) {
-        let maybe_bufs = basic_single_in_single_out(context, connection_idx, num_samples);
-        if let None = maybe_bufs {
+        let inputs = context.get_inputs_for_connection(connection_idx);
+        let outputs = context.get_outputs_for_connection(connection_idx);
+
+        if outputs.len() < 1 {
             return;
         }
 
-        let (read_buf, mut write_buf) = maybe_bufs.unwrap();
-        let mut biquad = self.biquad.borrow_mut();
+        if inputs.len() < 1 {
+            let mut write_buf = context.get_buffer_for_write(outputs[0]);
+            for i in 0..num_samples {
+                write_buf.buf_write(i, 0.0f32);
+            }
+
+            return;
+        }
+
+        self.has_processed_audio.set(true);
+
+        let mut biquads = self.biquads.borrow_mut();
+        let mut corner_freq_hz = self.corner_freq_hz.borrow_mut();
+        let num_connected = inputs.len().min(outputs.len());
+
+        for channel in 0..num_connected {
+            let read_buf = context.get_buffer_for_read(inputs[channel]);
+            let mut write_buf = context.get_buffer_for_write(outputs[channel]);
+
+            // channels past the configured count have no filter state, so they pass through
+            match biquads.get_mut(channel) {
+                // every channel follows the same glide, starting from where the block did
+                Some(biquad) if corner_freq_hz.is_smoothing() => {
+                    let mut channel_corner_freq_hz = *corner_freq_hz;
+                    process_block(&read_buf, &mut write_buf, num_samples, |x_n| {
+                        biquad.change_cutoff(channel_corner_freq_hz.next());
+                        biquad.filter(x_n)
+                    });
+                }
+                Some(biquad) => {
+                    process_block(&read_buf, &mut write_buf, num_samples, |x_n| {
+                        biquad.filter(x_n)
+                    });
+                }
+                None => process_block(&read_buf, &mut write_buf, num_samples, |x_n| x_n),
+            }
+        }
 
-        for i in 0..num_samples {
-            let sample = read_buf.buf_read(i);
-            let filtered = biquad.filter(sample);
-            write_buf.buf_write(i, filtered);
+        for _ in 0..num_samples {
+            corner_freq_hz.next();
         }
     }
 }
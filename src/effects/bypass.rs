@@ -1,12 +1,15 @@
 use crate::conf::{AdvertisedParameter, AudioConfig, BoardEffectConfigParameterValue};
 use crate::context::BoardContext;
+use crate::effects::vocoder2::MIX_PARAM;
 use crate::effects::{basic_single_in_single_out, VocoderContext};
 use crate::traits::{AudioEffect, FrequencyDomainAudioEffect};
+use crate::utils::state::{StateReader, StateWriter};
 
 use fftw::array::AlignedVec;
 use fftw::types::c32;
 
 const PARAMS: &'static [AdvertisedParameter] = &[];
+const VOCODER_PARAMS: &'static [AdvertisedParameter] = &[MIX_PARAM];
 
 pub struct MonoBypass {}
 
@@ -37,7 +40,7 @@ impl VocoderBypass {
     }
 
     pub fn info() -> &'static [AdvertisedParameter] {
-        PARAMS
+        VOCODER_PARAMS
     }
 }
 
@@ -48,6 +51,15 @@ impl AudioEffect for MonoBypass {
 
     fn set_audio_parameters(&mut self, _new_config: &AudioConfig) {}
 
+    // nothing carries over between blocks
+    fn serialize_state(&self, _writer: &mut StateWriter) -> bool {
+        true
+    }
+
+    fn deserialize_state(&mut self, _reader: &mut StateReader) -> bool {
+        true
+    }
+
     fn set_effect_parameter(&mut self, _param_idx: usize, _param: BoardEffectConfigParameterValue) {
     }
 
@@ -59,9 +71,14 @@ impl AudioEffect for MonoBypass {
 
         let (read_buf, mut write_buf) = maybe_bufs.unwrap();
 
-        for i in 0..num_samples {
-            let r = read_buf.buf_read(i);
-            write_buf.buf_write(i, r);
+        match read_buf.read_slice(num_samples) {
+            Some(input) => write_buf.buf_write_slice(0, input),
+            None => {
+                for i in 0..num_samples {
+                    let r = read_buf.buf_read(i);
+                    write_buf.buf_write(i, r);
+                }
+            }
         }
     }
 }
@@ -98,7 +115,7 @@ impl AudioEffect for GenericBypass {
         // write 0 to extra outputs
         if inputs.len() == min_end {
             for i in min_end..outputs.len() {
-                let mut write_buf = context.get_buffer_for_write(i);
+                let mut write_buf = context.get_buffer_for_write(outputs[i]);
 
                 for j in 0..num_samples {
                     write_buf.buf_write(j, 0.0f32);
@@ -113,7 +130,7 @@ impl AudioEffect for GenericBypass {
 
 impl FrequencyDomainAudioEffect for VocoderBypass {
     fn advertise_parameters(&self) -> &'static [AdvertisedParameter] {
-        PARAMS
+        VocoderBypass::info()
     }
 
     fn post_initialize(&mut self, _vocoder_context: &VocoderContext) {}
@@ -0,0 +1,433 @@
+use crate::conf::{
+    AdvertisedParameter, AudioConfig, BoardEffectConfigParameterValue, ParameterRange,
+};
+use crate::context::BoardContext;
+use crate::effects::basic_single_in_single_out;
+use crate::traits::AudioEffect;
+use crate::utils::buf_rw::AudioBufferWriter;
+use crate::utils::linkwitz_riley::LinkwitzRileyCrossover;
+use crate::utils::state::{StateReader, StateWriter};
+
+use fftw::array::AlignedVec;
+use fftw::plan::*;
+use fftw::types::*;
+use num_derive::FromPrimitive;
+
+use std::cell::{Cell, RefCell};
+
+// length of the linear phase low pass. the band split is delayed by half of it
+const FIR_LEN: usize = 2048;
+
+// the low pass is applied in the frequency domain (uniformly partitioned overlap-save),
+// a partition at a time. collecting a partition adds its length to the latency
+const PARTITION_LEN: usize = 256;
+const NUM_PARTITIONS: usize = FIR_LEN / PARTITION_LEN;
+const PARTITION_FFT_LEN: usize = 2 * PARTITION_LEN;
+
+const FIR_LATENCY: usize = FIR_LEN / 2 + PARTITION_LEN;
+
+#[derive(Clone, Copy, FromPrimitive, PartialEq)]
+#[allow(non_camel_case_types)]
+pub enum CrossoverMode {
+    // linkwitz-riley 4th order. no latency, but the bands are phase shifted around the crossover
+    MinimumPhase = 0,
+    // fir designed with an fft. no phase shift (the bands just sum back to the delayed input)
+    // but FIR_LATENCY samples late and more work per sample
+    LinearPhase,
+
+    __NUM_CROSSOVER_MODES,
+}
+
+impl Default for CrossoverMode {
+    fn default() -> Self {
+        CrossoverMode::MinimumPhase
+    }
+}
+
+const PARAMS: &'static [AdvertisedParameter] = &[
+    AdvertisedParameter {
+        name: "crossover_freq_hz",
+        range: ParameterRange::F(20.0f32, 20000.0f32),
+        default_value: BoardEffectConfigParameterValue::F(1000.0f32),
+    },
+    AdvertisedParameter {
+        // 0 = minimum phase (iir), 1 = linear phase (fft designed fir)
+        name: "crossover_mode",
+        range: ParameterRange::N(0, CrossoverMode::__NUM_CROSSOVER_MODES as i32 - 1),
+        default_value: BoardEffectConfigParameterValue::N(CrossoverMode::MinimumPhase as i32),
+    },
+];
+
+const PARAM_CROSSOVER_FREQ_HZ: usize = 0;
+const PARAM_CROSSOVER_MODE: usize = 1;
+
+// the low band is a convolution with the designed kernel and the high band is
+// whatever's left of the (equally delayed) input
+struct LinearPhaseSplit {
+    design_plan: C2CPlan32,
+    design_response: AlignedVec<c32>,
+    design_impulse: AlignedVec<c32>,
+
+    forward_plan: C2CPlan32,
+    inverse_plan: C2CPlan32,
+    fft_in: AlignedVec<c32>,
+    fft_out: AlignedVec<c32>,
+
+    // the spectrum of each PARTITION_LEN slice of the kernel (zero padded), back to back
+    kernel_spectra: Vec<c32>,
+    // the spectra of the last NUM_PARTITIONS input windows, back to back
+    // the newest is at spectrum_idx
+    input_spectra: Vec<c32>,
+    spectrum_idx: usize,
+
+    // the previous partition followed by the one being collected
+    input_window: Vec<f32>,
+    input_fill: usize,
+    // the low band worked out from the last full partition, played out while the next one fills
+    low_block: Vec<f32>,
+
+    // the input, FIR_LATENCY samples late
+    dry_line: Vec<f32>,
+    dry_idx: usize,
+}
+
+// reads = [x], writes = [low, high]
+pub struct Crossover {
+    params: Vec<BoardEffectConfigParameterValue>,
+    sample_rate: f32,
+
+    linkwitz_riley: RefCell<LinkwitzRileyCrossover>,
+    linear_phase: RefCell<LinearPhaseSplit>,
+
+    // the mode that ran last block. switching clears the state the new mode left behind
+    last_mode: Cell<CrossoverMode>,
+}
+
+impl LinearPhaseSplit {
+    fn new() -> LinearPhaseSplit {
+        let design_plan: C2CPlan32 =
+            C2CPlan::aligned(&[FIR_LEN], Sign::Backward, Flag::ESTIMATE).unwrap();
+
+        let forward_plan: C2CPlan32 =
+            C2CPlan::aligned(&[PARTITION_FFT_LEN], Sign::Forward, Flag::ESTIMATE).unwrap();
+        let inverse_plan: C2CPlan32 =
+            C2CPlan::aligned(&[PARTITION_FFT_LEN], Sign::Backward, Flag::ESTIMATE).unwrap();
+
+        LinearPhaseSplit {
+            design_plan,
+            design_response: AlignedVec::new(FIR_LEN),
+            design_impulse: AlignedVec::new(FIR_LEN),
+            forward_plan,
+            inverse_plan,
+            fft_in: AlignedVec::new(PARTITION_FFT_LEN),
+            fft_out: AlignedVec::new(PARTITION_FFT_LEN),
+            kernel_spectra: vec![c32::new(0.0f32, 0.0f32); NUM_PARTITIONS * PARTITION_FFT_LEN],
+            input_spectra: vec![c32::new(0.0f32, 0.0f32); NUM_PARTITIONS * PARTITION_FFT_LEN],
+            spectrum_idx: 0,
+            input_window: vec![0.0f32; PARTITION_FFT_LEN],
+            input_fill: 0,
+            low_block: vec![0.0f32; PARTITION_LEN],
+            dry_line: vec![0.0f32; FIR_LATENCY],
+            dry_idx: 0,
+        }
+    }
+
+    // the low pass gets the magnitude of a linkwitz-riley low pass but no phase. doesn't allocate
+    fn design(&mut self, crossover_freq: f32, sample_rate: f32) {
+        for k in 0..FIR_LEN {
+            // bins past the middle are the negative frequencies
+            let bin = k.min(FIR_LEN - k);
+            let ratio = bin as f32 * sample_rate / (FIR_LEN as f32 * crossover_freq);
+            let magnitude = 1.0f32 / (1.0f32 + ratio * ratio * ratio * ratio);
+
+            self.design_response[k] = c32::new(magnitude, 0.0f32);
+        }
+
+        self.design_plan
+            .c2c(&mut self.design_response, &mut self.design_impulse)
+            .unwrap();
+
+        // the zero phase impulse is centered on 0. move it to the middle of the kernel and
+        // taper the ends (hann) so the cut off tails don't ripple. each partition of the kernel
+        // is zero padded to twice its length and transformed, ready for overlap-save
+        for p in 0..NUM_PARTITIONS {
+            for i in 0..PARTITION_FFT_LEN {
+                let n = p * PARTITION_LEN + i;
+                let tap = if i < PARTITION_LEN {
+                    let centered = (n + FIR_LEN / 2) % FIR_LEN;
+                    let phase = 2.0f32 * std::f32::consts::PI * n as f32 / FIR_LEN as f32;
+                    let window = 0.5f32 - 0.5f32 * phase.cos();
+
+                    self.design_impulse[centered].re * window / FIR_LEN as f32
+                } else {
+                    0.0f32
+                };
+
+                self.fft_in[i] = c32::new(tap, 0.0f32);
+            }
+
+            self.forward_plan
+                .c2c(&mut self.fft_in, &mut self.fft_out)
+                .unwrap();
+
+            let spectrum = p * PARTITION_FFT_LEN;
+            self.kernel_spectra[spectrum..spectrum + PARTITION_FFT_LEN]
+                .copy_from_slice(&self.fft_out);
+        }
+    }
+
+    fn clear(&mut self) {
+        for bin in self.input_spectra.iter_mut() {
+            *bin = c32::new(0.0f32, 0.0f32);
+        }
+
+        for sample in self
+            .input_window
+            .iter_mut()
+            .chain(self.low_block.iter_mut())
+            .chain(self.dry_line.iter_mut())
+        {
+            *sample = 0.0f32;
+        }
+
+        self.input_fill = 0;
+    }
+
+    // one overlap-save step: the newest window's spectrum times the first kernel partition,
+    // the one before times the second and so on. the last PARTITION_LEN samples are valid
+    fn process_partition(&mut self) {
+        for (bin, x) in self.fft_in.iter_mut().zip(self.input_window.iter()) {
+            *bin = c32::new(*x, 0.0f32);
+        }
+
+        self.forward_plan
+            .c2c(&mut self.fft_in, &mut self.fft_out)
+            .unwrap();
+
+        self.spectrum_idx = (self.spectrum_idx + 1) % NUM_PARTITIONS;
+        let newest = self.spectrum_idx * PARTITION_FFT_LEN;
+        self.input_spectra[newest..newest + PARTITION_FFT_LEN].copy_from_slice(&self.fft_out);
+
+        for bin in self.fft_in.iter_mut() {
+            *bin = c32::new(0.0f32, 0.0f32);
+        }
+
+        for p in 0..NUM_PARTITIONS {
+            let input = (self.spectrum_idx + NUM_PARTITIONS - p) % NUM_PARTITIONS;
+            let input = input * PARTITION_FFT_LEN;
+            let kernel = p * PARTITION_FFT_LEN;
+
+            for k in 0..PARTITION_FFT_LEN {
+                self.fft_in[k] += self.input_spectra[input + k] * self.kernel_spectra[kernel + k];
+            }
+        }
+
+        self.inverse_plan
+            .c2c(&mut self.fft_in, &mut self.fft_out)
+            .unwrap();
+
+        for (low, y) in self
+            .low_block
+            .iter_mut()
+            .zip(self.fft_out[PARTITION_LEN..].iter())
+        {
+            *low = y.re / PARTITION_FFT_LEN as f32;
+        }
+
+        self.input_window.copy_within(PARTITION_LEN.., 0);
+    }
+
+    fn process(&mut self, xn: f32) -> (f32, f32) {
+        // the low band lags the input by a partition on top of the kernel's own delay
+        let low = self.low_block[self.input_fill];
+
+        self.input_window[PARTITION_LEN + self.input_fill] = xn;
+        self.input_fill += 1;
+        if self.input_fill == PARTITION_LEN {
+            self.process_partition();
+            self.input_fill = 0;
+        }
+
+        let delayed = self.dry_line[self.dry_idx];
+        self.dry_line[self.dry_idx] = xn;
+        self.dry_idx = (self.dry_idx + 1) % FIR_LATENCY;
+
+        (low, delayed - low)
+    }
+
+    fn save_state(&self, writer: &mut StateWriter) {
+        for bin in self.input_spectra.iter() {
+            writer.write_f32(bin.re);
+            writer.write_f32(bin.im);
+        }
+
+        writer.write_usize(self.spectrum_idx);
+        writer.write_f32_slice(&self.input_window);
+        writer.write_usize(self.input_fill);
+        writer.write_f32_slice(&self.low_block);
+        writer.write_f32_slice(&self.dry_line);
+        writer.write_usize(self.dry_idx);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Option<()> {
+        for bin in self.input_spectra.iter_mut() {
+            *bin = c32::new(reader.read_f32()?, reader.read_f32()?);
+        }
+
+        self.spectrum_idx = reader.read_usize()?;
+        reader.read_f32_slice_into(&mut self.input_window)?;
+        self.input_fill = reader.read_usize()?;
+        reader.read_f32_slice_into(&mut self.low_block)?;
+        reader.read_f32_slice_into(&mut self.dry_line)?;
+        self.dry_idx = reader.read_usize()?;
+
+        if self.spectrum_idx >= NUM_PARTITIONS
+            || self.input_fill >= PARTITION_LEN
+            || self.dry_idx >= FIR_LATENCY
+        {
+            return None;
+        }
+
+        Some(())
+    }
+}
+
+impl Crossover {
+    pub fn new(ac: AudioConfig) -> Crossover {
+        let mut params = Vec::with_capacity(PARAMS.len());
+        for i in 0..PARAMS.len() {
+            params.push(PARAMS[i].default_value);
+        }
+
+        let crossover_freq = params[PARAM_CROSSOVER_FREQ_HZ].as_flt();
+        let mut linear_phase = LinearPhaseSplit::new();
+        linear_phase.design(crossover_freq, ac.sample_rate);
+
+        Crossover {
+            sample_rate: ac.sample_rate,
+            linkwitz_riley: RefCell::new(LinkwitzRileyCrossover::new(
+                crossover_freq,
+                ac.sample_rate,
+            )),
+            linear_phase: RefCell::new(linear_phase),
+            last_mode: Cell::new(params[PARAM_CROSSOVER_MODE].as_enum()),
+            params,
+        }
+    }
+
+    pub fn info() -> &'static [AdvertisedParameter] {
+        PARAMS
+    }
+
+    fn update_crossover_freq(&mut self) {
+        let crossover_freq = self.params[PARAM_CROSSOVER_FREQ_HZ].as_flt();
+
+        self.linkwitz_riley
+            .get_mut()
+            .change_crossover_freq(crossover_freq);
+        self.linear_phase
+            .get_mut()
+            .design(crossover_freq, self.sample_rate);
+    }
+
+    fn clear(&self) {
+        self.linkwitz_riley.borrow_mut().clear();
+        self.linear_phase.borrow_mut().clear();
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Option<()> {
+        self.linkwitz_riley.get_mut().load_state(reader)?;
+        self.linear_phase.get_mut().load_state(reader)
+    }
+}
+
+impl AudioEffect for Crossover {
+    fn advertise_parameters(&self) -> &'static [AdvertisedParameter] {
+        Crossover::info()
+    }
+
+    fn set_audio_parameters(&mut self, new_config: &AudioConfig) {
+        self.sample_rate = new_config.sample_rate;
+
+        self.linkwitz_riley
+            .get_mut()
+            .change_sample_rate(new_config.sample_rate);
+        self.update_crossover_freq();
+    }
+
+    fn reset(&mut self) {
+        self.clear();
+    }
+
+    fn latency_samples(&self) -> usize {
+        match self.params[PARAM_CROSSOVER_MODE].as_enum() {
+            CrossoverMode::LinearPhase => FIR_LATENCY,
+            _ => 0,
+        }
+    }
+
+    fn max_latency_samples(&self) -> usize {
+        FIR_LATENCY
+    }
+
+    fn serialize_state(&self, writer: &mut StateWriter) -> bool {
+        self.linkwitz_riley.borrow().save_state(writer);
+
+        self.linear_phase.borrow().save_state(writer);
+
+        true
+    }
+
+    fn deserialize_state(&mut self, reader: &mut StateReader) -> bool {
+        self.load_state(reader).is_some()
+    }
+
+    fn set_effect_parameter(
+        &mut self,
+        param_idx: usize,
+        param_value: BoardEffectConfigParameterValue,
+    ) {
+        self.params[param_idx] = param_value;
+
+        if param_idx == PARAM_CROSSOVER_FREQ_HZ {
+            self.update_crossover_freq();
+        }
+    }
+
+    fn execute(&self, context: &BoardContext, connection_idx: usize, num_samples: usize) {
+        let maybe_bufs = basic_single_in_single_out(context, connection_idx, num_samples);
+        if let None = maybe_bufs {
+            return;
+        }
+
+        let (read_buf, mut low_buf) = maybe_bufs.unwrap();
+
+        let outputs = context.get_outputs_for_connection(connection_idx);
+        let mut high_buf = if outputs.len() > 1 {
+            context.get_buffer_for_write(outputs[1])
+        } else {
+            AudioBufferWriter::Null
+        };
+
+        let mode: CrossoverMode = self.params[PARAM_CROSSOVER_MODE].as_enum();
+        if mode != self.last_mode.get() {
+            self.clear();
+            self.last_mode.set(mode);
+        }
+
+        let mut linkwitz_riley = self.linkwitz_riley.borrow_mut();
+        let mut linear_phase = self.linear_phase.borrow_mut();
+
+        for i in 0..num_samples {
+            let xn = read_buf.buf_read(i);
+
+            let (low, high) = match mode {
+                CrossoverMode::LinearPhase => linear_phase.process(xn),
+                _ => linkwitz_riley.split(xn),
+            };
+
+            low_buf.buf_write(i, low);
+            high_buf.buf_write(i, high);
+        }
+    }
+}
@@ -4,10 +4,14 @@ use crate::conf::{
 use crate::consts;
 use crate::context::BoardContext;
 use crate::effects::basic_single_in_single_out;
+use crate::effects::reverb::apf::DelayAPF;
 use crate::traits::AudioEffect;
 use crate::utils::delay_buf::DelayBuffer;
+use crate::utils::mathutils::{self, MonitorMode, NoteDivision};
+use crate::utils::smoothed_param::ParamSmoother;
+use crate::utils::state::{StateReader, StateWriter};
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 
 const BASIC_PARAMS: &'static [AdvertisedParameter] = &[
     AdvertisedParameter {
@@ -25,16 +29,63 @@ const BASIC_PARAMS: &'static [AdvertisedParameter] = &[
         range: ParameterRange::F(0.0f32, 1.0f32),
         default_value: BoardEffectConfigParameterValue::F(0.5f32),
     },
+    AdvertisedParameter {
+        name: "delay_glide_ms",
+        range: ParameterRange::F(0.0f32, 5000.0f32),
+        default_value: BoardEffectConfigParameterValue::F(0.0f32),
+    },
+    AdvertisedParameter {
+        // flips the polarity of the wet signal. at short delays this notches out the lows
+        name: "wet_invert?",
+        range: ParameterRange::N(0, 1),
+        default_value: BoardEffectConfigParameterValue::N(0),
+    },
+    AdvertisedParameter {
+        // 0 = wet/dry mix, 1 = wet only, 2 = dry only
+        name: "monitor_mode",
+        range: ParameterRange::N(0, MonitorMode::__NUM_MONITOR_MODES as i32 - 1),
+        default_value: BoardEffectConfigParameterValue::N(MonitorMode::Full as i32),
+    },
+    AdvertisedParameter {
+        // blends all pass diffusers into the feedback path, so each repeat smears more than the last
+        name: "diffusion",
+        range: ParameterRange::F(0.0f32, 1.0f32),
+        default_value: BoardEffectConfigParameterValue::F(0.0f32),
+    },
+    AdvertisedParameter {
+        // a note length at the host's tempo (see mathutils::NoteDivision). 0 = use delay_time_ms
+        name: "sync_division",
+        range: ParameterRange::N(0, NoteDivision::__NUM_NOTE_DIVISIONS as i32 - 1),
+        default_value: BoardEffectConfigParameterValue::N(NoteDivision::Free as i32),
+    },
 ];
 
 const PARAM_DELAY_TIME_MS: usize = 0;
 const PARAM_FEEDBACK_PCT: usize = 1;
 const PARAM_WET_DRY_PCT: usize = 2;
+const PARAM_DELAY_GLIDE_MS: usize = 3;
+const PARAM_WET_INVERT: usize = 4;
+const PARAM_MONITOR_MODE: usize = 5;
+const PARAM_DIFFUSION: usize = 6;
+const PARAM_SYNC_DIVISION: usize = 7;
+
+// mutually prime-ish so the diffusers' echoes don't line up
+const DIFFUSER_DELAYS_MS: [f32; 4] = [4.771f32, 3.595f32, 12.73f32, 9.307f32];
+const DIFFUSER_G: f32 = 0.6f32;
 
 pub struct MonoDelayBasic {
     params: Vec<BoardEffectConfigParameterValue>,
 
     delay_buf: RefCell<DelayBuffer>,
+
+    // delay time changes glide (tape-style) towards the target through a one-pole smoother
+    // a glide of 0 ms jumps immediately
+    delay_time_ms: RefCell<ParamSmoother>,
+    has_processed_audio: Cell<bool>,
+
+    diffusers: RefCell<Vec<DelayAPF>>,
+
+    tempo_bpm: f32,
 }
 
 impl MonoDelayBasic {
@@ -50,7 +101,68 @@ impl MonoDelayBasic {
 
         let delay_buf = RefCell::new(DelayBuffer::with_sample_rate(ac.sample_rate));
 
-        MonoDelayBasic { params, delay_buf }
+        MonoDelayBasic {
+            params,
+            delay_buf,
+            delay_time_ms: RefCell::new(ParamSmoother::new(0.0f32, 0.0f32, ac.sample_rate)),
+            has_processed_audio: Cell::new(false),
+            diffusers: RefCell::new(MonoDelayBasic::create_diffusers(ac.sample_rate)),
+            tempo_bpm: ac.tempo_bpm,
+        }
+    }
+
+    fn create_diffusers(sample_rate: f32) -> Vec<DelayAPF> {
+        DIFFUSER_DELAYS_MS
+            .iter()
+            .map(|delay_ms| DelayAPF::new(*delay_ms, DIFFUSER_G, sample_rate))
+            .collect()
+    }
+
+    fn update_glide_time(&mut self) {
+        let glide_ms = self.params[PARAM_DELAY_GLIDE_MS].as_flt();
+        let sample_rate = self.delay_buf.get_mut().get_sample_rate();
+
+        self.delay_time_ms
+            .get_mut()
+            .set_time_constant(glide_ms, sample_rate);
+    }
+
+    // the synced note length wins over delay_time_ms when there is one
+    fn target_delay_time_ms(&self) -> f32 {
+        let division = self.params[PARAM_SYNC_DIVISION].as_enum();
+        match mathutils::note_division_to_ms(self.tempo_bpm, division) {
+            Some(synced_ms) => synced_ms.min(consts::MAX_DELAY_MS),
+            None => self.params[PARAM_DELAY_TIME_MS].as_flt(),
+        }
+    }
+
+    fn jump_to_target_delay_time(&mut self) {
+        let target_delay_time_ms = self.target_delay_time_ms();
+
+        self.delay_time_ms
+            .get_mut()
+            .set_immediate(target_delay_time_ms);
+        self.delay_buf
+            .get_mut()
+            .set_delay_time_ms(target_delay_time_ms, true);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Option<()> {
+        self.delay_buf.get_mut().load_state(reader)?;
+
+        // the glide carries on from where the snapshot was towards the current target
+        let target_delay_time_ms = self.target_delay_time_ms();
+        let delay_time_ms = self.delay_time_ms.get_mut();
+        delay_time_ms.set_immediate(reader.read_f32()?);
+        delay_time_ms.set_target(target_delay_time_ms);
+
+        self.has_processed_audio.set(reader.read_bool()?);
+
+        for diffuser in self.diffusers.get_mut().iter_mut() {
+            diffuser.load_state(reader)?;
+        }
+
+        Some(())
     }
 }
 
@@ -63,6 +175,48 @@ impl AudioEffect for MonoDelayBasic {
         self.delay_buf
             .borrow_mut()
             .change_sample_rate(new_config.sample_rate);
+
+        for diffuser in self.diffusers.get_mut().iter_mut() {
+            diffuser.change_sample_rate(new_config.sample_rate);
+        }
+
+        self.update_glide_time();
+
+        // a synced delay moves with the tempo. no glide, the old tempo's time is just gone
+        self.tempo_bpm = new_config.tempo_bpm;
+        self.jump_to_target_delay_time();
+    }
+
+    fn reset(&mut self) {
+        self.delay_buf.borrow_mut().clear();
+
+        for diffuser in self.diffusers.get_mut().iter_mut() {
+            diffuser.clear();
+        }
+
+        // a glide that was under way is over, and the next change has nothing to glide from
+        self.jump_to_target_delay_time();
+        self.has_processed_audio.set(false);
+    }
+
+    fn copy_visualization_samples(&self, output: &mut [f32]) -> usize {
+        self.delay_buf.borrow().copy_recent_samples(output)
+    }
+
+    fn serialize_state(&self, writer: &mut StateWriter) -> bool {
+        self.delay_buf.borrow().save_state(writer);
+        writer.write_f32(self.delay_time_ms.borrow().current());
+        writer.write_bool(self.has_processed_audio.get());
+
+        for diffuser in self.diffusers.borrow().iter() {
+            diffuser.save_state(writer);
+        }
+
+        true
+    }
+
+    fn deserialize_state(&mut self, reader: &mut StateReader) -> bool {
+        self.load_state(reader).is_some()
     }
 
     fn set_effect_parameter(
@@ -72,10 +226,20 @@ impl AudioEffect for MonoDelayBasic {
     ) {
         self.params[param_idx] = param_value;
 
-        if param_idx == PARAM_DELAY_TIME_MS {
-            self.delay_buf
-                .borrow_mut()
-                .set_delay_time_ms(param_value.as_flt(), true);
+        if param_idx == PARAM_DELAY_TIME_MS || param_idx == PARAM_SYNC_DIVISION {
+            // nothing has been heard yet, so there's nothing to glide from
+            if self.delay_time_ms.get_mut().is_instant() || !self.has_processed_audio.get() {
+                self.jump_to_target_delay_time();
+            } else {
+                let target_delay_time_ms = self.target_delay_time_ms();
+                self.delay_time_ms.get_mut().set_target(target_delay_time_ms);
+            }
+        } else if param_idx == PARAM_DELAY_GLIDE_MS {
+            self.update_glide_time();
+
+            if self.delay_time_ms.get_mut().is_instant() {
+                self.jump_to_target_delay_time();
+            }
         }
     }
 
@@ -87,15 +251,40 @@ impl AudioEffect for MonoDelayBasic {
 
         let (read_buf, mut write_buf) = maybe_bufs.unwrap();
 
-        let wetness = self.params[PARAM_WET_DRY_PCT].as_flt();
-        let dryness = 1.0f32 - wetness;
+        let (dryness, mut wetness) = mathutils::wet_dry_gains(
+            self.params[PARAM_WET_DRY_PCT].as_flt(),
+            self.params[PARAM_MONITOR_MODE].as_enum(),
+        );
         let feedback = self.params[PARAM_FEEDBACK_PCT].as_flt();
+        let diffusion = self.params[PARAM_DIFFUSION].as_flt();
+
+        if self.params[PARAM_WET_INVERT].as_int() != 0 {
+            wetness = -wetness;
+        }
+
+        self.has_processed_audio.set(true);
+
+        let mut delay_time_ms = self.delay_time_ms.borrow_mut();
+        let is_gliding = delay_time_ms.is_smoothing();
 
         let mut delay_ref = self.delay_buf.borrow_mut();
+        let mut diffusers = self.diffusers.borrow_mut();
         for i in 0..num_samples {
+            if is_gliding {
+                delay_ref.set_delay_time_ms(delay_time_ms.next(), true);
+            }
+
             let xn = read_buf.buf_read(i);
             let yn = delay_ref.read_delayed_sample();
-            let dn = xn + feedback * yn;
+
+            // skipped at 0 so a plain delay costs what it always has
+            let fed_back = if diffusion > 0.0f32 {
+                let diffused = diffusers.iter_mut().fold(yn, |s, apf| apf.process(s));
+                yn + diffusion * (diffused - yn)
+            } else {
+                yn
+            };
+            let dn = xn + feedback * fed_back;
 
             delay_ref.write_sample(dn);
 
@@ -0,0 +1,337 @@
+use crate::conf::{
+    AdvertisedParameter, AudioConfig, BoardEffectConfigParameterValue, ParameterRange,
+};
+use crate::context::BoardContext;
+use crate::effects::basic_single_in_single_out;
+use crate::traits::AudioEffect;
+use crate::utils::fast_rand::WyHashPRNG;
+use crate::utils::mathutils::{self, MonitorMode};
+use crate::utils::state::{StateReader, StateWriter};
+
+use std::cell::{Cell, RefCell};
+use std::f32::consts::PI;
+use std::time::SystemTime;
+
+// the voice pool is allocated once at this size. max_voices only limits how much of it is used
+const VOICE_POOL_SIZE: usize = 64;
+
+// grains are read from this much captured input
+const MAX_GRAIN_SIZE_MS: f32 = 500.0f32;
+const MAX_SPREAD_MS: f32 = 500.0f32;
+
+const PARAMS: &'static [AdvertisedParameter] = &[
+    AdvertisedParameter {
+        name: "grain_size_ms",
+        range: ParameterRange::F(1.0f32, MAX_GRAIN_SIZE_MS),
+        default_value: BoardEffectConfigParameterValue::F(80.0f32),
+    },
+    AdvertisedParameter {
+        // new grains per second
+        name: "density_hz",
+        range: ParameterRange::F(0.1f32, 1000.0f32),
+        default_value: BoardEffectConfigParameterValue::F(20.0f32),
+    },
+    AdvertisedParameter {
+        // grains start up to this far back in the captured input, chosen at random
+        name: "spread_ms",
+        range: ParameterRange::F(0.0f32, MAX_SPREAD_MS),
+        default_value: BoardEffectConfigParameterValue::F(50.0f32),
+    },
+    AdvertisedParameter {
+        name: "wet_dry_pct",
+        range: ParameterRange::F(0.0f32, 1.0f32),
+        default_value: BoardEffectConfigParameterValue::F(0.5f32),
+    },
+    AdvertisedParameter {
+        // when every voice is busy, the oldest grain is cut off to make room for the new one
+        name: "max_voices",
+        range: ParameterRange::N(1, VOICE_POOL_SIZE as i32),
+        default_value: BoardEffectConfigParameterValue::N(16),
+    },
+    AdvertisedParameter {
+        // 0 = wet/dry mix, 1 = wet only, 2 = dry only
+        name: "monitor_mode",
+        range: ParameterRange::N(0, MonitorMode::__NUM_MONITOR_MODES as i32 - 1),
+        default_value: BoardEffectConfigParameterValue::N(MonitorMode::Full as i32),
+    },
+];
+
+const PARAM_GRAIN_SIZE_MS: usize = 0;
+const PARAM_DENSITY_HZ: usize = 1;
+const PARAM_SPREAD_MS: usize = 2;
+const PARAM_WET_DRY_PCT: usize = 3;
+const PARAM_MAX_VOICES: usize = 4;
+const PARAM_MONITOR_MODE: usize = 5;
+
+#[derive(Clone, Copy)]
+struct GrainVoice {
+    active: bool,
+    read_idx: usize,
+    age: usize,
+    length: usize,
+}
+
+impl GrainVoice {
+    const IDLE: GrainVoice = GrainVoice {
+        active: false,
+        read_idx: 0,
+        age: 0,
+        length: 0,
+    };
+}
+
+pub struct Granular {
+    params: Vec<BoardEffectConfigParameterValue>,
+    sample_rate: f32,
+
+    capture_buf: RefCell<Vec<f32>>,
+    capture_write_idx: Cell<usize>,
+
+    voices: RefCell<[GrainVoice; VOICE_POOL_SIZE]>,
+    samples_until_next_grain: Cell<f32>,
+
+    prng: WyHashPRNG,
+}
+
+impl Granular {
+    pub fn new(ac: AudioConfig) -> Granular {
+        let mut params = Vec::with_capacity(PARAMS.len());
+        for i in 0..PARAMS.len() {
+            params.push(PARAMS[i].default_value);
+        }
+
+        Granular {
+            params,
+            sample_rate: ac.sample_rate,
+            capture_buf: RefCell::new(vec![0.0f32; Granular::capture_len(ac.sample_rate)]),
+            capture_write_idx: Cell::new(0),
+            voices: RefCell::new([GrainVoice::IDLE; VOICE_POOL_SIZE]),
+            samples_until_next_grain: Cell::new(0.0f32),
+            prng: WyHashPRNG::new(
+                SystemTime::now()
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .unwrap()
+                    .as_secs(),
+            ),
+        }
+    }
+
+    pub fn info() -> &'static [AdvertisedParameter] {
+        PARAMS
+    }
+
+    fn capture_len(sample_rate: f32) -> usize {
+        ((MAX_GRAIN_SIZE_MS + MAX_SPREAD_MS) * 0.001f32 * sample_rate) as usize + 1
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Option<()> {
+        let capture_buf = self.capture_buf.get_mut();
+        reader.read_f32_slice_into(&mut capture_buf[..])?;
+
+        let capture_len = capture_buf.len();
+        let capture_write_idx = reader.read_usize()?;
+        if capture_write_idx >= capture_len {
+            return None;
+        }
+        self.capture_write_idx.set(capture_write_idx);
+
+        for voice in self.voices.get_mut().iter_mut() {
+            *voice = GrainVoice {
+                active: reader.read_bool()?,
+                read_idx: reader.read_usize()?,
+                age: reader.read_usize()?,
+                length: reader.read_usize()?,
+            };
+            if voice.read_idx >= capture_len {
+                return None;
+            }
+        }
+
+        self.samples_until_next_grain.set(reader.read_f32()?);
+
+        // so a restored board picks the same grains it would have
+        self.prng.set_state(reader.read_u64()?);
+
+        Some(())
+    }
+
+    fn ms_to_samples(&self, ms: f32) -> usize {
+        (ms * 0.001f32 * self.sample_rate) as usize
+    }
+
+    fn spawn_grain(&self, voices: &mut [GrainVoice], capture_len: usize) {
+        let grain_len = self
+            .ms_to_samples(self.params[PARAM_GRAIN_SIZE_MS].as_flt())
+            .max(1);
+        let max_spread = self.ms_to_samples(self.params[PARAM_SPREAD_MS].as_flt());
+        let spread = if max_spread > 0 {
+            (self.prng.next() % (max_spread as u64 + 1)) as usize
+        } else {
+            0
+        };
+
+        // the grain plays forward at the same rate as the capture, so starting it one grain
+        // length back keeps it behind the write head for its whole life
+        let offset = (grain_len + spread).min(capture_len - 1);
+        let read_idx = (self.capture_write_idx.get() + capture_len - offset) % capture_len;
+
+        let max_voices = (self.params[PARAM_MAX_VOICES].as_int() as usize)
+            .max(1)
+            .min(VOICE_POOL_SIZE);
+        let voices = &mut voices[..max_voices];
+
+        let slot = match voices.iter().position(|v| !v.active) {
+            Some(idx) => idx,
+            None => {
+                // steal the oldest voice
+                let mut oldest = 0;
+                for i in 1..voices.len() {
+                    if voices[i].age > voices[oldest].age {
+                        oldest = i;
+                    }
+                }
+
+                oldest
+            }
+        };
+
+        voices[slot] = GrainVoice {
+            active: true,
+            read_idx,
+            age: 0,
+            length: grain_len,
+        };
+    }
+}
+
+impl AudioEffect for Granular {
+    fn advertise_parameters(&self) -> &'static [AdvertisedParameter] {
+        Granular::info()
+    }
+
+    fn set_audio_parameters(&mut self, new_config: &AudioConfig) {
+        self.sample_rate = new_config.sample_rate;
+
+        let mut capture_buf = self.capture_buf.borrow_mut();
+        capture_buf.clear();
+        capture_buf.resize(Granular::capture_len(new_config.sample_rate), 0.0f32);
+
+        self.capture_write_idx.set(0);
+        *self.voices.borrow_mut() = [GrainVoice::IDLE; VOICE_POOL_SIZE];
+    }
+
+    fn reset(&mut self) {
+        for sample in self.capture_buf.borrow_mut().iter_mut() {
+            *sample = 0.0f32;
+        }
+
+        *self.voices.borrow_mut() = [GrainVoice::IDLE; VOICE_POOL_SIZE];
+        self.samples_until_next_grain.set(0.0f32);
+    }
+
+    fn serialize_state(&self, writer: &mut StateWriter) -> bool {
+        writer.write_f32_slice(&self.capture_buf.borrow());
+        writer.write_usize(self.capture_write_idx.get());
+        for voice in self.voices.borrow().iter() {
+            writer.write_bool(voice.active);
+            writer.write_usize(voice.read_idx);
+            writer.write_usize(voice.age);
+            writer.write_usize(voice.length);
+        }
+        writer.write_f32(self.samples_until_next_grain.get());
+        writer.write_u64(self.prng.get_state());
+
+        true
+    }
+
+    fn deserialize_state(&mut self, reader: &mut StateReader) -> bool {
+        self.load_state(reader).is_some()
+    }
+
+    fn set_effect_parameter(
+        &mut self,
+        param_idx: usize,
+        param_value: BoardEffectConfigParameterValue,
+    ) {
+        self.params[param_idx] = param_value;
+
+        if param_idx == PARAM_MAX_VOICES {
+            // voices past the new cap would never be stolen, so cut them off now
+            let max_voices = (param_value.as_int().max(1) as usize).min(VOICE_POOL_SIZE);
+            for voice in self.voices.borrow_mut()[max_voices..].iter_mut() {
+                voice.active = false;
+            }
+        }
+    }
+
+    fn execute(&self, context: &BoardContext, connection_idx: usize, num_samples: usize) {
+        let maybe_bufs = basic_single_in_single_out(context, connection_idx, num_samples);
+        if let None = maybe_bufs {
+            return;
+        }
+
+        let (read_buf, mut write_buf) = maybe_bufs.unwrap();
+
+        let (dryness, wetness) = mathutils::wet_dry_gains(
+            self.params[PARAM_WET_DRY_PCT].as_flt(),
+            self.params[PARAM_MONITOR_MODE].as_enum(),
+        );
+        let grain_interval = self.sample_rate / self.params[PARAM_DENSITY_HZ].as_flt();
+
+        // about this many grains play at once, so scale their sum back down like uncorrelated
+        // signals add up. from the parameters rather than the live count, so it doesn't zipper
+        let grain_len = self
+            .ms_to_samples(self.params[PARAM_GRAIN_SIZE_MS].as_flt())
+            .max(1);
+        let max_voices = (self.params[PARAM_MAX_VOICES].as_int() as usize)
+            .max(1)
+            .min(VOICE_POOL_SIZE);
+        let expected_overlap = (grain_len as f32 / grain_interval)
+            .max(1.0f32)
+            .min(max_voices as f32);
+        let wetness = wetness / expected_overlap.sqrt();
+
+        let mut capture_buf = self.capture_buf.borrow_mut();
+        let capture_len = capture_buf.len();
+        let mut voices = self.voices.borrow_mut();
+        let mut samples_until_next_grain = self.samples_until_next_grain.get();
+
+        // over budget: let the playing grains finish but don't start new ones
+        let spawn_grains = !context.should_degrade();
+
+        for i in 0..num_samples {
+            let xn = read_buf.buf_read(i);
+
+            let write_idx = self.capture_write_idx.get();
+            capture_buf[write_idx] = xn;
+            self.capture_write_idx.set((write_idx + 1) % capture_len);
+
+            samples_until_next_grain -= 1.0f32;
+            if samples_until_next_grain <= 0.0f32 {
+                if spawn_grains {
+                    self.spawn_grain(&mut voices[..], capture_len);
+                }
+                samples_until_next_grain += grain_interval;
+            }
+
+            let mut yn = 0.0f32;
+            for voice in voices.iter_mut().filter(|v| v.active) {
+                // hann window over the grain
+                let phase = voice.age as f32 / voice.length as f32;
+                let window = 0.5f32 - 0.5f32 * (2.0f32 * PI * phase).cos();
+
+                yn += window * capture_buf[voice.read_idx];
+
+                voice.read_idx = (voice.read_idx + 1) % capture_len;
+                voice.age += 1;
+                if voice.age >= voice.length {
+                    voice.active = false;
+                }
+            }
+
+            write_buf.buf_write(i, dryness * xn + wetness * yn);
+        }
+
+        self.samples_until_next_grain.set(samples_until_next_grain);
+    }
+}
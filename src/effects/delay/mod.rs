@@ -1,3 +1,11 @@
 mod basic_delay;
+mod granular;
+mod multi_tap_delay;
+mod ping_pong_delay;
+mod stereo_delay;
 
 pub use basic_delay::MonoDelayBasic;
+pub use granular::Granular;
+pub use multi_tap_delay::MultiTapDelay;
+pub use ping_pong_delay::PingPongDelay;
+pub use stereo_delay::StereoDelay;
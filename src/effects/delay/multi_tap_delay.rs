@@ -0,0 +1,175 @@
+use crate::conf::{
+    AdvertisedParameter, AudioConfig, BoardEffectConfigParameterValue, ParameterRange,
+};
+use crate::consts;
+use crate::context::BoardContext;
+use crate::effects::basic_single_in_single_out;
+use crate::traits::AudioEffect;
+use crate::utils::delay_buf::DelayBuffer;
+use crate::utils::mathutils::{self, MonitorMode};
+use crate::utils::state::{StateReader, StateWriter};
+
+use std::cell::RefCell;
+
+const NUM_TAPS: usize = 4;
+
+const MULTI_TAP_PARAMS: &'static [AdvertisedParameter] = &[
+    AdvertisedParameter {
+        name: "tap1_ms",
+        range: ParameterRange::F(0.0f32, consts::MAX_DELAY_MS),
+        default_value: BoardEffectConfigParameterValue::F(125.0f32),
+    },
+    AdvertisedParameter {
+        name: "tap2_ms",
+        range: ParameterRange::F(0.0f32, consts::MAX_DELAY_MS),
+        default_value: BoardEffectConfigParameterValue::F(250.0f32),
+    },
+    AdvertisedParameter {
+        name: "tap3_ms",
+        range: ParameterRange::F(0.0f32, consts::MAX_DELAY_MS),
+        default_value: BoardEffectConfigParameterValue::F(375.0f32),
+    },
+    AdvertisedParameter {
+        name: "tap4_ms",
+        range: ParameterRange::F(0.0f32, consts::MAX_DELAY_MS),
+        default_value: BoardEffectConfigParameterValue::F(500.0f32),
+    },
+    AdvertisedParameter {
+        // negative gains flip the tap's polarity. 0 turns it off
+        name: "tap1_gain",
+        range: ParameterRange::F(-1.0f32, 1.0f32),
+        default_value: BoardEffectConfigParameterValue::F(0.8f32),
+    },
+    AdvertisedParameter {
+        name: "tap2_gain",
+        range: ParameterRange::F(-1.0f32, 1.0f32),
+        default_value: BoardEffectConfigParameterValue::F(0.6f32),
+    },
+    AdvertisedParameter {
+        name: "tap3_gain",
+        range: ParameterRange::F(-1.0f32, 1.0f32),
+        default_value: BoardEffectConfigParameterValue::F(0.4f32),
+    },
+    AdvertisedParameter {
+        name: "tap4_gain",
+        range: ParameterRange::F(-1.0f32, 1.0f32),
+        default_value: BoardEffectConfigParameterValue::F(0.2f32),
+    },
+    AdvertisedParameter {
+        name: "wet_dry_pct",
+        range: ParameterRange::F(0.0f32, 1.0f32),
+        default_value: BoardEffectConfigParameterValue::F(0.5f32),
+    },
+    AdvertisedParameter {
+        // 0 = wet/dry mix, 1 = wet only, 2 = dry only
+        name: "monitor_mode",
+        range: ParameterRange::N(0, MonitorMode::__NUM_MONITOR_MODES as i32 - 1),
+        default_value: BoardEffectConfigParameterValue::N(MonitorMode::Full as i32),
+    },
+];
+
+// tap n's time is param PARAM_FIRST_TAP_MS + n and its gain PARAM_FIRST_TAP_GAIN + n
+const PARAM_FIRST_TAP_MS: usize = 0;
+const PARAM_FIRST_TAP_GAIN: usize = 4;
+const PARAM_WET_DRY_PCT: usize = 8;
+const PARAM_MONITOR_MODE: usize = 9;
+
+// every tap reads the same line, which is written once per sample. no feedback
+pub struct MultiTapDelay {
+    params: Vec<BoardEffectConfigParameterValue>,
+
+    delay_buf: RefCell<DelayBuffer>,
+}
+
+impl MultiTapDelay {
+    pub fn info() -> &'static [AdvertisedParameter] {
+        MULTI_TAP_PARAMS
+    }
+
+    pub fn new(ac: AudioConfig) -> MultiTapDelay {
+        let mut params = Vec::with_capacity(MULTI_TAP_PARAMS.len());
+        for i in 0..MULTI_TAP_PARAMS.len() {
+            params.push(MULTI_TAP_PARAMS[i].default_value);
+        }
+
+        MultiTapDelay {
+            params,
+            delay_buf: RefCell::new(DelayBuffer::with_sample_rate(ac.sample_rate)),
+        }
+    }
+}
+
+impl AudioEffect for MultiTapDelay {
+    fn advertise_parameters(&self) -> &'static [AdvertisedParameter] {
+        MultiTapDelay::info()
+    }
+
+    fn set_audio_parameters(&mut self, new_config: &AudioConfig) {
+        self.delay_buf
+            .get_mut()
+            .change_sample_rate(new_config.sample_rate);
+    }
+
+    fn reset(&mut self) {
+        self.delay_buf.get_mut().clear();
+    }
+
+    fn copy_visualization_samples(&self, output: &mut [f32]) -> usize {
+        self.delay_buf.borrow().copy_recent_samples(output)
+    }
+
+    fn serialize_state(&self, writer: &mut StateWriter) -> bool {
+        self.delay_buf.borrow().save_state(writer);
+
+        true
+    }
+
+    fn deserialize_state(&mut self, reader: &mut StateReader) -> bool {
+        self.delay_buf.get_mut().load_state(reader).is_some()
+    }
+
+    fn set_effect_parameter(
+        &mut self,
+        param_idx: usize,
+        param_value: BoardEffectConfigParameterValue,
+    ) {
+        self.params[param_idx] = param_value;
+    }
+
+    fn execute(&self, context: &BoardContext, connection_idx: usize, num_samples: usize) {
+        let maybe_bufs = basic_single_in_single_out(context, connection_idx, num_samples);
+        if let None = maybe_bufs {
+            return;
+        }
+
+        let (read_buf, mut write_buf) = maybe_bufs.unwrap();
+
+        let (dryness, wetness) = mathutils::wet_dry_gains(
+            self.params[PARAM_WET_DRY_PCT].as_flt(),
+            self.params[PARAM_MONITOR_MODE].as_enum(),
+        );
+
+        let mut tap_times_ms = [0.0f32; NUM_TAPS];
+        let mut tap_gains = [0.0f32; NUM_TAPS];
+        for tap in 0..NUM_TAPS {
+            tap_times_ms[tap] = self.params[PARAM_FIRST_TAP_MS + tap].as_flt();
+            tap_gains[tap] = self.params[PARAM_FIRST_TAP_GAIN + tap].as_flt();
+        }
+
+        let mut delay_buf = self.delay_buf.borrow_mut();
+        for i in 0..num_samples {
+            let xn = read_buf.buf_read(i);
+
+            let mut yn = 0.0f32;
+            for tap in 0..NUM_TAPS {
+                if tap_gains[tap] != 0.0f32 {
+                    yn += tap_gains[tap] * delay_buf.read_delayed_sample_at_ms(tap_times_ms[tap]);
+                }
+            }
+
+            delay_buf.write_sample(xn);
+
+            write_buf.buf_write(i, dryness * xn + wetness * yn);
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use crate::conf::{
+    AdvertisedParameter, AudioConfig, BoardEffectConfigParameterValue, ParameterRange,
+};
+use crate::consts;
+use crate::context::BoardContext;
+use crate::effects::basic_stereo_in_stereo_out;
+use crate::traits::AudioEffect;
+use crate::utils::delay_buf::DelayBuffer;
+use crate::utils::mathutils::{self, MonitorMode};
+use crate::utils::state::{StateReader, StateWriter};
+
+use std::cell::RefCell;
+
+const STEREO_PARAMS: &'static [AdvertisedParameter] = &[
+    AdvertisedParameter {
+        name: "delay_time_ms",
+        range: ParameterRange::F(0.0f32, consts::MAX_DELAY_MS),
+        default_value: BoardEffectConfigParameterValue::F(500.0f32),
+    },
+    AdvertisedParameter {
+        name: "feedback_pct",
+        range: ParameterRange::F(-1.0f32, 1.0f32),
+        default_value: BoardEffectConfigParameterValue::F(0.0f32),
+    },
+    AdvertisedParameter {
+        name: "cross_feedback_pct",
+        range: ParameterRange::F(-1.0f32, 1.0f32),
+        default_value: BoardEffectConfigParameterValue::F(0.3f32),
+    },
+    AdvertisedParameter {
+        name: "wet_dry_pct",
+        range: ParameterRange::F(0.0f32, 1.0f32),
+        default_value: BoardEffectConfigParameterValue::F(0.5f32),
+    },
+    AdvertisedParameter {
+        // 0 = wet/dry mix, 1 = wet only, 2 = dry only
+        name: "monitor_mode",
+        range: ParameterRange::N(0, MonitorMode::__NUM_MONITOR_MODES as i32 - 1),
+        default_value: BoardEffectConfigParameterValue::N(MonitorMode::Full as i32),
+    },
+];
+
+const PARAM_DELAY_TIME_MS: usize = 0;
+const PARAM_FEEDBACK_PCT: usize = 1;
+const PARAM_CROSS_FEEDBACK_PCT: usize = 2;
+const PARAM_WET_DRY_PCT: usize = 3;
+const PARAM_MONITOR_MODE: usize = 4;
+
+// a pair of delay lines (reads = [L, R], writes = [L, R])
+// feedback_pct feeds each line back into itself, cross_feedback_pct feeds it into the opposite line
+// keep |feedback| + |cross_feedback| < 1 or the loop will grow
+pub struct StereoDelay {
+    params: Vec<BoardEffectConfigParameterValue>,
+
+    delay_bufs: RefCell<[DelayBuffer; 2]>,
+}
+
+impl StereoDelay {
+    pub fn info() -> &'static [AdvertisedParameter] {
+        STEREO_PARAMS
+    }
+
+    pub fn new(ac: AudioConfig) -> StereoDelay {
+        let mut params = Vec::with_capacity(STEREO_PARAMS.len());
+        for i in 0..STEREO_PARAMS.len() {
+            params.push(STEREO_PARAMS[i].default_value);
+        }
+
+        let delay_bufs = RefCell::new([
+            DelayBuffer::with_sample_rate(ac.sample_rate),
+            DelayBuffer::with_sample_rate(ac.sample_rate),
+        ]);
+
+        let mut delay = StereoDelay { params, delay_bufs };
+        delay.update_delay_time();
+
+        delay
+    }
+
+    fn update_delay_time(&mut self) {
+        let delay_time_ms = self.params[PARAM_DELAY_TIME_MS].as_flt();
+        for delay_buf in self.delay_bufs.borrow_mut().iter_mut() {
+            delay_buf.set_delay_time_ms(delay_time_ms, true);
+        }
+    }
+}
+
+impl AudioEffect for StereoDelay {
+    fn advertise_parameters(&self) -> &'static [AdvertisedParameter] {
+        StereoDelay::info()
+    }
+
+    fn set_audio_parameters(&mut self, new_config: &AudioConfig) {
+        for delay_buf in self.delay_bufs.borrow_mut().iter_mut() {
+            delay_buf.change_sample_rate(new_config.sample_rate);
+        }
+    }
+
+    fn reset(&mut self) {
+        for delay_buf in self.delay_bufs.borrow_mut().iter_mut() {
+            delay_buf.clear();
+        }
+    }
+
+    fn serialize_state(&self, writer: &mut StateWriter) -> bool {
+        for delay_buf in self.delay_bufs.borrow().iter() {
+            delay_buf.save_state(writer);
+        }
+
+        true
+    }
+
+    fn deserialize_state(&mut self, reader: &mut StateReader) -> bool {
+        self.delay_bufs
+            .get_mut()
+            .iter_mut()
+            .all(|delay_buf| delay_buf.load_state(reader).is_some())
+    }
+
+    fn set_effect_parameter(
+        &mut self,
+        param_idx: usize,
+        param_value: BoardEffectConfigParameterValue,
+    ) {
+        self.params[param_idx] = param_value;
+
+        if param_idx == PARAM_DELAY_TIME_MS {
+            self.update_delay_time();
+        }
+    }
+
+    fn execute(&self, context: &BoardContext, connection_idx: usize, num_samples: usize) {
+        let maybe_bufs = basic_stereo_in_stereo_out(context, connection_idx, num_samples);
+        if let None = maybe_bufs {
+            return;
+        }
+
+        let (read_bufs, mut write_bufs) = maybe_bufs.unwrap();
+
+        let (dryness, wetness) = mathutils::wet_dry_gains(
+            self.params[PARAM_WET_DRY_PCT].as_flt(),
+            self.params[PARAM_MONITOR_MODE].as_enum(),
+        );
+        let feedback = self.params[PARAM_FEEDBACK_PCT].as_flt();
+        let cross_feedback = self.params[PARAM_CROSS_FEEDBACK_PCT].as_flt();
+
+        let mut delay_ref = self.delay_bufs.borrow_mut();
+        for i in 0..num_samples {
+            let xl = read_bufs[0].buf_read(i);
+            let xr = read_bufs[1].buf_read(i);
+
+            let yl = delay_ref[0].read_delayed_sample();
+            let yr = delay_ref[1].read_delayed_sample();
+
+            delay_ref[0].write_sample(xl + feedback * yl + cross_feedback * yr);
+            delay_ref[1].write_sample(xr + feedback * yr + cross_feedback * yl);
+
+            write_bufs[0].buf_write(i, dryness * xl + wetness * yl);
+            write_bufs[1].buf_write(i, dryness * xr + wetness * yr);
+        }
+    }
+}
@@ -5,15 +5,18 @@ use crate::consts::MAX_DELAY_MS;
 use crate::context::BoardContext;
 use crate::traits::AudioEffect;
 
-use crate::effects::basic_single_in_single_out;
+use crate::effects::{basic_single_in_single_out, basic_stereo_in_stereo_out};
+use crate::utils::buf_rw::AudioBufferWriter;
 use crate::utils::delay_buf::DelayBuffer;
-use crate::utils::envelope::EnvelopeDetector;
+use crate::utils::envelope::{EnvelopeDetectMode, EnvelopeDetector};
 use crate::utils::mathutils;
+use crate::utils::peak_window::PeakWindow;
+use crate::utils::state::{StateReader, StateWriter};
 
-use num_derive::ToPrimitive;
+use num_derive::{FromPrimitive, ToPrimitive};
 use num_traits::ToPrimitive;
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 
 const PARAMS: &'static [AdvertisedParameter] = &[
     AdvertisedParameter {
@@ -56,9 +59,146 @@ const PARAMS: &'static [AdvertisedParameter] = &[
         range: ParameterRange::F(0.0f32, MAX_DELAY_MS),
         default_value: BoardEffectConfigParameterValue::F(0.0f32),
     },
+    AdvertisedParameter {
+        // turn off to let the detector see levels above 0 dBFS
+        name: "clamp_envelope?",
+        range: ParameterRange::N(0, 1),
+        default_value: BoardEffectConfigParameterValue::N(1),
+    },
+    AdvertisedParameter {
+        // smoothing for the gain reduction meter (writes[1]). 0 = instant, 1 = VU, 2 = PPM
+        name: "meter_ballistics",
+        range: ParameterRange::N(0, MeterBallistics::__NUM_BALLISTICS as i32 - 1),
+        default_value: BoardEffectConfigParameterValue::N(0),
+    },
+    AdvertisedParameter {
+        // keeps the detector at its peak this long before releasing (stops gates chattering)
+        name: "hold_ms",
+        range: ParameterRange::F(0.0f32, 500.0f32),
+        default_value: BoardEffectConfigParameterValue::F(0.0f32),
+    },
+    AdvertisedParameter {
+        // smoothing for the trigger output (writes[2]): 1 while the detector is at or over the
+        // threshold (the gate is open), 0 otherwise. for keying other effects off this one
+        name: "trigger_smoothing_ms",
+        range: ParameterRange::F(0.0f32, 100.0f32),
+        default_value: BoardEffectConfigParameterValue::F(5.0f32),
+    },
+    AdvertisedParameter {
+        // 0 = knee_width_db is the width in dB, 1 = knee_width_db is per 10 dB of threshold
+        // below 0 dBFS, so a deeper threshold gets a wider knee
+        name: "knee_mode",
+        range: ParameterRange::N(0, KneeMode::__NUM_KNEE_MODES as i32 - 1),
+        default_value: BoardEffectConfigParameterValue::N(KneeMode::Absolute as i32),
+    },
+    AdvertisedParameter {
+        // delays the audio but not the detector, so a gate opens (or a limiter clamps down)
+        // before the transient reaches the output. adds this much latency. the detector hears
+        // the loudest sample between it and the output, so a peak isn't forgotten on its way
+        // out. on top of delay_ms, which delays the detector too
+        name: "lookahead_ms",
+        range: ParameterRange::F(0.0f32, MAX_LOOKAHEAD_MS),
+        default_value: BoardEffectConfigParameterValue::F(0.0f32),
+    },
+    AdvertisedParameter {
+        // 0 = peak, 1 = mean square, 2 = RMS. RMS is smoother on sustained material
+        name: "detect_mode",
+        range: ParameterRange::N(0, EnvelopeDetectMode::__NUM_DETECT_MODES as i32 - 1),
+        default_value: BoardEffectConfigParameterValue::N(EnvelopeDetectMode::Peak as i32),
+    },
+    AdvertisedParameter {
+        // 1 = the release slows down the longer the detector has been over the threshold, up to
+        // AUTO_RELEASE_SLOW_FACTOR x release_time_ms
+        name: "auto_release",
+        range: ParameterRange::N(0, 1),
+        default_value: BoardEffectConfigParameterValue::N(0),
+    },
+    AdvertisedParameter {
+        // 1 = make up the gain a 0 dBFS signal loses at the current threshold/ratio/knee, on top
+        // of output_gain_db. compressor and limiter only
+        name: "auto_makeup",
+        range: ParameterRange::N(0, 1),
+        default_value: BoardEffectConfigParameterValue::N(0),
+    },
+];
+
+// the per band parameters of Dynamics/MSCompressor, mid first then side
+const MS_PARAMS: &'static [AdvertisedParameter] = &[
+    AdvertisedParameter {
+        name: "mid_threshold_db",
+        range: ParameterRange::F(-40.0f32, 0.0f32),
+        default_value: BoardEffectConfigParameterValue::F(-10.0f32),
+    },
+    AdvertisedParameter {
+        name: "mid_ratio",
+        range: ParameterRange::F(1.0f32, 20.0f32),
+        default_value: BoardEffectConfigParameterValue::F(1.0f32),
+    },
+    AdvertisedParameter {
+        name: "mid_knee_width_db",
+        range: ParameterRange::F(0.0f32, 20.0f32),
+        default_value: BoardEffectConfigParameterValue::F(5.0f32),
+    },
+    AdvertisedParameter {
+        name: "mid_attack_time_ms",
+        range: ParameterRange::F(1.0f32, 100.0f32),
+        default_value: BoardEffectConfigParameterValue::F(5.0f32),
+    },
+    AdvertisedParameter {
+        name: "mid_release_time_ms",
+        range: ParameterRange::F(1.0f32, 5000.0f32),
+        default_value: BoardEffectConfigParameterValue::F(500.0f32),
+    },
+    AdvertisedParameter {
+        name: "mid_output_gain_db",
+        range: ParameterRange::F(-20.0f32, 20.0f32),
+        default_value: BoardEffectConfigParameterValue::F(0.0f32),
+    },
+    AdvertisedParameter {
+        name: "side_threshold_db",
+        range: ParameterRange::F(-40.0f32, 0.0f32),
+        default_value: BoardEffectConfigParameterValue::F(-10.0f32),
+    },
+    AdvertisedParameter {
+        name: "side_ratio",
+        range: ParameterRange::F(1.0f32, 20.0f32),
+        default_value: BoardEffectConfigParameterValue::F(1.0f32),
+    },
+    AdvertisedParameter {
+        name: "side_knee_width_db",
+        range: ParameterRange::F(0.0f32, 20.0f32),
+        default_value: BoardEffectConfigParameterValue::F(5.0f32),
+    },
+    AdvertisedParameter {
+        name: "side_attack_time_ms",
+        range: ParameterRange::F(1.0f32, 100.0f32),
+        default_value: BoardEffectConfigParameterValue::F(5.0f32),
+    },
+    AdvertisedParameter {
+        name: "side_release_time_ms",
+        range: ParameterRange::F(1.0f32, 5000.0f32),
+        default_value: BoardEffectConfigParameterValue::F(500.0f32),
+    },
+    AdvertisedParameter {
+        name: "side_output_gain_db",
+        range: ParameterRange::F(-20.0f32, 20.0f32),
+        default_value: BoardEffectConfigParameterValue::F(0.0f32),
+    },
+];
+
+// where each band's MS_PARAMS land in that band's compressor
+const MS_BAND_PARAMS: &'static [usize] = &[
+    PARAM_THRESHOLD_DB,
+    PARAM_RATIO,
+    PARAM_KNEE_WIDTH_DB,
+    PARAM_ATTACK_TIME_MS,
+    PARAM_RELEASE_TIME_MS,
+    PARAM_OUTPUT_GAIN_DB,
 ];
 
-const GAIN_FNS: &'static [fn(f32, &Vec<BoardEffectConfigParameterValue>) -> f32] = &[
+type GainFn = fn(f32, &Vec<BoardEffectConfigParameterValue>) -> f32;
+
+const GAIN_FNS: &'static [GainFn] = &[
     calculate_compressor_gain_hard_knee,
     calculate_limiter_gain_hard_knee,
     calculate_expander_gain_hard_knee,
@@ -77,6 +217,65 @@ const PARAM_RELEASE_TIME_MS: usize = 4;
 const PARAM_OUTPUT_GAIN_DB: usize = 5;
 const PARAM_SOFT_KNEE: usize = 6;
 const PARAM_DELAY_MS: usize = 7;
+const PARAM_CLAMP_ENVELOPE: usize = 8;
+const PARAM_METER_BALLISTICS: usize = 9;
+const PARAM_HOLD_MS: usize = 10;
+const PARAM_TRIGGER_SMOOTHING_MS: usize = 11;
+const PARAM_KNEE_MODE: usize = 12;
+const PARAM_LOOKAHEAD_MS: usize = 13;
+const PARAM_DETECT_MODE: usize = 14;
+const PARAM_AUTO_RELEASE: usize = 15;
+const PARAM_AUTO_MAKEUP: usize = 16;
+
+const MAX_LOOKAHEAD_MS: f32 = 20.0f32;
+
+// the detector reports -inf on digital silence, which would make the gain NaN
+const DETECTOR_FLOOR_DB: f32 = -96.0f32;
+
+// with auto_release on, a long stretch over the threshold releases this many times slower. the
+// sustain time is about how long that stretch has to be
+const AUTO_RELEASE_SLOW_FACTOR: f32 = 6.0f32;
+const AUTO_RELEASE_SUSTAIN_MS: f32 = 300.0f32;
+
+// a relative knee is as wide as an absolute one at this threshold
+const RELATIVE_KNEE_REFERENCE_DB: f32 = 10.0f32;
+
+// one-pole time constants that approximate the standard meters
+// VU: ~300 ms to reach 99% both ways. PPM: fast rise, slow fall
+const VU_TIME_CONSTANT_MS: f32 = 65.0f32;
+const PPM_ATTACK_TIME_MS: f32 = 2.5f32;
+const PPM_RELEASE_TIME_MS: f32 = 650.0f32;
+
+#[derive(Clone, Copy, PartialEq, FromPrimitive)]
+#[allow(non_camel_case_types)]
+pub enum MeterBallistics {
+    Instant = 0,
+    VU,
+    PPM,
+
+    __NUM_BALLISTICS,
+}
+
+impl Default for MeterBallistics {
+    fn default() -> Self {
+        MeterBallistics::Instant
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, FromPrimitive)]
+#[allow(non_camel_case_types)]
+pub enum KneeMode {
+    Absolute = 0,
+    Relative,
+
+    __NUM_KNEE_MODES,
+}
+
+impl Default for KneeMode {
+    fn default() -> Self {
+        KneeMode::Absolute
+    }
+}
 
 #[derive(ToPrimitive)]
 pub enum DynamicsProcessorType {
@@ -86,12 +285,28 @@ pub enum DynamicsProcessorType {
     Gate,
 }
 
+// compresses mid and side on their own. reads = [L, R], writes = [L, R]
+pub struct MidSideCompressor {
+    params: Vec<BoardEffectConfigParameterValue>,
+    mid: Dynamics,
+    side: Dynamics,
+}
+
 pub struct Dynamics {
     params: Vec<BoardEffectConfigParameterValue>,
     envelope_detector: EnvelopeDetector,
     real_output_gain: f32,
     processor_type: DynamicsProcessorType,
     delay: RefCell<DelayBuffer>,
+    lookahead_peak: RefCell<PeakWindow>,
+
+    // gain reduction meter, exported in dB (<= 0) to writes[1] when it is connected
+    meter_ballistics: MeterBallistics,
+    meter_detector: EnvelopeDetector,
+
+    // open/closed trigger, exported to writes[2] when it is connected
+    trigger_coeff: f32,
+    trigger_state: Cell<f32>,
 }
 
 impl Dynamics {
@@ -102,13 +317,20 @@ impl Dynamics {
         ed.set_release_time_ms(params[PARAM_RELEASE_TIME_MS].as_flt());
 
         let output_gain_db = params[PARAM_OUTPUT_GAIN_DB].as_flt();
+        let trigger_coeff =
+            Dynamics::trigger_coeff(ac.sample_rate, params[PARAM_TRIGGER_SMOOTHING_MS].as_flt());
 
         Dynamics {
             params,
             envelope_detector: ed,
             real_output_gain: mathutils::db_to_linear(output_gain_db),
             processor_type: DynamicsProcessorType::Compressor,
-            delay: RefCell::new(DelayBuffer::with_sample_rate(ac.sample_rate)),
+            delay: RefCell::new(Dynamics::new_delay(ac.sample_rate)),
+            lookahead_peak: RefCell::new(Dynamics::new_lookahead_peak(ac.sample_rate)),
+            meter_ballistics: MeterBallistics::Instant,
+            meter_detector: Dynamics::new_meter_detector(ac.sample_rate, MeterBallistics::Instant),
+            trigger_coeff,
+            trigger_state: Cell::new(0.0f32),
         }
     }
 
@@ -119,13 +341,20 @@ impl Dynamics {
         ed.set_release_time_ms(params[PARAM_RELEASE_TIME_MS].as_flt());
 
         let output_gain_db = params[PARAM_OUTPUT_GAIN_DB].as_flt();
+        let trigger_coeff =
+            Dynamics::trigger_coeff(ac.sample_rate, params[PARAM_TRIGGER_SMOOTHING_MS].as_flt());
 
         Dynamics {
             params,
             envelope_detector: ed,
             real_output_gain: mathutils::db_to_linear(output_gain_db),
             processor_type: DynamicsProcessorType::Expander,
-            delay: RefCell::new(DelayBuffer::with_sample_rate(ac.sample_rate)),
+            delay: RefCell::new(Dynamics::new_delay(ac.sample_rate)),
+            lookahead_peak: RefCell::new(Dynamics::new_lookahead_peak(ac.sample_rate)),
+            meter_ballistics: MeterBallistics::Instant,
+            meter_detector: Dynamics::new_meter_detector(ac.sample_rate, MeterBallistics::Instant),
+            trigger_coeff,
+            trigger_state: Cell::new(0.0f32),
         }
     }
 
@@ -136,13 +365,20 @@ impl Dynamics {
         ed.set_release_time_ms(params[PARAM_RELEASE_TIME_MS].as_flt());
 
         let output_gain_db = params[PARAM_OUTPUT_GAIN_DB].as_flt();
+        let trigger_coeff =
+            Dynamics::trigger_coeff(ac.sample_rate, params[PARAM_TRIGGER_SMOOTHING_MS].as_flt());
 
         Dynamics {
             params,
             envelope_detector: ed,
             real_output_gain: mathutils::db_to_linear(output_gain_db),
             processor_type: DynamicsProcessorType::Limiter,
-            delay: RefCell::new(DelayBuffer::with_sample_rate(ac.sample_rate)),
+            delay: RefCell::new(Dynamics::new_delay(ac.sample_rate)),
+            lookahead_peak: RefCell::new(Dynamics::new_lookahead_peak(ac.sample_rate)),
+            meter_ballistics: MeterBallistics::Instant,
+            meter_detector: Dynamics::new_meter_detector(ac.sample_rate, MeterBallistics::Instant),
+            trigger_coeff,
+            trigger_state: Cell::new(0.0f32),
         }
     }
 
@@ -153,13 +389,20 @@ impl Dynamics {
         ed.set_release_time_ms(params[PARAM_RELEASE_TIME_MS].as_flt());
 
         let output_gain_db = params[PARAM_OUTPUT_GAIN_DB].as_flt();
+        let trigger_coeff =
+            Dynamics::trigger_coeff(ac.sample_rate, params[PARAM_TRIGGER_SMOOTHING_MS].as_flt());
 
         Dynamics {
             params,
             envelope_detector: ed,
             real_output_gain: mathutils::db_to_linear(output_gain_db),
             processor_type: DynamicsProcessorType::Gate,
-            delay: RefCell::new(DelayBuffer::with_sample_rate(ac.sample_rate)),
+            delay: RefCell::new(Dynamics::new_delay(ac.sample_rate)),
+            lookahead_peak: RefCell::new(Dynamics::new_lookahead_peak(ac.sample_rate)),
+            meter_ballistics: MeterBallistics::Instant,
+            meter_detector: Dynamics::new_meter_detector(ac.sample_rate, MeterBallistics::Instant),
+            trigger_coeff,
+            trigger_state: Cell::new(0.0f32),
         }
     }
 
@@ -172,9 +415,174 @@ impl Dynamics {
         params
     }
 
+    // the line holds delay_ms + lookahead_ms, so both can be at their max
+    fn new_delay(sample_rate: f32) -> DelayBuffer {
+        DelayBuffer::with_sample_rate_and_max_delay(sample_rate, MAX_DELAY_MS + MAX_LOOKAHEAD_MS)
+    }
+
+    fn new_lookahead_peak(sample_rate: f32) -> PeakWindow {
+        PeakWindow::new((MAX_LOOKAHEAD_MS * sample_rate / 1000.0f32).ceil() as usize + 1)
+    }
+
+    fn lookahead_ms(&self) -> f32 {
+        num::clamp(
+            self.params[PARAM_LOOKAHEAD_MS].as_flt(),
+            0.0f32,
+            MAX_LOOKAHEAD_MS,
+        )
+    }
+
+    // the lookahead is a whole number of samples. a fractional one would smear the peaks it's
+    // there to catch
+    fn lookahead_samples(&self) -> usize {
+        (self.lookahead_ms() * self.envelope_detector.get_sample_rate() / 1000.0f32).round()
+            as usize
+    }
+
+    fn update_delay_time(&mut self) {
+        let lookahead_samples = self.lookahead_samples();
+
+        let delay = self.delay.get_mut();
+        delay.set_delay_time_ms(self.params[PARAM_DELAY_MS].as_flt(), true);
+        let delay_samples = delay.get_delay_sample_count();
+        delay.set_delay_sample_count_directly(
+            delay_samples as i32 + lookahead_samples as i32,
+            delay_samples.fract(),
+        );
+
+        // everything from the detector's tap to the output's
+        self.lookahead_peak
+            .get_mut()
+            .set_length(lookahead_samples + 1);
+    }
+
+    // the static gain curve at 0 dBFS, turned around. 0 when auto_makeup is off
+    fn auto_makeup_db(&self) -> f32 {
+        if self.params[PARAM_AUTO_MAKEUP].as_int() == 0 {
+            return 0.0f32;
+        }
+
+        match self.processor_type {
+            DynamicsProcessorType::Compressor | DynamicsProcessorType::Limiter => {
+                -(self.gain_fn())(0.0f32, &self.params)
+            }
+            _ => 0.0f32,
+        }
+    }
+
+    fn update_output_gain(&mut self) {
+        self.real_output_gain = mathutils::db_to_linear(
+            self.params[PARAM_OUTPUT_GAIN_DB].as_flt() + self.auto_makeup_db(),
+        );
+    }
+
+    fn update_auto_release(&mut self) {
+        let release_time_ms = self.params[PARAM_RELEASE_TIME_MS].as_flt();
+
+        let detector = &mut self.envelope_detector;
+        detector.auto_release = self.params[PARAM_AUTO_RELEASE].as_int() != 0;
+        detector.set_slow_release_time_ms(release_time_ms * AUTO_RELEASE_SLOW_FACTOR);
+        detector.set_sustain_time_ms(AUTO_RELEASE_SUSTAIN_MS);
+        detector.set_auto_release_threshold_db(self.params[PARAM_THRESHOLD_DB].as_flt());
+    }
+
+    fn new_meter_detector(sample_rate: f32, ballistics: MeterBallistics) -> EnvelopeDetector {
+        let mut detector = EnvelopeDetector::new(sample_rate);
+        detector.should_clamp = false;
+        detector.should_return_db = false;
+
+        match ballistics {
+            MeterBallistics::VU => {
+                detector.set_attack_time_ms(VU_TIME_CONSTANT_MS);
+                detector.set_release_time_ms(VU_TIME_CONSTANT_MS);
+            }
+            MeterBallistics::PPM => {
+                detector.set_attack_time_ms(PPM_ATTACK_TIME_MS);
+                detector.set_release_time_ms(PPM_RELEASE_TIME_MS);
+            }
+            _ => (),
+        }
+
+        detector
+    }
+
+    // one pole, 0 ms = no smoothing
+    fn trigger_coeff(sample_rate: f32, smoothing_ms: f32) -> f32 {
+        if smoothing_ms <= 0.0f32 {
+            return 0.0f32;
+        }
+
+        (-1.0f32 / (smoothing_ms * 0.001f32 * sample_rate)).exp()
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Option<()> {
+        self.envelope_detector.load_state(reader)?;
+        self.delay.get_mut().load_state(reader)?;
+        self.lookahead_peak.get_mut().load_state(reader)?;
+        self.meter_detector.load_state(reader)?;
+        self.trigger_state.set(reader.read_f32()?);
+
+        Some(())
+    }
+
     pub fn dynamics_info() -> &'static [AdvertisedParameter] {
         PARAMS
     }
+
+    pub fn get_envelope_detector(&self) -> &EnvelopeDetector {
+        &self.envelope_detector
+    }
+
+    fn gain_fn(&self) -> GainFn {
+        let mut fn_idx = self.processor_type.to_usize().unwrap();
+
+        // a knee with no width is a hard knee (and would divide by zero in the soft knee fns)
+        if self.params[PARAM_SOFT_KNEE].as_int() != 0 && knee_width_db(&self.params) > 0.0f32 {
+            fn_idx += 4;
+        }
+
+        GAIN_FNS[fn_idx]
+    }
+
+    // the detector listens to the sidechain instead of the signal when there is one. the
+    // sidechain isn't delayed, so delay_ms becomes lookahead
+    // otherwise it hears the peak of the line from lookahead_ms ahead of the output up to it
+    // returns (output, gain reduction in dB, detector level in dB)
+    fn process_sample(
+        &self,
+        delay: &mut DelayBuffer,
+        gain_fn: GainFn,
+        xn: f32,
+        sidechain: Option<f32>,
+    ) -> (f32, f32, f32) {
+        let x = delay.read_delayed_sample();
+
+        let detect_x = match sidechain {
+            Some(sidechain) => sidechain,
+            None if self.lookahead_samples() > 0 => {
+                let ahead = delay.read_delayed_sample_at_ms(self.params[PARAM_DELAY_MS].as_flt());
+                self.lookahead_peak.borrow_mut().process(ahead)
+            }
+            None => x,
+        };
+
+        let detect_db = self
+            .envelope_detector
+            .process(detect_x)
+            .max(DETECTOR_FLOOR_DB);
+
+        let gain_db = gain_fn(detect_db, &self.params);
+        let gain_reduction_db = gain_db - detect_db;
+        let gain_reduction = mathutils::db_to_linear(gain_reduction_db);
+
+        delay.write_sample(xn);
+
+        (
+            x * gain_reduction * self.real_output_gain,
+            gain_reduction_db,
+            detect_db,
+        )
+    }
 }
 
 impl AudioEffect for Dynamics {
@@ -183,10 +591,58 @@ impl AudioEffect for Dynamics {
     }
 
     fn set_audio_parameters(&mut self, new_config: &AudioConfig) {
+        // a new detector starts over at the defaults, so everything set on it is reapplied
         self.envelope_detector = EnvelopeDetector::new(new_config.sample_rate);
+        self.envelope_detector
+            .set_attack_time_ms(self.params[PARAM_ATTACK_TIME_MS].as_flt());
+        self.envelope_detector
+            .set_release_time_ms(self.params[PARAM_RELEASE_TIME_MS].as_flt());
+        self.envelope_detector.detect_mode = self.params[PARAM_DETECT_MODE].as_enum();
+        self.envelope_detector.should_clamp = self.params[PARAM_CLAMP_ENVELOPE].as_int() != 0;
+        self.envelope_detector
+            .set_hold_time_ms(self.params[PARAM_HOLD_MS].as_flt());
+        self.update_auto_release();
         self.delay
             .borrow_mut()
             .change_sample_rate(new_config.sample_rate);
+        self.lookahead_peak = RefCell::new(Dynamics::new_lookahead_peak(new_config.sample_rate));
+        self.update_delay_time();
+        self.meter_detector =
+            Dynamics::new_meter_detector(new_config.sample_rate, self.meter_ballistics);
+        self.trigger_coeff = Dynamics::trigger_coeff(
+            new_config.sample_rate,
+            self.params[PARAM_TRIGGER_SMOOTHING_MS].as_flt(),
+        );
+    }
+
+    fn latency_samples(&self) -> usize {
+        self.lookahead_samples()
+    }
+
+    fn max_latency_samples(&self) -> usize {
+        (MAX_LOOKAHEAD_MS * self.envelope_detector.get_sample_rate() / 1000.0f32).round() as usize
+    }
+
+    fn reset(&mut self) {
+        self.envelope_detector.clear();
+        self.delay.get_mut().clear();
+        self.lookahead_peak.get_mut().clear();
+        self.meter_detector.clear();
+        self.trigger_state.set(0.0f32);
+    }
+
+    fn serialize_state(&self, writer: &mut StateWriter) -> bool {
+        self.envelope_detector.save_state(writer);
+        self.delay.borrow().save_state(writer);
+        self.lookahead_peak.borrow().save_state(writer);
+        self.meter_detector.save_state(writer);
+        writer.write_f32(self.trigger_state.get());
+
+        true
+    }
+
+    fn deserialize_state(&mut self, reader: &mut StateReader) -> bool {
+        self.load_state(reader).is_some()
     }
 
     fn set_effect_parameter(
@@ -202,12 +658,41 @@ impl AudioEffect for Dynamics {
         } else if param_idx == PARAM_RELEASE_TIME_MS {
             self.envelope_detector
                 .set_release_time_ms(param_value.as_flt());
-        } else if param_idx == PARAM_OUTPUT_GAIN_DB {
-            self.real_output_gain = mathutils::db_to_linear(param_value.as_flt());
-        } else if param_idx == PARAM_DELAY_MS {
-            self.delay
-                .borrow_mut()
-                .set_delay_time_ms(param_value.as_flt(), true);
+            self.update_auto_release();
+        } else if param_idx == PARAM_THRESHOLD_DB {
+            self.update_auto_release();
+            self.update_output_gain();
+        } else if param_idx == PARAM_AUTO_RELEASE {
+            self.update_auto_release();
+        } else if param_idx == PARAM_OUTPUT_GAIN_DB
+            || param_idx == PARAM_AUTO_MAKEUP
+            || param_idx == PARAM_RATIO
+            || param_idx == PARAM_KNEE_WIDTH_DB
+            || param_idx == PARAM_SOFT_KNEE
+            || param_idx == PARAM_KNEE_MODE
+        {
+            // the makeup follows the gain curve
+            self.update_output_gain();
+        } else if param_idx == PARAM_DELAY_MS || param_idx == PARAM_LOOKAHEAD_MS {
+            self.update_delay_time();
+        } else if param_idx == PARAM_CLAMP_ENVELOPE {
+            self.envelope_detector.should_clamp = param_value.as_int() != 0;
+        } else if param_idx == PARAM_HOLD_MS {
+            self.envelope_detector
+                .set_hold_time_ms(param_value.as_flt());
+        } else if param_idx == PARAM_METER_BALLISTICS {
+            self.meter_ballistics = param_value.as_enum();
+            self.meter_detector = Dynamics::new_meter_detector(
+                self.envelope_detector.get_sample_rate(),
+                self.meter_ballistics,
+            );
+        } else if param_idx == PARAM_DETECT_MODE {
+            self.envelope_detector.detect_mode = param_value.as_enum();
+        } else if param_idx == PARAM_TRIGGER_SMOOTHING_MS {
+            self.trigger_coeff = Dynamics::trigger_coeff(
+                self.envelope_detector.get_sample_rate(),
+                param_value.as_flt(),
+            );
         }
     }
 
@@ -218,27 +703,177 @@ impl AudioEffect for Dynamics {
         }
 
         let (read_buf, mut write_buf) = maybe_bufs.unwrap();
+
+        // reads[1], when connected, is the sidechain the detector listens to (e.g. for ducking)
+        let inputs = context.get_inputs_for_connection(connection_idx);
+        let sidechain_buf = if inputs.len() > 1 {
+            Some(context.get_buffer_for_read(inputs[1]))
+        } else {
+            None
+        };
+
+        let outputs = context.get_outputs_for_connection(connection_idx);
+        let mut meter_buf = if outputs.len() > 1 {
+            context.get_buffer_for_write(outputs[1])
+        } else {
+            AudioBufferWriter::Null
+        };
+        let mut trigger_buf = if outputs.len() > 2 {
+            context.get_buffer_for_write(outputs[2])
+        } else {
+            AudioBufferWriter::Null
+        };
+
+        let threshold_db = self.params[PARAM_THRESHOLD_DB].as_flt();
+        let mut trigger = self.trigger_state.get();
+
+        let gain_fn = self.gain_fn();
+
         let mut delay = self.delay.borrow_mut();
         for i in 0..num_samples {
-            let x = delay.read_delayed_sample();
+            let sidechain = sidechain_buf.as_ref().map(|buf| buf.buf_read(i));
+            let (yn, gain_reduction_db, detect_db) =
+                self.process_sample(&mut delay, gain_fn, read_buf.buf_read(i), sidechain);
+
+            write_buf.buf_write(i, yn);
+
+            let meter_db = if self.meter_ballistics == MeterBallistics::Instant {
+                gain_reduction_db
+            } else {
+                // smooth the depth of the reduction so the meter rises on more reduction
+                -self.meter_detector.process(-gain_reduction_db.min(0.0f32))
+            };
+            meter_buf.buf_write(i, meter_db);
+
+            let open = if detect_db >= threshold_db {
+                1.0f32
+            } else {
+                0.0f32
+            };
+            trigger = open + self.trigger_coeff * (trigger - open);
+            trigger_buf.buf_write(i, trigger);
+        }
 
-            let detect_db = self.envelope_detector.process(x);
+        self.trigger_state.set(trigger);
+    }
+}
 
-            let mut fn_idx = self.processor_type.to_usize().unwrap();
-            if self.params[PARAM_SOFT_KNEE].as_int() != 0 {
-                fn_idx += 4;
-            }
+impl MidSideCompressor {
+    pub fn new(ac: AudioConfig) -> MidSideCompressor {
+        let mut params = Vec::with_capacity(MS_PARAMS.len());
+        for i in 0..MS_PARAMS.len() {
+            params.push(MS_PARAMS[i].default_value);
+        }
+
+        let mut ms = MidSideCompressor {
+            params,
+            mid: Dynamics::new_compressor(ac),
+            side: Dynamics::new_compressor(ac),
+        };
+
+        for i in 0..MS_PARAMS.len() {
+            ms.set_effect_parameter(i, MS_PARAMS[i].default_value);
+        }
+
+        ms
+    }
+
+    pub fn info() -> &'static [AdvertisedParameter] {
+        MS_PARAMS
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Option<()> {
+        self.mid.load_state(reader)?;
+        self.side.load_state(reader)?;
+
+        Some(())
+    }
+}
+
+impl AudioEffect for MidSideCompressor {
+    fn advertise_parameters(&self) -> &'static [AdvertisedParameter] {
+        MidSideCompressor::info()
+    }
+
+    fn set_audio_parameters(&mut self, new_config: &AudioConfig) {
+        self.mid.set_audio_parameters(new_config);
+        self.side.set_audio_parameters(new_config);
+    }
+
+    fn reset(&mut self) {
+        self.mid.reset();
+        self.side.reset();
+    }
+
+    fn serialize_state(&self, writer: &mut StateWriter) -> bool {
+        self.mid.serialize_state(writer) && self.side.serialize_state(writer)
+    }
+
+    fn deserialize_state(&mut self, reader: &mut StateReader) -> bool {
+        self.load_state(reader).is_some()
+    }
 
-            let gain_db = GAIN_FNS[fn_idx](detect_db, &self.params);
-            let gain_reduction_db = gain_db - detect_db;
-            let gain_reduction = mathutils::db_to_linear(gain_reduction_db);
+    fn set_effect_parameter(
+        &mut self,
+        param_idx: usize,
+        param_value: BoardEffectConfigParameterValue,
+    ) {
+        self.params[param_idx] = param_value;
+
+        let band = if param_idx < MS_BAND_PARAMS.len() {
+            &mut self.mid
+        } else {
+            &mut self.side
+        };
+        band.set_effect_parameter(
+            MS_BAND_PARAMS[param_idx % MS_BAND_PARAMS.len()],
+            param_value,
+        );
+    }
+
+    fn execute(&self, context: &BoardContext, connection_idx: usize, num_samples: usize) {
+        let maybe_bufs = basic_stereo_in_stereo_out(context, connection_idx, num_samples);
+        if let None = maybe_bufs {
+            return;
+        }
 
-            delay.write_sample(read_buf.buf_read(i));
-            write_buf.buf_write(i, x * gain_reduction * self.real_output_gain);
+        let (read_bufs, mut write_bufs) = maybe_bufs.unwrap();
+
+        let mid_gain_fn = self.mid.gain_fn();
+        let side_gain_fn = self.side.gain_fn();
+
+        let mut mid_delay = self.mid.delay.borrow_mut();
+        let mut side_delay = self.side.delay.borrow_mut();
+        for i in 0..num_samples {
+            let (mid, side) =
+                mathutils::mid_side_encode(read_bufs[0].buf_read(i), read_bufs[1].buf_read(i));
+
+            let (mid, _, _) = self
+                .mid
+                .process_sample(&mut mid_delay, mid_gain_fn, mid, None);
+            let (side, _, _) = self
+                .side
+                .process_sample(&mut side_delay, side_gain_fn, side, None);
+
+            let (left, right) = mathutils::mid_side_decode(mid, side);
+            write_bufs[0].buf_write(i, left);
+            write_bufs[1].buf_write(i, right);
         }
     }
 }
 
+fn knee_width_db(params: &Vec<BoardEffectConfigParameterValue>) -> f32 {
+    let knee_width = params[PARAM_KNEE_WIDTH_DB].as_flt();
+
+    match params[PARAM_KNEE_MODE].as_enum() {
+        KneeMode::Relative => {
+            let threshold_depth = -params[PARAM_THRESHOLD_DB].as_flt().min(0.0f32);
+            knee_width * threshold_depth / RELATIVE_KNEE_REFERENCE_DB
+        }
+        _ => knee_width,
+    }
+}
+
 fn calculate_compressor_gain_hard_knee(
     detect_db: f32,
     params: &Vec<BoardEffectConfigParameterValue>,
@@ -297,7 +932,7 @@ fn calculate_compressor_gain_soft_knee(
     params: &Vec<BoardEffectConfigParameterValue>,
 ) -> f32 {
     let threshold_db = params[PARAM_THRESHOLD_DB].as_flt();
-    let knee_width = params[PARAM_KNEE_WIDTH_DB].as_flt();
+    let knee_width = knee_width_db(params);
 
     let detect_threshold_diff = detect_db - threshold_db;
     let abs_detect_threshold_diff = detect_threshold_diff.abs();
@@ -320,7 +955,7 @@ fn calculate_limiter_gain_soft_knee(
     params: &Vec<BoardEffectConfigParameterValue>,
 ) -> f32 {
     let threshold_db = params[PARAM_THRESHOLD_DB].as_flt();
-    let knee_width = params[PARAM_KNEE_WIDTH_DB].as_flt();
+    let knee_width = knee_width_db(params);
 
     let detect_threshold_diff = detect_db - threshold_db;
     let abs_detect_threshold_diff = detect_threshold_diff.abs();
@@ -340,7 +975,7 @@ fn calculate_expander_gain_soft_knee(
     params: &Vec<BoardEffectConfigParameterValue>,
 ) -> f32 {
     let threshold_db = params[PARAM_THRESHOLD_DB].as_flt();
-    let knee_width = params[PARAM_KNEE_WIDTH_DB].as_flt();
+    let knee_width = knee_width_db(params);
 
     let detect_threshold_diff = detect_db - threshold_db;
     let abs_detect_threshold_diff = detect_threshold_diff.abs();
@@ -363,7 +998,7 @@ fn calculate_gate_gain_soft_knee(
 ) -> f32 {
     // mostly same as the soft-knee expander except for ratio
     let threshold_db = params[PARAM_THRESHOLD_DB].as_flt();
-    let knee_width = params[PARAM_KNEE_WIDTH_DB].as_flt();
+    let knee_width = knee_width_db(params);
 
     let detect_threshold_diff = detect_db - threshold_db;
     let abs_detect_threshold_diff = detect_threshold_diff.abs();
@@ -0,0 +1,242 @@
+use crate::conf::{
+    AdvertisedParameter, AudioConfig, BoardEffectConfigParameterValue, ParameterRange,
+};
+use crate::context::BoardContext;
+use crate::traits::AudioEffect;
+use crate::utils::{
+    biquad::{Biquad, BiquadCoefficients},
+    mathutils::{self, db_to_linear, MonitorMode},
+    state::{StateReader, StateWriter},
+};
+
+use crate::effects::basic_single_in_single_out;
+
+use std::cell::RefCell;
+
+const PARAMS: &'static [AdvertisedParameter] = &[
+    AdvertisedParameter {
+        // 0 = A, 1 = E, 2 = I, 3 = O, 4 = U. values in between morph between neighbouring vowels
+        name: "vowel",
+        range: ParameterRange::F(0.0f32, (NUM_VOWELS - 1) as f32),
+        default_value: BoardEffectConfigParameterValue::F(0.0f32),
+    },
+    AdvertisedParameter {
+        name: "wet_dry_pct",
+        range: ParameterRange::F(0.0f32, 1.0f32),
+        default_value: BoardEffectConfigParameterValue::F(1.0f32),
+    },
+    AdvertisedParameter {
+        // 0 = wet/dry mix, 1 = wet only, 2 = dry only
+        name: "monitor_mode",
+        range: ParameterRange::N(0, MonitorMode::__NUM_MONITOR_MODES as i32 - 1),
+        default_value: BoardEffectConfigParameterValue::N(MonitorMode::Full as i32),
+    },
+];
+
+const PARAM_VOWEL: usize = 0;
+const PARAM_WET_DRY_PCT: usize = 1;
+const PARAM_MONITOR_MODE: usize = 2;
+
+const NUM_VOWELS: usize = 5;
+const NUM_FORMANTS: usize = 5;
+
+struct Formant {
+    freq_hz: f32,
+    gain_db: f32,
+    bandwidth_hz: f32,
+}
+
+const fn formant(freq_hz: f32, gain_db: f32, bandwidth_hz: f32) -> Formant {
+    Formant {
+        freq_hz,
+        gain_db,
+        bandwidth_hz,
+    }
+}
+
+// bass voice formant table from the Csound manual (Appendix: Formant Values)
+const VOWEL_FORMANTS: [[Formant; NUM_FORMANTS]; NUM_VOWELS] = [
+    // A
+    [
+        formant(600.0f32, 0.0f32, 60.0f32),
+        formant(1040.0f32, -7.0f32, 70.0f32),
+        formant(2250.0f32, -9.0f32, 110.0f32),
+        formant(2450.0f32, -9.0f32, 120.0f32),
+        formant(2750.0f32, -20.0f32, 130.0f32),
+    ],
+    // E
+    [
+        formant(400.0f32, 0.0f32, 40.0f32),
+        formant(1620.0f32, -12.0f32, 80.0f32),
+        formant(2400.0f32, -9.0f32, 100.0f32),
+        formant(2800.0f32, -12.0f32, 120.0f32),
+        formant(3100.0f32, -18.0f32, 120.0f32),
+    ],
+    // I
+    [
+        formant(250.0f32, 0.0f32, 60.0f32),
+        formant(1750.0f32, -30.0f32, 90.0f32),
+        formant(2600.0f32, -16.0f32, 100.0f32),
+        formant(3050.0f32, -22.0f32, 120.0f32),
+        formant(3340.0f32, -28.0f32, 120.0f32),
+    ],
+    // O
+    [
+        formant(400.0f32, 0.0f32, 40.0f32),
+        formant(750.0f32, -11.0f32, 80.0f32),
+        formant(2400.0f32, -21.0f32, 100.0f32),
+        formant(2600.0f32, -20.0f32, 120.0f32),
+        formant(2900.0f32, -40.0f32, 120.0f32),
+    ],
+    // U
+    [
+        formant(350.0f32, 0.0f32, 40.0f32),
+        formant(600.0f32, -20.0f32, 80.0f32),
+        formant(2400.0f32, -32.0f32, 100.0f32),
+        formant(2675.0f32, -28.0f32, 120.0f32),
+        formant(2950.0f32, -36.0f32, 120.0f32),
+    ],
+];
+
+// parallel band-pass filters, one per formant, summed with per-formant gains
+pub struct FormantFilter {
+    params: Vec<BoardEffectConfigParameterValue>,
+    sample_rate: f32,
+
+    formant_gains: [f32; NUM_FORMANTS],
+    formant_filters: RefCell<Vec<Biquad>>,
+}
+
+impl FormantFilter {
+    pub fn new(ac: AudioConfig) -> FormantFilter {
+        let mut params = Vec::with_capacity(PARAMS.len());
+        for i in 0..PARAMS.len() {
+            params.push(PARAMS[i].default_value);
+        }
+
+        let mut formant_filters = Vec::with_capacity(NUM_FORMANTS);
+        for f in VOWEL_FORMANTS[0].iter() {
+            formant_filters.push(Biquad::new(BiquadCoefficients::second_order_bpf(
+                f.freq_hz,
+                ac.sample_rate,
+                Some(f.freq_hz / f.bandwidth_hz),
+            )));
+        }
+
+        let mut formant_filter = FormantFilter {
+            params,
+            sample_rate: ac.sample_rate,
+            formant_gains: [0.0f32; NUM_FORMANTS],
+            formant_filters: RefCell::new(formant_filters),
+        };
+
+        formant_filter.update_formants();
+
+        formant_filter
+    }
+
+    pub fn info() -> &'static [AdvertisedParameter] {
+        PARAMS
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Option<()> {
+        for filter in self.formant_filters.get_mut().iter_mut() {
+            filter.load_state(reader)?;
+        }
+
+        Some(())
+    }
+
+    fn update_formants(&mut self) {
+        let vowel = num::clamp(
+            self.params[PARAM_VOWEL].as_flt(),
+            0.0f32,
+            (NUM_VOWELS - 1) as f32,
+        );
+
+        let (ipart, fpart) = mathutils::vmodf(vowel);
+        let from = &VOWEL_FORMANTS[ipart as usize];
+        let to = &VOWEL_FORMANTS[(ipart as usize + 1).min(NUM_VOWELS - 1)];
+
+        let mut formant_filters = self.formant_filters.borrow_mut();
+        for i in 0..NUM_FORMANTS {
+            let freq_hz = mathutils::lerp(from[i].freq_hz, to[i].freq_hz, fpart);
+            let bandwidth_hz = mathutils::lerp(from[i].bandwidth_hz, to[i].bandwidth_hz, fpart);
+            let gain_db = mathutils::lerp(from[i].gain_db, to[i].gain_db, fpart);
+
+            self.formant_gains[i] = db_to_linear(gain_db);
+            formant_filters[i].change_params(BiquadCoefficients::second_order_bpf(
+                freq_hz,
+                self.sample_rate,
+                Some(freq_hz / bandwidth_hz),
+            ));
+        }
+    }
+}
+
+impl AudioEffect for FormantFilter {
+    fn advertise_parameters(&self) -> &'static [AdvertisedParameter] {
+        FormantFilter::info()
+    }
+
+    fn set_audio_parameters(&mut self, new_config: &AudioConfig) {
+        self.sample_rate = new_config.sample_rate;
+        self.update_formants();
+    }
+
+    fn reset(&mut self) {
+        for filter in self.formant_filters.get_mut().iter_mut() {
+            filter.clear();
+        }
+    }
+
+    fn serialize_state(&self, writer: &mut StateWriter) -> bool {
+        for filter in self.formant_filters.borrow().iter() {
+            filter.save_state(writer);
+        }
+
+        true
+    }
+
+    fn deserialize_state(&mut self, reader: &mut StateReader) -> bool {
+        self.load_state(reader).is_some()
+    }
+
+    fn set_effect_parameter(
+        &mut self,
+        param_idx: usize,
+        param_value: BoardEffectConfigParameterValue,
+    ) {
+        self.params[param_idx] = param_value;
+
+        if param_idx == PARAM_VOWEL {
+            self.update_formants();
+        }
+    }
+
+    fn execute(&self, context: &BoardContext, connection_idx: usize, num_samples: usize) {
+        let maybe_bufs = basic_single_in_single_out(context, connection_idx, num_samples);
+        if let None = maybe_bufs {
+            return;
+        }
+
+        let (read_buf, mut write_buf) = maybe_bufs.unwrap();
+
+        let (dryness, wetness) = mathutils::wet_dry_gains(
+            self.params[PARAM_WET_DRY_PCT].as_flt(),
+            self.params[PARAM_MONITOR_MODE].as_enum(),
+        );
+
+        let mut formant_filters = self.formant_filters.borrow_mut();
+        for i in 0..num_samples {
+            let xn = read_buf.buf_read(i);
+
+            let mut yn = 0.0f32;
+            for (filter, gain) in formant_filters.iter_mut().zip(self.formant_gains.iter()) {
+                yn += gain * filter.filter(xn);
+            }
+
+            write_buf.buf_write(i, dryness * xn + wetness * yn);
+        }
+    }
+}
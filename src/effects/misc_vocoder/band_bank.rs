@@ -0,0 +1,252 @@
+use crate::conf::{
+    AdvertisedParameter, AudioConfig, BoardEffectConfigParameterValue, ParameterRange,
+};
+use crate::context::BoardContext;
+use crate::traits::AudioEffect;
+use crate::utils::biquad::{Biquad, BiquadCoefficients};
+use crate::utils::envelope::EnvelopeDetector;
+use crate::utils::mathutils::db_to_linear;
+use crate::utils::state::{StateReader, StateWriter};
+
+use std::cell::RefCell;
+
+// every band is allocated up front, num_bands only picks how many run
+const MAX_BANDS: usize = 32;
+
+const PARAMS: &'static [AdvertisedParameter] = &[
+    AdvertisedParameter {
+        name: "num_bands",
+        range: ParameterRange::N(4, MAX_BANDS as i32),
+        default_value: BoardEffectConfigParameterValue::N(16),
+    },
+    AdvertisedParameter {
+        // center of the lowest band. the others are spaced evenly in log frequency up to high_freq_hz
+        name: "low_freq_hz",
+        range: ParameterRange::F(40.0f32, 1000.0f32),
+        default_value: BoardEffectConfigParameterValue::F(100.0f32),
+    },
+    AdvertisedParameter {
+        name: "high_freq_hz",
+        range: ParameterRange::F(1000.0f32, 16000.0f32),
+        default_value: BoardEffectConfigParameterValue::F(8000.0f32),
+    },
+    AdvertisedParameter {
+        // narrower bands are more intelligible but ring (and lag) more
+        name: "band_q",
+        range: ParameterRange::F(1.0f32, 30.0f32),
+        default_value: BoardEffectConfigParameterValue::F(8.0f32),
+    },
+    AdvertisedParameter {
+        name: "attack_time_ms",
+        range: ParameterRange::F(0.0f32, 100.0f32),
+        default_value: BoardEffectConfigParameterValue::F(0.0f32),
+    },
+    AdvertisedParameter {
+        name: "release_time_ms",
+        range: ParameterRange::F(1.0f32, 500.0f32),
+        default_value: BoardEffectConfigParameterValue::F(20.0f32),
+    },
+    AdvertisedParameter {
+        name: "output_gain_db",
+        range: ParameterRange::F(-20.0f32, 20.0f32),
+        default_value: BoardEffectConfigParameterValue::F(0.0f32),
+    },
+];
+
+const PARAM_NUM_BANDS: usize = 0;
+const PARAM_LOW_FREQ_HZ: usize = 1;
+const PARAM_HIGH_FREQ_HZ: usize = 2;
+const PARAM_BAND_Q: usize = 3;
+const PARAM_ATTACK_TIME_MS: usize = 4;
+const PARAM_RELEASE_TIME_MS: usize = 5;
+const PARAM_OUTPUT_GAIN_DB: usize = 6;
+
+struct VocoderBand {
+    modulator_filter: Biquad,
+    carrier_filter: Biquad,
+    envelope: EnvelopeDetector,
+}
+
+// classic channel vocoder with no fft, so no frame of latency
+// reads = [modulator, carrier], writes = [output]
+// each band's carrier is scaled by the level of the modulator in the same band
+pub struct BandBankVocoder {
+    params: Vec<BoardEffectConfigParameterValue>,
+    sample_rate: f32,
+    num_bands: usize,
+    output_gain: f32,
+
+    bands: RefCell<Vec<VocoderBand>>,
+}
+
+impl BandBankVocoder {
+    pub fn new(ac: AudioConfig) -> BandBankVocoder {
+        let mut params = Vec::with_capacity(PARAMS.len());
+        for i in 0..PARAMS.len() {
+            params.push(PARAMS[i].default_value);
+        }
+
+        let mut bands = Vec::with_capacity(MAX_BANDS);
+        for _ in 0..MAX_BANDS {
+            let coeff = BiquadCoefficients::second_order_bpf(1000.0f32, ac.sample_rate, None);
+            bands.push(VocoderBand {
+                modulator_filter: Biquad::new(coeff.clone()),
+                carrier_filter: Biquad::new(coeff),
+                envelope: EnvelopeDetector::new(ac.sample_rate),
+            });
+        }
+
+        let mut vocoder = BandBankVocoder {
+            params,
+            sample_rate: ac.sample_rate,
+            num_bands: 0,
+            output_gain: 1.0f32,
+            bands: RefCell::new(bands),
+        };
+
+        vocoder.update_bands();
+        vocoder.update_envelopes(true);
+        vocoder
+    }
+
+    pub fn info() -> &'static [AdvertisedParameter] {
+        PARAMS
+    }
+
+    fn update_bands(&mut self) {
+        self.num_bands = (self.params[PARAM_NUM_BANDS].as_int() as usize)
+            .max(2)
+            .min(MAX_BANDS);
+        self.output_gain = db_to_linear(self.params[PARAM_OUTPUT_GAIN_DB].as_flt());
+
+        let low_freq = self.params[PARAM_LOW_FREQ_HZ].as_flt();
+        let high_freq = self.params[PARAM_HIGH_FREQ_HZ]
+            .as_flt()
+            .min(0.45f32 * self.sample_rate);
+        let q = self.params[PARAM_BAND_Q].as_flt();
+        let band_ratio = (high_freq / low_freq).powf(1.0f32 / (self.num_bands - 1) as f32);
+
+        let mut bands = self.bands.borrow_mut();
+        for (i, band) in bands.iter_mut().take(self.num_bands).enumerate() {
+            let center_freq = low_freq * band_ratio.powi(i as i32);
+            let coeff =
+                BiquadCoefficients::second_order_bpf(center_freq, self.sample_rate, Some(q));
+            band.modulator_filter.change_params(coeff.clone());
+            band.carrier_filter.change_params(coeff);
+        }
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Option<()> {
+        for band in self.bands.get_mut().iter_mut() {
+            band.modulator_filter.load_state(reader)?;
+            band.carrier_filter.load_state(reader)?;
+            band.envelope.load_state(reader)?;
+        }
+
+        Some(())
+    }
+
+    // envelope detectors only know their sample rate at creation, so a new rate means new ones
+    fn update_envelopes(&mut self, sample_rate_changed: bool) {
+        let attack_time_ms = self.params[PARAM_ATTACK_TIME_MS].as_flt();
+        let release_time_ms = self.params[PARAM_RELEASE_TIME_MS].as_flt();
+
+        for band in self.bands.borrow_mut().iter_mut() {
+            if sample_rate_changed {
+                band.envelope = EnvelopeDetector::new(self.sample_rate);
+                band.envelope.should_clamp = false;
+                band.envelope.should_return_db = false;
+            }
+
+            band.envelope.set_attack_time_ms(attack_time_ms);
+            band.envelope.set_release_time_ms(release_time_ms);
+        }
+    }
+}
+
+impl AudioEffect for BandBankVocoder {
+    fn advertise_parameters(&self) -> &'static [AdvertisedParameter] {
+        BandBankVocoder::info()
+    }
+
+    fn set_audio_parameters(&mut self, new_config: &AudioConfig) {
+        self.sample_rate = new_config.sample_rate;
+        self.update_bands();
+        self.update_envelopes(true);
+    }
+
+    fn reset(&mut self) {
+        for band in self.bands.get_mut().iter_mut() {
+            band.modulator_filter.clear();
+            band.carrier_filter.clear();
+            band.envelope.clear();
+        }
+    }
+
+    // every band, so changing num_bands after a restore picks up where those bands were
+    fn serialize_state(&self, writer: &mut StateWriter) -> bool {
+        for band in self.bands.borrow().iter() {
+            band.modulator_filter.save_state(writer);
+            band.carrier_filter.save_state(writer);
+            band.envelope.save_state(writer);
+        }
+
+        true
+    }
+
+    fn deserialize_state(&mut self, reader: &mut StateReader) -> bool {
+        self.load_state(reader).is_some()
+    }
+
+    fn set_effect_parameter(
+        &mut self,
+        param_idx: usize,
+        param_value: BoardEffectConfigParameterValue,
+    ) {
+        self.params[param_idx] = param_value;
+
+        if param_idx == PARAM_ATTACK_TIME_MS || param_idx == PARAM_RELEASE_TIME_MS {
+            self.update_envelopes(false);
+        } else {
+            self.update_bands();
+        }
+    }
+
+    fn execute(&self, context: &BoardContext, connection_idx: usize, num_samples: usize) {
+        let inputs = context.get_inputs_for_connection(connection_idx);
+        let outputs = context.get_outputs_for_connection(connection_idx);
+        if outputs.len() < 1 {
+            return;
+        }
+
+        let mut write_buf = context.get_buffer_for_write(outputs[0]);
+
+        // without a carrier there's nothing to shape
+        if inputs.len() < 2 {
+            for i in 0..num_samples {
+                write_buf.buf_write(i, 0.0f32);
+            }
+
+            return;
+        }
+
+        let modulator_buf = context.get_buffer_for_read(inputs[0]);
+        let carrier_buf = context.get_buffer_for_read(inputs[1]);
+
+        let mut bands = self.bands.borrow_mut();
+        for i in 0..num_samples {
+            let modulator = modulator_buf.buf_read(i);
+            let carrier = carrier_buf.buf_read(i);
+
+            let mut yn = 0.0f32;
+            for band in bands[..self.num_bands].iter_mut() {
+                let level = band
+                    .envelope
+                    .process(band.modulator_filter.filter(modulator));
+                yn += level * band.carrier_filter.filter(carrier);
+            }
+
+            write_buf.buf_write(i, self.output_gain * yn);
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use crate::conf::{AdvertisedParameter, BoardEffectConfigParameterValue, ParameterRange};
+use crate::effects::VocoderContext;
+use crate::effects::vocoder2::MIX_PARAM;
+use crate::traits::FrequencyDomainAudioEffect;
+use crate::utils::mathutils::{vcosf, vsinf};
+use fftw::array::AlignedVec;
+use fftw::types::c32;
+
+use std::cell::{Cell, RefCell};
+
+const PARAMS: &[AdvertisedParameter] = &[
+    AdvertisedParameter {
+        // 1 = hold the last spectrum heard before freezing and keep playing it
+        name: "frozen",
+        range: ParameterRange::N(0, 1),
+        default_value: BoardEffectConfigParameterValue::N(0),
+    },
+    MIX_PARAM,
+];
+
+const PARAM_FROZEN: usize = 0;
+
+// sustains whatever was playing when frozen turns on. unfrozen, audio passes through
+pub struct Freeze {
+    is_frozen: bool,
+
+    hop_size: usize,
+
+    // the last frame heard while unfrozen. sized in post_initialize
+    held_frame: RefCell<AlignedVec<c32>>,
+    // hops since freezing, mod the frame size. each bin's phase has moved on by its bin
+    // frequency times this many hops
+    hops_frozen: Cell<usize>,
+    // e^(i * 2pi * j / N). looked up instead of rotating the held frame over and over, which
+    // would slowly drift its magnitudes
+    unity_roots: AlignedVec<c32>,
+}
+
+impl Freeze {
+    pub fn new() -> Freeze {
+        Freeze {
+            is_frozen: PARAMS[PARAM_FROZEN].default_value.as_int() != 0,
+            hop_size: 1,
+            held_frame: RefCell::new(AlignedVec::new(1)),
+            hops_frozen: Cell::new(0),
+            unity_roots: AlignedVec::new(1),
+        }
+    }
+
+    pub fn info() -> &'static [AdvertisedParameter] {
+        PARAMS
+    }
+}
+
+impl FrequencyDomainAudioEffect for Freeze {
+    fn advertise_parameters(&self) -> &'static [AdvertisedParameter] {
+        Freeze::info()
+    }
+
+    fn post_initialize(&mut self, vocoder_context: &VocoderContext) {
+        let frame_size = vocoder_context.frame_size;
+
+        let mut unity_roots = AlignedVec::new(frame_size);
+        for j in 0..frame_size {
+            let phase = j as f32 * crate::utils::TWO_PI / frame_size as f32;
+            unity_roots[j] = c32::new(vcosf(phase), vsinf(phase));
+        }
+
+        self.hop_size = vocoder_context.hop_size;
+        self.unity_roots = unity_roots;
+        *self.held_frame.borrow_mut() = AlignedVec::new(frame_size);
+        self.hops_frozen.set(0);
+    }
+
+    // forgets the held frame, but stays frozen if it was
+    fn reset(&mut self) {
+        for bin in self.held_frame.get_mut().iter_mut() {
+            *bin = c32::new(0.0f32, 0.0f32);
+        }
+
+        self.hops_frozen.set(0);
+    }
+
+    fn set_effect_parameter(
+        &mut self,
+        param_idx: usize,
+        param_value: BoardEffectConfigParameterValue,
+    ) {
+        if param_idx == PARAM_FROZEN {
+            self.is_frozen = param_value.as_int() != 0;
+        }
+    }
+
+    fn execute(&self, fft: &AlignedVec<c32>, output: &mut AlignedVec<c32>) {
+        let mut held_frame = self.held_frame.borrow_mut();
+        if held_frame.len() != fft.len() {
+            for i in 0..fft.len() {
+                output[i] = fft[i];
+            }
+
+            return;
+        }
+
+        let frame_size = fft.len();
+        if !self.is_frozen {
+            for i in 0..frame_size {
+                held_frame[i] = fft[i];
+                output[i] = fft[i];
+            }
+
+            self.hops_frozen.set(0);
+            return;
+        }
+
+        let hops_frozen = (self.hops_frozen.get() + 1) % frame_size;
+        self.hops_frozen.set(hops_frozen);
+
+        // bin k has turned k * hop * hops_frozen / N times. bin N - k turns the other way, so
+        // the frame stays the spectrum of a real signal
+        let advance_per_bin = self.hop_size * hops_frozen % frame_size;
+        for k in 0..frame_size {
+            output[k] = held_frame[k] * self.unity_roots[k * advance_per_bin % frame_size];
+        }
+    }
+
+    fn post_process(&self, _ifft: &mut AlignedVec<c32>) {}
+}
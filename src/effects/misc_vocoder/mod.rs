@@ -1,4 +1,10 @@
+mod band_bank;
+mod freeze;
 mod robotize;
+mod spectral_compressor;
+pub mod spectral_eq;
+mod spectral_gate;
+pub mod spectral_smoothing;
 mod whisper;
 
 
@@ -6,5 +12,10 @@ mod whisper;
 // and whisper is particularly scary sounding
 
 // Both effects are part of the vocoder example in Bela
+pub use band_bank::BandBankVocoder;
+pub use freeze::Freeze;
 pub use robotize::Robotize;
+pub use spectral_compressor::SpectralCompressor;
+pub use spectral_eq::SpectralEQ;
+pub use spectral_gate::SpectralGate;
 pub use whisper::Whisper;
\ No newline at end of file
@@ -1,11 +1,12 @@
 use crate::conf::{AdvertisedParameter, BoardEffectConfigParameterValue};
 use crate::effects::VocoderContext;
+use crate::effects::vocoder2::MIX_PARAM;
 use crate::traits::FrequencyDomainAudioEffect;
 use crate::utils::mathutils::vsqrtf;
 use fftw::array::AlignedVec;
 use fftw::types::c32;
 
-const PARAMS: &[AdvertisedParameter] = &[];
+const PARAMS: &[AdvertisedParameter] = &[MIX_PARAM];
 
 pub struct Robotize {}
 
@@ -0,0 +1,228 @@
+use crate::conf::{AdvertisedParameter, BoardEffectConfigParameterValue, ParameterRange};
+use crate::effects::misc_vocoder::spectral_smoothing::{smooth_bin_gains, MAX_SMOOTHING_BINS};
+use crate::effects::VocoderContext;
+use crate::effects::vocoder2::MIX_PARAM;
+use crate::traits::FrequencyDomainAudioEffect;
+use crate::utils::envelope::ANALOG_RC_TIME_CONSTANT;
+use crate::utils::mathutils::{self, db_to_linear};
+use fftw::array::AlignedVec;
+use fftw::types::c32;
+
+use std::cell::RefCell;
+
+const PARAMS: &[AdvertisedParameter] = &[
+    AdvertisedParameter {
+        // compared against each bin's level, scaled so a full scale sine at the bin reads 0 dB
+        name: "threshold_db",
+        range: ParameterRange::F(-60.0f32, 0.0f32),
+        default_value: BoardEffectConfigParameterValue::F(-20.0f32),
+    },
+    AdvertisedParameter {
+        name: "ratio",
+        range: ParameterRange::F(1.0f32, 20.0f32),
+        default_value: BoardEffectConfigParameterValue::F(4.0f32),
+    },
+    AdvertisedParameter {
+        name: "attack_time_ms",
+        range: ParameterRange::F(0.0f32, 500.0f32),
+        default_value: BoardEffectConfigParameterValue::F(10.0f32),
+    },
+    AdvertisedParameter {
+        name: "release_time_ms",
+        range: ParameterRange::F(0.0f32, 2000.0f32),
+        default_value: BoardEffectConfigParameterValue::F(100.0f32),
+    },
+    AdvertisedParameter {
+        // caps each bin at the threshold instead (ratio and attack are ignored).
+        // only the magnitude changes, so phase and transients come through untouched
+        name: "limiter?",
+        range: ParameterRange::N(0, 1),
+        default_value: BoardEffectConfigParameterValue::N(0),
+    },
+    AdvertisedParameter {
+        // each bin's gain is averaged with this many bins either side. cuts down musical noise
+        name: "smoothing_bins",
+        range: ParameterRange::N(0, MAX_SMOOTHING_BINS as i32),
+        default_value: BoardEffectConfigParameterValue::N(0),
+    },
+    MIX_PARAM,
+];
+
+const PARAM_THRESHOLD_DB: usize = 0;
+const PARAM_RATIO: usize = 1;
+const PARAM_ATTACK_TIME_MS: usize = 2;
+const PARAM_RELEASE_TIME_MS: usize = 3;
+const PARAM_LIMITER: usize = 4;
+const PARAM_SMOOTHING_BINS: usize = 5;
+
+// keeps silent bins out of log10(0)
+const MIN_BIN_LEVEL: f32 = 1e-9f32;
+
+// compresses every fft bin on its own. envelopes update once per hop
+pub struct SpectralCompressor {
+    sample_rate: f32,
+    hop_size: usize,
+
+    threshold_db: f32,
+    ratio: f32,
+    attack_time_ms: f32,
+    release_time_ms: f32,
+    is_limiter: bool,
+    smoothing_bins: usize,
+    attack_coefficient: f32,
+    release_coefficient: f32,
+
+    // turns a bin magnitude into the amplitude of the sine that produced it
+    magnitude_scale: f32,
+
+    // one envelope per fft bin. sized in post_initialize
+    bin_envelopes: RefCell<Vec<f32>>,
+    // per bin gains for the frame, before and after smoothing
+    bin_gains: RefCell<Vec<f32>>,
+    smoothed_gains: RefCell<Vec<f32>>,
+}
+
+impl SpectralCompressor {
+    pub fn new(sample_rate: f32) -> SpectralCompressor {
+        SpectralCompressor {
+            sample_rate,
+            hop_size: 1,
+            threshold_db: PARAMS[PARAM_THRESHOLD_DB].default_value.as_flt(),
+            ratio: PARAMS[PARAM_RATIO].default_value.as_flt(),
+            attack_time_ms: PARAMS[PARAM_ATTACK_TIME_MS].default_value.as_flt(),
+            release_time_ms: PARAMS[PARAM_RELEASE_TIME_MS].default_value.as_flt(),
+            is_limiter: PARAMS[PARAM_LIMITER].default_value.as_int() != 0,
+            smoothing_bins: PARAMS[PARAM_SMOOTHING_BINS].default_value.as_int() as usize,
+            attack_coefficient: 0.0f32,
+            release_coefficient: 0.0f32,
+            magnitude_scale: 1.0f32,
+            bin_envelopes: RefCell::new(Vec::new()),
+            bin_gains: RefCell::new(Vec::new()),
+            smoothed_gains: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub fn info() -> &'static [AdvertisedParameter] {
+        PARAMS
+    }
+
+    fn time_coefficient(&self, time_ms: f32) -> f32 {
+        if time_ms <= 0.0f32 {
+            return 0.0f32;
+        }
+
+        let hops_per_second = self.sample_rate / self.hop_size as f32;
+        mathutils::vexpf(ANALOG_RC_TIME_CONSTANT / (time_ms * hops_per_second * 0.001f32))
+    }
+
+    fn update_time_coefficients(&mut self) {
+        self.attack_coefficient = self.time_coefficient(self.attack_time_ms);
+        self.release_coefficient = self.time_coefficient(self.release_time_ms);
+    }
+}
+
+impl FrequencyDomainAudioEffect for SpectralCompressor {
+    fn advertise_parameters(&self) -> &'static [AdvertisedParameter] {
+        SpectralCompressor::info()
+    }
+
+    fn post_initialize(&mut self, vocoder_context: &VocoderContext) {
+        self.hop_size = vocoder_context.hop_size;
+        self.update_time_coefficients();
+
+        let window_sum: f32 = vocoder_context.analysis_window.iter().sum();
+        if window_sum > 0.0f32 {
+            self.magnitude_scale = 2.0f32 / window_sum;
+        }
+
+        *self.bin_envelopes.borrow_mut() = vec![0.0f32; vocoder_context.frame_size];
+        *self.bin_gains.borrow_mut() = vec![1.0f32; vocoder_context.frame_size];
+        *self.smoothed_gains.borrow_mut() = vec![1.0f32; vocoder_context.frame_size];
+    }
+
+    fn reset(&mut self) {
+        for envelope in self.bin_envelopes.get_mut().iter_mut() {
+            *envelope = 0.0f32;
+        }
+
+        for gain in self
+            .bin_gains
+            .get_mut()
+            .iter_mut()
+            .chain(self.smoothed_gains.get_mut().iter_mut())
+        {
+            *gain = 1.0f32;
+        }
+    }
+
+    fn set_effect_parameter(
+        &mut self,
+        param_idx: usize,
+        param_value: BoardEffectConfigParameterValue,
+    ) {
+        if param_idx == PARAM_THRESHOLD_DB {
+            self.threshold_db = param_value.as_flt();
+        } else if param_idx == PARAM_RATIO {
+            self.ratio = param_value.as_flt().max(1.0f32);
+        } else if param_idx == PARAM_ATTACK_TIME_MS {
+            self.attack_time_ms = param_value.as_flt();
+            self.update_time_coefficients();
+        } else if param_idx == PARAM_RELEASE_TIME_MS {
+            self.release_time_ms = param_value.as_flt();
+            self.update_time_coefficients();
+        } else if param_idx == PARAM_LIMITER {
+            self.is_limiter = param_value.as_int() != 0;
+        } else if param_idx == PARAM_SMOOTHING_BINS {
+            self.smoothing_bins = param_value.as_int().max(0) as usize;
+        }
+    }
+
+    fn execute(&self, fft: &AlignedVec<c32>, output: &mut AlignedVec<c32>) {
+        let mut bin_envelopes = self.bin_envelopes.borrow_mut();
+        let mut bin_gains = self.bin_gains.borrow_mut();
+        let mut smoothed_gains = self.smoothed_gains.borrow_mut();
+        if bin_envelopes.len() != fft.len() {
+            for i in 0..fft.len() {
+                output[i] = fft[i];
+            }
+
+            return;
+        }
+
+        let slope = 1.0f32 - 1.0f32 / self.ratio;
+
+        for i in 0..fft.len() {
+            let level = fft[i].norm() * self.magnitude_scale;
+
+            let last_envelope = bin_envelopes[i];
+            let coefficient = if level > last_envelope {
+                // a limiter can't let the first frame over the threshold through
+                if self.is_limiter {
+                    0.0f32
+                } else {
+                    self.attack_coefficient
+                }
+            } else {
+                self.release_coefficient
+            };
+            let envelope = coefficient * (last_envelope - level) + level;
+            bin_envelopes[i] = envelope;
+
+            let envelope_db = 20.0f32 * envelope.max(MIN_BIN_LEVEL).log10();
+            bin_gains[i] = if envelope_db <= self.threshold_db {
+                1.0f32
+            } else if self.is_limiter {
+                db_to_linear(self.threshold_db - envelope_db)
+            } else {
+                db_to_linear(slope * (self.threshold_db - envelope_db))
+            };
+        }
+
+        smooth_bin_gains(&bin_gains, self.smoothing_bins, &mut smoothed_gains);
+        for i in 0..fft.len() {
+            output[i] = fft[i] * smoothed_gains[i];
+        }
+    }
+
+    fn post_process(&self, _ifft: &mut AlignedVec<c32>) {}
+}
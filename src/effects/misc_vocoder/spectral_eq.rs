@@ -0,0 +1,142 @@
+use crate::conf::{AdvertisedParameter, BoardEffectConfigParameterValue, ParameterRange};
+use crate::effects::misc_vocoder::spectral_smoothing::{smooth_bin_gains, MAX_SMOOTHING_BINS};
+use crate::effects::VocoderContext;
+use crate::effects::vocoder2::MIX_PARAM;
+use crate::traits::FrequencyDomainAudioEffect;
+use crate::utils::mathutils::db_to_linear;
+use fftw::array::AlignedVec;
+use fftw::types::c32;
+
+pub const NUM_EQ_BANDS: usize = 10;
+
+const PARAMS: &[AdvertisedParameter] = &[
+    AdvertisedParameter {
+        // one gain per octave band, centered at 31.25 Hz, 62.5 Hz, ... 16 kHz
+        name: "band_gains_db",
+        range: ParameterRange::FArray(NUM_EQ_BANDS, -24.0f32, 24.0f32),
+        default_value: BoardEffectConfigParameterValue::F(0.0f32),
+    },
+    AdvertisedParameter {
+        // softens the steps between bands by averaging each bin's gain with its neighbours
+        name: "smoothing_bins",
+        range: ParameterRange::N(0, MAX_SMOOTHING_BINS as i32),
+        default_value: BoardEffectConfigParameterValue::N(0),
+    },
+    MIX_PARAM,
+];
+
+const PARAM_BAND_GAINS_DB: usize = 0;
+const PARAM_SMOOTHING_BINS: usize = 1;
+
+const LOWEST_BAND_CENTER_HZ: f32 = 31.25f32;
+
+// octave band graphic eq applied directly to the fft bins
+pub struct SpectralEQ {
+    sample_rate: f32,
+    band_gains_db: [f32; NUM_EQ_BANDS],
+    smoothing_bins: usize,
+
+    // linear gain per fft bin, before and after smoothing. sized in post_initialize
+    band_gains: Vec<f32>,
+    bin_gains: Vec<f32>,
+}
+
+impl SpectralEQ {
+    pub fn new(sample_rate: f32) -> SpectralEQ {
+        SpectralEQ {
+            sample_rate,
+            band_gains_db: [PARAMS[PARAM_BAND_GAINS_DB].default_value.as_flt(); NUM_EQ_BANDS],
+            smoothing_bins: PARAMS[PARAM_SMOOTHING_BINS].default_value.as_int() as usize,
+            band_gains: Vec::new(),
+            bin_gains: Vec::new(),
+        }
+    }
+
+    pub fn info() -> &'static [AdvertisedParameter] {
+        PARAMS
+    }
+
+    pub fn band_for_freq(freq: f32) -> usize {
+        if freq <= LOWEST_BAND_CENTER_HZ {
+            return 0;
+        }
+
+        let band = (freq / LOWEST_BAND_CENTER_HZ).log2().round() as usize;
+        band.min(NUM_EQ_BANDS - 1)
+    }
+
+    // the gains only change with the parameters, so the smoothing happens here and not per frame
+    fn update_bin_gains(&mut self) {
+        let frame_size = self.bin_gains.len();
+        for i in 0..frame_size {
+            // the upper half of the frame mirrors the negative frequencies
+            let bin = if i <= frame_size / 2 {
+                i
+            } else {
+                frame_size - i
+            };
+            let freq = bin as f32 * self.sample_rate / frame_size as f32;
+
+            self.band_gains[i] = db_to_linear(self.band_gains_db[SpectralEQ::band_for_freq(freq)]);
+        }
+
+        smooth_bin_gains(&self.band_gains, self.smoothing_bins, &mut self.bin_gains);
+    }
+}
+
+impl FrequencyDomainAudioEffect for SpectralEQ {
+    fn advertise_parameters(&self) -> &'static [AdvertisedParameter] {
+        SpectralEQ::info()
+    }
+
+    fn post_initialize(&mut self, vocoder_context: &VocoderContext) {
+        self.band_gains = vec![1.0f32; vocoder_context.frame_size];
+        self.bin_gains = vec![1.0f32; vocoder_context.frame_size];
+        self.update_bin_gains();
+    }
+
+    // a single value sets every band
+    fn set_effect_parameter(
+        &mut self,
+        param_idx: usize,
+        param_value: BoardEffectConfigParameterValue,
+    ) {
+        if param_idx == PARAM_BAND_GAINS_DB {
+            for band_gain_db in self.band_gains_db.iter_mut() {
+                *band_gain_db = param_value.as_flt();
+            }
+
+            self.update_bin_gains();
+        } else if param_idx == PARAM_SMOOTHING_BINS {
+            self.smoothing_bins = param_value.as_int().max(0) as usize;
+            self.update_bin_gains();
+        }
+    }
+
+    // extra values are ignored, missing ones leave their band alone
+    fn set_effect_parameter_array(&mut self, param_idx: usize, param_values: &[f32]) {
+        if param_idx == PARAM_BAND_GAINS_DB {
+            for (band_gain_db, value) in self.band_gains_db.iter_mut().zip(param_values.iter()) {
+                *band_gain_db = *value;
+            }
+
+            self.update_bin_gains();
+        }
+    }
+
+    fn execute(&self, fft: &AlignedVec<c32>, output: &mut AlignedVec<c32>) {
+        if self.bin_gains.len() != fft.len() {
+            for i in 0..fft.len() {
+                output[i] = fft[i];
+            }
+
+            return;
+        }
+
+        for i in 0..fft.len() {
+            output[i] = fft[i] * self.bin_gains[i];
+        }
+    }
+
+    fn post_process(&self, _ifft: &mut AlignedVec<c32>) {}
+}
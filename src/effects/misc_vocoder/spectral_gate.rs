@@ -0,0 +1,94 @@
+use crate::conf::{AdvertisedParameter, BoardEffectConfigParameterValue, ParameterRange};
+use crate::effects::VocoderContext;
+use crate::effects::vocoder2::MIX_PARAM;
+use crate::traits::FrequencyDomainAudioEffect;
+use crate::utils::mathutils::db_to_linear;
+use fftw::array::AlignedVec;
+use fftw::types::c32;
+
+const PARAMS: &[AdvertisedParameter] = &[
+    AdvertisedParameter {
+        // compared against each bin's level, scaled so a full scale sine at the bin reads 0 dB
+        name: "threshold_db",
+        range: ParameterRange::F(-120.0f32, 0.0f32),
+        default_value: BoardEffectConfigParameterValue::F(-60.0f32),
+    },
+    MIX_PARAM,
+];
+
+const PARAM_THRESHOLD_DB: usize = 0;
+
+// zeroes every fft bin quieter than the threshold. takes out broadband noise under the signal,
+// and at high thresholds leaves only the loudest partials
+pub struct SpectralGate {
+    threshold: f32,
+
+    // turns a bin magnitude into the amplitude of the sine that produced it
+    magnitude_scale: f32,
+}
+
+impl SpectralGate {
+    pub fn new() -> SpectralGate {
+        SpectralGate {
+            threshold: db_to_linear(PARAMS[PARAM_THRESHOLD_DB].default_value.as_flt()),
+            magnitude_scale: 1.0f32,
+        }
+    }
+
+    pub fn info() -> &'static [AdvertisedParameter] {
+        PARAMS
+    }
+}
+
+impl FrequencyDomainAudioEffect for SpectralGate {
+    fn advertise_parameters(&self) -> &'static [AdvertisedParameter] {
+        SpectralGate::info()
+    }
+
+    fn post_initialize(&mut self, vocoder_context: &VocoderContext) {
+        let window_sum: f32 = vocoder_context.analysis_window.iter().sum();
+        if window_sum > 0.0f32 {
+            self.magnitude_scale = 2.0f32 / window_sum;
+        }
+    }
+
+    fn set_effect_parameter(
+        &mut self,
+        param_idx: usize,
+        param_value: BoardEffectConfigParameterValue,
+    ) {
+        if param_idx == PARAM_THRESHOLD_DB {
+            self.threshold = db_to_linear(param_value.as_flt());
+        }
+    }
+
+    fn execute(&self, fft: &AlignedVec<c32>, output: &mut AlignedVec<c32>) {
+        let frame_size = fft.len();
+        let nyquist = frame_size / 2;
+
+        for i in 0..=nyquist {
+            // dc and nyquist have no mirror bin to share their energy with, so a sine there
+            // reads twice as large
+            let is_edge = i == 0 || 2 * i == frame_size;
+            let scale = if is_edge {
+                0.5f32 * self.magnitude_scale
+            } else {
+                self.magnitude_scale
+            };
+
+            let keep = fft[i].norm() * scale >= self.threshold;
+
+            // a bin and its mirror go together so the frame stays the spectrum of a real signal
+            let mirror = (frame_size - i) % frame_size;
+            for &bin in [i, mirror].iter() {
+                output[bin] = if keep {
+                    fft[bin]
+                } else {
+                    c32::new(0.0f32, 0.0f32)
+                };
+            }
+        }
+    }
+
+    fn post_process(&self, _ifft: &mut AlignedVec<c32>) {}
+}
@@ -0,0 +1,24 @@
+// bin by bin gains flicker from frame to frame on noisy input, and each flickering bin
+// is heard as a short tone ("musical noise"). averaging every gain with its neighbours
+// takes the isolated spikes out while keeping the broad shape
+
+// the most bins either side a gain can be averaged with
+pub const MAX_SMOOTHING_BINS: usize = 16;
+
+// circular box average over radius bins either side of each gain
+// wrapping around keeps the negative frequency half a mirror of the positive half
+pub fn smooth_bin_gains(gains: &[f32], radius: usize, smoothed: &mut [f32]) {
+    let n = gains.len();
+    let radius = radius.min(n.saturating_sub(1) / 2);
+    if radius == 0 {
+        smoothed.copy_from_slice(gains);
+        return;
+    }
+
+    let width = 2 * radius + 1;
+    let mut sum: f32 = (0..width).map(|k| gains[(k + n - radius) % n]).sum();
+    for i in 0..n {
+        smoothed[i] = sum / width as f32;
+        sum += gains[(i + radius + 1) % n] - gains[(i + n - radius) % n];
+    }
+}
@@ -1,5 +1,6 @@
 use crate::conf::{AdvertisedParameter, BoardEffectConfigParameterValue};
 use crate::effects::VocoderContext;
+use crate::effects::vocoder2::MIX_PARAM;
 use crate::traits::FrequencyDomainAudioEffect;
 use crate::utils::fast_rand::WyHashPRNG;
 use crate::utils::mathutils::{vcosf, vsinf, vsqrtf};
@@ -7,7 +8,7 @@ use fftw::array::AlignedVec;
 use fftw::types::c32;
 use std::time::SystemTime;
 
-const PARAMS: &[AdvertisedParameter] = &[];
+const PARAMS: &[AdvertisedParameter] = &[MIX_PARAM];
 
 const RAND_MAX: u64 = 0x7fff;
 
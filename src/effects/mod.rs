@@ -1,13 +1,18 @@
 mod biquad_filter;
-mod bypass;
+pub mod bypass;
+mod crossover;
 mod delay;
 mod dynamics;
-mod misc_vocoder;
+mod formant_filter;
+pub mod misc_vocoder;
 mod modulation;
 mod nonlinear;
 mod pitch;
-mod reverb;
-mod vocoder2;
+pub mod reverb;
+mod sub_board;
+mod synth;
+mod utility;
+pub mod vocoder2;
 
 use crate::conf::{AdvertisedParameter, AudioConfig};
 use crate::context::BoardContext;
@@ -19,6 +24,8 @@ use fftw::array::AlignedVec;
 use std::collections::HashMap;
 
 pub use bypass::GenericBypass;
+pub use dynamics::Dynamics;
+pub use sub_board::SubBoard;
 
 pub type AudioEffectConstructionFunction = Box<dyn Fn(AudioConfig) -> Box<dyn AudioEffect>>;
 pub type AudioEffectInformationFunction = Box<dyn Fn() -> &'static [AdvertisedParameter]>;
@@ -32,6 +39,8 @@ pub struct VocoderContext {
     pub hop_size: usize,
     pub frame_size: usize,
     pub analysis_window: AlignedVec<f32>,
+    // all ones unless the vocoder windows its resynthesized frames
+    pub synthesis_window: AlignedVec<f32>,
 }
 
 pub struct FactoryExtension {
@@ -63,6 +72,38 @@ fn delay_effects() -> FactoryExtension {
         },
     );
 
+    factory_fns.insert(
+        "Delay/Stereo",
+        AudioEffectConstructionInfo {
+            constructor: Box::new(|ac| Box::new(delay::StereoDelay::new(ac))),
+            info: Box::new(|| delay::StereoDelay::info()),
+        },
+    );
+
+    factory_fns.insert(
+        "Delay/PingPong",
+        AudioEffectConstructionInfo {
+            constructor: Box::new(|ac| Box::new(delay::PingPongDelay::new(ac))),
+            info: Box::new(|| delay::PingPongDelay::info()),
+        },
+    );
+
+    factory_fns.insert(
+        "Delay/MultiTap",
+        AudioEffectConstructionInfo {
+            constructor: Box::new(|ac| Box::new(delay::MultiTapDelay::new(ac))),
+            info: Box::new(|| delay::MultiTapDelay::info()),
+        },
+    );
+
+    factory_fns.insert(
+        "Delay/Granular",
+        AudioEffectConstructionInfo {
+            constructor: Box::new(|ac| Box::new(delay::Granular::new(ac))),
+            info: Box::new(|| delay::Granular::info()),
+        },
+    );
+
     FactoryExtension { factory_fns }
 }
 
@@ -109,12 +150,28 @@ fn modulation_effects() -> FactoryExtension {
         },
     );
 
+    factory_fns.insert(
+        "Pitch/Unison",
+        AudioEffectConstructionInfo {
+            constructor: Box::new(|ac| Box::new(pitch::Unison::new(ac))),
+            info: Box::new(|| pitch::Unison::info()),
+        },
+    );
+
     FactoryExtension { factory_fns }
 }
 
 fn nonlinear_processing_effects() -> FactoryExtension {
     let mut factory_fns: HashMap<&'static str, AudioEffectConstructionInfo> = HashMap::new();
 
+    factory_fns.insert(
+        "NonLinear/BassEnhance",
+        AudioEffectConstructionInfo {
+            constructor: Box::new(|ac| Box::new(nonlinear::BassEnhance::new(ac))),
+            info: Box::new(|| nonlinear::BassEnhance::info()),
+        },
+    );
+
     factory_fns.insert(
         "NonLinear/BitCrusher",
         AudioEffectConstructionInfo {
@@ -145,6 +202,22 @@ fn misc_effects() -> FactoryExtension {
         },
     );
 
+    factory_fns.insert(
+        "Filter/Crossover",
+        AudioEffectConstructionInfo {
+            constructor: Box::new(|ac| Box::new(crossover::Crossover::new(ac))),
+            info: Box::new(|| crossover::Crossover::info()),
+        },
+    );
+
+    factory_fns.insert(
+        "Filter/Formant",
+        AudioEffectConstructionInfo {
+            constructor: Box::new(|ac| Box::new(formant_filter::FormantFilter::new(ac))),
+            info: Box::new(|| formant_filter::FormantFilter::info()),
+        },
+    );
+
     FactoryExtension { factory_fns }
 }
 
@@ -183,6 +256,14 @@ fn dynamics_effects() -> FactoryExtension {
         },
     );
 
+    factory_fns.insert(
+        "Dynamics/MSCompressor",
+        AudioEffectConstructionInfo {
+            constructor: Box::new(|ac| Box::new(dynamics::MidSideCompressor::new(ac))),
+            info: Box::new(|| dynamics::MidSideCompressor::info()),
+        },
+    );
+
     FactoryExtension { factory_fns }
 }
 
@@ -193,12 +274,15 @@ fn vocoder_effects() -> FactoryExtension {
         "PitchShifter/Ocean",
         AudioEffectConstructionInfo {
             constructor: Box::new(|_ac| {
-                Box::new(vocoder2::PhaseVocoder::new(
-                    1024,
-                    256,
-                    vocoder2::FFTWindowType::Hann,
-                    pitch::OceanPitchShifter::new(),
-                ))
+                Box::new(
+                    vocoder2::PhaseVocoder::new(
+                        1024,
+                        256,
+                        vocoder2::FFTWindowType::Hann,
+                        pitch::OceanPitchShifter::new(),
+                    )
+                    .unwrap(),
+                )
             }),
             info: Box::new(|| pitch::OceanPitchShifter::info()),
         },
@@ -208,12 +292,15 @@ fn vocoder_effects() -> FactoryExtension {
         "Vocoder/Bypass",
         AudioEffectConstructionInfo {
             constructor: Box::new(|_ac| {
-                Box::new(vocoder2::PhaseVocoder::new(
-                    1024,
-                    256,
-                    vocoder2::FFTWindowType::Hamming,
-                    bypass::VocoderBypass::new(),
-                ))
+                Box::new(
+                    vocoder2::PhaseVocoder::new(
+                        1024,
+                        256,
+                        vocoder2::FFTWindowType::Hamming,
+                        bypass::VocoderBypass::new(),
+                    )
+                    .unwrap(),
+                )
             }),
             info: Box::new(|| bypass::VocoderBypass::info()),
         },
@@ -223,12 +310,15 @@ fn vocoder_effects() -> FactoryExtension {
         "Vocoder/Robotize",
         AudioEffectConstructionInfo {
             constructor: Box::new(|_ac| {
-                Box::new(vocoder2::PhaseVocoder::new(
-                    1024,
-                    256,
-                    vocoder2::FFTWindowType::Hamming,
-                    misc_vocoder::Robotize::new(),
-                ))
+                Box::new(
+                    vocoder2::PhaseVocoder::new(
+                        1024,
+                        256,
+                        vocoder2::FFTWindowType::Hamming,
+                        misc_vocoder::Robotize::new(),
+                    )
+                    .unwrap(),
+                )
             }),
             info: Box::new(|| misc_vocoder::Robotize::info()),
         },
@@ -238,23 +328,206 @@ fn vocoder_effects() -> FactoryExtension {
         "Vocoder/Whisper",
         AudioEffectConstructionInfo {
             constructor: Box::new(|_ac| {
-                Box::new(vocoder2::PhaseVocoder::new(
-                    1024,
-                    256,
-                    vocoder2::FFTWindowType::Hamming,
-                    misc_vocoder::Whisper::new(),
-                ))
+                Box::new(
+                    vocoder2::PhaseVocoder::new(
+                        1024,
+                        256,
+                        vocoder2::FFTWindowType::Hamming,
+                        misc_vocoder::Whisper::new(),
+                    )
+                    .unwrap(),
+                )
             }),
             info: Box::new(|| misc_vocoder::Whisper::info()),
         },
     );
 
+    factory_fns.insert(
+        "Vocoder/SpectralEQ",
+        AudioEffectConstructionInfo {
+            constructor: Box::new(|ac| {
+                Box::new(
+                    vocoder2::PhaseVocoder::new(
+                        1024,
+                        256,
+                        vocoder2::FFTWindowType::Hamming,
+                        misc_vocoder::SpectralEQ::new(ac.sample_rate),
+                    )
+                    .unwrap(),
+                )
+            }),
+            info: Box::new(|| misc_vocoder::SpectralEQ::info()),
+        },
+    );
+
+    factory_fns.insert(
+        "Vocoder/SpectralCompressor",
+        AudioEffectConstructionInfo {
+            constructor: Box::new(|ac| {
+                Box::new(
+                    vocoder2::PhaseVocoder::new(
+                        1024,
+                        256,
+                        vocoder2::FFTWindowType::Hamming,
+                        misc_vocoder::SpectralCompressor::new(ac.sample_rate),
+                    )
+                    .unwrap(),
+                )
+            }),
+            info: Box::new(|| misc_vocoder::SpectralCompressor::info()),
+        },
+    );
+
+    factory_fns.insert(
+        "Vocoder/SpectralGate",
+        AudioEffectConstructionInfo {
+            constructor: Box::new(|_ac| {
+                Box::new(
+                    vocoder2::PhaseVocoder::new(
+                        1024,
+                        256,
+                        vocoder2::FFTWindowType::Hamming,
+                        misc_vocoder::SpectralGate::new(),
+                    )
+                    .unwrap(),
+                )
+            }),
+            info: Box::new(|| misc_vocoder::SpectralGate::info()),
+        },
+    );
+
+    factory_fns.insert(
+        "Vocoder/Freeze",
+        AudioEffectConstructionInfo {
+            constructor: Box::new(|_ac| {
+                Box::new(
+                    vocoder2::PhaseVocoder::new(
+                        1024,
+                        256,
+                        vocoder2::FFTWindowType::Hamming,
+                        misc_vocoder::Freeze::new(),
+                    )
+                    .unwrap(),
+                )
+            }),
+            info: Box::new(|| misc_vocoder::Freeze::info()),
+        },
+    );
+
+    factory_fns.insert(
+        "Vocoder/BandBank",
+        AudioEffectConstructionInfo {
+            constructor: Box::new(|ac| Box::new(misc_vocoder::BandBankVocoder::new(ac))),
+            info: Box::new(|| misc_vocoder::BandBankVocoder::info()),
+        },
+    );
+
+    FactoryExtension { factory_fns }
+}
+
+fn synth_effects() -> FactoryExtension {
+    let mut factory_fns: HashMap<&'static str, AudioEffectConstructionInfo> = HashMap::new();
+
+    factory_fns.insert(
+        "Synth/KarplusString",
+        AudioEffectConstructionInfo {
+            constructor: Box::new(|ac| Box::new(synth::KarplusString::new(ac))),
+            info: Box::new(|| synth::KarplusString::info()),
+        },
+    );
+
+    FactoryExtension { factory_fns }
+}
+
+fn utility_effects() -> FactoryExtension {
+    let mut factory_fns: HashMap<&'static str, AudioEffectConstructionInfo> = HashMap::new();
+
+    factory_fns.insert(
+        "Utility/Calibrate",
+        AudioEffectConstructionInfo {
+            constructor: Box::new(|ac| Box::new(utility::Calibrate::new(ac))),
+            info: Box::new(|| utility::Calibrate::info()),
+        },
+    );
+
+    factory_fns.insert(
+        "Utility/Crossfeed",
+        AudioEffectConstructionInfo {
+            constructor: Box::new(|ac| Box::new(utility::Crossfeed::new(ac))),
+            info: Box::new(|| utility::Crossfeed::info()),
+        },
+    );
+
+    factory_fns.insert(
+        "Utility/Delay",
+        AudioEffectConstructionInfo {
+            constructor: Box::new(|_ac| Box::new(utility::SampleDelay::new())),
+            info: Box::new(|| utility::SampleDelay::info()),
+        },
+    );
+
+    factory_fns.insert(
+        "Utility/LevelHistogram",
+        AudioEffectConstructionInfo {
+            constructor: Box::new(|_ac| Box::new(utility::LevelHistogram::new())),
+            info: Box::new(|| utility::LevelHistogram::info()),
+        },
+    );
+
+    factory_fns.insert(
+        "Utility/Pan",
+        AudioEffectConstructionInfo {
+            constructor: Box::new(|_ac| Box::new(utility::Pan::new())),
+            info: Box::new(|| utility::Pan::info()),
+        },
+    );
+
+    factory_fns.insert(
+        "Utility/SpectrumAnalyzer",
+        AudioEffectConstructionInfo {
+            constructor: Box::new(|_ac| Box::new(utility::SpectrumAnalyzer::new())),
+            info: Box::new(|| utility::SpectrumAnalyzer::info()),
+        },
+    );
+
+    factory_fns.insert(
+        "Utility/StereoRotate",
+        AudioEffectConstructionInfo {
+            constructor: Box::new(|_ac| Box::new(utility::StereoRotate::new())),
+            info: Box::new(|| utility::StereoRotate::info()),
+        },
+    );
+
     FactoryExtension { factory_fns }
 }
 
 fn reverb_effects() -> FactoryExtension {
     let mut factory_fns: HashMap<&'static str, AudioEffectConstructionInfo> = HashMap::new();
 
+    factory_fns.insert(
+        "Reverb/Freeverb",
+        AudioEffectConstructionInfo {
+            constructor: Box::new(|ac| Box::new(reverb::Freeverb::new(ac))),
+            info: Box::new(|| reverb::Freeverb::info()),
+        },
+    );
+
+    factory_fns.insert(
+        "Reverb/Schroeder",
+        AudioEffectConstructionInfo {
+            constructor: Box::new(|ac| Box::new(reverb::SchroederReverb::new(ac))),
+            info: Box::new(|| reverb::SchroederReverb::info()),
+        },
+    );
+
+    factory_fns.insert(
+        "Reverb/Plate",
+        AudioEffectConstructionInfo {
+            constructor: Box::new(|ac| Box::new(reverb::PlateReverb::new(ac))),
+            info: Box::new(|| reverb::PlateReverb::info()),
+        },
+    );
+
     FactoryExtension { factory_fns }
 }
 
@@ -269,6 +542,8 @@ pub fn loaded_set() -> Vec<FactoryExtension> {
         dynamics_effects(),
         vocoder_effects(),
         reverb_effects(),
+        synth_effects(),
+        utility_effects(),
     ];
 }
 
@@ -297,3 +572,48 @@ pub fn basic_single_in_single_out(
     let read_buf = context.get_buffer_for_read(inputs[0]);
     Some((read_buf, write_buf))
 }
+
+// reads = [L, R], writes = [L, R]
+// a missing right input reads as silence and a missing right output is discarded
+pub fn basic_stereo_in_stereo_out<'a>(
+    context: &'a BoardContext,
+    connection_idx: usize,
+    num_samples: usize,
+) -> Option<([AudioBufferReader<'a>; 2], [AudioBufferWriter<'a>; 2])> {
+    let inputs = context.get_inputs_for_connection(connection_idx);
+    let outputs = context.get_outputs_for_connection(connection_idx);
+
+    if outputs.len() < 1 {
+        return None;
+    }
+
+    let mut write_bufs = [
+        context.get_buffer_for_write(outputs[0]),
+        if outputs.len() > 1 {
+            context.get_buffer_for_write(outputs[1])
+        } else {
+            AudioBufferWriter::Null
+        },
+    ];
+
+    if inputs.len() < 1 {
+        for write_buf in write_bufs.iter_mut() {
+            for i in 0..num_samples {
+                write_buf.buf_write(i, 0.0f32);
+            }
+        }
+
+        return None;
+    }
+
+    let read_bufs = [
+        context.get_buffer_for_read(inputs[0]),
+        if inputs.len() > 1 {
+            context.get_buffer_for_read(inputs[1])
+        } else {
+            AudioBufferReader::Null
+        },
+    ];
+
+    Some((read_bufs, write_bufs))
+}
@@ -5,8 +5,9 @@ use crate::context::BoardContext;
 use crate::traits::AudioEffect;
 use crate::utils::{
     delay_buf::DelayBuffer,
-    lfo::{bipolar_to_unipolar, LFOWaveForm, LowFrequencyOscillator},
+    lfo::{bipolar_to_unipolar, LFOCurve, LFOWaveForm, LowFrequencyOscillator},
     mathutils,
+    state::{StateReader, StateWriter},
 };
 
 use crate::effects::basic_single_in_single_out;
@@ -31,11 +32,18 @@ const PARAMS: &'static [AdvertisedParameter] = &[
         range: ParameterRange::F(0.0f32, 1.0f32),
         default_value: BoardEffectConfigParameterValue::F(0.5f32),
     },
+    AdvertisedParameter {
+        // shape of the lfo. 0 = linear, 1 = exponential (dwells at the extremes), 2 = logarithmic
+        name: "curve",
+        range: ParameterRange::N(0, LFOCurve::__NUM_LFO_CURVES as i32 - 1),
+        default_value: BoardEffectConfigParameterValue::N(LFOCurve::Linear as i32),
+    },
 ];
 
 const PARAM_MOD_RATE_HZ: usize = 0;
 const PARAM_DEPTH_PCT: usize = 1;
 const PARAM_FEEDBACK_PCT: usize = 2;
+const PARAM_CURVE: usize = 3;
 
 struct ModulatedDelayDerivedParameters {
     min_delay: f32,
@@ -186,12 +194,35 @@ impl AudioEffect for ModulatedDelay {
             .change_sample_rate(new_config.sample_rate);
     }
 
+    // the lfo keeps going, it's not something that can ring on
+    fn reset(&mut self) {
+        self.delay_buf.get_mut().clear();
+    }
+
+    fn serialize_state(&self, writer: &mut StateWriter) -> bool {
+        self.delay_buf.borrow().save_state(writer);
+        self.lfo.borrow().save_state(writer);
+
+        true
+    }
+
+    fn deserialize_state(&mut self, reader: &mut StateReader) -> bool {
+        self.delay_buf.get_mut().load_state(reader).is_some()
+            && self.lfo.get_mut().load_state(reader).is_some()
+    }
+
     fn set_effect_parameter(
         &mut self,
         param_idx: usize,
         param_value: BoardEffectConfigParameterValue,
     ) {
         self.params[param_idx] = param_value;
+
+        if param_idx == PARAM_CURVE {
+            self.lfo
+                .borrow_mut()
+                .set_curve(self.params[PARAM_CURVE].as_enum());
+        }
     }
 
     fn execute(&self, context: &BoardContext, connection_idx: usize, num_samples: usize) {
@@ -7,6 +7,7 @@ use crate::utils::{
     biquad::{Biquad, BiquadCoefficients},
     lfo::{LFOWaveForm, LowFrequencyOscillator},
     mathutils::bipolar_lerp,
+    state::{StateReader, StateWriter},
 };
 use std::cell::RefCell;
 
@@ -107,6 +108,15 @@ impl MonoPhaser {
     pub fn info() -> &'static [AdvertisedParameter] {
         PARAMS
     }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Option<()> {
+        for apf in self.apfs.get_mut().iter_mut() {
+            apf.filter.load_state(reader)?;
+        }
+        self.lfo.get_mut().load_state(reader)?;
+
+        Some(())
+    }
 }
 
 impl AudioEffect for MonoPhaser {
@@ -119,11 +129,33 @@ impl AudioEffect for MonoPhaser {
             .borrow_mut()
             .change_sample_rate(new_config.sample_rate);
 
+        // the sweep recomputes the coefficients every sample from the apf's own sample rate
         for apf in self.apfs.borrow_mut().iter_mut() {
+            apf.sample_rate = new_config.sample_rate;
             apf.filter.change_sample_rate(new_config.sample_rate);
         }
     }
 
+    fn reset(&mut self) {
+        for apf in self.apfs.get_mut().iter_mut() {
+            apf.filter.clear();
+        }
+    }
+
+    // the sweep re-derives the coefficients every sample, so the lfo phase covers them
+    fn serialize_state(&self, writer: &mut StateWriter) -> bool {
+        for apf in self.apfs.borrow().iter() {
+            apf.filter.save_state(writer);
+        }
+        self.lfo.borrow().save_state(writer);
+
+        true
+    }
+
+    fn deserialize_state(&mut self, reader: &mut StateReader) -> bool {
+        self.load_state(reader).is_some()
+    }
+
     fn set_effect_parameter(
         &mut self,
         param_idx: usize,
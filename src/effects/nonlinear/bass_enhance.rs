@@ -0,0 +1,227 @@
+use crate::conf::{
+    AdvertisedParameter, AudioConfig, BoardEffectConfigParameterValue, ParameterRange,
+};
+use crate::context::BoardContext;
+use crate::effects::basic_single_in_single_out;
+use crate::traits::AudioEffect;
+use crate::utils::biquad::{Biquad, BiquadCoefficients};
+use crate::utils::envelope::EnvelopeDetector;
+use crate::utils::state::{StateReader, StateWriter};
+
+use std::cell::RefCell;
+
+const MAX_HARMONICS: usize = 8;
+
+const BUTTERWORTH_Q: f32 = std::f32::consts::FRAC_1_SQRT_2;
+
+// the detector holds its peak for a cycle of the lowest bass note, so the level it
+// normalizes by doesn't ripple within a cycle
+const ENVELOPE_ATTACK_MS: f32 = 1.0f32;
+const ENVELOPE_RELEASE_MS: f32 = 100.0f32;
+const ENVELOPE_HOLD_MS: f32 = 25.0f32;
+
+// below this the low band is treated as silence rather than blown up to full scale
+const MIN_ENVELOPE: f32 = 1e-4f32;
+
+const PARAMS: &'static [AdvertisedParameter] = &[
+    AdvertisedParameter {
+        // the lowest frequency the speaker can reproduce. bass below it gets harmonics above it
+        name: "crossover_hz",
+        range: ParameterRange::F(40.0f32, 300.0f32),
+        default_value: BoardEffectConfigParameterValue::F(120.0f32),
+    },
+    AdvertisedParameter {
+        // how many harmonics (2f, 3f, ...) are synthesized
+        name: "harmonics",
+        range: ParameterRange::N(1, MAX_HARMONICS as i32),
+        default_value: BoardEffectConfigParameterValue::N(4),
+    },
+    AdvertisedParameter {
+        // how much of the synthesized harmonics are added to the untouched input
+        name: "blend",
+        range: ParameterRange::F(0.0f32, 1.0f32),
+        default_value: BoardEffectConfigParameterValue::F(0.5f32),
+    },
+];
+
+const PARAM_CROSSOVER_HZ: usize = 0;
+const PARAM_HARMONICS: usize = 1;
+const PARAM_BLEND: usize = 2;
+
+struct BassEnhanceFilters {
+    // isolates the bass the speaker can't play
+    low_pass: [Biquad; 2],
+    // keeps only the synthesized harmonics above the crossover (and the dc the even ones add)
+    high_pass: [Biquad; 2],
+}
+
+// "virtual bass": the low band is normalized by its envelope to a unit sine, and chebyshev
+// polynomials of it give exact harmonics of its fundamental (T_k(cos x) = cos kx). those are
+// scaled back to the band's level, so the harmonics follow the bass, and added to the input
+pub struct BassEnhance {
+    params: Vec<BoardEffectConfigParameterValue>,
+
+    filters: RefCell<BassEnhanceFilters>,
+    envelope_detector: EnvelopeDetector,
+}
+
+impl BassEnhanceFilters {
+    fn new(crossover_hz: f32, sample_rate: f32) -> BassEnhanceFilters {
+        let low_pass =
+            BiquadCoefficients::second_order_lpf(crossover_hz, sample_rate, Some(BUTTERWORTH_Q));
+        let high_pass =
+            BiquadCoefficients::second_order_hpf(crossover_hz, sample_rate, Some(BUTTERWORTH_Q));
+
+        BassEnhanceFilters {
+            low_pass: [Biquad::new(low_pass.clone()), Biquad::new(low_pass)],
+            high_pass: [Biquad::new(high_pass.clone()), Biquad::new(high_pass)],
+        }
+    }
+}
+
+impl BassEnhance {
+    pub fn new(ac: AudioConfig) -> BassEnhance {
+        let mut params = Vec::with_capacity(PARAMS.len());
+        for i in 0..PARAMS.len() {
+            params.push(PARAMS[i].default_value);
+        }
+
+        BassEnhance {
+            filters: RefCell::new(BassEnhanceFilters::new(
+                params[PARAM_CROSSOVER_HZ].as_flt(),
+                ac.sample_rate,
+            )),
+            envelope_detector: BassEnhance::create_envelope_detector(ac.sample_rate),
+            params,
+        }
+    }
+
+    pub fn info() -> &'static [AdvertisedParameter] {
+        PARAMS
+    }
+
+    fn create_envelope_detector(sample_rate: f32) -> EnvelopeDetector {
+        let mut detector = EnvelopeDetector::new(sample_rate);
+        detector.should_return_db = false;
+        detector.set_attack_time_ms(ENVELOPE_ATTACK_MS);
+        detector.set_release_time_ms(ENVELOPE_RELEASE_MS);
+        detector.set_hold_time_ms(ENVELOPE_HOLD_MS);
+
+        detector
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Option<()> {
+        let filters = self.filters.get_mut();
+        for biquad in filters
+            .low_pass
+            .iter_mut()
+            .chain(filters.high_pass.iter_mut())
+        {
+            biquad.load_state(reader)?;
+        }
+
+        self.envelope_detector.load_state(reader)
+    }
+}
+
+impl AudioEffect for BassEnhance {
+    fn advertise_parameters(&self) -> &'static [AdvertisedParameter] {
+        BassEnhance::info()
+    }
+
+    fn set_audio_parameters(&mut self, new_config: &AudioConfig) {
+        *self.filters.get_mut() = BassEnhanceFilters::new(
+            self.params[PARAM_CROSSOVER_HZ].as_flt(),
+            new_config.sample_rate,
+        );
+        self.envelope_detector = BassEnhance::create_envelope_detector(new_config.sample_rate);
+    }
+
+    fn reset(&mut self) {
+        *self.filters.get_mut() = BassEnhanceFilters::new(
+            self.params[PARAM_CROSSOVER_HZ].as_flt(),
+            self.envelope_detector.get_sample_rate(),
+        );
+        self.envelope_detector =
+            BassEnhance::create_envelope_detector(self.envelope_detector.get_sample_rate());
+    }
+
+    fn serialize_state(&self, writer: &mut StateWriter) -> bool {
+        let filters = self.filters.borrow();
+        for biquad in filters.low_pass.iter().chain(filters.high_pass.iter()) {
+            biquad.save_state(writer);
+        }
+
+        self.envelope_detector.save_state(writer);
+
+        true
+    }
+
+    fn deserialize_state(&mut self, reader: &mut StateReader) -> bool {
+        self.load_state(reader).is_some()
+    }
+
+    fn set_effect_parameter(
+        &mut self,
+        param_idx: usize,
+        param_value: BoardEffectConfigParameterValue,
+    ) {
+        self.params[param_idx] = param_value;
+
+        if param_idx == PARAM_CROSSOVER_HZ {
+            let filters = self.filters.get_mut();
+            for biquad in filters
+                .low_pass
+                .iter_mut()
+                .chain(filters.high_pass.iter_mut())
+            {
+                biquad.change_cutoff(param_value.as_flt());
+            }
+        }
+    }
+
+    fn execute(&self, context: &BoardContext, connection_idx: usize, num_samples: usize) {
+        let maybe_bufs = basic_single_in_single_out(context, connection_idx, num_samples);
+        if let None = maybe_bufs {
+            return;
+        }
+
+        let (read_buf, mut write_buf) = maybe_bufs.unwrap();
+
+        let num_harmonics =
+            (self.params[PARAM_HARMONICS].as_int().max(1) as usize).min(MAX_HARMONICS);
+        let blend = self.params[PARAM_BLEND].as_flt();
+
+        let mut filters = self.filters.borrow_mut();
+        for i in 0..num_samples {
+            let xn = read_buf.buf_read(i);
+
+            let low = filters.low_pass[0].filter(xn);
+            let low = filters.low_pass[1].filter(low);
+
+            let envelope = self.envelope_detector.process(low);
+            let mut harmonics = 0.0f32;
+            if envelope > MIN_ENVELOPE {
+                let u = (low / envelope).max(-1.0f32).min(1.0f32);
+
+                // T_0 = 1, T_1 = u, T_k+1 = 2u T_k - T_k-1. harmonic k is weighted by 1/k
+                let mut t_prev = 1.0f32;
+                let mut t = u;
+                for k in 2..num_harmonics + 2 {
+                    let t_next = 2.0f32 * u * t - t_prev;
+                    t_prev = t;
+                    t = t_next;
+
+                    harmonics += t / k as f32;
+                }
+
+                harmonics *= envelope;
+            }
+
+            let harmonics = filters.high_pass[0].filter(harmonics);
+            let harmonics = filters.high_pass[1].filter(harmonics);
+
+            write_buf.buf_write(i, xn + blend * harmonics);
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use crate::conf::{AdvertisedParameter, BoardEffectConfigParameterValue, ParameterRange};
 use crate::effects::basic_single_in_single_out;
 use crate::traits::AudioEffect;
+use crate::utils::state::{StateReader, StateWriter};
 
 const PARAMS: &'static [AdvertisedParameter] = &[AdvertisedParameter {
     name: "quantized_bit_depth",
@@ -42,6 +43,15 @@ impl AudioEffect for BitCrusher {
     }
 
     fn set_audio_parameters(&mut self, _new_config: &crate::conf::AudioConfig) {}
+    // nothing carries over between blocks
+    fn serialize_state(&self, _writer: &mut StateWriter) -> bool {
+        true
+    }
+
+    fn deserialize_state(&mut self, _reader: &mut StateReader) -> bool {
+        true
+    }
+
     fn set_effect_parameter(
         &mut self,
         param_idx: usize,
@@ -1,5 +1,7 @@
+mod bass_enhance;
 mod bitcrusher;
 mod waveshaping;
 
+pub use bass_enhance::BassEnhance;
 pub use bitcrusher::BitCrusher;
 pub use waveshaping::WaveShaper;
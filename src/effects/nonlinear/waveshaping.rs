@@ -2,11 +2,17 @@ use crate::conf::{AdvertisedParameter, BoardEffectConfigParameterValue, Paramete
 use crate::effects::basic_single_in_single_out;
 use crate::{
     traits::AudioEffect,
+    utils::buf_rw::process_block,
     utils::mathutils::{vatan, vtanh},
+    utils::state::{StateReader, StateWriter},
 };
 use num_derive::FromPrimitive;
+use std::cell::Cell;
 use std::fmt;
 
+// below this step between samples the adaa difference quotient is all rounding error
+const ADAA_MIN_STEP: f32 = 1e-5f32;
+
 const PARAMS: &'static [AdvertisedParameter] = &[
     AdvertisedParameter {
         name: "waveshaper_function",
@@ -18,10 +24,17 @@ const PARAMS: &'static [AdvertisedParameter] = &[
         range: ParameterRange::F(1.0f32, 64.0f32),
         default_value: BoardEffectConfigParameterValue::F(4.0f32),
     },
+    AdvertisedParameter {
+        // where the hard clippers flatten the driven signal
+        name: "clip_level",
+        range: ParameterRange::F(0.05f32, 1.0f32),
+        default_value: BoardEffectConfigParameterValue::F(0.5f32),
+    },
 ];
 
 const PARAM_WAVESHAPER_FUNCTION: usize = 0;
 const PARAM_GAIN: usize = 1;
+const PARAM_CLIP_LEVEL: usize = 2;
 
 #[derive(Clone, Copy, FromPrimitive)]
 #[allow(non_camel_case_types)]
@@ -38,6 +51,9 @@ pub enum WaveShaperFunction {
     HardClip,
     HalfRectifier,
     FullRectifier,
+    Wavefold,
+    Wrap,
+    HardClipADAA,
 
     __NUM_FUNCTIONS,
 }
@@ -66,6 +82,9 @@ impl fmt::Display for WaveShaperFunction {
             WaveShaperFunction::HardClip => write!(f, "HardClip:X"),
             WaveShaperFunction::HalfRectifier => write!(f, "Half Wave Rectifier:NG,X"),
             WaveShaperFunction::FullRectifier => write!(f, "Full Wave Rectifier:NG,X"),
+            WaveShaperFunction::Wavefold => write!(f, "Wavefold"),
+            WaveShaperFunction::Wrap => write!(f, "Wrap:X"),
+            WaveShaperFunction::HardClipADAA => write!(f, "HardClipADAA"),
 
             WaveShaperFunction::__NUM_FUNCTIONS => write!(f, "!InvalidWaveShaperFunction"),
         }
@@ -75,6 +94,9 @@ impl fmt::Display for WaveShaperFunction {
 pub struct WaveShaper {
     params: Vec<BoardEffectConfigParameterValue>,
     real_waveshaper_function: WaveShaperFunction,
+
+    // the previous driven input, for the antiderivative anti-aliased clipper
+    prev_driven_sample: Cell<f32>,
 }
 
 impl WaveShaper {
@@ -87,6 +109,7 @@ impl WaveShaper {
         WaveShaper {
             params,
             real_waveshaper_function: WaveShaperFunction::Identity,
+            prev_driven_sample: Cell::new(0.0f32),
         }
     }
 
@@ -100,6 +123,26 @@ impl AudioEffect for WaveShaper {
         WaveShaper::info()
     }
     fn set_audio_parameters(&mut self, _new_config: &crate::conf::AudioConfig) {}
+    fn reset(&mut self) {
+        self.prev_driven_sample.set(0.0f32);
+    }
+
+    fn serialize_state(&self, writer: &mut StateWriter) -> bool {
+        writer.write_f32(self.prev_driven_sample.get());
+
+        true
+    }
+
+    fn deserialize_state(&mut self, reader: &mut StateReader) -> bool {
+        match reader.read_f32() {
+            Some(prev_driven_sample) => {
+                self.prev_driven_sample.set(prev_driven_sample);
+                true
+            }
+            None => false,
+        }
+    }
+
     fn set_effect_parameter(
         &mut self,
         param_idx: usize,
@@ -125,21 +168,36 @@ impl AudioEffect for WaveShaper {
         // actual processing
         // TODO: Low-hanging fruit for vectorization
         let (read_buf, mut write_buf) = maybe_bufs.unwrap();
-        for i in 0..num_samples {
-            let s = read_buf.buf_read(i);
-            write_buf.buf_write(
-                i,
-                execute_waveshaper_function(
-                    self.real_waveshaper_function,
-                    self.params[PARAM_GAIN].as_flt(),
-                    s,
-                ),
-            );
+        let gain = self.params[PARAM_GAIN].as_flt();
+        let clip_level = self.params[PARAM_CLIP_LEVEL].as_flt();
+
+        if let WaveShaperFunction::HardClipADAA = self.real_waveshaper_function {
+            let mut prev_driven_sample = self.prev_driven_sample.get();
+            process_block(&read_buf, &mut write_buf, num_samples, |s| {
+                let driven_sample = gain * s;
+                let y = ws_hclip_adaa(driven_sample, prev_driven_sample, clip_level);
+                prev_driven_sample = driven_sample;
+
+                y
+            });
+
+            self.prev_driven_sample.set(prev_driven_sample);
+            return;
         }
+
+        let function = self.real_waveshaper_function;
+        process_block(&read_buf, &mut write_buf, num_samples, |s| {
+            execute_waveshaper_function(function, gain, clip_level, s)
+        });
     }
 }
 
-fn execute_waveshaper_function(function: WaveShaperFunction, gain: f32, sample: f32) -> f32 {
+fn execute_waveshaper_function(
+    function: WaveShaperFunction,
+    gain: f32,
+    clip_level: f32,
+    sample: f32,
+) -> f32 {
     let v = match function {
         WaveShaperFunction::Identity => sample,
         WaveShaperFunction::Arraya => ws_arraya(sample),
@@ -156,13 +214,14 @@ fn execute_waveshaper_function(function: WaveShaperFunction, gain: f32, sample:
 
         WaveShaperFunction::SquareSign => x_ws_sqs(sample),
 
-        // TODO clip location should be configurable
-        // maybe just extract to a separate unit?
-        WaveShaperFunction::HardClip => x_ws_hclip(gain, sample, 0.5f32),
+        WaveShaperFunction::HardClip => x_ws_hclip(gain, sample, clip_level),
 
         WaveShaperFunction::HalfRectifier => x_ws_half_rec(sample),
         WaveShaperFunction::FullRectifier => x_ws_full_rec(sample),
 
+        WaveShaperFunction::Wavefold => ws_fold(gain, sample),
+        WaveShaperFunction::Wrap => x_ws_wrap(gain, sample),
+
         _ => 0f32,
     };
 
@@ -210,6 +269,31 @@ fn x_ws_hclip(gain: f32, sample: f32, clip_at: f32) -> f32 {
     }
 }
 
+// first order antiderivative anti-aliasing: the output is the average of the clipper over the
+// straight line between the last two driven samples, (F(u[n]) - F(u[n-1])) / (u[n] - u[n-1])
+// costs half a sample of delay and a little top end, but aliases far less than x_ws_hclip
+fn ws_hclip_adaa(driven_sample: f32, prev_driven_sample: f32, clip_at: f32) -> f32 {
+    let step = driven_sample - prev_driven_sample;
+
+    if step.abs() < ADAA_MIN_STEP {
+        // the quotient's limit is the clipper itself, at the midpoint
+        let midpoint = 0.5f32 * (driven_sample + prev_driven_sample);
+        return midpoint.max(-clip_at).min(clip_at);
+    }
+
+    (hclip_antiderivative(driven_sample, clip_at)
+        - hclip_antiderivative(prev_driven_sample, clip_at))
+        / step
+}
+
+fn hclip_antiderivative(u: f32, clip_at: f32) -> f32 {
+    if u.abs() <= clip_at {
+        0.5f32 * u * u
+    } else {
+        clip_at * u.abs() - 0.5f32 * clip_at * clip_at
+    }
+}
+
 fn x_ws_half_rec(sample: f32) -> f32 {
     0.5f32 * (sample + sample.abs())
 }
@@ -217,3 +301,19 @@ fn x_ws_half_rec(sample: f32) -> f32 {
 fn x_ws_full_rec(sample: f32) -> f32 {
     sample.abs()
 }
+
+// reflects the driven signal back off the +-1 boundaries (a triangle wave of the input)
+fn ws_fold(gain: f32, sample: f32) -> f32 {
+    let t = (gain * sample + 1.0f32).rem_euclid(4.0f32);
+
+    if t < 2.0f32 {
+        t - 1.0f32
+    } else {
+        3.0f32 - t
+    }
+}
+
+// anything past +-1 comes back in from the other side
+fn x_ws_wrap(gain: f32, sample: f32) -> f32 {
+    (gain * sample + 1.0f32).rem_euclid(2.0f32) - 1.0f32
+}
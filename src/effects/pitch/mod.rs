@@ -1,3 +1,5 @@
 mod ocean;
+mod unison;
 
-pub use ocean::OceanPitchShifter;
\ No newline at end of file
+pub use ocean::OceanPitchShifter;
+pub use unison::Unison;
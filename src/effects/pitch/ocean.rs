@@ -2,12 +2,15 @@ use crate::conf::{
     AdvertisedParameter, BoardEffectConfigParameterValue, ParameterRange,
 };
 use crate::effects::VocoderContext;
-use crate::utils::mathutils::{vcosf, vsinf};
+use crate::effects::vocoder2::MIX_PARAM;
+use crate::utils::mathutils::{db_to_linear, vcosf, vsinf};
 use crate::traits::FrequencyDomainAudioEffect;
 use fftw::array::AlignedVec;
 use fftw::types::c32;
 
-use std::cell::Cell;
+use num_derive::FromPrimitive;
+
+use std::cell::{Cell, RefCell};
 
 const PARAMS: &[AdvertisedParameter] = &[
     // TODO: maybe support microtones in the future
@@ -16,16 +19,47 @@ const PARAMS: &[AdvertisedParameter] = &[
         range: ParameterRange::N(-12, 12), // [-12, 12] => down 1 or up 1 octave
         default_value: BoardEffectConfigParameterValue::N(0),
     },
+    AdvertisedParameter {
+        // 0 = each source bin goes to the nearest destination bin
+        // 1 = each source bin is split between the two nearest, for finer pitch between bins
+        name: "bin_mapping",
+        range: ParameterRange::N(0, BinMapping::__NUM_BIN_MAPPINGS as i32 - 1),
+        default_value: BoardEffectConfigParameterValue::N(BinMapping::Nearest as i32),
+    },
+    AdvertisedParameter {
+        // 1 = the bins around each spectral peak move with the peak and keep their phases
+        // relative to it. less phasy on chords, less smeared transients
+        name: "phase_locking",
+        range: ParameterRange::N(0, 1),
+        default_value: BoardEffectConfigParameterValue::N(0),
+    },
+    AdvertisedParameter {
+        // 1 = the spectral envelope stays put and only the partials under it move, so voices keep
+        // their formants instead of going chipmunk
+        name: "preserve_formants",
+        range: ParameterRange::N(0, 1),
+        default_value: BoardEffectConfigParameterValue::N(0),
+    },
+    MIX_PARAM,
 ];
 
 const PARAM_SEMITONE_DIFFERENCE: usize = 0;
+const PARAM_BIN_MAPPING: usize = 1;
+const PARAM_PHASE_LOCKING: usize = 2;
+const PARAM_PRESERVE_FORMANTS: usize = 3;
 
 const FRAME_SIZE: usize = 1024; // must be a power of 2. higher for better results
 const OVERLAP_PCT: f32 = 0.75f32;
 
+// the envelope is the bin magnitudes averaged over this many bins either side. has to be wider
+// than the spacing of a voice's harmonics (~2-7 bins at 1024 / 44.1k) or it follows them too
+const ENVELOPE_SMOOTHING_BINS: usize = 8;
+// the envelope doesn't go below this much under its loudest bin, so moving a partial out of a
+// near silent part of the spectrum can't boost it without limit
+const ENVELOPE_FLOOR_DB: f32 = -60.0f32;
+
 struct OceanPitchShifterExtraParams {
     overlap_factor: usize,
-    overlap_factor_sq: usize,
     output_hop_index: Cell<isize>,
     frame_size: usize,
     hop_size: usize,
@@ -39,11 +73,36 @@ struct OceanPitchShifterExtraParams {
     copied_window: AlignedVec<f32>,
 
     unity_roots: AlignedVec<c32>,
+
+    // phase locking scratch, one per input bin
+    magnitudes: RefCell<Vec<f32>>,
+    // the peak each bin is locked to
+    region_peaks: RefCell<Vec<usize>>,
+    // formant preservation's smoothed magnitudes, one per input bin
+    envelope: RefCell<Vec<f32>>,
+}
+
+#[derive(Clone, Copy, PartialEq, FromPrimitive)]
+#[allow(non_camel_case_types)]
+pub enum BinMapping {
+    Nearest = 0,
+    Interpolated,
+
+    __NUM_BIN_MAPPINGS,
+}
+
+impl Default for BinMapping {
+    fn default() -> Self {
+        BinMapping::Nearest
+    }
 }
 
 pub struct OceanPitchShifter {
     params: Vec<BoardEffectConfigParameterValue>,
     frequency_multiplier: f32,
+    bin_mapping: BinMapping,
+    phase_locking: bool,
+    preserve_formants: bool,
 
     extra_params: Option<OceanPitchShifterExtraParams>,
 }
@@ -56,9 +115,15 @@ impl OceanPitchShifter {
         }
 
         let frequency_multiplier = semitones_to_freq(params[PARAM_SEMITONE_DIFFERENCE].as_int());
+        let bin_mapping = params[PARAM_BIN_MAPPING].as_enum();
+        let phase_locking = params[PARAM_PHASE_LOCKING].as_int() != 0;
+        let preserve_formants = params[PARAM_PRESERVE_FORMANTS].as_int() != 0;
         OceanPitchShifter {
             params,
             frequency_multiplier,
+            bin_mapping,
+            phase_locking,
+            preserve_formants,
 
             extra_params: None,
         }
@@ -76,7 +141,6 @@ impl FrequencyDomainAudioEffect for OceanPitchShifter {
 
     fn post_initialize(&mut self, vocoder_context: &VocoderContext) {
         let overlap_factor = vocoder_context.frame_size / vocoder_context.hop_size;
-        let overlap_factor_sq = 1_usize << overlap_factor;
 
         // lifted from reference java implementation by Nicolas Juillerat
         /*
@@ -87,7 +151,6 @@ impl FrequencyDomainAudioEffect for OceanPitchShifter {
          */
         self.extra_params = Some(OceanPitchShifterExtraParams {
             overlap_factor,
-            overlap_factor_sq,
             output_hop_index: Cell::new(-(overlap_factor as isize) - 1),
             hop_size: vocoder_context.hop_size,
             frame_size: vocoder_context.frame_size,
@@ -97,10 +160,23 @@ impl FrequencyDomainAudioEffect for OceanPitchShifter {
             num_output_bins: vocoder_context.frame_size * 1 / 2 + 1, // * 1 is zero pad factor, which is 1 for our use case
             
             copied_window: vocoder_context.analysis_window.clone(),
-            unity_roots: generate_unity_roots((overlap_factor_sq * 1) as isize), // same as above re: zero pad
+            unity_roots: generate_unity_roots((overlap_factor * 1) as isize), // same as above re: zero pad
+
+            magnitudes: RefCell::new(vec![0.0f32; vocoder_context.frame_size / 2 + 1]),
+            region_peaks: RefCell::new(vec![0; vocoder_context.frame_size / 2 + 1]),
+            envelope: RefCell::new(vec![1.0f32; vocoder_context.frame_size / 2 + 1]),
         });
     }
 
+    // the phase rotation cycle starts over, the same as when the vocoder was made
+    fn reset(&mut self) {
+        if let Some(extra_params) = &self.extra_params {
+            extra_params
+                .output_hop_index
+                .set(-(extra_params.overlap_factor as isize) - 1);
+        }
+    }
+
     fn set_effect_parameter(
         &mut self,
         param_idx: usize,
@@ -110,6 +186,12 @@ impl FrequencyDomainAudioEffect for OceanPitchShifter {
 
         if param_idx == PARAM_SEMITONE_DIFFERENCE {
             self.frequency_multiplier = semitones_to_freq(param_value.as_int());
+        } else if param_idx == PARAM_BIN_MAPPING {
+            self.bin_mapping = param_value.as_enum();
+        } else if param_idx == PARAM_PHASE_LOCKING {
+            self.phase_locking = param_value.as_int() != 0;
+        } else if param_idx == PARAM_PRESERVE_FORMANTS {
+            self.preserve_formants = param_value.as_int() != 0;
         }
     }
 
@@ -125,33 +207,79 @@ impl FrequencyDomainAudioEffect for OceanPitchShifter {
             output[i] = c32::new(0_f32, 0_f32);
         }
 
-        let cycle_length = extra_params.overlap_factor_sq * extra_params.zero_pad_factor;
+        let cycle_length = extra_params.overlap_factor * extra_params.zero_pad_factor;
         let cycle_idx = (extra_params.output_hop_index.get() + (cycle_length as isize) * 2) % (cycle_length as isize);
         let cycle_idx = cycle_idx as usize;
 
-        for src_bin_idx in 1..extra_params.num_input_bins {
-            let padded_src_bin_idx = src_bin_idx * extra_params.zero_pad_factor;
-
-            let dst_bin_idx = (padded_src_bin_idx as f32 * self.frequency_multiplier + 0.5_f32) as usize;
+        if self.phase_locking {
+            find_peak_regions(extra_params, fft);
+        }
+        let region_peaks = extra_params.region_peaks.borrow();
 
-            if dst_bin_idx <= 0 || dst_bin_idx >= extra_params.num_output_bins {
-                continue;
-            }
+        // the input is flattened by its envelope before the shift, and the shifted partials get
+        // the envelope back where they land
+        if self.preserve_formants {
+            estimate_envelope(extra_params, fft);
+        }
+        let envelope = extra_params.envelope.borrow();
 
-            let mut work = fft[src_bin_idx];
+        for src_bin_idx in 1..extra_params.num_input_bins {
+            let padded_src_bin_idx = src_bin_idx * extra_params.zero_pad_factor;
+            let src_bin = if self.preserve_formants {
+                fft[src_bin_idx] / envelope[src_bin_idx]
+            } else {
+                fft[src_bin_idx]
+            };
 
-            let cycle_shift = if dst_bin_idx >= padded_src_bin_idx {
-                (dst_bin_idx - padded_src_bin_idx) as usize % cycle_length
+            // locked, a bin lands as far from its peak's destination as it was from the peak.
+            // dst - src is then the same for the whole region, so it all gets the peak's rotation
+            let (dst_bin_pos, dst_bin_offset) = if self.phase_locking {
+                let peak_bin_idx = region_peaks[src_bin_idx] * extra_params.zero_pad_factor;
+                (
+                    peak_bin_idx as f32 * self.frequency_multiplier,
+                    padded_src_bin_idx as isize - peak_bin_idx as isize,
+                )
             } else {
-                cycle_length - (padded_src_bin_idx - dst_bin_idx) as usize % cycle_length
+                (padded_src_bin_idx as f32 * self.frequency_multiplier, 0)
             };
 
-            let phase_shift = (cycle_idx * cycle_shift) % cycle_length;
-            if phase_shift != 0 {
-                work *= extra_params.unity_roots[(cycle_length - phase_shift) % cycle_length];
+            if self.bin_mapping == BinMapping::Interpolated {
+                let lower_dst_bin_idx = dst_bin_pos as isize + dst_bin_offset;
+                let upper_weight = dst_bin_pos - dst_bin_pos.floor();
+
+                add_shifted_bin(
+                    extra_params,
+                    output,
+                    src_bin * (1.0_f32 - upper_weight),
+                    padded_src_bin_idx,
+                    lower_dst_bin_idx,
+                    cycle_idx,
+                );
+                add_shifted_bin(
+                    extra_params,
+                    output,
+                    src_bin * upper_weight,
+                    padded_src_bin_idx,
+                    lower_dst_bin_idx + 1,
+                    cycle_idx,
+                );
+            } else {
+                add_shifted_bin(
+                    extra_params,
+                    output,
+                    src_bin,
+                    padded_src_bin_idx,
+                    (dst_bin_pos + 0.5_f32) as isize + dst_bin_offset,
+                    cycle_idx,
+                );
             }
+        }
 
-            output[dst_bin_idx] += work;
+        // without zero padding the output bins line up with the input bins the envelope is for
+        if self.preserve_formants {
+            for i in 1..extra_params.num_output_bins {
+                output[i] *= envelope[i];
+            }
         }
 
         extra_params
@@ -180,6 +308,124 @@ impl FrequencyDomainAudioEffect for OceanPitchShifter {
     }
 }
 
+// moving a bin up by d bins means its phase has to advance d / overlap of a turn more each hop
+// (cycle_length hops make a whole turn), or it comes out at the wrong frequency
+fn add_shifted_bin(
+    extra_params: &OceanPitchShifterExtraParams,
+    output: &mut AlignedVec<c32>,
+    mut work: c32,
+    padded_src_bin_idx: usize,
+    dst_bin_idx: isize,
+    cycle_idx: usize,
+) {
+    if dst_bin_idx <= 0 || dst_bin_idx as usize >= extra_params.num_output_bins {
+        return;
+    }
+    let dst_bin_idx = dst_bin_idx as usize;
+
+    let cycle_length = extra_params.overlap_factor * extra_params.zero_pad_factor;
+    let cycle_shift = if dst_bin_idx >= padded_src_bin_idx {
+        (dst_bin_idx - padded_src_bin_idx) as usize % cycle_length
+    } else {
+        cycle_length - (padded_src_bin_idx - dst_bin_idx) as usize % cycle_length
+    };
+
+    let phase_shift = (cycle_idx * cycle_shift) % cycle_length;
+    if phase_shift != 0 {
+        work *= extra_params.unity_roots[phase_shift];
+    }
+
+    output[dst_bin_idx] += work;
+}
+
+// splits the spectrum into one region per magnitude peak, with the boundaries at the quietest
+// bin between two peaks, and records each bin's peak in region_peaks
+fn find_peak_regions(extra_params: &OceanPitchShifterExtraParams, fft: &AlignedVec<c32>) {
+    let mut magnitudes = extra_params.magnitudes.borrow_mut();
+    let mut region_peaks = extra_params.region_peaks.borrow_mut();
+    let num_bins = extra_params.num_input_bins;
+
+    for i in 0..num_bins {
+        magnitudes[i] = fft[i].norm();
+    }
+
+    let is_peak = |i: usize| {
+        magnitudes[i] > magnitudes[i - 1]
+            && (i + 1 >= num_bins || magnitudes[i] >= magnitudes[i + 1])
+    };
+
+    let mut last_peak: Option<usize> = None;
+    for i in 1..num_bins {
+        if !is_peak(i) {
+            continue;
+        }
+
+        let region_start = match last_peak {
+            Some(last_peak) => {
+                let mut quietest = last_peak + 1;
+                for j in last_peak + 1..i {
+                    if magnitudes[j] < magnitudes[quietest] {
+                        quietest = j;
+                    }
+                }
+
+                for j in last_peak + 1..quietest {
+                    region_peaks[j] = last_peak;
+                }
+
+                quietest
+            }
+            None => 1,
+        };
+
+        for j in region_start..=i {
+            region_peaks[j] = i;
+        }
+
+        last_peak = Some(i);
+    }
+
+    match last_peak {
+        Some(last_peak) => {
+            for j in last_peak + 1..num_bins {
+                region_peaks[j] = last_peak;
+            }
+        }
+        None => {
+            // a flat spectrum (e.g. a click) has no peak, so everything moves with the first bin
+            for j in 1..num_bins {
+                region_peaks[j] = 1;
+            }
+        }
+    }
+}
+
+// moving average of the bin magnitudes, floored at ENVELOPE_FLOOR_DB under its peak
+fn estimate_envelope(extra_params: &OceanPitchShifterExtraParams, fft: &AlignedVec<c32>) {
+    let mut magnitudes = extra_params.magnitudes.borrow_mut();
+    let mut envelope = extra_params.envelope.borrow_mut();
+    let num_bins = extra_params.num_input_bins;
+
+    for i in 0..num_bins {
+        magnitudes[i] = fft[i].norm();
+    }
+
+    // dc is left out, it isn't part of anything's timbre
+    let mut loudest = 0.0f32;
+    for i in 1..num_bins {
+        let start = i.saturating_sub(ENVELOPE_SMOOTHING_BINS).max(1);
+        let end = (i + ENVELOPE_SMOOTHING_BINS + 1).min(num_bins);
+
+        envelope[i] = magnitudes[start..end].iter().sum::<f32>() / (end - start) as f32;
+        loudest = loudest.max(envelope[i]);
+    }
+
+    let floor = (loudest * db_to_linear(ENVELOPE_FLOOR_DB)).max(std::f32::MIN_POSITIVE);
+    for i in 1..num_bins {
+        envelope[i] = envelope[i].max(floor);
+    }
+}
+
 fn semitones_to_freq(semitones: i32) -> f32 {
     2.0f32.powf((semitones as f32) / 12.0f32)
 }
@@ -0,0 +1,222 @@
+use crate::conf::{
+    AdvertisedParameter, AudioConfig, BoardEffectConfigParameterValue, ParameterRange,
+};
+use crate::context::BoardContext;
+use crate::effects::basic_single_in_single_out;
+use crate::traits::AudioEffect;
+use crate::utils::delay_buf::DelayBuffer;
+use crate::utils::mathutils::vmodf;
+use crate::utils::state::{StateReader, StateWriter};
+
+use std::cell::RefCell;
+use std::f32::consts::PI;
+
+const MAX_VOICES: usize = 8;
+
+// each voice reads the input through a delay that keeps sliding, which shifts its pitch
+// the slide wraps around every WINDOW_MS, crossfaded between two taps half a window apart
+const MIN_DELAY_MS: f32 = 5.0f32;
+const WINDOW_MS: f32 = 50.0f32;
+
+const PARAMS: &'static [AdvertisedParameter] = &[
+    AdvertisedParameter {
+        name: "voices",
+        range: ParameterRange::N(1, MAX_VOICES as i32),
+        default_value: BoardEffectConfigParameterValue::N(4),
+    },
+    AdvertisedParameter {
+        // the outermost voices are this far off. the others are spread evenly inside it, in pairs
+        name: "detune_cents",
+        range: ParameterRange::F(5.0f32, 20.0f32),
+        default_value: BoardEffectConfigParameterValue::F(10.0f32),
+    },
+    AdvertisedParameter {
+        name: "mix",
+        range: ParameterRange::F(0.0f32, 1.0f32),
+        default_value: BoardEffectConfigParameterValue::F(0.5f32),
+    },
+];
+
+const PARAM_VOICES: usize = 0;
+const PARAM_DETUNE_CENTS: usize = 1;
+const PARAM_MIX: usize = 2;
+
+#[derive(Clone, Copy)]
+struct UnisonVoice {
+    // [0, 1) position in the delay window
+    phase: f32,
+    // how far phase moves per sample. negative (shrinking delay) raises the pitch
+    phase_inc: f32,
+}
+
+// several slightly detuned copies of the input summed with the dry signal
+// the Ocean shifter moves whole fft bins, so it can't detune by a few cents;
+// these are time-domain (rotating tap) shifters instead, all reading one delay line
+pub struct Unison {
+    params: Vec<BoardEffectConfigParameterValue>,
+    sample_rate: f32,
+    num_voices: usize,
+
+    delay_buf: RefCell<DelayBuffer>,
+    voices: RefCell<[UnisonVoice; MAX_VOICES]>,
+}
+
+impl Unison {
+    pub fn new(ac: AudioConfig) -> Unison {
+        let mut params = Vec::with_capacity(PARAMS.len());
+        for i in 0..PARAMS.len() {
+            params.push(PARAMS[i].default_value);
+        }
+
+        let mut unison = Unison {
+            params,
+            sample_rate: ac.sample_rate,
+            num_voices: 0,
+            delay_buf: RefCell::new(Unison::create_delay_buf(ac.sample_rate)),
+            voices: RefCell::new(
+                [UnisonVoice {
+                    phase: 0.0f32,
+                    phase_inc: 0.0f32,
+                }; MAX_VOICES],
+            ),
+        };
+
+        unison.update_voices();
+        unison.stagger_phases();
+        unison
+    }
+
+    pub fn info() -> &'static [AdvertisedParameter] {
+        PARAMS
+    }
+
+    fn create_delay_buf(sample_rate: f32) -> DelayBuffer {
+        DelayBuffer::with_sample_rate_and_max_delay(sample_rate, MIN_DELAY_MS + WINDOW_MS + 1.0f32)
+    }
+
+    // voices come in +/- pairs, so no voice sits at 0 cents and combs with the dry signal
+    // (an odd voice out goes sharp)
+    fn voice_detune_cents(voice_idx: usize, num_voices: usize, detune_cents: f32) -> f32 {
+        let num_steps = (num_voices + 1) / 2;
+        let magnitude = detune_cents * (voice_idx / 2 + 1) as f32 / num_steps as f32;
+
+        if voice_idx % 2 == 0 {
+            magnitude
+        } else {
+            -magnitude
+        }
+    }
+
+    fn update_voices(&mut self) {
+        self.num_voices = (self.params[PARAM_VOICES].as_int() as usize)
+            .max(1)
+            .min(MAX_VOICES);
+        let detune_cents = self.params[PARAM_DETUNE_CENTS].as_flt();
+        let window_samples = WINDOW_MS * self.sample_rate / 1000.0f32;
+
+        for (i, voice) in self.voices.borrow_mut().iter_mut().enumerate() {
+            let cents = Unison::voice_detune_cents(i, self.num_voices, detune_cents);
+            let pitch_ratio = 2.0f32.powf(cents / 1200.0f32);
+
+            voice.phase_inc = (1.0f32 - pitch_ratio) / window_samples;
+        }
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Option<()> {
+        self.delay_buf.get_mut().load_state(reader)?;
+        for voice in self.voices.get_mut().iter_mut() {
+            voice.phase = reader.read_f32()?;
+        }
+
+        Some(())
+    }
+
+    // so the voices don't all wrap around at the same time
+    fn stagger_phases(&self) {
+        for (i, voice) in self.voices.borrow_mut().iter_mut().enumerate() {
+            voice.phase = i as f32 / MAX_VOICES as f32;
+        }
+    }
+}
+
+impl AudioEffect for Unison {
+    fn advertise_parameters(&self) -> &'static [AdvertisedParameter] {
+        Unison::info()
+    }
+
+    fn set_audio_parameters(&mut self, new_config: &AudioConfig) {
+        self.sample_rate = new_config.sample_rate;
+        self.delay_buf = RefCell::new(Unison::create_delay_buf(self.sample_rate));
+        self.update_voices();
+    }
+
+    fn set_effect_parameter(
+        &mut self,
+        param_idx: usize,
+        param_value: BoardEffectConfigParameterValue,
+    ) {
+        self.params[param_idx] = param_value;
+
+        if param_idx != PARAM_MIX {
+            self.update_voices();
+        }
+    }
+
+    fn reset(&mut self) {
+        self.delay_buf.borrow_mut().clear();
+        self.stagger_phases();
+    }
+
+    // phase_inc comes from parameters
+    fn serialize_state(&self, writer: &mut StateWriter) -> bool {
+        self.delay_buf.borrow().save_state(writer);
+        for voice in self.voices.borrow().iter() {
+            writer.write_f32(voice.phase);
+        }
+
+        true
+    }
+
+    fn deserialize_state(&mut self, reader: &mut StateReader) -> bool {
+        self.load_state(reader).is_some()
+    }
+
+    fn execute(&self, context: &BoardContext, connection_idx: usize, num_samples: usize) {
+        let maybe_bufs = basic_single_in_single_out(context, connection_idx, num_samples);
+        if let None = maybe_bufs {
+            return;
+        }
+
+        let (read_buf, mut write_buf) = maybe_bufs.unwrap();
+
+        let mix = self.params[PARAM_MIX].as_flt();
+        let voice_gain = mix / self.num_voices as f32;
+        let min_delay_samples = MIN_DELAY_MS * self.sample_rate / 1000.0f32;
+        let window_samples = WINDOW_MS * self.sample_rate / 1000.0f32;
+
+        let mut delay_buf = self.delay_buf.borrow_mut();
+        let mut voices = self.voices.borrow_mut();
+        for i in 0..num_samples {
+            let xn = read_buf.buf_read(i);
+            delay_buf.write_sample(xn);
+
+            let mut wet = 0.0f32;
+            for voice in voices[..self.num_voices].iter_mut() {
+                for tap_offset in &[0.0f32, 0.5f32] {
+                    let tap_phase = (voice.phase + tap_offset).fract();
+
+                    // sin^2 and cos^2 of the two taps sum to 1. each tap is silent as it wraps
+                    let tap_gain = (PI * tap_phase).sin().powi(2);
+                    let (whole, fract) = vmodf(min_delay_samples + tap_phase * window_samples);
+
+                    delay_buf.set_delay_sample_count_directly(whole, fract);
+                    wet += tap_gain * delay_buf.read_delayed_sample();
+                }
+
+                voice.phase = (voice.phase + voice.phase_inc).rem_euclid(1.0f32);
+            }
+
+            write_buf.buf_write(i, (1.0f32 - mix) * xn + voice_gain * wet);
+        }
+    }
+}
@@ -1,9 +1,13 @@
 use crate::utils::{
     delay_buf::DelayBuffer,
-    lfo::{bipolar_to_unipolar, LowFrequencyOscillator},
+    lfo::{bipolar_to_unipolar, LFOWaveForm, LowFrequencyOscillator},
     mathutils::lerp,
+    state::{StateReader, StateWriter},
 };
 
+// the delay line is sized for the delay time plus this much modulation
+const MAX_MODULATION_MS: f32 = 2.0f32;
+
 pub struct DelayAPF {
     lfo: LowFrequencyOscillator,
     lfo_depth: f32,
@@ -11,6 +15,8 @@ pub struct DelayAPF {
 
     delay_time_ms: f32,
     delay_buf: DelayBuffer,
+    // what delay_buf was sized for. delay times past it are clamped
+    max_delay_ms: f32,
 
     apf_g: f32,
     lpf_g: f32,
@@ -18,29 +24,144 @@ pub struct DelayAPF {
 }
 
 impl DelayAPF {
+    // unmodulated and undamped until told otherwise
+    pub fn new(delay_time_ms: f32, apf_g: f32, sample_rate: f32) -> DelayAPF {
+        DelayAPF::with_max_delay_ms(delay_time_ms, delay_time_ms, apf_g, sample_rate)
+    }
+
+    // for an all pass whose delay time will change: the line is sized for max_delay_ms up front
+    pub fn with_max_delay_ms(
+        delay_time_ms: f32,
+        max_delay_time_ms: f32,
+        apf_g: f32,
+        sample_rate: f32,
+    ) -> DelayAPF {
+        let max_delay_ms = DelayAPF::max_delay_ms_for(max_delay_time_ms.max(delay_time_ms));
+        let delay_buf = DelayBuffer::with_sample_rate_and_max_delay(sample_rate, max_delay_ms);
+
+        let mut apf = DelayAPF {
+            lfo: LowFrequencyOscillator::new(LFOWaveForm::Sine, 0.0f32, sample_rate),
+            lfo_depth: 0.0f32,
+            lfo_max_modulation_ms: 0.0f32,
+            delay_time_ms,
+            delay_buf,
+            max_delay_ms,
+            apf_g,
+            lpf_g: 0.0f32,
+            lpf_state: 0.0f32,
+        };
+
+        apf.set_unmodulated_delay();
+        apf
+    }
+
+    pub fn change_sample_rate(&mut self, new_sample_rate: f32) {
+        self.delay_buf.change_sample_rate(new_sample_rate);
+        self.lfo.change_sample_rate(new_sample_rate);
+        self.lpf_state = 0.0f32;
+        self.set_unmodulated_delay();
+    }
+
+    // 1 ms of slack so the longest modulated delay doesn't get clamped
+    fn max_delay_ms_for(delay_time_ms: f32) -> f32 {
+        delay_time_ms + MAX_MODULATION_MS + 1.0f32
+    }
+
+    fn is_modulated(&self) -> bool {
+        self.lfo_depth != 0.0f32 && self.lfo_max_modulation_ms > 0.0f32
+    }
+
+    // rounded to whole samples: the interpolation for a fractional delay is a low pass,
+    // and inside the loop it would eat the highs (the filter wouldn't be all pass anymore)
+    // the line is read before this sample is written, which adds one sample to the loop,
+    // so the line itself is one short of D
+    fn set_unmodulated_delay(&mut self) {
+        let delay_samples =
+            (self.delay_time_ms * self.delay_buf.get_sample_rate() / 1000.0f32).round();
+        self.delay_buf
+            .set_delay_sample_count_directly((delay_samples as i32 - 1).max(0), 0.0f32);
+    }
+
+    // clamped to what the line was sized for (see with_max_delay_ms), so it never reallocates
+    pub fn set_delay_time_ms(&mut self, delay_time_ms: f32) {
+        let longest_delay_ms = self.max_delay_ms - MAX_MODULATION_MS - 1.0f32;
+        self.delay_time_ms = delay_time_ms.max(0.0f32).min(longest_delay_ms);
+
+        if !self.is_modulated() {
+            self.set_unmodulated_delay();
+        }
+    }
+
+    pub fn set_apf_g(&mut self, apf_g: f32) {
+        self.apf_g = apf_g;
+    }
+
+    pub fn set_modulation(&mut self, rate_hz: f32, depth: f32, max_modulation_ms: f32) {
+        self.lfo.change_oscillation_freq(rate_hz);
+        self.lfo_depth = depth;
+        self.lfo_max_modulation_ms = max_modulation_ms.min(MAX_MODULATION_MS);
+
+        if !self.is_modulated() {
+            self.set_unmodulated_delay();
+        }
+    }
+
+    // one pole low pass on the delayed signal, 0 = off
+    pub fn set_damping(&mut self, lpf_g: f32) {
+        self.lpf_g = lpf_g;
+    }
+
+    // reads the line (w, the signal inside the all pass) at any delay. for output taps
+    pub fn read_tap_ms(&self, delay_time_ms: f32) -> f32 {
+        self.delay_buf.read_delayed_sample_at_ms(delay_time_ms)
+    }
+
+    pub fn clear(&mut self) {
+        self.delay_buf.clear();
+        self.lpf_state = 0.0f32;
+    }
+
+    pub fn save_state(&self, writer: &mut StateWriter) {
+        self.lfo.save_state(writer);
+        self.delay_buf.save_state(writer);
+        writer.write_f32(self.lpf_state);
+    }
+
+    pub fn load_state(&mut self, reader: &mut StateReader) -> Option<()> {
+        self.lfo.load_state(reader)?;
+        self.delay_buf.load_state(reader)?;
+        self.lpf_state = reader.read_f32()?;
+
+        Some(())
+    }
+
     pub fn process(&mut self, x_n: f32) -> f32 {
-        let min_delay = self.delay_time_ms;
-        let max_delay = min_delay + self.lfo_max_modulation_ms;
+        if self.is_modulated() {
+            let min_delay = self.delay_time_ms;
+            let max_delay = min_delay + self.lfo_max_modulation_ms;
+
+            let modulated_delay = lerp(
+                min_delay,
+                max_delay,
+                bipolar_to_unipolar(self.lfo.current_sample() * self.lfo_depth),
+            );
+            self.lfo.oscillate();
 
-        let modulated_delay = lerp(
-            min_delay,
-            max_delay,
-            bipolar_to_unipolar(self.lfo.current_sample() * self.lfo_depth),
-        );
+            self.delay_buf.set_delay_time_ms(modulated_delay, true);
+        }
 
-        self.delay_buf.set_delay_time_ms(modulated_delay, true);
         let mut wn_D = self.delay_buf.read_delayed_sample();
 
         wn_D = wn_D * (1_f32 - self.lpf_g) + self.lpf_g * self.lpf_state;
         self.lpf_state = wn_D;
 
-        // TODO:
-        // This doesn't seem to exactly match the formula in the book:
-        // y(n) = -g * x(n) + x(n - D) + g * y(n - D)
+        // w(n) = x(n) + g * w(n - D)
+        // y(n) = -g * w(n) + w(n - D)
         let w_n = x_n + self.apf_g * wn_D;
         let y_n = -self.apf_g * w_n + wn_D;
 
-        self.delay_buf.write_sample(y_n);
+        // the line holds w, not y. feeding y back made this a comb rather than an all pass
+        self.delay_buf.write_sample(w_n);
 
         y_n
     }
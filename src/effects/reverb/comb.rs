@@ -1,17 +1,34 @@
+use crate::utils::biquad::{Biquad, BiquadCoefficients};
 use crate::utils::delay_buf::DelayBuffer;
-
-use std::cell::RefCell;
-
-struct LPFCombFilter {
+use crate::utils::state::{StateReader, StateWriter};
+
+// the comb is only stable while its loop gain stays below 1.0
+// the damping filter is a butterworth low pass (|H| <= 1 everywhere) blended with the
+// undamped signal, so the loop gain never exceeds comb_g and clamping comb_g alone is enough.
+// very long rt60s or very short delays would otherwise push comb_g to (or past) 1.0
+// and the comb would ring forever
+const MAX_COMB_G: f32 = 0.999f32;
+
+// everything above damping_freq_hz loses a bit more on every trip around the loop,
+// so highs die out faster than lows. damping_amount [0, 1] blends the low pass in
+pub struct LPFCombFilter {
     delay_buf: DelayBuffer,
     comb_g: f32,
-    lpf_g: f32, // [0, 0.9999]
-    lpf_state: f32,
     rt60_ms: f32,
+
+    damping_freq_hz: f32,
+    damping_amount: f32,
+    damping_lpf: Biquad,
 }
 
 impl LPFCombFilter {
-    pub fn new(delay_time_ms: f32, sample_rate: f32, rt60_ms: f32, lpf_g: f32) -> LPFCombFilter {
+    pub fn new(
+        delay_time_ms: f32,
+        sample_rate: f32,
+        rt60_ms: f32,
+        damping_freq_hz: f32,
+        damping_amount: f32,
+    ) -> LPFCombFilter {
         let mut delay_buf = DelayBuffer::with_sample_rate(sample_rate);
         delay_buf.set_delay_time_ms(delay_time_ms, true);
 
@@ -22,9 +39,10 @@ impl LPFCombFilter {
         LPFCombFilter {
             delay_buf,
             comb_g: comb_gain,
-            lpf_g,
-            lpf_state: 0_f32,
             rt60_ms,
+            damping_freq_hz,
+            damping_amount: num::clamp(damping_amount, 0_f32, 1_f32),
+            damping_lpf: create_damping_lpf(damping_freq_hz, sample_rate),
         }
     }
 
@@ -39,7 +57,7 @@ impl LPFCombFilter {
         );
 
         // reset lpf
-        self.lpf_state = 0_f32;
+        self.damping_lpf = create_damping_lpf(self.damping_freq_hz, new_sample_rate);
     }
 
     pub fn change_delay_time(&mut self, new_delay_time: f32) {
@@ -53,11 +71,17 @@ impl LPFCombFilter {
         );
 
         // reset lpf
-        self.lpf_state = 0_f32;
+        self.damping_lpf =
+            create_damping_lpf(self.damping_freq_hz, self.delay_buf.get_sample_rate());
     }
 
-    pub fn set_lpf_g(&mut self, new_lpf_g: f32) {
-        self.lpf_g = new_lpf_g;
+    pub fn set_damping_freq_hz(&mut self, damping_freq_hz: f32) {
+        self.damping_freq_hz = damping_freq_hz;
+        self.damping_lpf.change_cutoff(damping_freq_hz);
+    }
+
+    pub fn set_damping_amount(&mut self, damping_amount: f32) {
+        self.damping_amount = num::clamp(damping_amount, 0_f32, 1_f32);
     }
 
     pub fn set_rt60_ms(&mut self, rt60_ms: f32) {
@@ -67,20 +91,38 @@ impl LPFCombFilter {
             rt60_ms,
         );
         self.comb_g = new_g;
+        self.rt60_ms = rt60_ms;
     }
 
     pub fn set_comb_g_directly(&mut self, new_comb_g: f32) {
-        self.comb_g = new_comb_g;
+        self.comb_g = new_comb_g.min(MAX_COMB_G);
+    }
+
+    pub fn clear(&mut self) {
+        self.delay_buf.clear();
+        self.damping_lpf =
+            create_damping_lpf(self.damping_freq_hz, self.delay_buf.get_sample_rate());
+    }
+
+    pub fn save_state(&self, writer: &mut StateWriter) {
+        self.delay_buf.save_state(writer);
+        self.damping_lpf.save_state(writer);
+    }
+
+    pub fn load_state(&mut self, reader: &mut StateReader) -> Option<()> {
+        self.delay_buf.load_state(reader)?;
+        self.damping_lpf.load_state(reader)?;
+
+        Some(())
     }
 
     pub fn process(&mut self, x_n: f32) -> f32 {
         let y_n = self.delay_buf.read_delayed_sample();
 
-        let g2 = self.lpf_g * (1_f32 - self.comb_g);
-        let lpf_sample = y_n + g2 * self.lpf_state;
+        let lpf_sample = self.damping_lpf.filter(y_n);
+        let damped_sample = y_n + self.damping_amount * (lpf_sample - y_n);
 
-        let delay_input = x_n + self.comb_g * lpf_sample;
-        self.lpf_state = lpf_sample;
+        let delay_input = x_n + self.comb_g * damped_sample;
 
         self.delay_buf.write_sample(delay_input);
 
@@ -88,9 +130,17 @@ impl LPFCombFilter {
     }
 }
 
+fn create_damping_lpf(damping_freq_hz: f32, sample_rate: f32) -> Biquad {
+    Biquad::new(BiquadCoefficients::second_order_lpf(
+        damping_freq_hz,
+        sample_rate,
+        None,
+    ))
+}
+
 fn calculate_comb_gain(delay_sample_count: f32, sample_rate: f32, rt60_ms: f32) -> f32 {
     let exponent = -3_f32 * delay_sample_count / sample_rate;
     let rt60_s = rt60_ms / 1000_f32;
 
-    10_f32.powf(exponent / rt60_s)
+    10_f32.powf(exponent / rt60_s).min(MAX_COMB_G)
 }
@@ -0,0 +1,304 @@
+use crate::conf::{
+    AdvertisedParameter, AudioConfig, BoardEffectConfigParameterValue, ParameterRange,
+};
+use crate::context::BoardContext;
+use crate::effects::basic_stereo_in_stereo_out;
+use crate::traits::AudioEffect;
+use crate::utils::delay_buf::DelayBuffer;
+use crate::utils::mathutils::{self, MonitorMode};
+use crate::utils::state::{StateReader, StateWriter};
+
+use super::{create_line, scaled_length};
+
+use std::cell::RefCell;
+
+// jezar's tunings, in samples at REFERENCE_SAMPLE_RATE. scaled to the real sample rate
+const REFERENCE_SAMPLE_RATE: f32 = 44100.0f32;
+const COMB_LENGTHS: [usize; 8] = [1116, 1188, 1277, 1356, 1422, 1491, 1557, 1617];
+const ALL_PASS_LENGTHS: [usize; 4] = [556, 441, 341, 225];
+// the right channel's lines are this much longer, so the two tails don't correlate
+const STEREO_SPREAD: usize = 23;
+
+const ALL_PASS_FEEDBACK: f32 = 0.5f32;
+// the combs sum eight copies of the input, so it goes in quietly
+const INPUT_GAIN: f32 = 0.015f32;
+const SCALE_ROOM: f32 = 0.28f32;
+const OFFSET_ROOM: f32 = 0.7f32;
+const SCALE_DAMPING: f32 = 0.4f32;
+
+const PARAMS: &'static [AdvertisedParameter] = &[
+    AdvertisedParameter {
+        // the combs' feedback. bigger rooms ring longer
+        name: "room_size",
+        range: ParameterRange::F(0.0f32, 1.0f32),
+        default_value: BoardEffectConfigParameterValue::F(0.5f32),
+    },
+    AdvertisedParameter {
+        // how fast the highs die out compared to the lows
+        name: "damping",
+        range: ParameterRange::F(0.0f32, 1.0f32),
+        default_value: BoardEffectConfigParameterValue::F(0.5f32),
+    },
+    AdvertisedParameter {
+        // 0 = the same (mono) tail on both sides, 1 = each side gets its own
+        name: "width",
+        range: ParameterRange::F(0.0f32, 1.0f32),
+        default_value: BoardEffectConfigParameterValue::F(1.0f32),
+    },
+    AdvertisedParameter {
+        name: "wet_dry_pct",
+        range: ParameterRange::F(0.0f32, 1.0f32),
+        default_value: BoardEffectConfigParameterValue::F(0.3f32),
+    },
+    AdvertisedParameter {
+        // 0 = wet/dry mix, 1 = wet only, 2 = dry only
+        name: "monitor_mode",
+        range: ParameterRange::N(0, MonitorMode::__NUM_MONITOR_MODES as i32 - 1),
+        default_value: BoardEffectConfigParameterValue::N(MonitorMode::Full as i32),
+    },
+    AdvertisedParameter {
+        // flips the polarity of both wet outputs
+        name: "wet_invert?",
+        range: ParameterRange::N(0, 1),
+        default_value: BoardEffectConfigParameterValue::N(0),
+    },
+];
+
+const PARAM_ROOM_SIZE: usize = 0;
+const PARAM_DAMPING: usize = 1;
+const PARAM_WIDTH: usize = 2;
+const PARAM_WET_DRY_PCT: usize = 3;
+const PARAM_MONITOR_MODE: usize = 4;
+const PARAM_WET_INVERT: usize = 5;
+
+// a comb with a one pole low pass in its feedback path
+struct FreeverbComb {
+    line: DelayBuffer,
+    filter_state: f32,
+}
+
+// freeverb's "all pass" (only truly all pass at a feedback of 0.618, but that's the sound)
+struct FreeverbAllPass {
+    line: DelayBuffer,
+}
+
+struct FreeverbChannel {
+    combs: Vec<FreeverbComb>,
+    all_passes: Vec<FreeverbAllPass>,
+}
+
+// reads = [L, R], writes = [L, R]. both sides are fed the sum of the inputs
+pub struct Freeverb {
+    params: Vec<BoardEffectConfigParameterValue>,
+    sample_rate: f32,
+
+    channels: RefCell<[FreeverbChannel; 2]>,
+}
+
+impl FreeverbComb {
+    fn process(&mut self, x_n: f32, feedback: f32, damping: f32) -> f32 {
+        let y_n = self.line.read_delayed_sample();
+
+        self.filter_state = y_n + damping * (self.filter_state - y_n);
+        self.line.write_sample(x_n + feedback * self.filter_state);
+
+        y_n
+    }
+}
+
+impl FreeverbAllPass {
+    fn process(&mut self, x_n: f32) -> f32 {
+        let delayed = self.line.read_delayed_sample();
+        self.line.write_sample(x_n + ALL_PASS_FEEDBACK * delayed);
+
+        delayed - x_n
+    }
+}
+
+impl FreeverbChannel {
+    fn new(sample_rate: f32, spread: usize) -> FreeverbChannel {
+        FreeverbChannel {
+            combs: COMB_LENGTHS
+                .iter()
+                .map(|length| FreeverbComb {
+                    line: create_line(
+                        scaled_length(length + spread, REFERENCE_SAMPLE_RATE, sample_rate),
+                        sample_rate,
+                    ),
+                    filter_state: 0.0f32,
+                })
+                .collect(),
+            all_passes: ALL_PASS_LENGTHS
+                .iter()
+                .map(|length| FreeverbAllPass {
+                    line: create_line(
+                        scaled_length(length + spread, REFERENCE_SAMPLE_RATE, sample_rate),
+                        sample_rate,
+                    ),
+                })
+                .collect(),
+        }
+    }
+
+    fn clear(&mut self) {
+        for comb in self.combs.iter_mut() {
+            comb.line.clear();
+            comb.filter_state = 0.0f32;
+        }
+
+        for all_pass in self.all_passes.iter_mut() {
+            all_pass.line.clear();
+        }
+    }
+
+    fn save_state(&self, writer: &mut StateWriter) {
+        for comb in self.combs.iter() {
+            comb.line.save_state(writer);
+            writer.write_f32(comb.filter_state);
+        }
+
+        for all_pass in self.all_passes.iter() {
+            all_pass.line.save_state(writer);
+        }
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Option<()> {
+        for comb in self.combs.iter_mut() {
+            comb.line.load_state(reader)?;
+            comb.filter_state = reader.read_f32()?;
+        }
+
+        for all_pass in self.all_passes.iter_mut() {
+            all_pass.line.load_state(reader)?;
+        }
+
+        Some(())
+    }
+
+    fn process(&mut self, x_n: f32, feedback: f32, damping: f32) -> f32 {
+        let mut y_n = 0.0f32;
+        for comb in self.combs.iter_mut() {
+            y_n += comb.process(x_n, feedback, damping);
+        }
+
+        for all_pass in self.all_passes.iter_mut() {
+            y_n = all_pass.process(y_n);
+        }
+
+        y_n
+    }
+}
+
+impl Freeverb {
+    pub fn new(ac: AudioConfig) -> Freeverb {
+        let mut params = Vec::with_capacity(PARAMS.len());
+        for i in 0..PARAMS.len() {
+            params.push(PARAMS[i].default_value);
+        }
+
+        Freeverb {
+            params,
+            sample_rate: ac.sample_rate,
+            channels: RefCell::new(Freeverb::create_channels(ac.sample_rate)),
+        }
+    }
+
+    pub fn info() -> &'static [AdvertisedParameter] {
+        PARAMS
+    }
+
+    fn create_channels(sample_rate: f32) -> [FreeverbChannel; 2] {
+        [
+            FreeverbChannel::new(sample_rate, 0),
+            FreeverbChannel::new(sample_rate, STEREO_SPREAD),
+        ]
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Option<()> {
+        for channel in self.channels.get_mut().iter_mut() {
+            channel.load_state(reader)?;
+        }
+
+        Some(())
+    }
+}
+
+impl AudioEffect for Freeverb {
+    fn advertise_parameters(&self) -> &'static [AdvertisedParameter] {
+        Freeverb::info()
+    }
+
+    fn set_audio_parameters(&mut self, new_config: &AudioConfig) {
+        self.sample_rate = new_config.sample_rate;
+        *self.channels.get_mut() = Freeverb::create_channels(new_config.sample_rate);
+    }
+
+    fn reset(&mut self) {
+        for channel in self.channels.get_mut().iter_mut() {
+            channel.clear();
+        }
+    }
+
+    fn serialize_state(&self, writer: &mut StateWriter) -> bool {
+        for channel in self.channels.borrow().iter() {
+            channel.save_state(writer);
+        }
+
+        true
+    }
+
+    fn deserialize_state(&mut self, reader: &mut StateReader) -> bool {
+        self.load_state(reader).is_some()
+    }
+
+    fn set_effect_parameter(
+        &mut self,
+        param_idx: usize,
+        param_value: BoardEffectConfigParameterValue,
+    ) {
+        self.params[param_idx] = param_value;
+    }
+
+    fn execute(&self, context: &BoardContext, connection_idx: usize, num_samples: usize) {
+        let maybe_bufs = basic_stereo_in_stereo_out(context, connection_idx, num_samples);
+        if let None = maybe_bufs {
+            return;
+        }
+
+        let (read_bufs, mut write_bufs) = maybe_bufs.unwrap();
+
+        let feedback = self.params[PARAM_ROOM_SIZE].as_flt() * SCALE_ROOM + OFFSET_ROOM;
+        let damping = self.params[PARAM_DAMPING].as_flt() * SCALE_DAMPING;
+
+        let (dryness, mut wetness) = mathutils::wet_dry_gains(
+            self.params[PARAM_WET_DRY_PCT].as_flt(),
+            self.params[PARAM_MONITOR_MODE].as_enum(),
+        );
+        if self.params[PARAM_WET_INVERT].as_int() != 0 {
+            wetness = -wetness;
+        }
+        let width = self.params[PARAM_WIDTH].as_flt();
+        let wet_same_side = wetness * (0.5f32 + 0.5f32 * width);
+        let wet_other_side = wetness * (0.5f32 - 0.5f32 * width);
+
+        let mut channels = self.channels.borrow_mut();
+        let (left_channel, right_channel) = channels.split_at_mut(1);
+        for i in 0..num_samples {
+            let x_l = read_bufs[0].buf_read(i);
+            let x_r = read_bufs[1].buf_read(i);
+            let input = (x_l + x_r) * INPUT_GAIN;
+
+            let y_l = left_channel[0].process(input, feedback, damping);
+            let y_r = right_channel[0].process(input, feedback, damping);
+
+            write_bufs[0].buf_write(
+                i,
+                dryness * x_l + wet_same_side * y_l + wet_other_side * y_r,
+            );
+            write_bufs[1].buf_write(
+                i,
+                dryness * x_r + wet_same_side * y_r + wet_other_side * y_l,
+            );
+        }
+    }
+}
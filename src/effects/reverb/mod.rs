@@ -1,2 +1,28 @@
 pub mod apf;
 pub mod comb;
+mod freeverb;
+mod plate;
+pub mod pre_delay;
+mod schroeder;
+
+pub use freeverb::Freeverb;
+pub use plate::PlateReverb;
+pub use schroeder::SchroederReverb;
+
+use crate::utils::delay_buf::DelayBuffer;
+
+// a plain delay line of a whole number of samples
+// the line is read before it's written, which adds a sample, so it's set one short
+fn create_line(length_samples: usize, sample_rate: f32) -> DelayBuffer {
+    // 1 ms of slack so the full length doesn't get clamped
+    let max_delay_ms = length_samples as f32 * 1000.0f32 / sample_rate + 1.0f32;
+    let mut line = DelayBuffer::with_sample_rate_and_max_delay(sample_rate, max_delay_ms);
+    line.set_delay_sample_count_directly(length_samples as i32 - 1, 0.0f32);
+
+    line
+}
+
+// published tunings are in samples at some reference rate
+fn scaled_length(reference_length: usize, reference_sample_rate: f32, sample_rate: f32) -> usize {
+    ((reference_length as f32 * sample_rate / reference_sample_rate).round() as usize).max(1)
+}
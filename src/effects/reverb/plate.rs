@@ -0,0 +1,465 @@
+use crate::conf::{
+    AdvertisedParameter, AudioConfig, BoardEffectConfigParameterValue, ParameterRange,
+};
+use crate::context::BoardContext;
+use crate::effects::basic_stereo_in_stereo_out;
+use crate::traits::AudioEffect;
+use crate::utils::delay_buf::DelayBuffer;
+use crate::utils::mathutils::{self, vtanh, MonitorMode};
+use crate::utils::state::{StateReader, StateWriter};
+
+use super::apf::DelayAPF;
+use super::pre_delay::{PreDelay, MAX_PRE_DELAY_MS};
+use super::{create_line, scaled_length};
+
+use std::cell::RefCell;
+
+// dattorro's tunings ("effect design part 1", 1997), in samples at REFERENCE_SAMPLE_RATE
+const REFERENCE_SAMPLE_RATE: f32 = 29761.0f32;
+
+// one pole low pass on the input. close to 1 lets nearly everything through
+const BANDWIDTH: f32 = 0.9995f32;
+
+// four series all passes that smear the input into a burst of noise before the tank
+const INPUT_DIFFUSION_LENGTHS: [usize; 4] = [142, 107, 379, 277];
+const INPUT_DIFFUSION_G: [f32; 4] = [0.75f32, 0.75f32, 0.625f32, 0.625f32];
+
+// each half of the tank, in order: modulated all pass, delay, (damping), all pass, delay
+const TANK_LENGTHS: [[usize; 4]; 2] = [[672, 4453, 1800, 3720], [908, 4217, 2656, 3163]];
+// the tank's first all passes run the other way round from the rest
+const DECAY_DIFFUSION_1: f32 = -0.7f32;
+
+// how far the first all passes' delays wander, at full modulation
+const MODULATION_EXCURSION: usize = 16;
+// slightly different rates, so the halves never line up
+const MODULATION_RATES_HZ: [f32; 2] = [1.0f32, 0.8f32];
+
+// damping of 1 would hold the low pass where it is forever
+const MAX_DAMPING: f32 = 0.9f32;
+
+// tanh's gain going into the feedback at full drive
+const MAX_DRIVE_GAIN: f32 = 8.0f32;
+
+const OUTPUT_GAIN: f32 = 0.6f32;
+
+const PARAMS: &'static [AdvertisedParameter] = &[
+    AdvertisedParameter {
+        // how much of the tank goes round again. higher rings longer
+        name: "decay",
+        range: ParameterRange::F(0.0f32, 0.99f32),
+        default_value: BoardEffectConfigParameterValue::F(0.5f32),
+    },
+    AdvertisedParameter {
+        // how fast the highs die out compared to the lows
+        name: "damping",
+        range: ParameterRange::F(0.0f32, 1.0f32),
+        default_value: BoardEffectConfigParameterValue::F(0.3f32),
+    },
+    AdvertisedParameter {
+        // how far the tank's all passes are swept. keeps the tail from ringing metallic
+        name: "modulation",
+        range: ParameterRange::F(0.0f32, 1.0f32),
+        default_value: BoardEffectConfigParameterValue::F(0.5f32),
+    },
+    AdvertisedParameter {
+        // soft saturation on what goes round the tank. loud tails get squashed and warmer,
+        // quiet ones are left alone. 0 = clean
+        name: "drive",
+        range: ParameterRange::F(0.0f32, 1.0f32),
+        default_value: BoardEffectConfigParameterValue::F(0.0f32),
+    },
+    AdvertisedParameter {
+        name: "pre_delay_ms",
+        range: ParameterRange::F(0.0f32, MAX_PRE_DELAY_MS),
+        default_value: BoardEffectConfigParameterValue::F(0.0f32),
+    },
+    AdvertisedParameter {
+        name: "wet_dry_pct",
+        range: ParameterRange::F(0.0f32, 1.0f32),
+        default_value: BoardEffectConfigParameterValue::F(0.3f32),
+    },
+    AdvertisedParameter {
+        // 0 = wet/dry mix, 1 = wet only, 2 = dry only
+        name: "monitor_mode",
+        range: ParameterRange::N(0, MonitorMode::__NUM_MONITOR_MODES as i32 - 1),
+        default_value: BoardEffectConfigParameterValue::N(MonitorMode::Full as i32),
+    },
+    AdvertisedParameter {
+        // flips the polarity of the tank's output (both sides)
+        name: "wet_invert?",
+        range: ParameterRange::N(0, 1),
+        default_value: BoardEffectConfigParameterValue::N(0),
+    },
+];
+
+const PARAM_DECAY: usize = 0;
+const PARAM_DAMPING: usize = 1;
+const PARAM_MODULATION: usize = 2;
+const PARAM_DRIVE: usize = 3;
+const PARAM_PRE_DELAY_MS: usize = 4;
+const PARAM_WET_DRY_PCT: usize = 5;
+const PARAM_MONITOR_MODE: usize = 6;
+const PARAM_WET_INVERT: usize = 7;
+
+#[derive(Clone, Copy)]
+enum TankLine {
+    FirstDelay,
+    DecayAPF,
+    SecondDelay,
+}
+
+// each output is a sum of taps all over the tank instead of the tank's own output,
+// which is what gets rid of any audible early echo
+struct OutputTap {
+    half: usize,
+    line: TankLine,
+    reference_delay: usize,
+    gain: f32,
+}
+
+const fn tap(half: usize, line: TankLine, reference_delay: usize, gain: f32) -> OutputTap {
+    OutputTap {
+        half,
+        line,
+        reference_delay,
+        gain,
+    }
+}
+
+const LEFT_TAPS: [OutputTap; 7] = [
+    tap(1, TankLine::FirstDelay, 266, 1.0f32),
+    tap(1, TankLine::FirstDelay, 2974, 1.0f32),
+    tap(1, TankLine::DecayAPF, 1913, -1.0f32),
+    tap(1, TankLine::SecondDelay, 1996, 1.0f32),
+    tap(0, TankLine::FirstDelay, 1990, -1.0f32),
+    tap(0, TankLine::DecayAPF, 187, -1.0f32),
+    tap(0, TankLine::SecondDelay, 1066, -1.0f32),
+];
+
+const RIGHT_TAPS: [OutputTap; 7] = [
+    tap(0, TankLine::FirstDelay, 353, 1.0f32),
+    tap(0, TankLine::FirstDelay, 3627, 1.0f32),
+    tap(0, TankLine::DecayAPF, 1228, -1.0f32),
+    tap(0, TankLine::SecondDelay, 2673, 1.0f32),
+    tap(1, TankLine::FirstDelay, 2111, -1.0f32),
+    tap(1, TankLine::DecayAPF, 335, -1.0f32),
+    tap(1, TankLine::SecondDelay, 121, -1.0f32),
+];
+
+fn reference_ms(reference_length: usize) -> f32 {
+    reference_length as f32 * 1000.0f32 / REFERENCE_SAMPLE_RATE
+}
+
+struct PlateTankHalf {
+    modulated_apf: DelayAPF,
+    first_delay: DelayBuffer,
+    damping_state: f32,
+    decay_apf: DelayAPF,
+    second_delay: DelayBuffer,
+}
+
+struct PlateNetwork {
+    pre_delay: PreDelay,
+    bandwidth_state: f32,
+    input_diffusers: Vec<DelayAPF>,
+    tank: [PlateTankHalf; 2],
+
+    decay: f32,
+    damping: f32,
+    drive_gain: f32,
+}
+
+// pre-delay -> bandwidth low pass -> input diffusion -> a figure eight tank, where each half
+// feeds the other. reads = [L, R], writes = [L, R]. the tank is fed the sum of the inputs
+pub struct PlateReverb {
+    params: Vec<BoardEffectConfigParameterValue>,
+    network: RefCell<PlateNetwork>,
+}
+
+impl PlateTankHalf {
+    fn new(lengths: &[usize; 4], sample_rate: f32) -> PlateTankHalf {
+        let line = |length: usize| {
+            create_line(
+                scaled_length(length, REFERENCE_SAMPLE_RATE, sample_rate),
+                sample_rate,
+            )
+        };
+
+        PlateTankHalf {
+            modulated_apf: DelayAPF::new(reference_ms(lengths[0]), DECAY_DIFFUSION_1, sample_rate),
+            first_delay: line(lengths[1]),
+            damping_state: 0.0f32,
+            decay_apf: DelayAPF::new(reference_ms(lengths[2]), 0.0f32, sample_rate),
+            second_delay: line(lengths[3]),
+        }
+    }
+
+    // the input to this half is the other half's output plus the diffused input
+    fn output(&self, decay: f32) -> f32 {
+        decay * self.second_delay.read_delayed_sample()
+    }
+
+    fn read_tap(&self, line: TankLine, delay_ms: f32) -> f32 {
+        match line {
+            TankLine::FirstDelay => self.first_delay.read_delayed_sample_at_ms(delay_ms),
+            TankLine::DecayAPF => self.decay_apf.read_tap_ms(delay_ms),
+            TankLine::SecondDelay => self.second_delay.read_delayed_sample_at_ms(delay_ms),
+        }
+    }
+
+    fn clear(&mut self) {
+        self.modulated_apf.clear();
+        self.first_delay.clear();
+        self.damping_state = 0.0f32;
+        self.decay_apf.clear();
+        self.second_delay.clear();
+    }
+
+    fn save_state(&self, writer: &mut StateWriter) {
+        self.modulated_apf.save_state(writer);
+        self.first_delay.save_state(writer);
+        writer.write_f32(self.damping_state);
+        self.decay_apf.save_state(writer);
+        self.second_delay.save_state(writer);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Option<()> {
+        self.modulated_apf.load_state(reader)?;
+        self.first_delay.load_state(reader)?;
+        self.damping_state = reader.read_f32()?;
+        self.decay_apf.load_state(reader)?;
+        self.second_delay.load_state(reader)?;
+
+        Some(())
+    }
+
+    fn process(&mut self, x_n: f32, decay: f32, damping: f32) {
+        let diffused = self.modulated_apf.process(x_n);
+
+        let delayed = self.first_delay.read_delayed_sample();
+        self.first_delay.write_sample(diffused);
+
+        self.damping_state = delayed + damping * (self.damping_state - delayed);
+
+        let diffused = self.decay_apf.process(decay * self.damping_state);
+        self.second_delay.write_sample(diffused);
+    }
+}
+
+impl PlateNetwork {
+    fn new(params: &Vec<BoardEffectConfigParameterValue>, sample_rate: f32) -> PlateNetwork {
+        let mut network = PlateNetwork {
+            pre_delay: PreDelay::new(params[PARAM_PRE_DELAY_MS].as_flt(), sample_rate),
+            bandwidth_state: 0.0f32,
+            input_diffusers: INPUT_DIFFUSION_LENGTHS
+                .iter()
+                .zip(INPUT_DIFFUSION_G.iter())
+                .map(|(length, g)| DelayAPF::new(reference_ms(*length), *g, sample_rate))
+                .collect(),
+            tank: [
+                PlateTankHalf::new(&TANK_LENGTHS[0], sample_rate),
+                PlateTankHalf::new(&TANK_LENGTHS[1], sample_rate),
+            ],
+            decay: 0.0f32,
+            damping: 0.0f32,
+            drive_gain: 0.0f32,
+        };
+
+        network.set_decay(params[PARAM_DECAY].as_flt());
+        network.set_damping(params[PARAM_DAMPING].as_flt());
+        network.set_modulation(params[PARAM_MODULATION].as_flt());
+        network.set_drive(params[PARAM_DRIVE].as_flt());
+
+        network
+    }
+
+    fn set_decay(&mut self, decay: f32) {
+        self.decay = decay;
+
+        // the second all passes diffuse less as the tail gets shorter
+        let decay_diffusion_2 = num::clamp(decay + 0.15f32, 0.25f32, 0.5f32);
+        for half in self.tank.iter_mut() {
+            half.decay_apf.set_apf_g(decay_diffusion_2);
+        }
+    }
+
+    fn set_damping(&mut self, damping: f32) {
+        self.damping = damping * MAX_DAMPING;
+    }
+
+    fn set_modulation(&mut self, modulation: f32) {
+        for (half, rate_hz) in self.tank.iter_mut().zip(MODULATION_RATES_HZ.iter()) {
+            half.modulated_apf.set_modulation(
+                *rate_hz,
+                modulation,
+                reference_ms(MODULATION_EXCURSION),
+            );
+        }
+    }
+
+    fn set_drive(&mut self, drive: f32) {
+        self.drive_gain = drive * MAX_DRIVE_GAIN;
+    }
+
+    // tanh(g * x) / g has a slope of 1 around 0 and never more anywhere else, so small signals go
+    // through as they are and the loop gain can only come down
+    fn saturate(&self, x_n: f32) -> f32 {
+        if self.drive_gain <= 0.0f32 {
+            return x_n;
+        }
+
+        vtanh(self.drive_gain * x_n) / self.drive_gain
+    }
+
+    fn clear(&mut self) {
+        self.pre_delay.clear();
+        self.bandwidth_state = 0.0f32;
+        for apf in self.input_diffusers.iter_mut() {
+            apf.clear();
+        }
+        for half in self.tank.iter_mut() {
+            half.clear();
+        }
+    }
+
+    fn save_state(&self, writer: &mut StateWriter) {
+        self.pre_delay.save_state(writer);
+        writer.write_f32(self.bandwidth_state);
+        for apf in self.input_diffusers.iter() {
+            apf.save_state(writer);
+        }
+        for half in self.tank.iter() {
+            half.save_state(writer);
+        }
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Option<()> {
+        self.pre_delay.load_state(reader)?;
+        self.bandwidth_state = reader.read_f32()?;
+        for apf in self.input_diffusers.iter_mut() {
+            apf.load_state(reader)?;
+        }
+        for half in self.tank.iter_mut() {
+            half.load_state(reader)?;
+        }
+
+        Some(())
+    }
+
+    fn sum_taps(&self, taps: &[OutputTap]) -> f32 {
+        let mut y_n = 0.0f32;
+        for tap in taps.iter() {
+            y_n += tap.gain
+                * self.tank[tap.half].read_tap(tap.line, reference_ms(tap.reference_delay));
+        }
+
+        OUTPUT_GAIN * y_n
+    }
+
+    fn process(&mut self, x_n: f32) -> (f32, f32) {
+        let pre_delayed = self.pre_delay.process(x_n);
+
+        self.bandwidth_state += BANDWIDTH * (pre_delayed - self.bandwidth_state);
+
+        let mut diffused = self.bandwidth_state;
+        for apf in self.input_diffusers.iter_mut() {
+            diffused = apf.process(diffused);
+        }
+
+        // both halves read what the other put out before either is written
+        let left_feedback = self.saturate(self.tank[1].output(self.decay));
+        let right_feedback = self.saturate(self.tank[0].output(self.decay));
+        self.tank[0].process(diffused + left_feedback, self.decay, self.damping);
+        self.tank[1].process(diffused + right_feedback, self.decay, self.damping);
+
+        (self.sum_taps(&LEFT_TAPS), self.sum_taps(&RIGHT_TAPS))
+    }
+}
+
+impl PlateReverb {
+    pub fn new(ac: AudioConfig) -> PlateReverb {
+        let mut params = Vec::with_capacity(PARAMS.len());
+        for i in 0..PARAMS.len() {
+            params.push(PARAMS[i].default_value);
+        }
+
+        PlateReverb {
+            network: RefCell::new(PlateNetwork::new(&params, ac.sample_rate)),
+            params,
+        }
+    }
+
+    pub fn info() -> &'static [AdvertisedParameter] {
+        PARAMS
+    }
+}
+
+impl AudioEffect for PlateReverb {
+    fn advertise_parameters(&self) -> &'static [AdvertisedParameter] {
+        PlateReverb::info()
+    }
+
+    // every line is scaled from the reference rate, so the whole network is rebuilt
+    fn set_audio_parameters(&mut self, new_config: &AudioConfig) {
+        *self.network.get_mut() = PlateNetwork::new(&self.params, new_config.sample_rate);
+    }
+
+    fn reset(&mut self) {
+        self.network.get_mut().clear();
+    }
+
+    fn serialize_state(&self, writer: &mut StateWriter) -> bool {
+        self.network.borrow().save_state(writer);
+
+        true
+    }
+
+    fn deserialize_state(&mut self, reader: &mut StateReader) -> bool {
+        self.network.get_mut().load_state(reader).is_some()
+    }
+
+    fn set_effect_parameter(
+        &mut self,
+        param_idx: usize,
+        param_value: BoardEffectConfigParameterValue,
+    ) {
+        self.params[param_idx] = param_value;
+
+        let network = self.network.get_mut();
+        match param_idx {
+            PARAM_DECAY => network.set_decay(param_value.as_flt()),
+            PARAM_DAMPING => network.set_damping(param_value.as_flt()),
+            PARAM_MODULATION => network.set_modulation(param_value.as_flt()),
+            PARAM_DRIVE => network.set_drive(param_value.as_flt()),
+            PARAM_PRE_DELAY_MS => network.pre_delay.set_pre_delay_ms(param_value.as_flt()),
+            _ => (),
+        }
+    }
+
+    fn execute(&self, context: &BoardContext, connection_idx: usize, num_samples: usize) {
+        let maybe_bufs = basic_stereo_in_stereo_out(context, connection_idx, num_samples);
+        if let None = maybe_bufs {
+            return;
+        }
+
+        let (read_bufs, mut write_bufs) = maybe_bufs.unwrap();
+
+        let (dryness, mut wetness) = mathutils::wet_dry_gains(
+            self.params[PARAM_WET_DRY_PCT].as_flt(),
+            self.params[PARAM_MONITOR_MODE].as_enum(),
+        );
+        if self.params[PARAM_WET_INVERT].as_int() != 0 {
+            wetness = -wetness;
+        }
+
+        let mut network = self.network.borrow_mut();
+        for i in 0..num_samples {
+            let x_l = read_bufs[0].buf_read(i);
+            let x_r = read_bufs[1].buf_read(i);
+            let (y_l, y_r) = network.process(0.5f32 * (x_l + x_r));
+
+            write_bufs[0].buf_write(i, dryness * x_l + wetness * y_l);
+            write_bufs[1].buf_write(i, dryness * x_r + wetness * y_r);
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use crate::utils::delay_buf::DelayBuffer;
+use crate::utils::state::{StateReader, StateWriter};
+
+// pre-delays past this stop sounding like room size and start sounding like an echo
+pub const MAX_PRE_DELAY_MS: f32 = 250.0f32;
+
+// the gap between the direct sound and the start of the tail
+// sits in front of a reverb network with its own short delay line,
+// so it doesn't have to share (or be sized like) the network's delays
+pub struct PreDelay {
+    delay_buf: DelayBuffer,
+    pre_delay_ms: f32,
+}
+
+impl PreDelay {
+    pub fn new(pre_delay_ms: f32, sample_rate: f32) -> PreDelay {
+        // 1 ms of slack so the full MAX_PRE_DELAY_MS doesn't get clamped
+        let mut delay_buf =
+            DelayBuffer::with_sample_rate_and_max_delay(sample_rate, MAX_PRE_DELAY_MS + 1.0f32);
+        let pre_delay_ms = num::clamp(pre_delay_ms, 0.0f32, MAX_PRE_DELAY_MS);
+        delay_buf.set_delay_time_ms(pre_delay_ms, true);
+
+        PreDelay {
+            delay_buf,
+            pre_delay_ms,
+        }
+    }
+
+    pub fn change_sample_rate(&mut self, new_sample_rate: f32) {
+        self.delay_buf.change_sample_rate(new_sample_rate);
+    }
+
+    pub fn set_pre_delay_ms(&mut self, pre_delay_ms: f32) {
+        self.pre_delay_ms = num::clamp(pre_delay_ms, 0.0f32, MAX_PRE_DELAY_MS);
+        self.delay_buf.set_delay_time_ms(self.pre_delay_ms, true);
+    }
+
+    pub fn clear(&mut self) {
+        self.delay_buf.clear();
+    }
+
+    pub fn save_state(&self, writer: &mut StateWriter) {
+        self.delay_buf.save_state(writer);
+    }
+
+    pub fn load_state(&mut self, reader: &mut StateReader) -> Option<()> {
+        self.delay_buf.load_state(reader)
+    }
+
+    // writing before reading means a pre-delay of 0 passes the input straight through
+    pub fn process(&mut self, x_n: f32) -> f32 {
+        self.delay_buf.write_sample(x_n);
+        self.delay_buf.read_delayed_sample()
+    }
+}
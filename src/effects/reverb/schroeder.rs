@@ -0,0 +1,240 @@
+use crate::conf::{
+    AdvertisedParameter, AudioConfig, BoardEffectConfigParameterValue, ParameterRange,
+};
+use crate::context::BoardContext;
+use crate::effects::basic_single_in_single_out;
+use crate::traits::AudioEffect;
+use crate::utils::mathutils::{self, MonitorMode};
+use crate::utils::state::{StateReader, StateWriter};
+
+use super::apf::DelayAPF;
+use super::comb::LPFCombFilter;
+use super::pre_delay::{PreDelay, MAX_PRE_DELAY_MS};
+
+use std::cell::RefCell;
+
+// schroeder's original (mutually prime-ish) delays. the combs set the echo density and decay,
+// the all passes smear each echo out without coloring it
+const COMB_DELAYS_MS: [f32; 4] = [29.7f32, 37.1f32, 41.1f32, 43.7f32];
+const APF_DELAYS_MS: [f32; 2] = [5.0f32, 1.7f32];
+const APF_G: f32 = 0.7f32;
+
+// the combs' low pass corner. damping only sets how much of it is blended in
+const DAMPING_FREQ_HZ: f32 = 4000.0f32;
+
+const PARAMS: &'static [AdvertisedParameter] = &[
+    AdvertisedParameter {
+        name: "rt60_ms",
+        range: ParameterRange::F(100.0f32, 10000.0f32),
+        default_value: BoardEffectConfigParameterValue::F(1500.0f32),
+    },
+    AdvertisedParameter {
+        // 0 = every frequency decays at the same rate, 1 = the highs die out much faster
+        name: "damping",
+        range: ParameterRange::F(0.0f32, 1.0f32),
+        default_value: BoardEffectConfigParameterValue::F(0.3f32),
+    },
+    AdvertisedParameter {
+        name: "pre_delay_ms",
+        range: ParameterRange::F(0.0f32, MAX_PRE_DELAY_MS),
+        default_value: BoardEffectConfigParameterValue::F(0.0f32),
+    },
+    AdvertisedParameter {
+        name: "wet_dry_pct",
+        range: ParameterRange::F(0.0f32, 1.0f32),
+        default_value: BoardEffectConfigParameterValue::F(0.3f32),
+    },
+    AdvertisedParameter {
+        // 0 = wet/dry mix, 1 = wet only, 2 = dry only
+        name: "monitor_mode",
+        range: ParameterRange::N(0, MonitorMode::__NUM_MONITOR_MODES as i32 - 1),
+        default_value: BoardEffectConfigParameterValue::N(MonitorMode::Full as i32),
+    },
+    AdvertisedParameter {
+        // flips the polarity of the wet signal
+        name: "wet_invert?",
+        range: ParameterRange::N(0, 1),
+        default_value: BoardEffectConfigParameterValue::N(0),
+    },
+];
+
+const PARAM_RT60_MS: usize = 0;
+const PARAM_DAMPING: usize = 1;
+const PARAM_PRE_DELAY_MS: usize = 2;
+const PARAM_WET_DRY_PCT: usize = 3;
+const PARAM_MONITOR_MODE: usize = 4;
+const PARAM_WET_INVERT: usize = 5;
+
+struct SchroederNetwork {
+    pre_delay: PreDelay,
+    combs: Vec<LPFCombFilter>,
+    apfs: Vec<DelayAPF>,
+}
+
+// pre-delay -> four parallel combs -> two series all passes
+pub struct SchroederReverb {
+    params: Vec<BoardEffectConfigParameterValue>,
+    network: RefCell<SchroederNetwork>,
+}
+
+impl SchroederNetwork {
+    fn new(params: &Vec<BoardEffectConfigParameterValue>, sample_rate: f32) -> SchroederNetwork {
+        let rt60_ms = params[PARAM_RT60_MS].as_flt();
+        let damping = params[PARAM_DAMPING].as_flt();
+
+        SchroederNetwork {
+            pre_delay: PreDelay::new(params[PARAM_PRE_DELAY_MS].as_flt(), sample_rate),
+            combs: COMB_DELAYS_MS
+                .iter()
+                .map(|delay_ms| {
+                    LPFCombFilter::new(*delay_ms, sample_rate, rt60_ms, DAMPING_FREQ_HZ, damping)
+                })
+                .collect(),
+            apfs: APF_DELAYS_MS
+                .iter()
+                .map(|delay_ms| DelayAPF::new(*delay_ms, APF_G, sample_rate))
+                .collect(),
+        }
+    }
+
+    fn process(&mut self, x_n: f32) -> f32 {
+        let pre_delayed = self.pre_delay.process(x_n);
+
+        let mut y_n = 0.0f32;
+        for comb in self.combs.iter_mut() {
+            y_n += comb.process(pre_delayed);
+        }
+        y_n /= self.combs.len() as f32;
+
+        for apf in self.apfs.iter_mut() {
+            y_n = apf.process(y_n);
+        }
+
+        y_n
+    }
+}
+
+impl SchroederReverb {
+    pub fn new(ac: AudioConfig) -> SchroederReverb {
+        let mut params = Vec::with_capacity(PARAMS.len());
+        for i in 0..PARAMS.len() {
+            params.push(PARAMS[i].default_value);
+        }
+
+        SchroederReverb {
+            network: RefCell::new(SchroederNetwork::new(&params, ac.sample_rate)),
+            params,
+        }
+    }
+
+    pub fn info() -> &'static [AdvertisedParameter] {
+        PARAMS
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Option<()> {
+        let network = self.network.get_mut();
+        network.pre_delay.load_state(reader)?;
+        for comb in network.combs.iter_mut() {
+            comb.load_state(reader)?;
+        }
+        for apf in network.apfs.iter_mut() {
+            apf.load_state(reader)?;
+        }
+
+        Some(())
+    }
+}
+
+impl AudioEffect for SchroederReverb {
+    fn advertise_parameters(&self) -> &'static [AdvertisedParameter] {
+        SchroederReverb::info()
+    }
+
+    fn set_audio_parameters(&mut self, new_config: &AudioConfig) {
+        let network = self.network.get_mut();
+        network.pre_delay.change_sample_rate(new_config.sample_rate);
+        for comb in network.combs.iter_mut() {
+            comb.change_sample_rate(new_config.sample_rate);
+        }
+        for apf in network.apfs.iter_mut() {
+            apf.change_sample_rate(new_config.sample_rate);
+        }
+    }
+
+    fn reset(&mut self) {
+        let network = self.network.get_mut();
+        network.pre_delay.clear();
+        for comb in network.combs.iter_mut() {
+            comb.clear();
+        }
+        for apf in network.apfs.iter_mut() {
+            apf.clear();
+        }
+    }
+
+    fn serialize_state(&self, writer: &mut StateWriter) -> bool {
+        let network = self.network.borrow();
+        network.pre_delay.save_state(writer);
+        for comb in network.combs.iter() {
+            comb.save_state(writer);
+        }
+        for apf in network.apfs.iter() {
+            apf.save_state(writer);
+        }
+
+        true
+    }
+
+    fn deserialize_state(&mut self, reader: &mut StateReader) -> bool {
+        self.load_state(reader).is_some()
+    }
+
+    fn set_effect_parameter(
+        &mut self,
+        param_idx: usize,
+        param_value: BoardEffectConfigParameterValue,
+    ) {
+        self.params[param_idx] = param_value;
+
+        let network = self.network.get_mut();
+        match param_idx {
+            PARAM_RT60_MS => {
+                for comb in network.combs.iter_mut() {
+                    comb.set_rt60_ms(param_value.as_flt());
+                }
+            }
+            PARAM_DAMPING => {
+                for comb in network.combs.iter_mut() {
+                    comb.set_damping_amount(param_value.as_flt());
+                }
+            }
+            PARAM_PRE_DELAY_MS => network.pre_delay.set_pre_delay_ms(param_value.as_flt()),
+            _ => (),
+        }
+    }
+
+    fn execute(&self, context: &BoardContext, connection_idx: usize, num_samples: usize) {
+        let maybe_bufs = basic_single_in_single_out(context, connection_idx, num_samples);
+        if let None = maybe_bufs {
+            return;
+        }
+
+        let (read_buf, mut write_buf) = maybe_bufs.unwrap();
+
+        let (dryness, mut wetness) = mathutils::wet_dry_gains(
+            self.params[PARAM_WET_DRY_PCT].as_flt(),
+            self.params[PARAM_MONITOR_MODE].as_enum(),
+        );
+        if self.params[PARAM_WET_INVERT].as_int() != 0 {
+            wetness = -wetness;
+        }
+
+        let mut network = self.network.borrow_mut();
+        for i in 0..num_samples {
+            let x_n = read_buf.buf_read(i);
+            let y_n = network.process(x_n);
+
+            write_buf.buf_write(i, dryness * x_n + wetness * y_n);
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use crate::conf::{AdvertisedParameter, AudioConfig, BoardConfig, BoardEffectConfigParameterValue};
+use crate::context::{BoardContext, MAX_ALLOWABLE_INPUTS, MAX_ALLOWABLE_OUTPUTS};
+use crate::effects::loaded_set;
+use crate::errors::OttersInitError;
+use crate::otters::Otters;
+use crate::traits::AudioEffect;
+use crate::utils::state::{StateReader, StateWriter};
+
+use std::cell::RefCell;
+
+// the sub board's own effects are configured by its board config
+const PARAMS: &'static [AdvertisedParameter] = &[];
+
+// runs a whole board as one effect. reads[n] feeds the sub board's @SOURCE_n and
+// @SINK_n ends up in writes[n]. the sub board can use any of the default effects
+pub struct SubBoard {
+    board: RefCell<Otters>,
+
+    // the sub board's sources and sinks are bound to these, so they must never be resized
+    source_bufs: RefCell<Vec<Vec<f32>>>,
+    sink_bufs: RefCell<Vec<Vec<f32>>>,
+}
+
+impl SubBoard {
+    pub fn new(ac: AudioConfig, board_config: BoardConfig) -> Result<SubBoard, OttersInitError> {
+        let mut board = Otters::create_from_config(ac, loaded_set(), board_config)?;
+
+        let source_bufs: Vec<Vec<f32>> = (0..MAX_ALLOWABLE_INPUTS)
+            .map(|_| vec![0.0f32; ac.max_block_size])
+            .collect();
+        let mut sink_bufs: Vec<Vec<f32>> = (0..MAX_ALLOWABLE_OUTPUTS)
+            .map(|_| vec![0.0f32; ac.max_block_size])
+            .collect();
+
+        for (i, source_buf) in source_bufs.iter().enumerate() {
+            board.bind_input(i, source_buf.as_ptr());
+        }
+
+        for (i, sink_buf) in sink_bufs.iter_mut().enumerate() {
+            board.bind_output(i, sink_buf.as_mut_ptr());
+        }
+
+        Ok(SubBoard {
+            board: RefCell::new(board),
+            source_bufs: RefCell::new(source_bufs),
+            sink_bufs: RefCell::new(sink_bufs),
+        })
+    }
+
+    pub fn info() -> &'static [AdvertisedParameter] {
+        PARAMS
+    }
+}
+
+impl AudioEffect for SubBoard {
+    fn advertise_parameters(&self) -> &'static [AdvertisedParameter] {
+        SubBoard::info()
+    }
+
+    // the factory builds a new sub board for a new audio config
+    fn set_audio_parameters(&mut self, _new_config: &AudioConfig) {}
+
+    fn set_effect_parameter(
+        &mut self,
+        _param_idx: usize,
+        _param_value: BoardEffectConfigParameterValue,
+    ) {
+    }
+
+    fn reset(&mut self) {
+        self.board.borrow_mut().reset_all();
+    }
+
+    // the source and sink buffers are rewritten every block
+    fn serialize_state(&self, writer: &mut StateWriter) -> bool {
+        self.board.borrow().write_state(writer)
+    }
+
+    fn deserialize_state(&mut self, reader: &mut StateReader) -> bool {
+        self.board.get_mut().read_state(reader)
+    }
+
+    fn latency_samples(&self) -> usize {
+        self.board.borrow().total_latency_samples()
+    }
+
+    fn execute(&self, context: &BoardContext, connection_idx: usize, num_samples: usize) {
+        let inputs = context.get_inputs_for_connection(connection_idx);
+        let outputs = context.get_outputs_for_connection(connection_idx);
+
+        let num_samples = num_samples.min(self.source_bufs.borrow()[0].len());
+
+        for (input, source_buf) in inputs.iter().zip(self.source_bufs.borrow_mut().iter_mut()) {
+            let read_buf = context.get_buffer_for_read(*input);
+            for i in 0..num_samples {
+                source_buf[i] = read_buf.buf_read(i);
+            }
+        }
+
+        // a sink the sub board doesn't write comes out silent rather than stale
+        for sink_buf in self.sink_bufs.borrow_mut().iter_mut().take(outputs.len()) {
+            for sample in sink_buf[..num_samples].iter_mut() {
+                *sample = 0.0f32;
+            }
+        }
+
+        self.board.borrow_mut().frolic(num_samples);
+
+        for (output, sink_buf) in outputs.iter().zip(self.sink_bufs.borrow().iter()) {
+            let mut write_buf = context.get_buffer_for_write(*output);
+            for i in 0..num_samples {
+                write_buf.buf_write(i, sink_buf[i]);
+            }
+        }
+    }
+}
@@ -0,0 +1,226 @@
+use crate::conf::{
+    AdvertisedParameter, AudioConfig, BoardEffectConfigParameterValue, ParameterRange,
+};
+use crate::context::BoardContext;
+use crate::effects::basic_single_in_single_out;
+use crate::traits::AudioEffect;
+use crate::utils::delay_buf::DelayBuffer;
+use crate::utils::fast_rand::WyHashPRNG;
+use crate::utils::mathutils;
+use crate::utils::state::{StateReader, StateWriter};
+
+use std::cell::{Cell, RefCell};
+
+const PARAMS: &'static [AdvertisedParameter] = &[
+    AdvertisedParameter {
+        name: "pitch_hz",
+        range: ParameterRange::F(20.0f32, 4000.0f32),
+        default_value: BoardEffectConfigParameterValue::F(220.0f32),
+    },
+    AdvertisedParameter {
+        name: "decay_pct",
+        range: ParameterRange::F(0.8f32, 0.9999f32),
+        default_value: BoardEffectConfigParameterValue::F(0.996f32),
+    },
+    AdvertisedParameter {
+        // 0 is the classic (darkest) two point average, 0.5 leaves the loop unfiltered
+        name: "brightness",
+        range: ParameterRange::F(0.0f32, 0.5f32),
+        default_value: BoardEffectConfigParameterValue::F(0.0f32),
+    },
+    AdvertisedParameter {
+        name: "pluck",
+        range: ParameterRange::N(0, 1),
+        default_value: BoardEffectConfigParameterValue::N(0),
+    },
+    AdvertisedParameter {
+        // picks a different (but repeatable) noise burst
+        name: "seed",
+        range: ParameterRange::N(0, i32::MAX),
+        default_value: BoardEffectConfigParameterValue::N(0),
+    },
+];
+
+const PARAM_PITCH_HZ: usize = 0;
+const PARAM_DECAY_PCT: usize = 1;
+const PARAM_BRIGHTNESS: usize = 2;
+const PARAM_PLUCK: usize = 3;
+const PARAM_SEED: usize = 4;
+
+// longest string we need to hold is the lowest advertised pitch
+const MAX_STRING_DELAY_MS: f32 = 100.0f32;
+
+const RAND_MAX: u64 = 0x7fff;
+
+// fixed so renders come out the same every time. the seed parameter is added to it
+const NOISE_SEED: u64 = 0x6b61_7270_6c75_7300;
+
+// Karplus-Strong plucked string
+// A noise burst one period long is fired into a delay line whose length sets the pitch.
+// The loop filter is a two-point average: y(n) = x(n) + g * ((1 - b) * y(n - P) + b * y(n - P - 1))
+// with b = 0.5 - brightness. b both darkens the tone and adds b samples of delay, so P is
+// shortened to keep the pitch in tune.
+pub struct KarplusString {
+    params: Vec<BoardEffectConfigParameterValue>,
+    sample_rate: f32,
+
+    delay_buf: RefCell<DelayBuffer>,
+    last_delayed_sample: Cell<f32>,
+    burst_samples_remaining: Cell<usize>,
+
+    prng: WyHashPRNG,
+}
+
+impl KarplusString {
+    pub fn new(ac: AudioConfig) -> KarplusString {
+        let mut params = Vec::with_capacity(PARAMS.len());
+        for i in 0..PARAMS.len() {
+            params.push(PARAMS[i].default_value);
+        }
+
+        let mut string = KarplusString {
+            params,
+            sample_rate: ac.sample_rate,
+            delay_buf: RefCell::new(DelayBuffer::with_sample_rate_and_max_delay(
+                ac.sample_rate,
+                MAX_STRING_DELAY_MS,
+            )),
+            last_delayed_sample: Cell::new(0.0f32),
+            burst_samples_remaining: Cell::new(0),
+            prng: WyHashPRNG::new(NOISE_SEED),
+        };
+
+        string.tune();
+        string
+    }
+
+    pub fn info() -> &'static [AdvertisedParameter] {
+        PARAMS
+    }
+
+    fn period_samples(&self) -> f32 {
+        self.sample_rate / self.params[PARAM_PITCH_HZ].as_flt().max(1.0f32)
+    }
+
+    // the b in the loop filter
+    fn damping(&self) -> f32 {
+        num::clamp(0.5f32 - self.params[PARAM_BRIGHTNESS].as_flt(), 0.0f32, 0.5f32)
+    }
+
+    fn tune(&mut self) {
+        // reading before writing already adds one sample to the loop
+        let loop_delay = (self.period_samples() - self.damping() - 1.0f32).max(0.0f32);
+        let (ipart, fpart) = mathutils::vmodf(loop_delay);
+
+        self.delay_buf
+            .borrow_mut()
+            .set_delay_sample_count_directly(ipart, fpart);
+    }
+
+    // starts the noise over from the beginning of the seed's sequence
+    fn reseed(&mut self) {
+        self.prng = WyHashPRNG::new(NOISE_SEED + self.params[PARAM_SEED].as_int().max(0) as u64);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Option<()> {
+        self.delay_buf.get_mut().load_state(reader)?;
+        self.last_delayed_sample.set(reader.read_f32()?);
+        self.burst_samples_remaining.set(reader.read_usize()?);
+        self.prng.set_state(reader.read_u64()?);
+
+        Some(())
+    }
+
+    fn next_noise_sample(&self) -> f32 {
+        let next_rand = (self.prng.next() % RAND_MAX) as f32;
+        2.0f32 * (next_rand / RAND_MAX as f32) - 1.0f32
+    }
+}
+
+impl AudioEffect for KarplusString {
+    fn advertise_parameters(&self) -> &'static [AdvertisedParameter] {
+        KarplusString::info()
+    }
+
+    fn set_audio_parameters(&mut self, new_config: &AudioConfig) {
+        self.sample_rate = new_config.sample_rate;
+        self.delay_buf
+            .borrow_mut()
+            .change_sample_rate(new_config.sample_rate);
+
+        self.tune();
+    }
+
+    // stops the string ringing, and any burst still going in
+    fn reset(&mut self) {
+        self.delay_buf.get_mut().clear();
+        self.last_delayed_sample.set(0.0f32);
+        self.burst_samples_remaining.set(0);
+        self.reseed();
+    }
+
+    // a burst still going in carries on with the same noise
+    fn serialize_state(&self, writer: &mut StateWriter) -> bool {
+        self.delay_buf.borrow().save_state(writer);
+        writer.write_f32(self.last_delayed_sample.get());
+        writer.write_usize(self.burst_samples_remaining.get());
+        writer.write_u64(self.prng.get_state());
+
+        true
+    }
+
+    fn deserialize_state(&mut self, reader: &mut StateReader) -> bool {
+        self.load_state(reader).is_some()
+    }
+
+    fn set_effect_parameter(
+        &mut self,
+        param_idx: usize,
+        param_value: BoardEffectConfigParameterValue,
+    ) {
+        self.params[param_idx] = param_value;
+
+        if param_idx == PARAM_PITCH_HZ || param_idx == PARAM_BRIGHTNESS {
+            self.tune();
+        } else if param_idx == PARAM_PLUCK && param_value.as_int() != 0 {
+            self.burst_samples_remaining
+                .set(self.period_samples().round() as usize);
+        } else if param_idx == PARAM_SEED {
+            self.reseed();
+        }
+    }
+
+    fn execute(&self, context: &BoardContext, connection_idx: usize, num_samples: usize) {
+        let maybe_bufs = basic_single_in_single_out(context, connection_idx, num_samples);
+        if let None = maybe_bufs {
+            return;
+        }
+
+        let (read_buf, mut write_buf) = maybe_bufs.unwrap();
+
+        let decay = self.params[PARAM_DECAY_PCT].as_flt();
+        let damping = self.damping();
+
+        let mut delay_ref = self.delay_buf.borrow_mut();
+        for i in 0..num_samples {
+            let burst_samples_remaining = self.burst_samples_remaining.get();
+            let excitation = if burst_samples_remaining > 0 {
+                self.burst_samples_remaining
+                    .set(burst_samples_remaining - 1);
+                self.next_noise_sample()
+            } else {
+                0.0f32
+            };
+
+            let delayed = delay_ref.read_delayed_sample();
+            let filtered =
+                (1.0f32 - damping) * delayed + damping * self.last_delayed_sample.get();
+            self.last_delayed_sample.set(delayed);
+
+            let yn = excitation + decay * filtered;
+            delay_ref.write_sample(yn);
+
+            write_buf.buf_write(i, read_buf.buf_read(i) + yn);
+        }
+    }
+}
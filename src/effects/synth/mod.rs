@@ -0,0 +1,3 @@
+mod karplus;
+
+pub use karplus::KarplusString;
@@ -0,0 +1,170 @@
+use crate::conf::{
+    AdvertisedParameter, AudioConfig, BoardEffectConfigParameterValue, ParameterRange,
+};
+use crate::context::BoardContext;
+use crate::effects::basic_single_in_single_out;
+use crate::traits::AudioEffect;
+use crate::utils::buf_rw::AudioBufferWriter;
+use crate::utils::dc_blocker::DcBlocker;
+use crate::utils::state::{StateReader, StateWriter};
+
+use std::cell::{Cell, RefCell};
+
+const PARAMS: &'static [AdvertisedParameter] = &[
+    AdvertisedParameter {
+        name: "remove_dc?",
+        range: ParameterRange::N(0, 1),
+        default_value: BoardEffectConfigParameterValue::N(0),
+    },
+    AdvertisedParameter {
+        // time constant of the mean and rms averages. longer reads steadier but settles slower
+        name: "averaging_ms",
+        range: ParameterRange::F(10.0f32, 5000.0f32),
+        default_value: BoardEffectConfigParameterValue::F(300.0f32),
+    },
+];
+
+const PARAM_REMOVE_DC: usize = 0;
+const PARAM_AVERAGING_MS: usize = 1;
+
+// low enough to leave anything audible alone
+const DC_BLOCKER_CUTOFF_HZ: f32 = 5.0f32;
+
+// passes its input through (optionally with dc removed) and meters it
+// reads = [input], writes = [output, mean (dc offset), rms]
+// the meters are of the input, before dc removal, and are only written when connected
+pub struct Calibrate {
+    params: Vec<BoardEffectConfigParameterValue>,
+    sample_rate: f32,
+    averaging_coeff: f32,
+
+    dc_blocker: RefCell<DcBlocker>,
+    mean: Cell<f32>,
+    mean_square: Cell<f32>,
+}
+
+impl Calibrate {
+    pub fn new(ac: AudioConfig) -> Calibrate {
+        let mut params = Vec::with_capacity(PARAMS.len());
+        for i in 0..PARAMS.len() {
+            params.push(PARAMS[i].default_value);
+        }
+
+        let mut calibrate = Calibrate {
+            params,
+            sample_rate: ac.sample_rate,
+            averaging_coeff: 0.0f32,
+            dc_blocker: RefCell::new(DcBlocker::new(DC_BLOCKER_CUTOFF_HZ, ac.sample_rate)),
+            mean: Cell::new(0.0f32),
+            mean_square: Cell::new(0.0f32),
+        };
+
+        calibrate.update_averaging_coeff();
+        calibrate
+    }
+
+    pub fn info() -> &'static [AdvertisedParameter] {
+        PARAMS
+    }
+
+    fn update_averaging_coeff(&mut self) {
+        let averaging_ms = self.params[PARAM_AVERAGING_MS].as_flt();
+        self.averaging_coeff = (-1000.0f32 / (averaging_ms * self.sample_rate)).exp();
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Option<()> {
+        self.dc_blocker.get_mut().load_state(reader)?;
+        self.mean.set(reader.read_f32()?);
+        self.mean_square.set(reader.read_f32()?);
+
+        Some(())
+    }
+}
+
+impl AudioEffect for Calibrate {
+    fn advertise_parameters(&self) -> &'static [AdvertisedParameter] {
+        Calibrate::info()
+    }
+
+    fn set_audio_parameters(&mut self, new_config: &AudioConfig) {
+        self.sample_rate = new_config.sample_rate;
+        self.dc_blocker
+            .borrow_mut()
+            .set_cutoff(DC_BLOCKER_CUTOFF_HZ, self.sample_rate);
+        self.update_averaging_coeff();
+    }
+
+    fn set_effect_parameter(
+        &mut self,
+        param_idx: usize,
+        param_value: BoardEffectConfigParameterValue,
+    ) {
+        self.params[param_idx] = param_value;
+
+        if param_idx == PARAM_AVERAGING_MS {
+            self.update_averaging_coeff();
+        }
+    }
+
+    fn reset(&mut self) {
+        self.dc_blocker.borrow_mut().clear();
+        self.mean.set(0.0f32);
+        self.mean_square.set(0.0f32);
+    }
+
+    fn serialize_state(&self, writer: &mut StateWriter) -> bool {
+        self.dc_blocker.borrow().save_state(writer);
+        writer.write_f32(self.mean.get());
+        writer.write_f32(self.mean_square.get());
+
+        true
+    }
+
+    fn deserialize_state(&mut self, reader: &mut StateReader) -> bool {
+        self.load_state(reader).is_some()
+    }
+
+    fn execute(&self, context: &BoardContext, connection_idx: usize, num_samples: usize) {
+        let maybe_bufs = basic_single_in_single_out(context, connection_idx, num_samples);
+        if let None = maybe_bufs {
+            return;
+        }
+
+        let (read_buf, mut write_buf) = maybe_bufs.unwrap();
+
+        let outputs = context.get_outputs_for_connection(connection_idx);
+        let mut mean_buf = if outputs.len() > 1 {
+            context.get_buffer_for_write(outputs[1])
+        } else {
+            AudioBufferWriter::Null
+        };
+        let mut rms_buf = if outputs.len() > 2 {
+            context.get_buffer_for_write(outputs[2])
+        } else {
+            AudioBufferWriter::Null
+        };
+
+        let remove_dc = self.params[PARAM_REMOVE_DC].as_int() != 0;
+        let a = self.averaging_coeff;
+
+        let mut dc_blocker = self.dc_blocker.borrow_mut();
+        let mut mean = self.mean.get();
+        let mut mean_square = self.mean_square.get();
+        for i in 0..num_samples {
+            let x = read_buf.buf_read(i);
+
+            mean = a * mean + (1.0f32 - a) * x;
+            mean_square = a * mean_square + (1.0f32 - a) * x * x;
+
+            // keep the blocker running while off so switching it on doesn't start with a jump
+            let blocked = dc_blocker.process(x);
+            write_buf.buf_write(i, if remove_dc { blocked } else { x });
+
+            mean_buf.buf_write(i, mean);
+            rms_buf.buf_write(i, mean_square.sqrt());
+        }
+
+        self.mean.set(mean);
+        self.mean_square.set(mean_square);
+    }
+}
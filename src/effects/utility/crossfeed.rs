@@ -0,0 +1,197 @@
+use crate::conf::{
+    AdvertisedParameter, AudioConfig, BoardEffectConfigParameterValue, ParameterRange,
+};
+use crate::context::BoardContext;
+use crate::effects::basic_stereo_in_stereo_out;
+use crate::traits::AudioEffect;
+use crate::utils::{
+    biquad::{Biquad, BiquadCoefficients},
+    delay_buf::DelayBuffer,
+    state::{StateReader, StateWriter},
+};
+
+use std::cell::RefCell;
+
+const PARAMS: &'static [AdvertisedParameter] = &[
+    AdvertisedParameter {
+        name: "amount",
+        range: ParameterRange::F(0.0f32, 1.0f32),
+        default_value: BoardEffectConfigParameterValue::F(0.3f32),
+    },
+    AdvertisedParameter {
+        name: "cutoff_hz",
+        range: ParameterRange::F(200.0f32, 2000.0f32),
+        default_value: BoardEffectConfigParameterValue::F(700.0f32),
+    },
+    AdvertisedParameter {
+        name: "delay_us",
+        range: ParameterRange::F(0.0f32, MAX_CROSSFEED_DELAY_US),
+        default_value: BoardEffectConfigParameterValue::F(300.0f32),
+    },
+];
+
+const PARAM_AMOUNT: usize = 0;
+const PARAM_CUTOFF_HZ: usize = 1;
+const PARAM_DELAY_US: usize = 2;
+
+const MAX_CROSSFEED_DELAY_US: f32 = 1000.0f32;
+
+// each output channel gets a low passed, slightly delayed copy of the opposite input
+// (roughly what the far ear hears from a speaker). reads = [L, R], writes = [L, R]
+pub struct Crossfeed {
+    params: Vec<BoardEffectConfigParameterValue>,
+
+    // indexed by the channel the signal comes from
+    delay_bufs: RefCell<[DelayBuffer; 2]>,
+    lpfs: RefCell<[Biquad; 2]>,
+}
+
+impl Crossfeed {
+    pub fn new(ac: AudioConfig) -> Crossfeed {
+        let mut params = Vec::with_capacity(PARAMS.len());
+        for i in 0..PARAMS.len() {
+            params.push(PARAMS[i].default_value);
+        }
+
+        let cutoff_hz = PARAMS[PARAM_CUTOFF_HZ].default_value.as_flt();
+
+        let mut crossfeed = Crossfeed {
+            params,
+            delay_bufs: RefCell::new([
+                Crossfeed::create_delay_buf(ac.sample_rate),
+                Crossfeed::create_delay_buf(ac.sample_rate),
+            ]),
+            lpfs: RefCell::new([
+                Biquad::new(BiquadCoefficients::second_order_lpf(
+                    cutoff_hz,
+                    ac.sample_rate,
+                    None,
+                )),
+                Biquad::new(BiquadCoefficients::second_order_lpf(
+                    cutoff_hz,
+                    ac.sample_rate,
+                    None,
+                )),
+            ]),
+        };
+
+        crossfeed.update_delay_time();
+
+        crossfeed
+    }
+
+    pub fn info() -> &'static [AdvertisedParameter] {
+        PARAMS
+    }
+
+    fn create_delay_buf(sample_rate: f32) -> DelayBuffer {
+        // a few ms of headroom over the max so the delay never has to clamp
+        DelayBuffer::with_sample_rate_and_max_delay(
+            sample_rate,
+            MAX_CROSSFEED_DELAY_US / 1000.0f32 + 2.0f32,
+        )
+    }
+
+    fn update_delay_time(&mut self) {
+        let delay_time_ms = self.params[PARAM_DELAY_US].as_flt() / 1000.0f32;
+        for delay_buf in self.delay_bufs.borrow_mut().iter_mut() {
+            delay_buf.set_delay_time_ms(delay_time_ms, true);
+        }
+    }
+}
+
+impl AudioEffect for Crossfeed {
+    fn advertise_parameters(&self) -> &'static [AdvertisedParameter] {
+        Crossfeed::info()
+    }
+
+    fn set_audio_parameters(&mut self, new_config: &AudioConfig) {
+        for delay_buf in self.delay_bufs.borrow_mut().iter_mut() {
+            *delay_buf = Crossfeed::create_delay_buf(new_config.sample_rate);
+        }
+
+        for lpf in self.lpfs.borrow_mut().iter_mut() {
+            lpf.change_sample_rate(new_config.sample_rate);
+        }
+
+        self.update_delay_time();
+    }
+
+    fn reset(&mut self) {
+        for delay_buf in self.delay_bufs.get_mut().iter_mut() {
+            delay_buf.clear();
+        }
+
+        for lpf in self.lpfs.get_mut().iter_mut() {
+            lpf.clear();
+        }
+    }
+
+    fn serialize_state(&self, writer: &mut StateWriter) -> bool {
+        for delay_buf in self.delay_bufs.borrow().iter() {
+            delay_buf.save_state(writer);
+        }
+
+        for lpf in self.lpfs.borrow().iter() {
+            lpf.save_state(writer);
+        }
+
+        true
+    }
+
+    fn deserialize_state(&mut self, reader: &mut StateReader) -> bool {
+        self.delay_bufs
+            .get_mut()
+            .iter_mut()
+            .all(|delay_buf| delay_buf.load_state(reader).is_some())
+            && self
+                .lpfs
+                .get_mut()
+                .iter_mut()
+                .all(|lpf| lpf.load_state(reader).is_some())
+    }
+
+    fn set_effect_parameter(
+        &mut self,
+        param_idx: usize,
+        param_value: BoardEffectConfigParameterValue,
+    ) {
+        self.params[param_idx] = param_value;
+
+        if param_idx == PARAM_CUTOFF_HZ {
+            for lpf in self.lpfs.borrow_mut().iter_mut() {
+                lpf.change_cutoff(param_value.as_flt());
+            }
+        } else if param_idx == PARAM_DELAY_US {
+            self.update_delay_time();
+        }
+    }
+
+    fn execute(&self, context: &BoardContext, connection_idx: usize, num_samples: usize) {
+        let maybe_bufs = basic_stereo_in_stereo_out(context, connection_idx, num_samples);
+        if let None = maybe_bufs {
+            return;
+        }
+
+        let (read_bufs, mut write_bufs) = maybe_bufs.unwrap();
+
+        let amount = self.params[PARAM_AMOUNT].as_flt();
+
+        let mut delay_ref = self.delay_bufs.borrow_mut();
+        let mut lpf_ref = self.lpfs.borrow_mut();
+        for i in 0..num_samples {
+            let xl = read_bufs[0].buf_read(i);
+            let xr = read_bufs[1].buf_read(i);
+
+            // write first so a delay of 0 is a straight pass through
+            delay_ref[0].write_sample(xl);
+            delay_ref[1].write_sample(xr);
+
+            let feed_from_l = lpf_ref[0].filter(delay_ref[0].read_delayed_sample());
+            let feed_from_r = lpf_ref[1].filter(delay_ref[1].read_delayed_sample());
+
+            write_bufs[0].buf_write(i, xl + amount * feed_from_r);
+            write_bufs[1].buf_write(i, xr + amount * feed_from_l);
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use crate::conf::{AdvertisedParameter, BoardEffectConfigParameterValue, ParameterRange};
+use crate::context::BoardContext;
+use crate::effects::basic_single_in_single_out;
+use crate::traits::AudioEffect;
+use crate::utils::buf_rw::AudioBufferWriter;
+use crate::utils::state::{StateReader, StateWriter};
+
+use std::cell::{Cell, RefCell};
+
+pub const NUM_HISTOGRAM_BINS: usize = 96;
+
+const PARAMS: &'static [AdvertisedParameter] = &[AdvertisedParameter {
+    // bin k holds levels in (-(k + 1) * width, -k * width] dBFS. the last bin takes everything quieter
+    name: "bin_width_db",
+    range: ParameterRange::F(0.25f32, 2.0f32),
+    default_value: BoardEffectConfigParameterValue::F(1.0f32),
+}];
+
+const PARAM_BIN_WIDTH_DB: usize = 0;
+
+// pass-through analyzer that counts how often the input sits at each level
+// reads = [input], writes = [output, crest factor in dB (optional)]
+// the histogram accumulates until reset and is read with Otters::copy_effect_visualization,
+// as the fraction of all samples seen that fell into each bin
+pub struct LevelHistogram {
+    params: Vec<BoardEffectConfigParameterValue>,
+
+    counts: RefCell<[u64; NUM_HISTOGRAM_BINS]>,
+    num_samples_seen: Cell<u64>,
+    peak: Cell<f32>,
+    sum_of_squares: Cell<f64>,
+}
+
+impl LevelHistogram {
+    pub fn new() -> LevelHistogram {
+        let mut params = Vec::with_capacity(PARAMS.len());
+        for i in 0..PARAMS.len() {
+            params.push(PARAMS[i].default_value);
+        }
+
+        LevelHistogram {
+            params,
+            counts: RefCell::new([0; NUM_HISTOGRAM_BINS]),
+            num_samples_seen: Cell::new(0),
+            peak: Cell::new(0.0f32),
+            sum_of_squares: Cell::new(0.0f64),
+        }
+    }
+
+    pub fn info() -> &'static [AdvertisedParameter] {
+        PARAMS
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Option<()> {
+        for count in self.counts.get_mut().iter_mut() {
+            *count = reader.read_u64()?;
+        }
+        self.num_samples_seen.set(reader.read_u64()?);
+        self.peak.set(reader.read_f32()?);
+        self.sum_of_squares.set(reader.read_f64()?);
+
+        Some(())
+    }
+
+    fn clear(&self) {
+        *self.counts.borrow_mut() = [0; NUM_HISTOGRAM_BINS];
+        self.num_samples_seen.set(0);
+        self.peak.set(0.0f32);
+        self.sum_of_squares.set(0.0f64);
+    }
+}
+
+impl AudioEffect for LevelHistogram {
+    fn advertise_parameters(&self) -> &'static [AdvertisedParameter] {
+        LevelHistogram::info()
+    }
+
+    fn set_audio_parameters(&mut self, _new_config: &crate::conf::AudioConfig) {}
+
+    fn set_effect_parameter(
+        &mut self,
+        param_idx: usize,
+        param_value: BoardEffectConfigParameterValue,
+    ) {
+        self.params[param_idx] = param_value;
+
+        // counts binned at the old width can't be rebinned
+        if param_idx == PARAM_BIN_WIDTH_DB {
+            self.clear();
+        }
+    }
+
+    fn reset(&mut self) {
+        self.clear();
+    }
+
+    fn serialize_state(&self, writer: &mut StateWriter) -> bool {
+        for count in self.counts.borrow().iter() {
+            writer.write_u64(*count);
+        }
+        writer.write_u64(self.num_samples_seen.get());
+        writer.write_f32(self.peak.get());
+        writer.write_f64(self.sum_of_squares.get());
+
+        true
+    }
+
+    fn deserialize_state(&mut self, reader: &mut StateReader) -> bool {
+        self.load_state(reader).is_some()
+    }
+
+    fn copy_visualization_samples(&self, output: &mut [f32]) -> usize {
+        let num_samples_seen = self.num_samples_seen.get().max(1) as f32;
+        let counts = self.counts.borrow();
+
+        let num_bins = output.len().min(NUM_HISTOGRAM_BINS);
+        for (o, count) in output.iter_mut().zip(counts.iter()).take(num_bins) {
+            *o = *count as f32 / num_samples_seen;
+        }
+
+        num_bins
+    }
+
+    fn execute(&self, context: &BoardContext, connection_idx: usize, num_samples: usize) {
+        let maybe_bufs = basic_single_in_single_out(context, connection_idx, num_samples);
+        if let None = maybe_bufs {
+            return;
+        }
+
+        let (read_buf, mut write_buf) = maybe_bufs.unwrap();
+
+        let outputs = context.get_outputs_for_connection(connection_idx);
+        let mut crest_buf = if outputs.len() > 1 {
+            context.get_buffer_for_write(outputs[1])
+        } else {
+            AudioBufferWriter::Null
+        };
+
+        let bin_width_db = self.params[PARAM_BIN_WIDTH_DB].as_flt();
+        let last_bin = (NUM_HISTOGRAM_BINS - 1) as f32;
+
+        let mut counts = self.counts.borrow_mut();
+        let mut num_samples_seen = self.num_samples_seen.get();
+        let mut peak = self.peak.get();
+        let mut sum_of_squares = self.sum_of_squares.get();
+        for i in 0..num_samples {
+            let x = read_buf.buf_read(i);
+            write_buf.buf_write(i, x);
+
+            let magnitude = x.abs();
+            // silence is -inf dB, which clamps into the last bin
+            let level_db = 20.0f32 * magnitude.log10();
+            let bin = (-level_db / bin_width_db).max(0.0f32).min(last_bin) as usize;
+            counts[bin] += 1;
+
+            num_samples_seen += 1;
+            peak = peak.max(magnitude);
+            sum_of_squares += (x as f64) * (x as f64);
+
+            let rms = (sum_of_squares / num_samples_seen as f64).sqrt() as f32;
+            let crest_db = if rms > 0.0f32 {
+                20.0f32 * (peak / rms).log10()
+            } else {
+                0.0f32
+            };
+            crest_buf.buf_write(i, crest_db);
+        }
+
+        self.num_samples_seen.set(num_samples_seen);
+        self.peak.set(peak);
+        self.sum_of_squares.set(sum_of_squares);
+    }
+}
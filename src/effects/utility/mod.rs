@@ -0,0 +1,15 @@
+mod calibrate;
+mod crossfeed;
+mod level_histogram;
+mod pan;
+mod sample_delay;
+mod spectrum_analyzer;
+mod stereo_rotate;
+
+pub use calibrate::Calibrate;
+pub use crossfeed::Crossfeed;
+pub use level_histogram::LevelHistogram;
+pub use pan::Pan;
+pub use sample_delay::SampleDelay;
+pub use spectrum_analyzer::SpectrumAnalyzer;
+pub use stereo_rotate::StereoRotate;
@@ -0,0 +1,108 @@
+use crate::conf::{AdvertisedParameter, BoardEffectConfigParameterValue, ParameterRange};
+use crate::context::BoardContext;
+use crate::effects::basic_stereo_in_stereo_out;
+use crate::traits::AudioEffect;
+use crate::utils::mathutils::{self, PanLaw};
+use crate::utils::state::{StateReader, StateWriter};
+
+const PARAMS: &'static [AdvertisedParameter] = &[
+    AdvertisedParameter {
+        // -1 = hard left, 1 = hard right
+        name: "pan",
+        range: ParameterRange::F(-1.0f32, 1.0f32),
+        default_value: BoardEffectConfigParameterValue::F(0.0f32),
+    },
+    AdvertisedParameter {
+        // 0 = linear (0 dB center), 1 = constant power (-3 dB), 2 = -4.5 dB, 3 = linear taper (-6 dB)
+        name: "pan_law",
+        range: ParameterRange::N(0, PanLaw::__NUM_PAN_LAWS as i32 - 1),
+        default_value: BoardEffectConfigParameterValue::N(PanLaw::ConstantPower as i32),
+    },
+];
+
+const PARAM_PAN: usize = 0;
+const PARAM_PAN_LAW: usize = 1;
+
+// reads = [L] or [L, R], writes = [L, R]
+// a mono input is placed in the stereo field. a stereo input has each side scaled by the pan gains
+pub struct Pan {
+    params: Vec<BoardEffectConfigParameterValue>,
+    gains: (f32, f32),
+}
+
+impl Pan {
+    pub fn new() -> Pan {
+        let mut params = Vec::with_capacity(PARAMS.len());
+        for i in 0..PARAMS.len() {
+            params.push(PARAMS[i].default_value);
+        }
+
+        let mut pan = Pan {
+            params,
+            gains: (1.0f32, 1.0f32),
+        };
+        pan.update_gains();
+
+        pan
+    }
+
+    pub fn info() -> &'static [AdvertisedParameter] {
+        PARAMS
+    }
+
+    fn update_gains(&mut self) {
+        self.gains = mathutils::pan_gains(
+            self.params[PARAM_PAN].as_flt(),
+            self.params[PARAM_PAN_LAW].as_enum(),
+        );
+    }
+}
+
+impl AudioEffect for Pan {
+    fn advertise_parameters(&self) -> &'static [AdvertisedParameter] {
+        Pan::info()
+    }
+
+    fn set_audio_parameters(&mut self, _new_config: &crate::conf::AudioConfig) {}
+
+    // nothing carries over between blocks
+    fn serialize_state(&self, _writer: &mut StateWriter) -> bool {
+        true
+    }
+
+    fn deserialize_state(&mut self, _reader: &mut StateReader) -> bool {
+        true
+    }
+
+    fn set_effect_parameter(
+        &mut self,
+        param_idx: usize,
+        param_value: BoardEffectConfigParameterValue,
+    ) {
+        self.params[param_idx] = param_value;
+        self.update_gains();
+    }
+
+    fn execute(&self, context: &BoardContext, connection_idx: usize, num_samples: usize) {
+        let maybe_bufs = basic_stereo_in_stereo_out(context, connection_idx, num_samples);
+        if let None = maybe_bufs {
+            return;
+        }
+
+        let (read_bufs, mut write_bufs) = maybe_bufs.unwrap();
+        let is_mono = context.get_inputs_for_connection(connection_idx).len() < 2;
+
+        let (left_gain, right_gain) = self.gains;
+        for i in 0..num_samples {
+            let xl = read_bufs[0].buf_read(i);
+            let xr = if is_mono {
+                xl
+            } else {
+                read_bufs[1].buf_read(i)
+            };
+
+            write_bufs[0].buf_write(i, left_gain * xl);
+            write_bufs[1].buf_write(i, right_gain * xr);
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use crate::conf::{
+    AdvertisedParameter, AudioConfig, BoardEffectConfigParameterValue, ParameterRange,
+};
+use crate::context::BoardContext;
+use crate::effects::basic_single_in_single_out;
+use crate::traits::AudioEffect;
+use crate::utils::state::{StateReader, StateWriter};
+
+use std::cell::{Cell, RefCell};
+
+// in samples, so the longest delay doesn't depend on the sample rate
+const MAX_DELAY_SAMPLES: usize = 65536;
+
+const PARAMS: &'static [AdvertisedParameter] = &[AdvertisedParameter {
+    name: "delay_samples",
+    range: ParameterRange::N(0, MAX_DELAY_SAMPLES as i32),
+    default_value: BoardEffectConfigParameterValue::N(0),
+}];
+
+const PARAM_DELAY_SAMPLES: usize = 0;
+
+// a whole number of samples of plain delay, for lining a dry path up with a latent one.
+// no interpolation, so nothing gets filtered. reported as latency
+pub struct SampleDelay {
+    params: Vec<BoardEffectConfigParameterValue>,
+
+    // one longer than the longest delay, since the current sample is written before reading
+    line: RefCell<Vec<f32>>,
+    write_idx: Cell<usize>,
+}
+
+impl SampleDelay {
+    pub fn new() -> SampleDelay {
+        let mut params = Vec::with_capacity(PARAMS.len());
+        for i in 0..PARAMS.len() {
+            params.push(PARAMS[i].default_value);
+        }
+
+        SampleDelay {
+            params,
+            line: RefCell::new(vec![0.0f32; MAX_DELAY_SAMPLES + 1]),
+            write_idx: Cell::new(0),
+        }
+    }
+
+    pub fn info() -> &'static [AdvertisedParameter] {
+        PARAMS
+    }
+
+    fn delay_samples(&self) -> usize {
+        (self.params[PARAM_DELAY_SAMPLES].as_int().max(0) as usize).min(MAX_DELAY_SAMPLES)
+    }
+}
+
+impl AudioEffect for SampleDelay {
+    fn advertise_parameters(&self) -> &'static [AdvertisedParameter] {
+        SampleDelay::info()
+    }
+
+    fn set_audio_parameters(&mut self, _new_config: &AudioConfig) {}
+
+    fn reset(&mut self) {
+        for sample in self.line.get_mut().iter_mut() {
+            *sample = 0.0f32;
+        }
+    }
+
+    fn latency_samples(&self) -> usize {
+        self.delay_samples()
+    }
+
+    fn max_latency_samples(&self) -> usize {
+        MAX_DELAY_SAMPLES
+    }
+
+    fn serialize_state(&self, writer: &mut StateWriter) -> bool {
+        writer.write_f32_slice(&self.line.borrow());
+        writer.write_usize(self.write_idx.get());
+
+        true
+    }
+
+    fn deserialize_state(&mut self, reader: &mut StateReader) -> bool {
+        if reader.read_f32_slice_into(self.line.get_mut()).is_none() {
+            return false;
+        }
+
+        match reader.read_usize() {
+            Some(write_idx) if write_idx <= MAX_DELAY_SAMPLES => {
+                self.write_idx.set(write_idx);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn set_effect_parameter(
+        &mut self,
+        param_idx: usize,
+        param_value: BoardEffectConfigParameterValue,
+    ) {
+        self.params[param_idx] = param_value;
+    }
+
+    fn execute(&self, context: &BoardContext, connection_idx: usize, num_samples: usize) {
+        let maybe_bufs = basic_single_in_single_out(context, connection_idx, num_samples);
+        if let None = maybe_bufs {
+            return;
+        }
+
+        let (read_buf, mut write_buf) = maybe_bufs.unwrap();
+
+        let mut line = self.line.borrow_mut();
+        let line_len = line.len();
+        let delay_samples = self.delay_samples();
+        let mut write_idx = self.write_idx.get();
+
+        for i in 0..num_samples {
+            line[write_idx] = read_buf.buf_read(i);
+
+            let read_idx = (write_idx + line_len - delay_samples) % line_len;
+            write_buf.buf_write(i, line[read_idx]);
+
+            write_idx = (write_idx + 1) % line_len;
+        }
+
+        self.write_idx.set(write_idx);
+    }
+}
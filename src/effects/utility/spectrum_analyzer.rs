@@ -0,0 +1,287 @@
+use crate::conf::{
+    AdvertisedParameter, AudioConfig, BoardEffectConfigParameterValue, ParameterRange,
+};
+use crate::context::BoardContext;
+use crate::effects::basic_single_in_single_out;
+use crate::traits::AudioEffect;
+use crate::utils::state::{StateReader, StateWriter};
+
+use fftw::array::AlignedVec;
+use fftw::plan::*;
+use fftw::types::*;
+
+use std::cell::RefCell;
+
+const PARAMS: &'static [AdvertisedParameter] = &[
+    AdvertisedParameter {
+        // rounded up to a power of 2
+        name: "fft_size",
+        range: ParameterRange::N(MIN_FFT_SIZE as i32, MAX_FFT_SIZE as i32),
+        default_value: BoardEffectConfigParameterValue::N(2048),
+    },
+    AdvertisedParameter {
+        // how much of the previous spectrum each bin keeps when a new one comes in
+        name: "smoothing",
+        range: ParameterRange::F(0.0f32, 0.99f32),
+        default_value: BoardEffectConfigParameterValue::F(0.5f32),
+    },
+];
+
+const PARAM_FFT_SIZE: usize = 0;
+const PARAM_SMOOTHING: usize = 1;
+
+const MIN_FFT_SIZE: usize = 256;
+const MAX_FFT_SIZE: usize = 8192;
+
+// a new spectrum every quarter window
+const FRAMES_PER_WINDOW: usize = 4;
+
+// pass-through analyzer that takes the magnitude spectrum of the input over a sliding window
+// reads = [input], writes = [output]
+// the latest spectrum is read with Otters::copy_effect_visualization, fft_size / 2 + 1 bins from
+// dc to nyquist. a full scale sine in the middle of a bin reads ~1.0
+pub struct SpectrumAnalyzer {
+    params: Vec<BoardEffectConfigParameterValue>,
+
+    frames: RefCell<SpectrumFrames>,
+}
+
+// everything a single fft size needs. one of these is made for every size up front, so
+// changing fft_size just picks a different one
+struct SpectrumPlan {
+    plan: C2CPlan32,
+    window: Vec<f32>,
+    // scales the window's sum back to 1 and counts both halves of the spectrum
+    magnitude_scale: f32,
+
+    fft_in: AlignedVec<c32>,
+    fft_out: AlignedVec<c32>,
+}
+
+struct SpectrumFrames {
+    // MIN_FFT_SIZE, twice that, ... MAX_FFT_SIZE
+    plans: Vec<SpectrumPlan>,
+    plan_idx: usize,
+    fft_size: usize,
+
+    // sized for MAX_FFT_SIZE. only the first fft_size samples (and bins) are used
+    history: Vec<f32>,
+    write_idx: usize,
+    samples_until_frame: usize,
+    magnitudes: Vec<f32>,
+}
+
+impl SpectrumAnalyzer {
+    pub fn new() -> SpectrumAnalyzer {
+        let mut params = Vec::with_capacity(PARAMS.len());
+        for i in 0..PARAMS.len() {
+            params.push(PARAMS[i].default_value);
+        }
+
+        let fft_size = SpectrumAnalyzer::fft_size(&params);
+        SpectrumAnalyzer {
+            params,
+            frames: RefCell::new(SpectrumFrames::new(fft_size)),
+        }
+    }
+
+    pub fn info() -> &'static [AdvertisedParameter] {
+        PARAMS
+    }
+
+    fn fft_size(params: &Vec<BoardEffectConfigParameterValue>) -> usize {
+        let fft_size = num::clamp(
+            params[PARAM_FFT_SIZE].as_int(),
+            MIN_FFT_SIZE as i32,
+            MAX_FFT_SIZE as i32,
+        );
+
+        (fft_size as usize).next_power_of_two()
+    }
+}
+
+impl SpectrumPlan {
+    fn new(fft_size: usize) -> SpectrumPlan {
+        let plan: C2CPlan32 = C2CPlan::aligned(&[fft_size], Sign::Forward, Flag::ESTIMATE).unwrap();
+
+        let window: Vec<f32> = (0..fft_size)
+            .map(|n| {
+                0.5f32 - 0.5f32 * (2.0f32 * std::f32::consts::PI * n as f32 / fft_size as f32).cos()
+            })
+            .collect();
+        let magnitude_scale = 2.0f32 / window.iter().sum::<f32>();
+
+        SpectrumPlan {
+            plan,
+            window,
+            magnitude_scale,
+            fft_in: AlignedVec::new(fft_size),
+            fft_out: AlignedVec::new(fft_size),
+        }
+    }
+}
+
+impl SpectrumFrames {
+    fn new(fft_size: usize) -> SpectrumFrames {
+        let mut plans = Vec::new();
+        let mut plan_size = MIN_FFT_SIZE;
+        while plan_size <= MAX_FFT_SIZE {
+            plans.push(SpectrumPlan::new(plan_size));
+            plan_size *= 2;
+        }
+
+        let mut frames = SpectrumFrames {
+            plans,
+            plan_idx: 0,
+            fft_size: MIN_FFT_SIZE,
+            history: vec![0.0f32; MAX_FFT_SIZE],
+            write_idx: 0,
+            samples_until_frame: 0,
+            magnitudes: vec![0.0f32; MAX_FFT_SIZE / 2 + 1],
+        };
+        frames.change_fft_size(fft_size);
+
+        frames
+    }
+
+    // fft_size has to be a power of 2 between MIN_FFT_SIZE and MAX_FFT_SIZE
+    // starts over with an empty history. doesn't allocate
+    fn change_fft_size(&mut self, fft_size: usize) {
+        self.fft_size = fft_size;
+        self.plan_idx = (fft_size / MIN_FFT_SIZE).trailing_zeros() as usize;
+        self.clear();
+    }
+
+    fn clear(&mut self) {
+        for x in self.history.iter_mut() {
+            *x = 0.0f32;
+        }
+
+        for magnitude in self.magnitudes.iter_mut() {
+            *magnitude = 0.0f32;
+        }
+
+        self.write_idx = 0;
+        self.samples_until_frame = self.fft_size / FRAMES_PER_WINDOW;
+    }
+
+    // fft_size comes from parameters. the plans' scratch buffers are rewritten every frame
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_f32_slice(&self.history);
+        writer.write_usize(self.write_idx);
+        writer.write_usize(self.samples_until_frame);
+        writer.write_f32_slice(&self.magnitudes);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Option<()> {
+        reader.read_f32_slice_into(&mut self.history)?;
+
+        let write_idx = reader.read_usize()?;
+        let samples_until_frame = reader.read_usize()?;
+        if write_idx >= self.fft_size
+            || samples_until_frame == 0
+            || samples_until_frame > self.fft_size / FRAMES_PER_WINDOW
+        {
+            return None;
+        }
+        self.write_idx = write_idx;
+        self.samples_until_frame = samples_until_frame;
+
+        reader.read_f32_slice_into(&mut self.magnitudes)
+    }
+
+    fn push(&mut self, x: f32, smoothing: f32) {
+        let fft_size = self.fft_size;
+
+        self.history[self.write_idx] = x;
+        self.write_idx = (self.write_idx + 1) % fft_size;
+
+        self.samples_until_frame -= 1;
+        if self.samples_until_frame > 0 {
+            return;
+        }
+        self.samples_until_frame = fft_size / FRAMES_PER_WINDOW;
+
+        let plan = &mut self.plans[self.plan_idx];
+
+        // oldest sample first. write_idx is where the next one goes, so it's the oldest
+        for n in 0..fft_size {
+            let x = self.history[(self.write_idx + n) % fft_size];
+            plan.fft_in[n] = c32::new(x * plan.window[n], 0.0f32);
+        }
+
+        plan.plan.c2c(&mut plan.fft_in, &mut plan.fft_out).unwrap();
+
+        let num_bins = fft_size / 2 + 1;
+        for (k, magnitude) in self.magnitudes[..num_bins].iter_mut().enumerate() {
+            let latest = plan.fft_out[k].norm() * plan.magnitude_scale;
+            *magnitude = smoothing * *magnitude + (1.0f32 - smoothing) * latest;
+        }
+    }
+}
+
+impl AudioEffect for SpectrumAnalyzer {
+    fn advertise_parameters(&self) -> &'static [AdvertisedParameter] {
+        SpectrumAnalyzer::info()
+    }
+
+    fn set_audio_parameters(&mut self, _new_config: &AudioConfig) {}
+
+    fn set_effect_parameter(
+        &mut self,
+        param_idx: usize,
+        param_value: BoardEffectConfigParameterValue,
+    ) {
+        self.params[param_idx] = param_value;
+
+        if param_idx == PARAM_FFT_SIZE {
+            let fft_size = SpectrumAnalyzer::fft_size(&self.params);
+            let frames = self.frames.get_mut();
+            if fft_size != frames.fft_size {
+                frames.change_fft_size(fft_size);
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.frames.get_mut().clear();
+    }
+
+    fn serialize_state(&self, writer: &mut StateWriter) -> bool {
+        self.frames.borrow().save_state(writer);
+
+        true
+    }
+
+    fn deserialize_state(&mut self, reader: &mut StateReader) -> bool {
+        self.frames.get_mut().load_state(reader).is_some()
+    }
+
+    fn copy_visualization_samples(&self, output: &mut [f32]) -> usize {
+        let frames = self.frames.borrow();
+
+        let num_bins = output.len().min(frames.fft_size / 2 + 1);
+        output[..num_bins].copy_from_slice(&frames.magnitudes[..num_bins]);
+
+        num_bins
+    }
+
+    fn execute(&self, context: &BoardContext, connection_idx: usize, num_samples: usize) {
+        let maybe_bufs = basic_single_in_single_out(context, connection_idx, num_samples);
+        if let None = maybe_bufs {
+            return;
+        }
+
+        let (read_buf, mut write_buf) = maybe_bufs.unwrap();
+
+        let smoothing = self.params[PARAM_SMOOTHING].as_flt();
+
+        let mut frames = self.frames.borrow_mut();
+        for i in 0..num_samples {
+            let x = read_buf.buf_read(i);
+            write_buf.buf_write(i, x);
+
+            frames.push(x, smoothing);
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use crate::conf::{AdvertisedParameter, BoardEffectConfigParameterValue, ParameterRange};
+use crate::context::BoardContext;
+use crate::effects::basic_stereo_in_stereo_out;
+use crate::traits::AudioEffect;
+use crate::utils::state::{StateReader, StateWriter};
+
+const PARAMS: &'static [AdvertisedParameter] = &[AdvertisedParameter {
+    name: "angle_deg",
+    range: ParameterRange::F(-90.0f32, 90.0f32),
+    default_value: BoardEffectConfigParameterValue::F(0.0f32),
+}];
+
+const PARAM_ANGLE_DEG: usize = 0;
+
+// rotates the (L, R) vector by angle_deg. reads = [L, R], writes = [L, R]
+// unlike a balance control nothing is thrown away, the channels are remixed:
+// L' = cos * L + sin * R, R' = cos * R - sin * L
+// 45 degrees turns L/R into (scaled) mid/side, 90 swaps the channels (with R inverted)
+pub struct StereoRotate {
+    params: Vec<BoardEffectConfigParameterValue>,
+    cos_sin: (f32, f32),
+}
+
+impl StereoRotate {
+    pub fn new() -> StereoRotate {
+        let mut params = Vec::with_capacity(PARAMS.len());
+        for i in 0..PARAMS.len() {
+            params.push(PARAMS[i].default_value);
+        }
+
+        let mut rotate = StereoRotate {
+            params,
+            cos_sin: (1.0f32, 0.0f32),
+        };
+        rotate.update_rotation();
+
+        rotate
+    }
+
+    pub fn info() -> &'static [AdvertisedParameter] {
+        PARAMS
+    }
+
+    fn update_rotation(&mut self) {
+        let angle = self.params[PARAM_ANGLE_DEG].as_flt().to_radians();
+        self.cos_sin = (angle.cos(), angle.sin());
+    }
+}
+
+impl AudioEffect for StereoRotate {
+    fn advertise_parameters(&self) -> &'static [AdvertisedParameter] {
+        StereoRotate::info()
+    }
+
+    fn set_audio_parameters(&mut self, _new_config: &crate::conf::AudioConfig) {}
+
+    // nothing carries over between blocks
+    fn serialize_state(&self, _writer: &mut StateWriter) -> bool {
+        true
+    }
+
+    fn deserialize_state(&mut self, _reader: &mut StateReader) -> bool {
+        true
+    }
+
+    fn set_effect_parameter(
+        &mut self,
+        param_idx: usize,
+        param_value: BoardEffectConfigParameterValue,
+    ) {
+        self.params[param_idx] = param_value;
+        self.update_rotation();
+    }
+
+    fn execute(&self, context: &BoardContext, connection_idx: usize, num_samples: usize) {
+        let maybe_bufs = basic_stereo_in_stereo_out(context, connection_idx, num_samples);
+        if let None = maybe_bufs {
+            return;
+        }
+
+        let (read_bufs, mut write_bufs) = maybe_bufs.unwrap();
+
+        let (cos, sin) = self.cos_sin;
+        for i in 0..num_samples {
+            let xl = read_bufs[0].buf_read(i);
+            let xr = read_bufs[1].buf_read(i);
+
+            write_bufs[0].buf_write(i, cos * xl + sin * xr);
+            write_bufs[1].buf_write(i, cos * xr - sin * xl);
+        }
+    }
+}
@@ -1,9 +1,12 @@
 use super::VocoderContext;
-use crate::conf::{AdvertisedParameter, AudioConfig, BoardEffectConfigParameterValue};
+use crate::conf::{
+    AdvertisedParameter, AudioConfig, BoardEffectConfigParameterValue, ParameterRange,
+};
 use crate::context::BoardContext;
 use crate::effects::basic_single_in_single_out;
+use crate::errors::VocoderInitError;
 use crate::traits::{AudioEffect, FrequencyDomainAudioEffect};
-use crate::utils::mathutils::vcosf;
+use crate::utils::mathutils::{is_power_of_2, vcosf};
 use crate::utils::ringbuf::FFTCollectionBuffer;
 use crate::utils::TWO_PI;
 use fftw::array::AlignedVec;
@@ -19,6 +22,24 @@ pub enum FFTWindowType {
     BlackmanHarris,
 }
 
+// how much work fftw puts into planning a vocoder's transforms. Estimate plans straight
+// away from heuristics. Measure times candidate plans first, which can stall for a while on
+// big frames (and on every board load with several vocoders) for a somewhat faster transform
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FFTPlanning {
+    Estimate,
+    Measure,
+}
+
+// every vocoder processor ends its static parameter list with this. the vocoder handles it,
+// so the processor's set_effect_parameter never sees it
+pub const MIX_PARAM: AdvertisedParameter = AdvertisedParameter {
+    // 0 = only the dry signal, delayed to line up with what the vocoder puts out
+    name: "mix_pct",
+    range: ParameterRange::F(0.0f32, 1.0f32),
+    default_value: BoardEffectConfigParameterValue::F(1.0f32),
+};
+
 struct FFTContext {
     forward_plan: C2CPlan32,
     backward_plan: C2CPlan32,
@@ -27,6 +48,9 @@ struct FFTContext {
 }
 
 pub struct PhaseVocoder<T> {
+    params: &'static [AdvertisedParameter],
+    mix_pct: f32,
+
     vocoder_context: VocoderContext,
     overlap_factor: f32,
     inv_gain_correction: f32,
@@ -34,8 +58,10 @@ pub struct PhaseVocoder<T> {
     input_collection_buf: RefCell<FFTCollectionBuffer>,
     output_collection_buf: RefCell<FFTCollectionBuffer>,
     accumulated_sample_count: Cell<usize>,
+    skipped_last_frame: Cell<bool>,
 
     fft_context: RefCell<FFTContext>,
+    planning: FFTPlanning,
 
     freq_processor: T,
 }
@@ -67,15 +93,61 @@ impl FFTContext {
 }
 
 impl<T: FrequencyDomainAudioEffect> PhaseVocoder<T> {
+    // windowed on the analysis side only
     pub fn new(
         frame_size: usize,
         hop_size: usize,
         window_type: FFTWindowType,
         freq_processor: T,
-    ) -> PhaseVocoder<T> {
+    ) -> Result<PhaseVocoder<T>, VocoderInitError> {
+        PhaseVocoder::with_windowing(
+            frame_size,
+            hop_size,
+            window_type,
+            true,
+            false,
+            freq_processor,
+        )
+    }
+
+    // a disabled side gets a rectangular window. the overlap-add gain correction
+    // follows whichever windows end up applied
+    pub fn with_windowing(
+        frame_size: usize,
+        hop_size: usize,
+        window_type: FFTWindowType,
+        analysis_windowing: bool,
+        synthesis_windowing: bool,
+        mut freq_processor: T,
+    ) -> Result<PhaseVocoder<T>, VocoderInitError> {
+        validate_frame_and_hop_size(frame_size, hop_size)?;
+
+        let params = freq_processor.advertise_parameters();
+        if params.last().map(|param| param.name) != Some(MIX_PARAM.name) {
+            return Err(VocoderInitError(String::from(
+                "The processor's parameters must end with MIX_PARAM",
+            )));
+        }
+
         // if hop size is 256 and frame size is 1024, this becomes 75%
         let overlap_factor = 1_f32 - ((hop_size as f32) / (frame_size as f32));
-        let (window, inv_gain_correction) = create_window(window_type, overlap_factor, frame_size);
+        let (window, _) = create_window(window_type, overlap_factor, frame_size);
+
+        let analysis_window = if analysis_windowing {
+            window.clone()
+        } else {
+            rectangular_window(frame_size)
+        };
+        let synthesis_window = if synthesis_windowing {
+            window
+        } else {
+            rectangular_window(frame_size)
+        };
+
+        let window_product_sum = (0..frame_size).fold(0.0f32, |acc, i| {
+            acc + analysis_window[i] * synthesis_window[i]
+        });
+        let inv_gain_correction = (1.0f32 - overlap_factor) / window_product_sum;
 
         let input_collection_buf = RefCell::new(FFTCollectionBuffer::new(frame_size << 2).unwrap());
         let output_collection_buf =
@@ -84,10 +156,9 @@ impl<T: FrequencyDomainAudioEffect> PhaseVocoder<T> {
             output_collection_buf.borrow_mut().set_write_idx(frame_size);
         }
 
-        let forward_plan: C2CPlan32 =
-            C2CPlan::aligned(&[frame_size], Sign::Forward, Flag::MEASURE).unwrap();
-        let backward_plan: C2CPlan32 =
-            C2CPlan::aligned(&[frame_size], Sign::Backward, Flag::MEASURE).unwrap();
+        let planning = FFTPlanning::Estimate;
+        let forward_plan = create_plan(frame_size, Sign::Forward, planning)?;
+        let backward_plan = create_plan(frame_size, Sign::Backward, planning)?;
 
         let mut fft_input_buf = AlignedVec::new(frame_size);
         let mut fft_output_buf = AlignedVec::new(frame_size);
@@ -107,10 +178,16 @@ impl<T: FrequencyDomainAudioEffect> PhaseVocoder<T> {
         let vocoder_context = VocoderContext {
             frame_size,
             hop_size,
-            analysis_window: window,
+            analysis_window,
+            synthesis_window,
         };
 
-        PhaseVocoder {
+        freq_processor.post_initialize(&vocoder_context);
+
+        Ok(PhaseVocoder {
+            params,
+            mix_pct: MIX_PARAM.default_value.as_flt(),
+
             vocoder_context,
             overlap_factor,
 
@@ -119,14 +196,38 @@ impl<T: FrequencyDomainAudioEffect> PhaseVocoder<T> {
             input_collection_buf,
             output_collection_buf,
             accumulated_sample_count: Cell::new(0),
+            skipped_last_frame: Cell::new(false),
 
             fft_context: RefCell::new(fft_context),
+            planning,
 
             freq_processor,
+        })
+    }
+
+    // replans the transforms. for a vocoder that's worth measured plans, i.e. a long lived one
+    pub fn with_planning(
+        mut self,
+        planning: FFTPlanning,
+    ) -> Result<PhaseVocoder<T>, VocoderInitError> {
+        if planning != self.planning {
+            let frame_size = self.vocoder_context.frame_size;
+            let fft_context = self.fft_context.get_mut();
+            fft_context.forward_plan = create_plan(frame_size, Sign::Forward, planning)?;
+            fft_context.backward_plan = create_plan(frame_size, Sign::Backward, planning)?;
+            self.planning = planning;
         }
+
+        Ok(self)
+    }
+
+    pub fn planning(&self) -> FFTPlanning {
+        self.planning
     }
 
-    fn execute_one(&self, sample: f32) -> f32 {
+    // when degrade is set, every other frame skips analysis and resynthesis and the last
+    // synthesized frame is overlap-added again instead. smeary, but half the fft work
+    pub(crate) fn execute_one(&self, sample: f32, degrade: bool) -> f32 {
         let mut input_collection_buf = self.input_collection_buf.borrow_mut();
         let mut output_collection_buf = self.output_collection_buf.borrow_mut();
 
@@ -136,6 +237,15 @@ impl<T: FrequencyDomainAudioEffect> PhaseVocoder<T> {
         output_collection_buf.set_at_idx(current_output_read_idx, 0_f32);
         output_collection_buf.advance_read_idx();
 
+        // the input buffer holds a few frames, so the sample that went in latency_samples ago is
+        // still there. mixed with that, the dry and wet line up and don't comb filter
+        let result = if self.mix_pct < 1.0f32 {
+            let dry = input_collection_buf.get_behind_write_idx(self.vocoder_context.frame_size);
+            self.mix_pct * result + (1.0f32 - self.mix_pct) * dry
+        } else {
+            result
+        };
+
         let current_input_write_idx = input_collection_buf.get_write_idx();
         input_collection_buf.set_at_idx(current_input_write_idx, sample);
         input_collection_buf.advance_write_idx();
@@ -143,33 +253,44 @@ impl<T: FrequencyDomainAudioEffect> PhaseVocoder<T> {
         self.accumulated_sample_count
             .set(self.accumulated_sample_count.get() + 1);
         if self.accumulated_sample_count.get() == self.vocoder_context.frame_size {
-            // time to do fft!
             let mut fft_context = self.fft_context.borrow_mut();
-            for i in 0..self.vocoder_context.frame_size {
-                let current_input_sample = input_collection_buf.get_at_read_idx();
-                input_collection_buf.advance_read_idx();
-
-                fft_context.fft_input_buf[i] = c32::new(
-                    current_input_sample * self.vocoder_context.analysis_window[i],
-                    0_f32,
+            let skip_frame = degrade && !self.skipped_last_frame.get();
+            self.skipped_last_frame.set(skip_frame);
+
+            if skip_frame {
+                // the skipped frame's input still moves on by a hop, same as below
+                for _ in 0..self.vocoder_context.hop_size {
+                    input_collection_buf.advance_read_idx();
+                }
+            } else {
+                // time to do fft!
+                for i in 0..self.vocoder_context.frame_size {
+                    let current_input_sample = input_collection_buf.get_at_read_idx();
+                    input_collection_buf.advance_read_idx();
+
+                    fft_context.fft_input_buf[i] = c32::new(
+                        current_input_sample * self.vocoder_context.analysis_window[i],
+                        0_f32,
+                    );
+                }
+                // overlap the read frames for fft
+                input_collection_buf.rewind_read_idx(
+                    self.vocoder_context.frame_size - self.vocoder_context.hop_size,
                 );
-            }
-            // overlap the read frames for fft
-            input_collection_buf
-                .rewind_read_idx(self.vocoder_context.frame_size - self.vocoder_context.hop_size);
 
-            fft_context.forward();
+                fft_context.forward();
 
-            execute_freq_effect(&mut fft_context, &self.freq_processor);
+                execute_freq_effect(&mut fft_context, &self.freq_processor);
 
-            fft_context.backward();
+                fft_context.backward();
 
-            execute_post_processing(&mut fft_context, &self.freq_processor);
+                execute_post_processing(&mut fft_context, &self.freq_processor);
+            }
 
             // overlap add
             overlap_add(
                 &mut fft_context,
-                self.vocoder_context.frame_size,
+                &self.vocoder_context.synthesis_window,
                 &mut output_collection_buf,
                 self.inv_gain_correction,
             );
@@ -188,20 +309,59 @@ impl<T: FrequencyDomainAudioEffect> PhaseVocoder<T> {
 
 impl<T: FrequencyDomainAudioEffect> AudioEffect for PhaseVocoder<T> {
     fn advertise_parameters(&self) -> &'static [AdvertisedParameter] {
-        self.freq_processor.advertise_parameters()
+        self.params
     }
 
     fn set_audio_parameters(&mut self, _new_config: &AudioConfig) {}
 
+    // empties the collected input, the overlap-added output and whatever the processor
+    // carries between frames. everything is cleared in place, nothing is reallocated
+    fn reset(&mut self) {
+        self.input_collection_buf.get_mut().clear();
+
+        let output_collection_buf = self.output_collection_buf.get_mut();
+        output_collection_buf.clear();
+        output_collection_buf.set_write_idx(self.vocoder_context.frame_size);
+
+        let fft_context = self.fft_context.get_mut();
+        for bin in fft_context
+            .fft_input_buf
+            .iter_mut()
+            .chain(fft_context.fft_output_buf.iter_mut())
+        {
+            *bin = c32::new(0_f32, 0_f32);
+        }
+
+        self.accumulated_sample_count.set(0);
+        self.skipped_last_frame.set(false);
+
+        self.freq_processor.reset();
+    }
+
     fn set_effect_parameter(
         &mut self,
         param_idx: usize,
         param_value: BoardEffectConfigParameterValue,
     ) {
+        if param_idx == self.params.len() - 1 {
+            self.mix_pct = num::clamp(param_value.as_flt(), 0.0f32, 1.0f32);
+            return;
+        }
+
         self.freq_processor
             .set_effect_parameter(param_idx, param_value);
     }
 
+    fn set_effect_parameter_array(&mut self, param_idx: usize, param_values: &[f32]) {
+        self.freq_processor
+            .set_effect_parameter_array(param_idx, param_values);
+    }
+
+    // a sample comes out once the frame it went into has been collected and synthesized
+    fn latency_samples(&self) -> usize {
+        self.vocoder_context.frame_size
+    }
+
     fn execute(&self, context: &BoardContext, connection_idx: usize, num_samples: usize) {
         let maybe_bufs = basic_single_in_single_out(context, connection_idx, num_samples);
         if let None = maybe_bufs {
@@ -209,17 +369,50 @@ impl<T: FrequencyDomainAudioEffect> AudioEffect for PhaseVocoder<T> {
         }
 
         let (read_buf, mut write_buf) = maybe_bufs.unwrap();
+        let degrade = context.should_degrade();
 
         for i in 0..num_samples {
             let current_sample = read_buf.buf_read(i);
 
-            let next_sample = self.execute_one(current_sample);
+            let next_sample = self.execute_one(current_sample, degrade);
 
             write_buf.buf_write(i, next_sample);
         }
     }
 }
 
+// overlap-add (and the ocean shifter's demodulation) assume every frame
+// is made of a whole number of hops
+pub fn validate_frame_and_hop_size(
+    frame_size: usize,
+    hop_size: usize,
+) -> Result<(), VocoderInitError> {
+    if !is_power_of_2(frame_size) {
+        return Err(VocoderInitError(format!(
+            "Frame size {} must be a power of 2",
+            frame_size
+        )));
+    }
+
+    if hop_size == 0 || hop_size > frame_size {
+        return Err(VocoderInitError(format!(
+            "Hop size {} must be in [1, {}] (the frame size)",
+            hop_size, frame_size
+        )));
+    }
+
+    if frame_size % hop_size != 0 {
+        return Err(VocoderInitError(format!(
+            "Hop size {} does not evenly divide frame size {}. Overlap factor would be {:.3}, which must be an integer",
+            hop_size,
+            frame_size,
+            frame_size as f32 / hop_size as f32
+        )));
+    }
+
+    Ok(())
+}
+
 pub fn create_window(
     window_type: FFTWindowType,
     overlap_pct: f32,
@@ -244,6 +437,35 @@ pub fn create_window(
     (r, (1.0f32 - overlap_pct) / inv_gain_correction)
 }
 
+// every vocoder gets its own plans, since boards (and the vocoders on them) can run on
+// different threads
+fn create_plan(
+    frame_size: usize,
+    sign: Sign,
+    planning: FFTPlanning,
+) -> Result<C2CPlan32, VocoderInitError> {
+    let flag = match planning {
+        FFTPlanning::Estimate => Flag::ESTIMATE,
+        FFTPlanning::Measure => Flag::MEASURE,
+    };
+
+    C2CPlan::aligned(&[frame_size], sign, flag).map_err(|err| {
+        VocoderInitError(format!(
+            "Couldn't plan a {} point FFT: {:?}",
+            frame_size, err
+        ))
+    })
+}
+
+fn rectangular_window(frame_size: usize) -> AlignedVec<f32> {
+    let mut r = AlignedVec::new(frame_size);
+    for i in 0..frame_size {
+        r[i] = 1.0f32;
+    }
+
+    r
+}
+
 fn execute_freq_effect<T: FrequencyDomainAudioEffect>(
     fft_context: &mut FFTContext,
     freq_processor: &T,
@@ -264,17 +486,18 @@ fn execute_post_processing<T: FrequencyDomainAudioEffect>(
 
 fn overlap_add(
     fft_context: &mut FFTContext,
-    frame_size: usize,
+    synthesis_window: &AlignedVec<f32>,
     output_collection_buf: &mut FFTCollectionBuffer,
     inv_gain_correction: f32,
 ) {
     let output_buf = fft_context.ifft_buf();
 
-    for i in 0..frame_size {
+    for i in 0..synthesis_window.len() {
         let current_sample =
             output_collection_buf.get_at_idx(output_collection_buf.get_write_idx());
-        output_collection_buf
-            .set_at_write_idx(output_buf[i].re * inv_gain_correction + current_sample);
+        output_collection_buf.set_at_write_idx(
+            output_buf[i].re * synthesis_window[i] * inv_gain_correction + current_sample,
+        );
         output_collection_buf.advance_write_idx();
     }
 }
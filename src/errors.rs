@@ -1,3 +1,4 @@
+use std::fmt;
 use std::io;
 
 #[derive(Debug)]
@@ -6,6 +7,15 @@ pub struct FactoryErrors(pub Vec<String>);
 #[derive(Debug)]
 pub struct ContextInitError(pub Vec<String>);
 
+#[derive(Debug)]
+pub struct VocoderInitError(pub String);
+
+impl fmt::Display for VocoderInitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Vocoder init failed: {}", self.0)
+    }
+}
+
 #[derive(Debug)]
 pub enum OttersInitError {
     IOError(io::Error),
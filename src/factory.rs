@@ -1,12 +1,16 @@
-use crate::conf::{AdvertisedParameter, AudioConfig};
-use crate::effects::FactoryExtension;
+use crate::conf::{AdvertisedParameter, AudioConfig, BoardConfig};
+use crate::effects::{FactoryExtension, SubBoard};
 use crate::traits::AudioEffect;
 
 use std::collections::HashMap;
 
+// effect names with this prefix refer to one of the board's sub boards
+pub const SUB_BOARD_PREFIX: &'static str = "SubBoard/";
+
 pub struct EffectFactory {
     audio_config: AudioConfig,
     factory_exts: Vec<FactoryExtension>,
+    sub_boards: HashMap<String, BoardConfig>,
 }
 
 impl EffectFactory {
@@ -17,11 +21,20 @@ impl EffectFactory {
         EffectFactory {
             audio_config,
             factory_exts: extensions,
+            sub_boards: HashMap::new(),
         }
     }
 
+    pub fn set_sub_boards(&mut self, sub_boards: HashMap<String, BoardConfig>) {
+        self.sub_boards = sub_boards;
+    }
+
     pub fn create_effect_unit(&self, name: &str) -> Option<Box<dyn AudioEffect>> {
         print!("Creating effect {}...", name);
+        if name.starts_with(SUB_BOARD_PREFIX) {
+            return self.create_sub_board(&name[SUB_BOARD_PREFIX.len()..]);
+        }
+
         for factory_ext in &self.factory_exts {
             if factory_ext.factory_fns.contains_key(name) {
                 print!("Success!\n");
@@ -35,6 +48,27 @@ impl EffectFactory {
         None
     }
 
+    fn create_sub_board(&self, sub_board_name: &str) -> Option<Box<dyn AudioEffect>> {
+        let sub_board_config = match self.sub_boards.get(sub_board_name) {
+            Some(sub_board_config) => sub_board_config.clone(),
+            None => {
+                print!("FAILED! No sub board named {}\n", sub_board_name);
+                return None;
+            }
+        };
+
+        match SubBoard::new(self.audio_config, sub_board_config) {
+            Ok(sub_board) => {
+                print!("Success!\n");
+                Some(Box::new(sub_board))
+            }
+            Err(e) => {
+                print!("FAILED! {:?}\n", e);
+                None
+            }
+        }
+    }
+
     pub fn change_audio_config(&mut self, new_audio_config: AudioConfig) {
         self.audio_config = new_audio_config;
     }
@@ -1,17 +1,28 @@
-use crate::conf::AudioConfig;
+use crate::conf::{AudioConfig, DEFAULT_TEMPO_BPM};
 use crate::otters::Otters;
 use crate::OttersParamModifierContext;
 use std::ffi;
 
 pub type OttersString = *mut libc::c_char;
 
+// mono. see otters_hello_multichannel
 #[no_mangle]
 pub extern "C" fn otters_hello(
     sample_rate: libc::c_float,
     max_block_size: libc::c_uint,
     config_file_name: *const libc::c_char,
 ) -> *mut Otters {
-    if sample_rate <= 0f32 || max_block_size <= 0 {
+    otters_hello_multichannel(sample_rate, max_block_size, 1, config_file_name)
+}
+
+#[no_mangle]
+pub extern "C" fn otters_hello_multichannel(
+    sample_rate: libc::c_float,
+    max_block_size: libc::c_uint,
+    num_channels: libc::c_uint,
+    config_file_name: *const libc::c_char,
+) -> *mut Otters {
+    if sample_rate <= 0f32 || max_block_size <= 0 || num_channels <= 0 {
         return 0 as *mut Otters;
     }
 
@@ -32,6 +43,8 @@ pub extern "C" fn otters_hello(
         AudioConfig {
             sample_rate: sample_rate as f32,
             max_block_size: max_block_size as usize,
+            num_channels: num_channels as usize,
+            tempo_bpm: DEFAULT_TEMPO_BPM,
         },
         valid_rs_str.unwrap(),
     );
@@ -67,9 +80,11 @@ pub extern "C" fn otters_update_audio_parameters(
 
     unsafe {
         let mut o: Box<Otters> = Box::from_raw(otters);
+        let audio_config = o.audio_config();
         let _ = o.update_audio_config(AudioConfig {
             sample_rate: new_sample_rate as f32,
             max_block_size: new_max_block_size as usize,
+            ..audio_config
         });
 
         // don't accidentally delete the instance
@@ -77,6 +92,25 @@ pub extern "C" fn otters_update_audio_parameters(
     }
 }
 
+// rebuilds the board like a sample rate change does, so tempo synced effects pick it up
+#[no_mangle]
+pub extern "C" fn otters_update_tempo(otters: *mut Otters, new_tempo_bpm: libc::c_float) {
+    if otters.is_null() || new_tempo_bpm <= 0.0f32 {
+        return;
+    }
+
+    unsafe {
+        let mut o: Box<Otters> = Box::from_raw(otters);
+        let audio_config = o.audio_config();
+        let _ = o.update_audio_config(AudioConfig {
+            tempo_bpm: new_tempo_bpm as f32,
+            ..audio_config
+        });
+
+        Box::into_raw(o);
+    }
+}
+
 #[no_mangle]
 pub extern "C" fn otters_bind_input(otters: *mut Otters, input_num: libc::c_uint, input_ptr: *const f32) {
     if otters.is_null() {
@@ -112,7 +146,6 @@ pub extern "C" fn otters_frolic(otters: *mut Otters, block_size: libc::c_uint) {
     }
 
     unsafe {
-        #[allow(unused_mut)]
         let mut o: Box<Otters> = Box::from_raw(otters);
 
         o.frolic(block_size as usize);
@@ -121,6 +154,100 @@ pub extern "C" fn otters_frolic(otters: *mut Otters, block_size: libc::c_uint) {
     }
 }
 
+// clears every effect's delay lines, filter memory and tails, e.g. when the host seeks
+#[no_mangle]
+pub extern "C" fn otters_reset(otters: *mut Otters) {
+    if otters.is_null() {
+        return;
+    }
+
+    unsafe {
+        let mut o: Box<Otters> = Box::from_raw(otters);
+
+        o.reset_all();
+
+        Box::into_raw(o);
+    }
+}
+
+// returns false if the name is invalid or there's no effect bound to it
+#[no_mangle]
+pub extern "C" fn otters_set_effect_enabled(
+    otters: *mut Otters,
+    bind_name: *const libc::c_char,
+    enabled: bool,
+) -> bool {
+    if otters.is_null() || bind_name.is_null() {
+        return false;
+    }
+
+    unsafe {
+        let name = match ffi::CStr::from_ptr(bind_name).to_str() {
+            Ok(name) => name,
+            Err(_) => return false,
+        };
+
+        let mut o: Box<Otters> = Box::from_raw(otters);
+        let found = o.set_effect_enabled(name, enabled);
+
+        Box::into_raw(o);
+        found
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn otters_get_latency_samples(otters: *mut Otters) -> libc::c_uint {
+    if otters.is_null() {
+        return 0;
+    }
+
+    unsafe {
+        let o: Box<Otters> = Box::from_raw(otters);
+        let latency = o.total_latency_samples();
+
+        Box::into_raw(o);
+        latency as libc::c_uint
+    }
+}
+
+// a negative latency clears the override
+#[no_mangle]
+pub extern "C" fn otters_set_manual_latency(otters: *mut Otters, latency_samples: libc::c_int) {
+    if otters.is_null() {
+        return;
+    }
+
+    unsafe {
+        let mut o: Box<Otters> = Box::from_raw(otters);
+        if latency_samples < 0 {
+            o.set_manual_latency(None);
+        } else {
+            o.set_manual_latency(Some(latency_samples as usize));
+        }
+
+        Box::into_raw(o);
+    }
+}
+
+// budget is a fraction of the block's duration. a negative budget turns it off
+#[no_mangle]
+pub extern "C" fn otters_set_cpu_budget(otters: *mut Otters, budget: libc::c_float) {
+    if otters.is_null() {
+        return;
+    }
+
+    unsafe {
+        let mut o: Box<Otters> = Box::from_raw(otters);
+        if budget < 0.0f32 {
+            o.set_cpu_budget(None);
+        } else {
+            o.set_cpu_budget(Some(budget));
+        }
+
+        Box::into_raw(o);
+    }
+}
+
 // it's totally safe to use an OttersParamModifierContext even if the Otters object it's attached to dies.
 // Allocation is also ok if necessary here as these functions will usually be called from a UI thread
 #[no_mangle]
@@ -213,6 +340,37 @@ pub extern "C" fn param_set_int_param_value(pu: *mut OttersParamModifierContext,
     }
 }
 
+// what the ui last set, or the board's value when the updater was made
+#[no_mangle]
+pub extern "C" fn param_get_flt_param_value(pu: *mut OttersParamModifierContext, global_param_idx: u32) -> libc::c_float {
+    if pu.is_null() {
+        return 0.0f32;
+    }
+
+    unsafe {
+        let u = Box::from_raw(pu);
+        let value = u.get_flt_param_value(global_param_idx);
+
+        Box::into_raw(u);
+        value as libc::c_float
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn param_get_int_param_value(pu: *mut OttersParamModifierContext, global_param_idx: u32) -> libc::c_int {
+    if pu.is_null() {
+        return 0;
+    }
+
+    unsafe {
+        let u = Box::from_raw(pu);
+        let value = u.get_int_param_value(global_param_idx);
+
+        Box::into_raw(u);
+        value as libc::c_int
+    }
+}
+
 fn str_ref_to_cstr(s: &str) -> OttersString {
     let cstr_s = ffi::CString::new(s).unwrap();
     cstr_s.into_raw()
@@ -0,0 +1,41 @@
+use crate::otters::Otters;
+
+// renders a mono board (@SOURCE_0 -> @SINK_0) offline with the untouched input kept alongside
+// the output, so the two can be A/B-ed in an external editor
+pub struct ABRender {
+    pub dry: Vec<f32>,
+    pub wet: Vec<f32>,
+}
+
+impl ABRender {
+    // one stereo signal with the dry input on the left and the processed output on the right
+    pub fn interleaved(&self) -> Vec<f32> {
+        let mut interleaved = Vec::with_capacity(2 * self.dry.len());
+        for (dry, wet) in self.dry.iter().zip(self.wet.iter()) {
+            interleaved.push(*dry);
+            interleaved.push(*wet);
+        }
+
+        interleaved
+    }
+}
+
+// block_size must not be more than the board's max block size
+pub fn render_ab(otters: &mut Otters, input: &[f32], block_size: usize) -> ABRender {
+    let mut wet = vec![0.0f32; input.len()];
+
+    let mut offset = 0;
+    while offset < input.len() {
+        let num_samples = block_size.min(input.len() - offset);
+        otters.bind_input(0, input[offset..].as_ptr());
+        otters.bind_output(0, wet[offset..].as_mut_ptr());
+        otters.frolic(num_samples);
+
+        offset += num_samples;
+    }
+
+    ABRender {
+        dry: input.to_vec(),
+        wet,
+    }
+}
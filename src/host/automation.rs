@@ -0,0 +1,63 @@
+use crate::utils::smoothed_param::SmoothedParam;
+
+pub use crate::utils::smoothed_param::RampCurve;
+
+// one breakpoint of an automation lane. curve is how the lane travels here from the previous point
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct AutomationPoint {
+    pub sample_pos: usize,
+    pub value: f32,
+    pub curve: RampCurve,
+}
+
+// replays recorded automation as a value per sample, so a host can ramp a parameter
+// through a block instead of stepping it once per block
+// before the first point the lane holds the first point's value, after the last it holds the last
+pub struct AutomationLane {
+    points: Vec<AutomationPoint>,
+    next_point: usize,
+    position: usize,
+    param: SmoothedParam,
+}
+
+impl AutomationLane {
+    pub fn new(mut points: Vec<AutomationPoint>) -> AutomationLane {
+        points.sort_by_key(|p| p.sample_pos);
+        let initial_value = points.first().map_or(0.0f32, |p| p.value);
+
+        AutomationLane {
+            points,
+            next_point: 0,
+            position: 0,
+            param: SmoothedParam::new(initial_value),
+        }
+    }
+
+    pub fn get_position(&self) -> usize {
+        self.position
+    }
+
+    pub fn rewind(&mut self) {
+        self.next_point = 0;
+        self.position = 0;
+        self.param
+            .set_immediate(self.points.first().map_or(0.0f32, |p| p.value));
+    }
+
+    // writes the lane's value for the next output.len() samples
+    pub fn fill_block(&mut self, output: &mut [f32]) {
+        for value in output.iter_mut() {
+            if !self.param.is_ramping() && self.next_point < self.points.len() {
+                let point = self.points[self.next_point];
+
+                // lands on point.value exactly at point.sample_pos
+                let ramp_samples = (point.sample_pos + 1).saturating_sub(self.position).max(1);
+                self.param.ramp_to(point.value, ramp_samples, point.curve);
+                self.next_point += 1;
+            }
+
+            *value = self.param.next();
+            self.position += 1;
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use crate::otters::Otters;
+
+// smooths over where a runner starts and stops processing
+// warm up: silence goes through the board first (and the output is thrown away) so whatever
+// the board does on its own (constant buffers, dc, start up ramps) has settled before the
+// first real block. fade in / out: linear ramps on the first and last samples of the output
+pub struct BoundaryFades {
+    fade_in_samples: usize,
+    fade_out_samples: usize,
+    warm_up_samples: usize,
+
+    // how far into the fade in the output is, across blocks
+    fade_in_position: usize,
+}
+
+impl BoundaryFades {
+    pub fn new(
+        sample_rate: f32,
+        fade_in_ms: f32,
+        fade_out_ms: f32,
+        warm_up_ms: f32,
+    ) -> BoundaryFades {
+        let ms_to_samples = |ms: f32| (ms.max(0.0f32) * sample_rate / 1000.0f32).round() as usize;
+
+        BoundaryFades {
+            fade_in_samples: ms_to_samples(fade_in_ms),
+            fade_out_samples: ms_to_samples(fade_out_ms),
+            warm_up_samples: ms_to_samples(warm_up_ms),
+            fade_in_position: 0,
+        }
+    }
+
+    // how much output fade_out needs. a realtime runner keeps processing this long after it's
+    // asked to stop
+    pub fn get_fade_out_samples(&self) -> usize {
+        self.fade_out_samples
+    }
+
+    // runs the warm up through a mono board (@SOURCE_0 -> @SINK_0) and starts the fade in over
+    // block_size must not be more than the board's max block size
+    pub fn warm_up(&mut self, otters: &mut Otters, block_size: usize) {
+        let silence = vec![0.0f32; self.warm_up_samples];
+        render_mono(otters, &silence, block_size);
+
+        self.fade_in_position = 0;
+    }
+
+    // call on every output block from the start. does nothing once the fade in is done
+    pub fn fade_in(&mut self, block: &mut [f32]) {
+        for x in block.iter_mut() {
+            if self.fade_in_position >= self.fade_in_samples {
+                return;
+            }
+
+            *x *= self.fade_in_position as f32 / self.fade_in_samples as f32;
+            self.fade_in_position += 1;
+        }
+    }
+
+    // ramps the end of output down to silence. the last sample comes out at 0
+    pub fn fade_out(&self, output: &mut [f32]) {
+        let num_samples = self.fade_out_samples.min(output.len());
+        let start = output.len() - num_samples;
+
+        for (i, x) in output[start..].iter_mut().enumerate() {
+            *x *= (num_samples - 1 - i) as f32 / num_samples as f32;
+        }
+    }
+
+    // offline version of the whole thing: warm up, process input, fade both ends
+    pub fn render(&mut self, otters: &mut Otters, input: &[f32], block_size: usize) -> Vec<f32> {
+        self.warm_up(otters, block_size);
+
+        let mut output = render_mono(otters, input, block_size);
+        self.fade_in(&mut output);
+        self.fade_out(&mut output);
+
+        output
+    }
+}
+
+fn render_mono(otters: &mut Otters, input: &[f32], block_size: usize) -> Vec<f32> {
+    let mut output = vec![0.0f32; input.len()];
+
+    let mut offset = 0;
+    while offset < input.len() {
+        let num_samples = block_size.min(input.len() - offset);
+        otters.bind_input(0, input[offset..].as_ptr());
+        otters.bind_output(0, output[offset..].as_mut_ptr());
+        otters.frolic(num_samples);
+
+        offset += num_samples;
+    }
+
+    output
+}
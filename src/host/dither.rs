@@ -0,0 +1,72 @@
+use crate::utils::fast_rand::WyHashPRNG;
+
+// converts float samples ([-1, 1]) to signed integer samples of a given bit depth
+// TPDF dither is added before rounding so the quantization error is uncorrelated with the signal.
+// with noise shaping on, the previous sample's error is fed back (1 - z^-1), which
+// moves the error towards nyquist where it's less audible
+pub struct OutputQuantizer {
+    scale: f32,
+    min_value: f32,
+    max_value: f32,
+    noise_shaping: bool,
+    dither: bool,
+
+    last_error: f32,
+    rng: WyHashPRNG,
+}
+
+impl OutputQuantizer {
+    pub fn new(bit_depth: u32, dither: bool, noise_shaping: bool, seed: u64) -> OutputQuantizer {
+        let bit_depth = num::clamp(bit_depth, 2, 32);
+        let scale = (1u64 << (bit_depth - 1)) as f32;
+
+        OutputQuantizer {
+            scale,
+            min_value: -scale,
+            max_value: scale - 1.0f32,
+            noise_shaping,
+            dither,
+            last_error: 0.0f32,
+            rng: WyHashPRNG::new(seed),
+        }
+    }
+
+    // e.g. 32768 for 16 bit output
+    pub fn get_scale(&self) -> f32 {
+        self.scale
+    }
+
+    pub fn quantize(&mut self, sample: f32) -> i32 {
+        let mut target = sample * self.scale;
+        if self.noise_shaping {
+            target -= self.last_error;
+        }
+
+        let dithered = if self.dither {
+            target + self.tpdf()
+        } else {
+            target
+        };
+
+        let quantized = num::clamp(dithered.round(), self.min_value, self.max_value);
+
+        // clipped samples would feed back a huge error, so only shape what rounding did
+        self.last_error = num::clamp(quantized - target, -2.0f32, 2.0f32);
+
+        quantized as i32
+    }
+
+    pub fn quantize_block(&mut self, input: &[f32], output: &mut [i32]) {
+        for (x, y) in input.iter().zip(output.iter_mut()) {
+            *y = self.quantize(*x);
+        }
+    }
+
+    // triangular pdf in (-1, 1) LSB: the sum of two uniform values
+    fn tpdf(&self) -> f32 {
+        let r1 = (self.rng.next() >> 40) as f32 / (1u64 << 24) as f32;
+        let r2 = (self.rng.next() >> 40) as f32 / (1u64 << 24) as f32;
+
+        r1 + r2 - 1.0f32
+    }
+}
@@ -0,0 +1,130 @@
+use crate::utils::biquad::{Biquad, BiquadCoefficients};
+use crate::utils::mathutils::db_to_linear;
+
+// offline (two pass) level measurement and normalization for whole signals
+
+// ITU-R BS.1770: 400 ms blocks with 75% overlap
+const BLOCK_LENGTH_S: f32 = 0.4f32;
+const BLOCK_OVERLAP: f32 = 0.75f32;
+
+const ABSOLUTE_GATE_LUFS: f32 = -70.0f32;
+const RELATIVE_GATE_LU: f32 = -10.0f32;
+
+const LOUDNESS_OFFSET: f32 = -0.691f32;
+
+// K-weighting: the BS.1770 pre-filter (a second order high shelf, ~+4 dB above ~1.7 kHz,
+// modelling the head), then the RLB high pass. these are the analog prototypes behind the
+// published 48 kHz coefficients, so other sample rates get the same curve
+const K_SHELF_FREQ_HZ: f32 = 1681.974450955533f32;
+const K_SHELF_GAIN_DB: f32 = 3.999843853973347f32;
+const K_SHELF_Q: f32 = 0.7071752369554196f32;
+const K_HIGH_PASS_FREQ_HZ: f32 = 38.13547087602444f32;
+const K_HIGH_PASS_Q: f32 = 0.5003270373238773f32;
+
+pub fn peak_db(signal: &[f32]) -> f32 {
+    let peak = signal.iter().fold(0.0f32, |acc, x| acc.max(x.abs()));
+    20.0f32 * peak.log10()
+}
+
+// gated integrated loudness of a mono signal in LUFS
+// returns -inf if everything falls under the absolute gate (or the signal is shorter than a block)
+pub fn integrated_loudness_lufs(signal: &[f32], sample_rate: f32) -> f32 {
+    let block_powers = k_weighted_block_powers(signal, sample_rate);
+
+    let above_absolute: Vec<f32> = block_powers
+        .into_iter()
+        .filter(|p| power_to_lufs(*p) > ABSOLUTE_GATE_LUFS)
+        .collect();
+    if above_absolute.is_empty() {
+        return f32::NEG_INFINITY;
+    }
+
+    let relative_gate_lufs = power_to_lufs(mean(&above_absolute)) + RELATIVE_GATE_LU;
+    let above_relative: Vec<f32> = above_absolute
+        .into_iter()
+        .filter(|p| power_to_lufs(*p) > relative_gate_lufs)
+        .collect();
+
+    power_to_lufs(mean(&above_relative))
+}
+
+// scales the signal so its peak sits at target_peak_db. returns the applied gain in dB
+pub fn normalize_peak(signal: &mut [f32], target_peak_db: f32) -> f32 {
+    let current_peak_db = peak_db(signal);
+    if !current_peak_db.is_finite() {
+        return 0.0f32;
+    }
+
+    let gain_db = target_peak_db - current_peak_db;
+    apply_gain_db(signal, gain_db);
+
+    gain_db
+}
+
+// scales the signal so its integrated loudness is target_lufs. returns the applied gain in dB
+// nothing stops this from pushing peaks over 0 dBFS. follow it with a limiter if that matters
+pub fn normalize_loudness(signal: &mut [f32], sample_rate: f32, target_lufs: f32) -> f32 {
+    let current_lufs = integrated_loudness_lufs(signal, sample_rate);
+    if !current_lufs.is_finite() {
+        return 0.0f32;
+    }
+
+    let gain_db = target_lufs - current_lufs;
+    apply_gain_db(signal, gain_db);
+
+    gain_db
+}
+
+fn apply_gain_db(signal: &mut [f32], gain_db: f32) {
+    let gain = db_to_linear(gain_db);
+    for x in signal.iter_mut() {
+        *x *= gain;
+    }
+}
+
+fn k_weighted_block_powers(signal: &[f32], sample_rate: f32) -> Vec<f32> {
+    let mut shelf = Biquad::new(BiquadCoefficients::second_order_high_shelf(
+        K_SHELF_FREQ_HZ,
+        sample_rate,
+        K_SHELF_GAIN_DB,
+        Some(K_SHELF_Q),
+    ));
+    let mut high_pass = Biquad::new(BiquadCoefficients::second_order_hpf(
+        K_HIGH_PASS_FREQ_HZ,
+        sample_rate,
+        Some(K_HIGH_PASS_Q),
+    ));
+
+    let squared: Vec<f64> = signal
+        .iter()
+        .map(|x| {
+            let weighted = high_pass.filter(shelf.filter(*x)) as f64;
+            weighted * weighted
+        })
+        .collect();
+
+    let block_size = (BLOCK_LENGTH_S * sample_rate) as usize;
+    let step = ((1.0f32 - BLOCK_OVERLAP) * block_size as f32) as usize;
+    if block_size == 0 || step == 0 || squared.len() < block_size {
+        return Vec::new();
+    }
+
+    let mut block_powers = Vec::with_capacity((squared.len() - block_size) / step + 1);
+    let mut start = 0;
+    while start + block_size <= squared.len() {
+        let sum: f64 = squared[start..start + block_size].iter().sum();
+        block_powers.push((sum / block_size as f64) as f32);
+
+        start += step;
+    }
+
+    block_powers
+}
+
+fn power_to_lufs(power: f32) -> f32 {
+    LOUDNESS_OFFSET + 10.0f32 * power.log10()
+}
+
+fn mean(values: &[f32]) -> f32 {
+    values.iter().sum::<f32>() / values.len() as f32
+}
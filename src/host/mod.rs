@@ -0,0 +1,9 @@
+// helpers for programs hosting otters (runners, plugins) that work outside of the realtime path
+
+pub mod ab_compare;
+pub mod automation;
+pub mod boundary_fades;
+pub mod dither;
+pub mod loudness;
+pub mod pcm;
+pub mod test_signal;
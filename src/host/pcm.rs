@@ -0,0 +1,53 @@
+// integer PCM (e.g. from a WAV file) to float conversion for hosts loading audio into memory
+// negative samples are scaled by 2^(bits - 1) and positive ones by 2^(bits - 1) - 1
+// so that both ends of the integer range land exactly on -1.0 and 1.0
+pub struct IntToFloatConversion {
+    pub samples: Vec<f32>,
+
+    // samples sitting at (or beyond) the integer min/max. a lot of these usually means
+    // the source was clipped before it ever got to us
+    pub clipped_samples: usize,
+}
+
+impl IntToFloatConversion {
+    pub fn clip_warning(&self) -> Option<String> {
+        if self.clipped_samples == 0 {
+            return None;
+        }
+
+        Some(format!(
+            "Input has {} of {} samples at full scale. It was probably clipped before it was loaded",
+            self.clipped_samples,
+            self.samples.len()
+        ))
+    }
+}
+
+pub fn int_samples_to_float(samples: &[i32], bit_depth: u32) -> IntToFloatConversion {
+    let bit_depth = num::clamp(bit_depth, 2, 32);
+    let min_value = -((1i64 << (bit_depth - 1)) as f64);
+    let max_value = ((1i64 << (bit_depth - 1)) - 1) as f64;
+
+    let mut clipped_samples = 0usize;
+    let converted = samples
+        .iter()
+        .map(|x| {
+            // saturate anything that doesn't fit the declared bit depth
+            let x = num::clamp(*x as f64, min_value, max_value);
+            if x == min_value || x == max_value {
+                clipped_samples += 1;
+            }
+
+            if x < 0.0f64 {
+                (x / -min_value) as f32
+            } else {
+                (x / max_value) as f32
+            }
+        })
+        .collect();
+
+    IntToFloatConversion {
+        samples: converted,
+        clipped_samples,
+    }
+}
@@ -0,0 +1,207 @@
+use crate::utils::fast_rand::WyHashPRNG;
+use crate::utils::TWO_PI;
+
+const TEST_SIGNAL_LEVEL: f32 = 0.5f32;
+const TEST_SIGNAL_NOISE_SEED: u64 = 0x6f74_7465_7273;
+
+// signals that can be fed into a board instead of host input (see Otters::frolic_with_test_signal)
+// the generator keeps its position across blocks, so an impulse only happens once
+// and a sine stays continuous until the kind changes
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TestSignal {
+    Impulse,
+    Step,
+    Sine(f32),
+    Noise,
+    // one pass from start_hz to end_hz, then silence so the board's tail can ring out
+    Sweep {
+        start_hz: f32,
+        end_hz: f32,
+        duration_secs: f32,
+        sweep_type: SweepType,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SweepType {
+    // same number of hz every second
+    Linear,
+    // same number of octaves every second. what deconvolution (see log_sweep_inverse_filter) needs
+    Logarithmic,
+}
+
+pub struct TestSignalGenerator {
+    sample_rate: f32,
+    kind: Option<TestSignal>,
+    position: usize,
+    rng: WyHashPRNG,
+
+    buf: Vec<f32>,
+}
+
+impl TestSignalGenerator {
+    pub fn new(sample_rate: f32, max_block_size: usize) -> TestSignalGenerator {
+        TestSignalGenerator {
+            sample_rate,
+            kind: None,
+            position: 0,
+            rng: WyHashPRNG::new(TEST_SIGNAL_NOISE_SEED),
+            buf: vec![0.0f32; max_block_size],
+        }
+    }
+
+    // fills the next num_samples of the signal and returns a pointer to them
+    // the pointer stays valid until the next call
+    pub fn generate(&mut self, kind: TestSignal, num_samples: usize) -> *const f32 {
+        if self.kind != Some(kind) {
+            self.kind = Some(kind);
+            self.position = 0;
+            self.rng.set_state(TEST_SIGNAL_NOISE_SEED);
+        }
+
+        let num_samples = num_samples.min(self.buf.len());
+        for i in 0..num_samples {
+            let n = self.position + i;
+            self.buf[i] = match kind {
+                TestSignal::Impulse => {
+                    if n == 0 {
+                        1.0f32
+                    } else {
+                        0.0f32
+                    }
+                }
+                TestSignal::Step => 1.0f32,
+                TestSignal::Sine(freq_hz) => {
+                    TEST_SIGNAL_LEVEL * (TWO_PI * freq_hz * n as f32 / self.sample_rate).sin()
+                }
+                TestSignal::Noise => {
+                    let r = (self.rng.next() >> 40) as f32 / (1u64 << 24) as f32;
+                    TEST_SIGNAL_LEVEL * (2.0f32 * r - 1.0f32)
+                }
+                TestSignal::Sweep {
+                    start_hz,
+                    end_hz,
+                    duration_secs,
+                    sweep_type,
+                } => sweep_sample(
+                    n,
+                    self.sample_rate,
+                    start_hz,
+                    end_hz,
+                    duration_secs,
+                    sweep_type,
+                ),
+            };
+        }
+
+        self.position += num_samples;
+
+        self.buf.as_ptr()
+    }
+}
+
+fn sweep_num_samples(sample_rate: f32, duration_secs: f32) -> usize {
+    (duration_secs * sample_rate) as usize
+}
+
+// the phase is worked out in f64: a few seconds in, f32 can't resolve a fraction of a cycle
+fn sweep_sample(
+    n: usize,
+    sample_rate: f32,
+    start_hz: f32,
+    end_hz: f32,
+    duration_secs: f32,
+    sweep_type: SweepType,
+) -> f32 {
+    if n >= sweep_num_samples(sample_rate, duration_secs) {
+        return 0.0f32;
+    }
+
+    let t = n as f64 / sample_rate as f64;
+    let (f1, f2, duration) = (start_hz as f64, end_hz as f64, duration_secs as f64);
+
+    let phase = match sweep_type {
+        SweepType::Linear => {
+            std::f64::consts::PI * 2.0f64 * (f1 * t + (f2 - f1) * t * t / (2.0f64 * duration))
+        }
+        SweepType::Logarithmic => {
+            // frequency is f1 * e^(t / l), which gets to f2 right at the end
+            let l = duration / (f2 / f1).ln();
+            std::f64::consts::PI * 2.0f64 * f1 * l * ((t / l).exp() - 1.0f64)
+        }
+    };
+
+    TEST_SIGNAL_LEVEL * phase.sin() as f32
+}
+
+// the time reversed log sweep, tilted down 6 db per octave to undo the sweep's pink spectrum
+// (it spends as long on every octave, so the highs would otherwise come out too quiet)
+// scaled so a board that passes the sweep through untouched deconvolves to a unit impulse
+// (band limited to the sweep, of course)
+pub fn log_sweep_inverse_filter(
+    start_hz: f32,
+    end_hz: f32,
+    duration_secs: f32,
+    sample_rate: f32,
+) -> Vec<f32> {
+    let num_samples = sweep_num_samples(sample_rate, duration_secs);
+    let l = duration_secs / (end_hz / start_hz).ln();
+
+    let sweep: Vec<f32> = (0..num_samples)
+        .map(|n| {
+            sweep_sample(
+                n,
+                sample_rate,
+                start_hz,
+                end_hz,
+                duration_secs,
+                SweepType::Logarithmic,
+            )
+        })
+        .collect();
+
+    let mut inverse: Vec<f32> = (0..num_samples)
+        .map(|n| sweep[num_samples - 1 - n] * (-(n as f32 / sample_rate) / l).exp())
+        .collect();
+
+    // the sweep through its inverse is flat across the swept band, so the gain can be read off
+    // at any one frequency in it. the middle (in octaves) is furthest from the edges
+    let reference_hz = (start_hz * end_hz).sqrt();
+    let gain = dft_magnitude(&sweep, reference_hz, sample_rate)
+        * dft_magnitude(&inverse, reference_hz, sample_rate);
+    for v in inverse.iter_mut() {
+        *v /= gain;
+    }
+
+    inverse
+}
+
+fn dft_magnitude(signal: &[f32], freq_hz: f32, sample_rate: f32) -> f32 {
+    let w = std::f64::consts::PI * 2.0f64 * freq_hz as f64 / sample_rate as f64;
+    let (mut re, mut im) = (0.0f64, 0.0f64);
+    for (n, x) in signal.iter().enumerate() {
+        re += *x as f64 * (w * n as f64).cos();
+        im -= *x as f64 * (w * n as f64).sin();
+    }
+
+    (re * re + im * im).sqrt() as f32
+}
+
+// convolves what the board put out for the sweep with the inverse filter and returns the first
+// ir_len samples of the impulse response. the harmonic distortion products land before it
+pub fn deconvolve_sweep(recording: &[f32], inverse_filter: &[f32], ir_len: usize) -> Vec<f32> {
+    let lag = inverse_filter.len() - 1;
+
+    (lag..lag + ir_len)
+        .map(|n| {
+            let mut acc = 0.0f32;
+            for (k, h) in inverse_filter.iter().enumerate() {
+                if let Some(x) = recording.get(n - k) {
+                    acc += h * x;
+                }
+            }
+
+            acc
+        })
+        .collect()
+}
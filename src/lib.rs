@@ -14,6 +14,7 @@ mod effects;
 mod errors;
 mod factory;
 pub mod ffi;
+pub mod host;
 pub mod otters;
 mod param;
 pub mod traits;
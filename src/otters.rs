@@ -1,24 +1,38 @@
 
-use crate::utils::async_utils::Receiver;
+use crate::utils::async_utils::{Receiver, Sender};
 use crate::conf::{
-    AudioConfig, BoardConfig, BoardEffectConfigParameterValue, BoardEffectDeclaration,
+    AudioConfig, BoardConfig, DEFAULT_TEMPO_BPM, BoardEffectConfigParameterArrayValue,
+    BoardEffectConfigParameterValue, BoardEffectConfigValue, BoardEffectDeclaration,
+    ParameterRange,
 };
-use crate::context::BoardContext;
+use crate::context::{BoardContext, DryBlend, MAX_ALLOWABLE_OUTPUTS};
 use crate::effects::{loaded_set, FactoryExtension, GenericBypass};
 use crate::errors::{FactoryErrors, OttersInitError};
 use crate::factory::EffectFactory;
+use crate::host::test_signal::{TestSignal, TestSignalGenerator};
 use crate::param::{AsyncParamUpdate, ParamNameAndIndex, ParameterMappingManager};
 use crate::traits::AudioEffect;
+use crate::utils::state::{StateReader, StateWriter};
 use crate::OttersParamModifierContext;
 
+use std::cell::Cell;
 use std::collections::HashMap;
 use std::fs;
+use std::time::Instant;
 
 // (ordinal or identifier, effect)
 // ordinal must be > 0 and < # total effects
 pub type IdentifiedEffect = (usize, Box<dyn AudioEffect>, bool);
 pub type LoadedEffects = HashMap<String, IdentifiedEffect>;
 
+// every effect gets this parameter in addition to the ones it advertises
+// 0 = dry (bypassed), 1 = fully processed. anything in between crossfades the two
+pub const WET_AMOUNT_PARAM_NAME: &'static str = "wet_amount";
+
+// after an overrun the board stays degraded for this many blocks. the degraded blocks are
+// cheaper, so dropping back after one of them would just flip flop between the two
+const DEGRADED_HOLD_BLOCKS: usize = 16;
+
 struct ConfiguredState {
     parsed_config: BoardConfig,
     factory: EffectFactory,
@@ -32,22 +46,50 @@ pub struct Otters {
     // two arrays in one array to exploit locality
     effects: Vec<Box<dyn AudioEffect>>,
     enable_info: Vec<bool>,
+    wet_amounts: Vec<f32>,
+    // per connection, for blending each effect's output with its (latency aligned) input
+    dry_blends: Vec<DryBlend>,
+    output_routing: Vec<Vec<f32>>,
+    // quick fixes applied on top of output_routing (swap first, then invert per output)
+    output_invert: Vec<bool>,
+    output_swap: bool,
+    // indices into the context's connections, in the order frolic runs them
+    processing_order: Vec<usize>,
+    test_signal_generator: TestSignalGenerator,
 
     // just so we don't have to reload the file later
     // in case things need to be rebuilt
     configured_state: ConfiguredState,
 
     global_param_manager: ParameterMappingManager,
+    // the last value set on each global idx. the advertised default until something sets it
+    param_values: Vec<BoardEffectConfigParameterValue>,
     async_param_update_queue: Option<Receiver<AsyncParamUpdate>>,
+    spent_param_update_queue: Option<Sender<AsyncParamUpdate>>,
+
+    // computed from the effects at load time, and again whenever a parameter changes an
+    // effect's latency. the host can override it (e.g. for external gear)
+    computed_latency_samples: usize,
+    // what each (enabled) effect reported when computed_latency_samples was last worked out
+    effect_latencies: Vec<usize>,
+    // scratch for compute_latency, so a parameter update from frolic doesn't allocate
+    connection_latencies: Vec<usize>,
+    manual_latency_samples: Option<usize>,
 
     disabled_effect_bypass: GenericBypass,
+
+    // fraction of a block's real time duration that frolic may take. None = no budget
+    cpu_budget: Option<f32>,
+    degraded_blocks_remaining: Cell<usize>,
 }
 
 impl Otters {
     pub fn get_available_effect_names() -> Vec<String> {
         let mock_ac = AudioConfig {
             sample_rate: 1_f32,
-            max_block_size: 1
+            max_block_size: 1,
+            num_channels: 1,
+            tempo_bpm: DEFAULT_TEMPO_BPM,
         };
 
         let factory = EffectFactory::assemble_factory(mock_ac, loaded_set());
@@ -58,6 +100,8 @@ impl Otters {
         let mock_ac = AudioConfig {
             sample_rate: 1_f32,
             max_block_size: 1,
+            num_channels: 1,
+            tempo_bpm: DEFAULT_TEMPO_BPM,
         };
 
         let fake_factory = EffectFactory::assemble_factory(mock_ac, loaded_set());
@@ -87,16 +131,56 @@ impl Otters {
     ) -> Result<Otters, OttersInitError> {
         let parsed_config: BoardConfig = serde_json::from_str(&config_str)?;
 
-        let factory = EffectFactory::assemble_factory(audio_config, factory_extensions);
+        Otters::create_from_config(audio_config, factory_extensions, parsed_config)
+    }
+
+    pub fn create_from_config(
+        audio_config: AudioConfig,
+        factory_extensions: Vec<FactoryExtension>,
+        mut parsed_config: BoardConfig,
+    ) -> Result<Otters, OttersInitError> {
+        parsed_config.expand_series();
+
+        let mut factory = EffectFactory::assemble_factory(audio_config, factory_extensions);
+        factory.set_sub_boards(std::mem::replace(
+            &mut parsed_config.sub_boards,
+            HashMap::new(),
+        ));
 
         let effects = create_effect_units(&factory, &parsed_config.effects)?;
         debug_print_loaded_effects(&effects);
 
         let context = BoardContext::initialize_context(&parsed_config, &audio_config, &effects)?;
 
-        let (mut effects_arr, enabled_arr, global_param_manager) = effect_map_to_vec(effects);
+        let (mut effects_arr, enabled_arr, mut global_param_manager) = effect_map_to_vec(effects);
+        record_effect_types(&parsed_config, &mut global_param_manager);
+
+        let mut wet_amounts = vec![1.0f32; effects_arr.len()];
+        let mut param_values = default_param_values(&global_param_manager, &effects_arr);
+        set_initial_config_on_effects(
+            &parsed_config,
+            &global_param_manager,
+            &mut effects_arr,
+            &mut wet_amounts,
+            &mut param_values,
+        );
 
-        set_initial_config_on_effects(&parsed_config, &global_param_manager, &mut effects_arr);
+        let mut effect_latencies = vec![0; effects_arr.len()];
+        let mut connection_latencies = vec![0; context.get_connections().len()];
+        let computed_latency_samples = compute_latency(
+            &context,
+            &effects_arr,
+            &enabled_arr,
+            &mut effect_latencies,
+            &mut connection_latencies,
+        );
+        let dry_blends = create_dry_blends(
+            &context,
+            &effects_arr,
+            &wet_amounts,
+            audio_config.max_block_size,
+        );
+        let processing_order = (0..context.get_connections().len()).collect();
 
         println!("Otters is ready to go!");
         Ok(Otters {
@@ -108,9 +192,27 @@ impl Otters {
                 factory,
             },
             enable_info: enabled_arr,
+            wet_amounts,
+            dry_blends,
+            output_routing: Vec::new(),
+            output_invert: vec![false; MAX_ALLOWABLE_OUTPUTS],
+            output_swap: false,
+            processing_order,
+            test_signal_generator: TestSignalGenerator::new(
+                audio_config.sample_rate,
+                audio_config.max_block_size,
+            ),
             global_param_manager,
+            param_values,
             async_param_update_queue: None,
+            spent_param_update_queue: None,
+            computed_latency_samples,
+            effect_latencies,
+            connection_latencies,
+            manual_latency_samples: None,
             disabled_effect_bypass: GenericBypass::new(),
+            cpu_budget: None,
+            degraded_blocks_remaining: Cell::new(0),
         })
     }
 
@@ -135,11 +237,44 @@ impl Otters {
             &self.audio_config,
             &effects,
         )?;
+        self.context.set_output_routing(
+            &self.effective_output_routing(),
+            audio_config.max_block_size,
+        );
+        self.test_signal_generator =
+            TestSignalGenerator::new(audio_config.sample_rate, audio_config.max_block_size);
+
+        let (mut effects, _, mut global_param_manager) = effect_map_to_vec(effects);
+        record_effect_types(
+            &self.configured_state.parsed_config,
+            &mut global_param_manager,
+        );
 
-        let (effects, _, global_param_manager) = effect_map_to_vec(effects);
+        // the new units start out at their defaults, so the config has to go on again
+        // (for the arrays), then whatever was set since goes on top of it
+        let mut wet_amounts = vec![1.0f32; effects.len()];
+        let mut param_values = default_param_values(&global_param_manager, &effects);
+        set_initial_config_on_effects(
+            &self.configured_state.parsed_config,
+            &global_param_manager,
+            &mut effects,
+            &mut wet_amounts,
+            &mut param_values,
+        );
+        reapply_param_values(&global_param_manager, &mut effects, &self.param_values);
 
+        self.dry_blends = create_dry_blends(
+            &self.context,
+            &effects,
+            &self.wet_amounts,
+            audio_config.max_block_size,
+        );
         self.effects = effects;
         self.global_param_manager = global_param_manager;
+        self.effect_latencies = vec![0; self.effects.len()];
+        self.connection_latencies = vec![0; self.context.get_connections().len()];
+        self.update_latency();
+        self.degraded_blocks_remaining.set(0);
 
         Ok(())
     }
@@ -151,7 +286,315 @@ impl Otters {
         value: BoardEffectConfigParameterValue,
     ) {
         let (e_idx, p_idx) = self.global_param_manager.effect_and_param_idx(global_idx);
+        if is_wet_amount_param(&self.effects[e_idx], p_idx) {
+            self.wet_amounts[e_idx] = clamp_wet_amount(value);
+            self.param_values[global_idx] =
+                BoardEffectConfigParameterValue::F(self.wet_amounts[e_idx]);
+            return;
+        }
+
         self.effects[e_idx].set_effect_parameter(p_idx, value);
+        self.param_values[global_idx] = value;
+
+        // e.g. a lookahead or a delay. this also runs from frolic, hence the scratch buffers
+        if self.enable_info[e_idx]
+            && self.effects[e_idx].latency_samples() != self.effect_latencies[e_idx]
+        {
+            self.update_latency();
+        }
+    }
+
+    // the last value set on a parameter (from the config or since), or its default
+    // array parameters only report their default. None for an index the board doesn't have
+    pub fn get_effect_parameter(&self, global_idx: usize) -> Option<BoardEffectConfigParameterValue> {
+        self.param_values.get(global_idx).copied()
+    }
+
+    // an unknown index, or wet_amount (which isn't an array), is ignored
+    // WARNING: this function is usually called from a UI thread!
+    pub fn set_effect_parameter_array(&mut self, global_idx: usize, values: &[f32]) {
+        if global_idx >= self.global_param_manager.num_parameters() {
+            return;
+        }
+
+        let (e_idx, p_idx) = self.global_param_manager.effect_and_param_idx(global_idx);
+        if is_wet_amount_param(&self.effects[e_idx], p_idx) {
+            return;
+        }
+
+        self.effects[e_idx].set_effect_parameter_array(p_idx, values);
+    }
+
+    // clears the state (delay lines, tails) of one effect. returns false if there's no such bind name
+    // WARNING: this function is usually called from a UI thread!
+    pub fn reset_effect(&mut self, bind_name: &str) -> bool {
+        match self.global_param_manager.effect_idx_for_bind_name(bind_name) {
+            Some(effect_idx) => {
+                self.reset_effect_idx(effect_idx);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // disabled effects run as a bypass. returns false if there's no such bind name
+    // switching an effect off also clears it, so it doesn't come back with a stale tail
+    // WARNING: this function is usually called from a UI thread!
+    pub fn set_effect_enabled(&mut self, bind_name: &str, enabled: bool) -> bool {
+        let effect_idx = match self.global_param_manager.effect_idx_for_bind_name(bind_name) {
+            Some(effect_idx) => effect_idx,
+            None => return false,
+        };
+
+        if self.enable_info[effect_idx] && !enabled {
+            self.reset_effect_idx(effect_idx);
+        }
+
+        self.enable_info[effect_idx] = enabled;
+        self.update_latency();
+
+        true
+    }
+
+    // reset_effect for every effect on the board
+    pub fn reset_all(&mut self) {
+        for effect in self.effects.iter_mut() {
+            effect.reset();
+        }
+
+        for dry_blend in self.dry_blends.iter_mut() {
+            dry_blend.clear();
+        }
+    }
+
+    fn update_latency(&mut self) {
+        self.computed_latency_samples = compute_latency(
+            &self.context,
+            &self.effects,
+            &self.enable_info,
+            &mut self.effect_latencies,
+            &mut self.connection_latencies,
+        );
+    }
+
+    // clears the effect along with the dry signal held back for its wet/dry blend
+    fn reset_effect_idx(&mut self, effect_idx: usize) {
+        self.effects[effect_idx].reset();
+
+        for (connection, dry_blend) in self
+            .context
+            .get_connections()
+            .iter()
+            .zip(self.dry_blends.iter_mut())
+        {
+            if connection.ordinal == effect_idx {
+                dry_blend.clear();
+            }
+        }
+    }
+
+    // copies the most recent contents of an effect's delay line (or similar) into output
+    // returns the number of samples copied. 0 if the effect doesn't exist or has nothing to show
+    pub fn copy_effect_visualization(&self, bind_name: &str, output: &mut [f32]) -> usize {
+        match self.global_param_manager.effect_idx_for_bind_name(bind_name) {
+            Some(effect_idx) => self.effects[effect_idx].copy_visualization_samples(output),
+            None => 0,
+        }
+    }
+
+    // snapshots the internal state (delay lines, filter memory, envelopes) of every effect
+    // parameters aren't included. None if any effect on the board doesn't support snapshots
+    // WARNING: this allocates. call it between blocks, not from the audio thread
+    pub fn serialize_state(&self) -> Option<Vec<u8>> {
+        let mut writer = StateWriter::new();
+        if !self.write_state(&mut writer) {
+            return None;
+        }
+
+        Some(writer.into_bytes())
+    }
+
+    // restores a snapshot taken from this board (same config and sample rate)
+    // on failure every effect is reset rather than left half restored
+    pub fn deserialize_state(&mut self, state: &[u8]) -> bool {
+        let mut reader = StateReader::new(state);
+        if !self.read_state(&mut reader) {
+            return false;
+        }
+
+        // trailing bytes mean it came from a different board
+        if !reader.is_empty() {
+            self.reset_all();
+            return false;
+        }
+
+        true
+    }
+
+    // serialize_state into an existing writer, so a board can be snapshotted inside another
+    pub(crate) fn write_state(&self, writer: &mut StateWriter) -> bool {
+        writer.write_usize(self.effects.len());
+
+        self.effects
+            .iter()
+            .all(|effect| effect.serialize_state(writer))
+    }
+
+    // leaves anything after this board's state in the reader alone
+    pub(crate) fn read_state(&mut self, reader: &mut StateReader) -> bool {
+        let restored = reader.read_usize() == Some(self.effects.len())
+            && self
+                .effects
+                .iter_mut()
+                .all(|effect| effect.deserialize_state(reader));
+
+        if !restored {
+            self.reset_all();
+        }
+
+        restored
+    }
+
+    // matrix[output][sink] is the gain from @SINK_sink to the external output bound at `output`
+    // lets one processed signal reach several outputs (or none) without editing the config
+    // pass an empty matrix to go back to writing sinks straight to their outputs
+    pub fn set_output_routing(&mut self, matrix: Vec<Vec<f32>>) {
+        self.output_routing = matrix;
+        self.apply_output_routing();
+    }
+
+    // flips the polarity of the external output bound at output_idx (e.g. for an inverted cable)
+    pub fn set_output_invert(&mut self, output_idx: usize, invert: bool) {
+        if output_idx >= MAX_ALLOWABLE_OUTPUTS {
+            return;
+        }
+
+        self.output_invert[output_idx] = invert;
+        self.apply_output_routing();
+    }
+
+    // runs the connections in the order their effects are named here instead of the declared one
+    // an effect with several connections runs all of them (in declared order) at its spot
+    // every connected effect has to be named once, and connections sharing a buffer can't swap
+    // places (see BoardContext::order_keeps_dependencies). returns false and keeps the current
+    // order otherwise. pass an empty slice to go back to the declared order
+    // WARNING: this allocates. call it between blocks, not from the audio thread
+    pub fn set_processing_order(&mut self, bind_names: &[&str]) -> bool {
+        let connections = self.context.get_connections();
+        if bind_names.is_empty() {
+            self.processing_order = (0..connections.len()).collect();
+            return true;
+        }
+
+        let mut order = Vec::with_capacity(connections.len());
+        for (i, bind_name) in bind_names.iter().enumerate() {
+            let effect_idx = match self.global_param_manager.effect_idx_for_bind_name(bind_name) {
+                Some(effect_idx) if !bind_names[..i].contains(bind_name) => effect_idx,
+                _ => return false,
+            };
+
+            order.extend(
+                connections
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, connection)| connection.ordinal == effect_idx)
+                    .map(|(connection_idx, _)| connection_idx),
+            );
+        }
+
+        if !self.context.order_keeps_dependencies(&order) {
+            return false;
+        }
+
+        self.processing_order = order;
+        true
+    }
+
+    // exchanges external outputs 0 and 1
+    pub fn set_output_swap(&mut self, swap: bool) {
+        self.output_swap = swap;
+        self.apply_output_routing();
+    }
+
+    fn apply_output_routing(&mut self) {
+        self.context.set_output_routing(
+            &self.effective_output_routing(),
+            self.audio_config.max_block_size,
+        );
+    }
+
+    // output_routing with the swap and inverts folded in. stays empty (no routing, no extra
+    // copy) unless something asks for it
+    fn effective_output_routing(&self) -> Vec<Vec<f32>> {
+        if !self.output_swap && !self.output_invert.iter().any(|invert| *invert) {
+            return self.output_routing.clone();
+        }
+
+        let mut matrix = if self.output_routing.is_empty() {
+            // sink n straight to output n
+            (0..MAX_ALLOWABLE_OUTPUTS)
+                .map(|output_idx| {
+                    let mut row = vec![0.0f32; MAX_ALLOWABLE_OUTPUTS];
+                    row[output_idx] = 1.0f32;
+                    row
+                })
+                .collect()
+        } else {
+            self.output_routing.clone()
+        };
+
+        if self.output_swap {
+            if matrix.len() < 2 {
+                matrix.resize(2, Vec::new());
+            }
+
+            matrix.swap(0, 1);
+        }
+
+        for (row, invert) in matrix.iter_mut().zip(self.output_invert.iter()) {
+            if *invert {
+                for gain in row.iter_mut() {
+                    *gain = -*gain;
+                }
+            }
+        }
+
+        matrix
+    }
+
+    pub fn num_channels(&self) -> usize {
+        self.audio_config.num_channels
+    }
+
+    pub fn audio_config(&self) -> AudioConfig {
+        self.audio_config
+    }
+
+    // what the host should compensate for. the manual override wins when set
+    pub fn total_latency_samples(&self) -> usize {
+        self.manual_latency_samples
+            .unwrap_or(self.computed_latency_samples)
+    }
+
+    // None goes back to the latency computed from the effects
+    pub fn set_manual_latency(&mut self, samples: Option<usize>) {
+        self.manual_latency_samples = samples;
+    }
+
+    // budget is the fraction of a block's duration (at the configured sample rate) that
+    // processing may take, e.g. 0.5 = half the time the host has. when a block goes over,
+    // heavy effects (the spectral ones, granular) cut corners for the next few blocks
+    // None turns the budget off and ends any degradation in progress
+    pub fn set_cpu_budget(&mut self, budget: Option<f32>) {
+        self.cpu_budget = budget;
+        if budget.is_none() {
+            self.degraded_blocks_remaining.set(0);
+            self.context.set_degraded(false);
+        }
+    }
+
+    pub fn is_degraded(&self) -> bool {
+        self.context.should_degrade()
     }
 
     pub fn bind_input(&mut self, input_idx: usize, input_ptr: *const f32) {
@@ -162,25 +605,150 @@ impl Otters {
         self.context.bind_sink(output_idx, output_ptr);
     }
 
-    pub fn frolic(&self, num_samples: usize) {
+    pub fn frolic(&mut self, num_samples: usize) {
         // any code that runs here must be rt-safe
         // this means heap mem allocation is not allowed
 
-        for (i, connection) in self.context.get_connections().iter().enumerate() {
+        self.apply_async_param_updates();
+
+        let started = self.cpu_budget.map(|_| Instant::now());
+
+        self.context.begin_ducking_block();
+
+        for i in self.processing_order.iter().cloned() {
+            let connection = &self.context.get_connections()[i];
             if self.enable_info[connection.ordinal] {
-                self.effects[connection.ordinal].execute(&self.context, i, num_samples)
+                let effect = &self.effects[connection.ordinal];
+                effect.execute(&self.context, i, num_samples);
+
+                self.context.blend_with_dry(
+                    i,
+                    &mut self.dry_blends[i],
+                    self.wet_amounts[connection.ordinal],
+                    effect.latency_samples(),
+                    num_samples,
+                );
             } else {
                 self.disabled_effect_bypass.execute(&self.context, i, num_samples);
             }
+
+            self.context.apply_ducking(i, num_samples);
+        }
+
+        self.context.route_outputs(num_samples);
+
+        if let (Some(budget), Some(started)) = (self.cpu_budget, started) {
+            self.update_degradation(budget, started, num_samples);
         }
     }
 
+    // decides whether the *next* block runs degraded. this one is already done
+    fn update_degradation(&self, budget: f32, started: Instant, num_samples: usize) {
+        let allowed_secs = budget * num_samples as f32 / self.audio_config.sample_rate;
+        let remaining = if started.elapsed().as_secs_f32() > allowed_secs {
+            DEGRADED_HOLD_BLOCKS
+        } else {
+            self.degraded_blocks_remaining.get().saturating_sub(1)
+        };
+
+        self.degraded_blocks_remaining.set(remaining);
+        self.context.set_degraded(remaining > 0);
+    }
+
+    // debugging aid: runs one block with every source replaced by a generated test signal
+    // outputs still go to whatever the host bound. num_samples is capped at the max block size
+    pub fn frolic_with_test_signal(&mut self, kind: TestSignal, num_samples: usize) {
+        let num_samples = num_samples.min(self.audio_config.max_block_size);
+        let test_signal_ptr = self.test_signal_generator.generate(kind, num_samples);
+
+        self.context.substitute_sources(test_signal_ptr);
+        self.frolic(num_samples);
+        self.context.restore_sources();
+    }
+
     pub fn setup_async_param_updater(&mut self) -> OttersParamModifierContext {
-        let (ctx, receiver) = self.global_param_manager.create_async_param_update_context();
+        let (ctx, receiver, spent_sender) = self
+            .global_param_manager
+            .create_async_param_update_context(self.param_values.clone());
         self.async_param_update_queue = Some(receiver);
+        self.spent_param_update_queue = Some(spent_sender);
 
         ctx
     }
+
+    // runs on the audio thread. the updates are sent back to the ui side instead of being
+    // dropped here, so nothing is freed (or allocated) along the way
+    fn apply_async_param_updates(&mut self) {
+        let (queue, spent) = match (
+            self.async_param_update_queue.take(),
+            self.spent_param_update_queue.take(),
+        ) {
+            (Some(queue), Some(spent)) => (queue, spent),
+            (queue, spent) => {
+                self.async_param_update_queue = queue;
+                self.spent_param_update_queue = spent;
+                return;
+            }
+        };
+
+        for update in queue.recv_items() {
+            let (global_idx, value) = *update;
+
+            // the ui can send anything. an unknown index is ignored rather than panicking here
+            if global_idx < self.global_param_manager.num_parameters() {
+                self.set_effect_parameter(global_idx, value);
+            }
+
+            spent.send_item(update);
+        }
+
+        self.async_param_update_queue = Some(queue);
+        self.spent_param_update_queue = Some(spent);
+    }
+}
+
+fn create_dry_blends(
+    context: &BoardContext,
+    effects: &Vec<Box<dyn AudioEffect>>,
+    wet_amounts: &Vec<f32>,
+    max_block_size: usize,
+) -> Vec<DryBlend> {
+    context
+        .get_connections()
+        .iter()
+        .map(|connection| {
+            DryBlend::new(
+                connection.output_idxs.len(),
+                effects[connection.ordinal].max_latency_samples(),
+                max_block_size,
+                wet_amounts[connection.ordinal],
+            )
+        })
+        .collect()
+}
+
+// disabled effects run as a plain bypass, so they don't add anything
+// effect_latencies (one per effect) and connection_latencies (one per connection) are filled in
+fn compute_latency(
+    context: &BoardContext,
+    effects: &Vec<Box<dyn AudioEffect>>,
+    enable_info: &Vec<bool>,
+    effect_latencies: &mut [usize],
+    connection_latencies: &mut [usize],
+) -> usize {
+    for ((latency, effect), is_enabled) in effect_latencies
+        .iter_mut()
+        .zip(effects.iter())
+        .zip(enable_info.iter())
+    {
+        *latency = if *is_enabled {
+            effect.latency_samples()
+        } else {
+            0
+        };
+    }
+
+    context.latency_at_sinks(effect_latencies, connection_latencies)
 }
 
 fn create_effect_units(
@@ -234,7 +802,7 @@ fn effect_map_to_vec(
         .map(|(i, (bind_name, (_, effect, is_enabled)))| {
             let advertised_params = effect.advertise_parameters();
             let mut global_param_idxs: Vec<ParamNameAndIndex> =
-                Vec::with_capacity(advertised_params.len());
+                Vec::with_capacity(advertised_params.len() + 1);
             for param_idx in 0..advertised_params.len() {
                 global_param_idxs.push((
                     advertised_params[param_idx].name,
@@ -242,6 +810,11 @@ fn effect_map_to_vec(
                 ));
             }
 
+            global_param_idxs.push((
+                WET_AMOUNT_PARAM_NAME,
+                pm.new_parameter((bind_name.clone(), i, advertised_params.len())),
+            ));
+
             pm.set_global_idxs_for_bind_name(bind_name.clone(), global_param_idxs);
 
             (effect, is_enabled)
@@ -253,10 +826,22 @@ fn effect_map_to_vec(
     (result_vec, result_enabled_vec, pm)
 }
 
+// the session info handed to async param updaters lists each bind name's effect type
+fn record_effect_types(loaded_conf: &BoardConfig, param_mgr: &mut ParameterMappingManager) {
+    for effect_decl in &loaded_conf.effects {
+        param_mgr.set_effect_type_for_bind_name(
+            effect_decl.bind_name.clone(),
+            effect_decl.effect_name.clone(),
+        );
+    }
+}
+
 fn set_initial_config_on_effects(
     loaded_conf: &BoardConfig,
     param_mgr: &ParameterMappingManager,
     effects: &mut Vec<Box<dyn AudioEffect>>,
+    wet_amounts: &mut Vec<f32>,
+    param_values: &mut Vec<BoardEffectConfigParameterValue>,
 ) {
     let mut param_name_to_idx = HashMap::<String, usize>::new();
     for effect_decl in &loaded_conf.effects {
@@ -271,15 +856,76 @@ fn set_initial_config_on_effects(
                 continue;
             }
 
-            let (eidx, pidx) =
-                param_mgr.effect_and_param_idx(param_name_to_idx[&effect_param.name]);
-            effects[eidx].set_effect_parameter(pidx, effect_param.value);
+            let global_idx = param_name_to_idx[&effect_param.name];
+            let (eidx, pidx) = param_mgr.effect_and_param_idx(global_idx);
+            match &effect_param.value {
+                BoardEffectConfigValue::Single(value)
+                    if is_wet_amount_param(&effects[eidx], pidx) =>
+                {
+                    wet_amounts[eidx] = clamp_wet_amount(*value);
+                    param_values[global_idx] =
+                        BoardEffectConfigParameterValue::F(wet_amounts[eidx]);
+                }
+                BoardEffectConfigValue::Single(value) => {
+                    effects[eidx].set_effect_parameter(pidx, *value);
+                    param_values[global_idx] = *value;
+                }
+                BoardEffectConfigValue::Array(BoardEffectConfigParameterArrayValue::FArray(
+                    values,
+                )) => effects[eidx].set_effect_parameter_array(pidx, values),
+            }
         }
 
         param_name_to_idx.clear();
     }
 }
 
+// the board layout doesn't change with the audio config, so the global indices still line up
+// wet_amount lives on the board and array parameters aren't tracked, so both are skipped
+fn reapply_param_values(
+    param_mgr: &ParameterMappingManager,
+    effects: &mut Vec<Box<dyn AudioEffect>>,
+    param_values: &Vec<BoardEffectConfigParameterValue>,
+) {
+    for (global_idx, value) in param_values.iter().enumerate() {
+        let (eidx, pidx) = param_mgr.effect_and_param_idx(global_idx);
+        let range = match effects[eidx].advertise_parameters().get(pidx) {
+            Some(param) => param.range,
+            None => continue,
+        };
+
+        if let ParameterRange::FArray(..) = range {
+            continue;
+        }
+
+        effects[eidx].set_effect_parameter(pidx, *value);
+    }
+}
+
+fn default_param_values(
+    param_mgr: &ParameterMappingManager,
+    effects: &Vec<Box<dyn AudioEffect>>,
+) -> Vec<BoardEffectConfigParameterValue> {
+    (0..param_mgr.num_parameters())
+        .map(|global_idx| {
+            let (eidx, pidx) = param_mgr.effect_and_param_idx(global_idx);
+            match effects[eidx].advertise_parameters().get(pidx) {
+                Some(param) => param.default_value,
+                // wet_amount
+                None => BoardEffectConfigParameterValue::F(1.0f32),
+            }
+        })
+        .collect()
+}
+
+fn is_wet_amount_param(effect: &Box<dyn AudioEffect>, param_idx: usize) -> bool {
+    param_idx == effect.advertise_parameters().len()
+}
+
+fn clamp_wet_amount(value: BoardEffectConfigParameterValue) -> f32 {
+    num::clamp(value.as_flt(), 0.0f32, 1.0f32)
+}
+
 fn debug_print_loaded_effects(effects: &LoadedEffects) {
     for (effect_name, (effect_ordinal, _, effect_enabled)) in effects.iter() {
         println!(
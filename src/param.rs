@@ -2,6 +2,7 @@ use crate::conf::BoardEffectConfigParameterValue;
 use crate::utils::async_utils::{RTQueue, Receiver, Sender};
 
 use serde::Serialize;
+use std::cell::RefCell;
 use std::collections::HashMap;
 
 // bind name, idx in effects vec, param idx
@@ -25,6 +26,10 @@ pub struct OttersSessionInfo {
 
 pub struct OttersParamModifierContext {
     sender: Sender<AsyncParamUpdate>,
+    // updates the audio thread is done with come back here to be freed, so it never deallocates
+    spent: Receiver<AsyncParamUpdate>,
+    // what the ui last sent for each global idx, seeded from the board when the context was made
+    values: RefCell<Vec<BoardEffectConfigParameterValue>>,
 
     session_info: OttersSessionInfo,
 }
@@ -43,6 +48,8 @@ impl OttersParamModifierContext {
     }
 
     pub fn set_flt_param_value(&self, global_idx: u32, value: f32) {
+        self.free_spent_updates();
+        self.remember_value(global_idx, BoardEffectConfigParameterValue::F(value));
         self.sender.send((
             global_idx as usize,
             BoardEffectConfigParameterValue::F(value),
@@ -50,11 +57,39 @@ impl OttersParamModifierContext {
     }
 
     pub fn set_int_param_value(&self, global_idx: u32, value: i32) {
+        self.free_spent_updates();
+        self.remember_value(global_idx, BoardEffectConfigParameterValue::N(value));
         self.sender.send((
             global_idx as usize,
             BoardEffectConfigParameterValue::N(value),
         ));
     }
+
+    // 0 for an index the board doesn't have
+    pub fn get_flt_param_value(&self, global_idx: u32) -> f32 {
+        self.values
+            .borrow()
+            .get(global_idx as usize)
+            .map_or(0.0f32, |v| v.as_flt())
+    }
+
+    pub fn get_int_param_value(&self, global_idx: u32) -> i32 {
+        self.values
+            .borrow()
+            .get(global_idx as usize)
+            .map_or(0, |v| v.as_int())
+    }
+
+    // the board ignores unknown indices, so they aren't remembered either
+    fn remember_value(&self, global_idx: u32, value: BoardEffectConfigParameterValue) {
+        if let Some(v) = self.values.borrow_mut().get_mut(global_idx as usize) {
+            *v = value;
+        }
+    }
+
+    fn free_spent_updates(&self) {
+        for _ in self.spent.recv() {}
+    }
 }
 
 impl ParameterMappingManager {
@@ -102,16 +137,36 @@ impl ParameterMappingManager {
         &self.bind_name_to_glob_idxs[bind_name]
     }
 
+    // every effect has at least its wet_amount parameter, so its global idxs point back at it
+    pub fn effect_idx_for_bind_name(&self, bind_name: &str) -> Option<usize> {
+        let global_idxs = self.bind_name_to_glob_idxs.get(bind_name)?;
+        let (_, global_idx) = global_idxs.first()?;
+
+        Some(self.effect_and_param_idx(*global_idx).0)
+    }
+
+    pub fn num_parameters(&self) -> usize {
+        self.mappings.len()
+    }
+
     pub fn effect_and_param_idx(&self, global_idx: usize) -> (usize, usize) {
         let (_, effect_idx, param_idx) = self.mappings[global_idx];
 
         (effect_idx, param_idx)
     }
 
+    // returns the UI side, plus the audio side: the update queue and the return path for spent updates
+    // current_values are the board's parameter values right now, by global idx
     pub fn create_async_param_update_context(
         &self,
-    ) -> (OttersParamModifierContext, Receiver<AsyncParamUpdate>) {
+        current_values: Vec<BoardEffectConfigParameterValue>,
+    ) -> (
+        OttersParamModifierContext,
+        Receiver<AsyncParamUpdate>,
+        Sender<AsyncParamUpdate>,
+    ) {
         let (sender, receiver) = RTQueue::<AsyncParamUpdate>::new();
+        let (spent_sender, spent) = RTQueue::<AsyncParamUpdate>::new();
 
         let mut session_info: HashMap<String, OttersSessionInfoEntry> = HashMap::new();
 
@@ -134,9 +189,11 @@ impl ParameterMappingManager {
                 infos: session_info,
             },
             sender,
+            spent,
+            values: RefCell::new(current_values),
         };
 
-        (context, receiver)
+        (context, receiver, spent_sender)
     }
 }
 
@@ -0,0 +1,29 @@
+#![cfg(test)]
+
+use super::test_utils::*;
+use crate::host::ab_compare::render_ab;
+
+#[test]
+fn test_ab_render_puts_dry_left_and_wet_right() {
+    let input = sine(440.0f32, 0.8f32, 5 * TEST_BLOCK_SIZE + 9);
+    let config = mono_board_config(
+        "NonLinear/BitCrusher",
+        r#"[{"name": "quantized_bit_depth", "value": {"N": 3}}]"#,
+    );
+
+    let mut reference = create_board(&config);
+    let processed = process_mono(&mut reference, &input);
+
+    let mut otters = create_board(&config);
+    let ab = render_ab(&mut otters, &input, TEST_BLOCK_SIZE);
+    assert_eq!(ab.dry, input);
+    assert_eq!(ab.wet, processed);
+    assert_ne!(ab.wet, ab.dry);
+
+    let interleaved = ab.interleaved();
+    assert_eq!(interleaved.len(), 2 * input.len());
+    for i in 0..input.len() {
+        assert_eq!(interleaved[2 * i], input[i]);
+        assert_eq!(interleaved[2 * i + 1], processed[i]);
+    }
+}
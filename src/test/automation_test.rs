@@ -0,0 +1,82 @@
+#![cfg(test)]
+
+use super::test_utils::*;
+use crate::host::automation::{AutomationLane, AutomationPoint, RampCurve};
+
+fn replay_in_blocks(lane: &mut AutomationLane, num_samples: usize) -> Vec<f32> {
+    let mut values = vec![0.0f32; num_samples];
+    for block in values.chunks_mut(TEST_BLOCK_SIZE) {
+        lane.fill_block(block);
+    }
+
+    values
+}
+
+fn two_point_lane(from: f32, to: f32, ramp_samples: usize, curve: RampCurve) -> AutomationLane {
+    AutomationLane::new(vec![
+        AutomationPoint {
+            sample_pos: 0,
+            value: from,
+            curve: RampCurve::Step,
+        },
+        AutomationPoint {
+            sample_pos: ramp_samples,
+            value: to,
+            curve,
+        },
+    ])
+}
+
+#[test]
+fn test_linear_automation_ramps_every_sample() {
+    let ramp_samples = 1000;
+    let mut lane = two_point_lane(0.0f32, 1.0f32, ramp_samples, RampCurve::Linear);
+    let values = replay_in_blocks(&mut lane, 2 * ramp_samples);
+
+    // the same small step on every sample, including across block boundaries
+    let expected_step = 1.0f32 / ramp_samples as f32;
+    for i in 1..=ramp_samples {
+        let step = values[i] - values[i - 1];
+        assert!(
+            (step - expected_step).abs() < 1e-5f32,
+            "step {} at {}",
+            step,
+            i
+        );
+    }
+
+    assert_eq!(values[0], 0.0f32);
+    assert_eq!(values[ramp_samples], 1.0f32);
+    assert!(values[ramp_samples..].iter().all(|x| *x == 1.0f32));
+
+    // a stepped lane holds and jumps once
+    let mut stepped = two_point_lane(0.0f32, 1.0f32, ramp_samples, RampCurve::Step);
+    let stepped_values = replay_in_blocks(&mut stepped, 2 * ramp_samples);
+    assert!(stepped_values[..ramp_samples].iter().all(|x| *x == 0.0f32));
+    assert_eq!(stepped_values[ramp_samples], 1.0f32);
+}
+
+#[test]
+fn test_exponential_automation_has_constant_ratio() {
+    let ramp_samples = 300;
+    let mut lane = two_point_lane(0.1f32, 1.0f32, ramp_samples, RampCurve::Exponential);
+    let values = replay_in_blocks(&mut lane, ramp_samples + 1);
+
+    let expected_ratio = 10.0f32.powf(1.0f32 / ramp_samples as f32);
+    for i in 1..=ramp_samples {
+        let ratio = values[i] / values[i - 1];
+        assert!(
+            (ratio - expected_ratio).abs() < 1e-4f32,
+            "ratio {} at {}",
+            ratio,
+            i
+        );
+    }
+
+    assert_eq!(values[ramp_samples], 1.0f32);
+
+    // a ramp through 0 can't be exponential and goes linear instead
+    let mut through_zero = two_point_lane(-1.0f32, 1.0f32, 100, RampCurve::Exponential);
+    let values = replay_in_blocks(&mut through_zero, 101);
+    assert!((values[50] - 0.0f32).abs() < 1e-5f32);
+}
@@ -0,0 +1,85 @@
+#![cfg(test)]
+
+use super::test_utils::*;
+use crate::host::boundary_fades::BoundaryFades;
+use crate::utils::biquad::IIRFilterType;
+
+#[test]
+fn test_fades_ramp_both_ends() {
+    let input = vec![0.5f32; TEST_SAMPLE_RATE as usize / 10];
+    let mut otters = create_board(&mono_board_config("Bypass/Mono", "[]"));
+
+    // 5 ms in, 10 ms out
+    let mut fades = BoundaryFades::new(TEST_SAMPLE_RATE, 5.0f32, 10.0f32, 0.0f32);
+    let output = fades.render(&mut otters, &input, TEST_BLOCK_SIZE);
+
+    let fade_in_samples = (0.005f32 * TEST_SAMPLE_RATE).round() as usize;
+    let fade_out_samples = fades.get_fade_out_samples();
+    let fade_out_start = output.len() - fade_out_samples;
+
+    assert_eq!(output[0], 0.0f32);
+    for i in 1..fade_in_samples {
+        assert!(output[i] > output[i - 1], "sample {}", i);
+    }
+
+    // untouched in the middle
+    assert!(output[fade_in_samples..fade_out_start]
+        .iter()
+        .all(|x| *x == 0.5f32));
+
+    for i in fade_out_start + 1..output.len() {
+        assert!(output[i] < output[i - 1], "sample {}", i);
+    }
+    assert_eq!(output[output.len() - 1], 0.0f32);
+
+    // a realtime runner fades in block by block and gets the same ramp
+    let mut blocked = input.clone();
+    let mut fades = BoundaryFades::new(TEST_SAMPLE_RATE, 5.0f32, 10.0f32, 0.0f32);
+    for block in blocked.chunks_mut(7) {
+        fades.fade_in(block);
+    }
+    assert_eq!(&blocked[..fade_out_start], &output[..fade_out_start]);
+}
+
+// a resonant lowpass on a dc offset rings when the board starts, whatever the input is
+fn resonant_dc_board() -> String {
+    format!(
+        r#"{{
+            "buffers": ["@SOURCE_0", "@CONST_0.5", "@SINK_0"],
+            "effects": [{{
+                "bind_name": "fx",
+                "effect_name": "Filter/Biquad",
+                "config": [
+                    {{"name": "filter_type", "value": {{"N": {}}}}},
+                    {{"name": "corner_freq_hz", "value": {{"F": 200.0}}}},
+                    {{"name": "q", "value": {{"F": 10.0}}}}
+                ],
+                "enabled": true
+            }}],
+            "connections": [{{"effect": "fx", "reads": ["@CONST_0.5"], "writes": ["@SINK_0"]}}]
+        }}"#,
+        IIRFilterType::SecondOrderLowPass as i32
+    )
+}
+
+#[test]
+fn test_warm_up_settles_a_resonant_filter() {
+    let input = vec![0.0f32; TEST_SAMPLE_RATE as usize / 10];
+    let swing = |output: &[f32]| {
+        output[..TEST_SAMPLE_RATE as usize / 20]
+            .iter()
+            .fold(0.0f32, |swing, x| swing.max((x - 0.5f32).abs()))
+    };
+
+    let mut cold = create_board(&resonant_dc_board());
+    let mut no_warm_up = BoundaryFades::new(TEST_SAMPLE_RATE, 0.0f32, 0.0f32, 0.0f32);
+    let cold_output = no_warm_up.render(&mut cold, &input, TEST_BLOCK_SIZE);
+
+    let mut warm = create_board(&resonant_dc_board());
+    let mut warm_up = BoundaryFades::new(TEST_SAMPLE_RATE, 0.0f32, 0.0f32, 500.0f32);
+    let warm_output = warm_up.render(&mut warm, &input, TEST_BLOCK_SIZE);
+
+    // the step rings way past the dc level cold, and has died out after the warm up
+    assert!(swing(&cold_output) > 0.25f32, "{}", swing(&cold_output));
+    assert!(swing(&warm_output) < 0.001f32, "{}", swing(&warm_output));
+}
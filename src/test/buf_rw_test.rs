@@ -0,0 +1,113 @@
+#![cfg(test)]
+
+use crate::utils::buf_rw::{process_block, AudioBufferReader, AudioBufferWriter};
+use crate::utils::ringbuf::SimpleFloatBuffer;
+
+use std::cell::RefCell;
+use std::time::Instant;
+
+fn per_sample(reader: &AudioBufferReader, num_samples: usize) -> Vec<f32> {
+    (0..num_samples).map(|i| reader.buf_read(i)).collect()
+}
+
+#[test]
+fn test_read_slice_matches_buf_read() {
+    let buf = RefCell::new(SimpleFloatBuffer::with_max_capacity(8));
+    for i in 0..8 {
+        buf.borrow_mut().write(i as f32);
+    }
+
+    // a full block leaves the write index back at the start, so the block is contiguous
+    {
+        let reader = AudioBufferReader::Internal(buf.borrow());
+        assert_eq!(reader.read_slice(8).unwrap(), &per_sample(&reader, 8)[..]);
+    }
+
+    // a short block doesn't, and the next full one wraps round the end
+    for i in 8..11 {
+        buf.borrow_mut().write(i as f32);
+    }
+    {
+        let reader = AudioBufferReader::Internal(buf.borrow());
+        assert!(reader.read_slice(8).is_none());
+        assert_eq!(reader.read_slice(5).unwrap(), &per_sample(&reader, 5)[..]);
+
+        // process_block falls back to reading sample by sample
+        let mut out = vec![0.0f32; 8];
+        let mut writer = AudioBufferWriter::External(out.as_mut_ptr());
+        process_block(&reader, &mut writer, 8, |x_n| 2.0f32 * x_n);
+        let expected: Vec<f32> = per_sample(&reader, 8).iter().map(|x| 2.0f32 * x).collect();
+        assert_eq!(out, expected);
+    }
+
+    let external = [1.0f32, 2.0f32, 3.0f32];
+    let reader = AudioBufferReader::External(external.as_ptr());
+    assert_eq!(reader.read_slice(3).unwrap(), &external[..]);
+    assert!(AudioBufferReader::External(std::ptr::null()).read_slice(3).is_none());
+    assert!(AudioBufferReader::Null.read_slice(3).is_none());
+}
+
+#[test]
+fn test_buf_write_slice_matches_buf_write() {
+    let values = [0.5f32, -0.25f32, 1.0f32];
+
+    let buf = RefCell::new(SimpleFloatBuffer::with_max_capacity(3));
+    AudioBufferWriter::Internal(buf.borrow_mut()).buf_write_slice(0, &values);
+    let reader = AudioBufferReader::Internal(buf.borrow());
+    assert_eq!(per_sample(&reader, 3), values);
+
+    let mut out = vec![0.0f32; 3];
+    AudioBufferWriter::External(out.as_mut_ptr()).buf_write_slice(0, &values);
+    assert_eq!(out, values);
+
+    let mut out = vec![0.0f32; 5];
+    AudioBufferWriter::External(out.as_mut_ptr()).buf_write_slice(2, &values);
+    assert_eq!(out, [0.0f32, 0.0f32, 0.5f32, -0.25f32, 1.0f32]);
+}
+
+#[test]
+fn test_process_block_in_place() {
+    // longer than a chunk, so the later chunks are read after the earlier ones are written
+    let num_samples = 1000;
+    let mut samples: Vec<f32> = (0..num_samples).map(|i| i as f32).collect();
+
+    let reader = AudioBufferReader::External(samples.as_ptr());
+    let mut writer = AudioBufferWriter::External(samples.as_mut_ptr());
+    process_block(&reader, &mut writer, num_samples, |x_n| 2.0f32 * x_n);
+
+    let expected: Vec<f32> = (0..num_samples).map(|i| 2.0f32 * i as f32).collect();
+    assert_eq!(samples, expected);
+}
+
+// cargo test --release -- --ignored --nocapture
+#[test]
+#[ignore]
+fn bench_process_block_against_buf_read() {
+    let num_samples = 1024;
+    let iterations = 20000;
+    let input: Vec<f32> = (0..num_samples).map(|i| (i as f32 * 0.01f32).sin()).collect();
+    let mut output = vec![0.0f32; num_samples];
+
+    let reader = AudioBufferReader::External(input.as_ptr());
+    let mut writer = AudioBufferWriter::External(output.as_mut_ptr());
+
+    let started = Instant::now();
+    for _ in 0..iterations {
+        for i in 0..num_samples {
+            writer.buf_write(i, 0.5f32 * reader.buf_read(i));
+        }
+    }
+    let per_sample_time = started.elapsed();
+
+    let started = Instant::now();
+    for _ in 0..iterations {
+        process_block(&reader, &mut writer, num_samples, |x_n| 0.5f32 * x_n);
+    }
+    let block_time = started.elapsed();
+
+    println!(
+        "{} blocks of {}: buf_read {:?}, process_block {:?}",
+        iterations, num_samples, per_sample_time, block_time
+    );
+    assert!(block_time < per_sample_time);
+}
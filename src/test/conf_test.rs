@@ -0,0 +1,88 @@
+#![cfg(test)]
+
+use super::test_utils::*;
+
+const CHAIN_EFFECTS: &str = r#"[
+    {"bind_name": "delay", "effect_name": "Delay/Basic", "enabled": true, "config": [
+        {"name": "delay_time_ms", "value": {"F": 3.0}},
+        {"name": "feedback_pct", "value": {"F": 0.4}}
+    ]},
+    {"bind_name": "lpf", "effect_name": "Filter/Biquad", "enabled": true, "config": [
+        {"name": "filter_type", "value": {"N": 1}},
+        {"name": "corner_freq_hz", "value": {"F": 2000.0}}
+    ]},
+    {"bind_name": "crush", "effect_name": "NonLinear/BitCrusher", "enabled": true, "config": []}
+]"#;
+
+#[test]
+fn test_series_matches_explicit_wiring() {
+    let explicit = format!(
+        r#"{{
+            "buffers": ["@SOURCE_0", "@SINK_0", "a", "b"],
+            "effects": {},
+            "connections": [
+                {{"effect": "delay", "reads": ["@SOURCE_0"], "writes": ["a"]}},
+                {{"effect": "lpf", "reads": ["a"], "writes": ["b"]}},
+                {{"effect": "crush", "reads": ["b"], "writes": ["@SINK_0"]}}
+            ]
+        }}"#,
+        CHAIN_EFFECTS
+    );
+
+    let series = format!(
+        r#"{{
+            "buffers": ["@SOURCE_0", "@SINK_0"],
+            "effects": {},
+            "connections": [],
+            "series": [
+                {{"effects": ["delay", "lpf", "crush"], "reads": ["@SOURCE_0"], "writes": ["@SINK_0"]}}
+            ]
+        }}"#,
+        CHAIN_EFFECTS
+    );
+
+    let input = sine(440.0f32, 0.8f32, 8192);
+    let explicit_output = process_mono(&mut create_board(&explicit), &input);
+    let series_output = process_mono(&mut create_board(&series), &input);
+
+    assert!(rms(&explicit_output) > 0.1f32);
+    assert_eq!(explicit_output, series_output);
+}
+
+#[test]
+fn test_explicit_connection_reading_a_series_runs_after_it() {
+    let explicit = format!(
+        r#"{{
+            "buffers": ["@SOURCE_0", "@SINK_0", "a", "b"],
+            "effects": {},
+            "connections": [
+                {{"effect": "delay", "reads": ["@SOURCE_0"], "writes": ["a"]}},
+                {{"effect": "lpf", "reads": ["a"], "writes": ["b"]}},
+                {{"effect": "crush", "reads": ["b"], "writes": ["@SINK_0"]}}
+            ]
+        }}"#,
+        CHAIN_EFFECTS
+    );
+
+    // crush is declared before the series feeding it, but still has to see this block's output
+    let mixed = format!(
+        r#"{{
+            "buffers": ["@SOURCE_0", "@SINK_0", "b"],
+            "effects": {},
+            "connections": [
+                {{"effect": "crush", "reads": ["b"], "writes": ["@SINK_0"]}}
+            ],
+            "series": [
+                {{"effects": ["delay", "lpf"], "reads": ["@SOURCE_0"], "writes": ["b"]}}
+            ]
+        }}"#,
+        CHAIN_EFFECTS
+    );
+
+    let input = sine(440.0f32, 0.8f32, 8192);
+    let explicit_output = process_mono(&mut create_board(&explicit), &input);
+    let mixed_output = process_mono(&mut create_board(&mixed), &input);
+
+    assert!(rms(&explicit_output) > 0.1f32);
+    assert_eq!(explicit_output, mixed_output);
+}
@@ -0,0 +1,511 @@
+#![cfg(test)]
+
+use super::test_utils::*;
+use crate::conf::{AudioConfig, BoardEffectConfigParameterValue};
+use crate::otters::Otters;
+use crate::utils::delay_buf::DelayBuffer;
+use crate::utils::mathutils::{note_division_to_ms, NoteDivision};
+
+#[test]
+fn test_stereo_delay_cross_feedback_migrates_energy() {
+    let feedback = 0.2f32;
+    let cross_feedback = 0.6f32;
+    let mut otters = create_board(&stereo_board_config(
+        "Delay/Stereo",
+        r#"[
+            {"name": "delay_time_ms", "value": {"F": 10.0}},
+            {"name": "feedback_pct", "value": {"F": 0.2}},
+            {"name": "cross_feedback_pct", "value": {"F": 0.6}},
+            {"name": "wet_dry_pct", "value": {"F": 1.0}}
+        ]"#,
+    ));
+
+    // impulse on the left channel only
+    let num_samples = 4096;
+    let (left, right) = process_stereo(
+        &mut otters,
+        &impulse(num_samples),
+        &vec![0.0f32; num_samples],
+    );
+
+    // successive echoes follow [l, r] <- [fb * l + xfb * r, fb * r + xfb * l]
+    let echo_period = (0..num_samples).find(|i| left[*i].abs() > 0.5f32).unwrap();
+    assert!(echo_period >= 441 && echo_period <= 442);
+
+    let (mut expected_l, mut expected_r) = (1.0f32, 0.0f32);
+    for echo in 1..6 {
+        let idx = echo * echo_period;
+        assert!(
+            (left[idx] - expected_l).abs() < 1e-4f32,
+            "echo {} left {} expected {}",
+            echo,
+            left[idx],
+            expected_l
+        );
+        assert!(
+            (right[idx] - expected_r).abs() < 1e-4f32,
+            "echo {} right {} expected {}",
+            echo,
+            right[idx],
+            expected_r
+        );
+
+        let next_l = feedback * expected_l + cross_feedback * expected_r;
+        let next_r = feedback * expected_r + cross_feedback * expected_l;
+        expected_l = next_l;
+        expected_r = next_r;
+    }
+
+    // nothing should leak between echoes
+    assert!(right[..echo_period * 2].iter().all(|x| *x == 0.0f32));
+}
+
+#[test]
+fn test_ping_pong_delay_alternates_sides() {
+    let mut otters = create_board(&stereo_board_config(
+        "Delay/PingPong",
+        r#"[
+            {"name": "delay_time_ms", "value": {"F": 10.0}},
+            {"name": "feedback_pct", "value": {"F": 0.5}},
+            {"name": "wet_dry_pct", "value": {"F": 1.0}}
+        ]"#,
+    ));
+
+    // a source hard on the left still bounces over to the right
+    let num_samples = 4096;
+    let (left, right) = process_stereo(
+        &mut otters,
+        &impulse(num_samples),
+        &vec![0.0f32; num_samples],
+    );
+
+    let echo_period = (0..num_samples).find(|i| left[*i] != 0.0f32).unwrap();
+    assert!(echo_period >= 441 && echo_period <= 442);
+
+    // the input is summed to mono at half level, then every bounce is another 0.5
+    let mut expected = 0.5f32;
+    for echo in 1..6 {
+        let idx = echo * echo_period;
+        let (on, off) = if echo % 2 == 1 {
+            (&left, &right)
+        } else {
+            (&right, &left)
+        };
+
+        assert!(
+            (on[idx] - expected).abs() < 1e-4f32,
+            "echo {}: {} expected {}",
+            echo,
+            on[idx],
+            expected
+        );
+        assert!(
+            off[idx].abs() < 1e-4f32,
+            "echo {} leaked {}",
+            echo,
+            off[idx]
+        );
+
+        expected *= 0.5f32;
+    }
+}
+
+fn max_step(signal: &[f32]) -> f32 {
+    signal
+        .windows(2)
+        .fold(0.0f32, |acc, w| acc.max((w[1] - w[0]).abs()))
+}
+
+fn zero_crossings(signal: &[f32]) -> usize {
+    signal
+        .windows(2)
+        .filter(|w| (w[0] < 0.0f32) != (w[1] < 0.0f32))
+        .count()
+}
+
+// run a 500 Hz sine through a fully wet delay and move the delay from 10 ms to 21 ms halfway
+// (5.5 periods apart, so jumping flips the phase)
+fn delay_time_change_output(glide_ms: f32) -> (Vec<f32>, usize) {
+    let mut otters = create_board(&mono_board_config(
+        "Delay/Basic",
+        &format!(
+            r#"[
+                {{"name": "delay_time_ms", "value": {{"F": 10.0}}}},
+                {{"name": "wet_dry_pct", "value": {{"F": 1.0}}}},
+                {{"name": "delay_glide_ms", "value": {{"F": {}}}}}
+            ]"#,
+            glide_ms
+        ),
+    ));
+
+    let input = sine(500.0f32, 1.0f32, TEST_SAMPLE_RATE as usize);
+    let change_at = 22050 - 22050 % TEST_BLOCK_SIZE;
+
+    let mut output = process_mono(&mut otters, &input[..change_at]);
+    otters.set_effect_parameter(0, BoardEffectConfigParameterValue::F(21.0f32));
+    output.extend(process_mono(&mut otters, &input[change_at..]));
+
+    (output, change_at)
+}
+
+#[test]
+fn test_basic_delay_glide_slides_pitch_without_click() {
+    // a sine at 500 Hz moves at most 2 * pi * 500 / 44100 ~= 0.07 per sample
+    let max_sine_step = 2.0f32 * std::f32::consts::PI * 500.0f32 / TEST_SAMPLE_RATE;
+
+    let (jumped, change_at) = delay_time_change_output(0.0f32);
+    assert!(max_step(&jumped[change_at - 1..change_at + 1024]) > 4.0f32 * max_sine_step);
+
+    let (glided, change_at) = delay_time_change_output(50.0f32);
+    assert!(max_step(&glided[change_at - 1..]) <= max_sine_step * 1.01f32);
+
+    // increasing the delay time drops the pitch while gliding, then returns to 500 Hz
+    let window = 2205;
+    let steady = zero_crossings(&glided[change_at - window..change_at]);
+    let during = zero_crossings(&glided[change_at..change_at + window]);
+    let after = zero_crossings(&glided[glided.len() - window..]);
+    assert!(during < steady - 5, "{} {}", during, steady);
+    assert!((after as i32 - steady as i32).abs() <= 1);
+}
+
+#[test]
+fn test_reset_finishes_a_delay_glide() {
+    let mut otters = create_board(&mono_board_config(
+        "Delay/Basic",
+        r#"[
+            {"name": "delay_time_ms", "value": {"F": 10.0}},
+            {"name": "wet_dry_pct", "value": {"F": 1.0}},
+            {"name": "delay_glide_ms", "value": {"F": 500.0}}
+        ]"#,
+    ));
+
+    process_mono(&mut otters, &sine(500.0f32, 1.0f32, 16 * TEST_BLOCK_SIZE));
+    otters.set_effect_parameter(0, BoardEffectConfigParameterValue::F(20.0f32));
+    process_mono(&mut otters, &sine(500.0f32, 1.0f32, TEST_BLOCK_SIZE));
+
+    // straight after the reset, the echo is already at the new delay time
+    otters.reset_all();
+    let output = process_mono(&mut otters, &impulse(2048));
+    let echo = (0..output.len())
+        .find(|i| output[*i].abs() > 0.25f32)
+        .unwrap();
+    assert!(echo >= 881 && echo <= 883, "echo at {}", echo);
+}
+
+#[test]
+fn test_reset_effect_clears_only_that_delay() {
+    let delay_config = r#"[
+        {"name": "delay_time_ms", "value": {"F": 10.0}},
+        {"name": "feedback_pct", "value": {"F": 0.7}},
+        {"name": "wet_dry_pct", "value": {"F": 0.5}}
+    ]"#;
+    let mut otters = create_board(&format!(
+        r#"{{
+            "buffers": ["@SOURCE_0", "@SOURCE_1", "@SINK_0", "@SINK_1"],
+            "effects": [
+                {{"bind_name": "delay_l", "effect_name": "Delay/Basic", "config": {}, "enabled": true}},
+                {{"bind_name": "delay_r", "effect_name": "Delay/Basic", "config": {}, "enabled": true}}
+            ],
+            "connections": [
+                {{"effect": "delay_l", "reads": ["@SOURCE_0"], "writes": ["@SINK_0"]}},
+                {{"effect": "delay_r", "reads": ["@SOURCE_1"], "writes": ["@SINK_1"]}}
+            ]
+        }}"#,
+        delay_config, delay_config
+    ));
+
+    // get both feedback loops going
+    let pulse = impulse(TEST_SAMPLE_RATE as usize / 20);
+    process_stereo(&mut otters, &pulse, &pulse);
+
+    assert!(otters.reset_effect("delay_l"));
+    assert!(!otters.reset_effect("no_such_delay"));
+
+    let silence = vec![0.0f32; TEST_SAMPLE_RATE as usize / 20];
+    let (left, right) = process_stereo(&mut otters, &silence, &silence);
+
+    assert_eq!(peak(&left), 0.0f32);
+    assert!(peak(&right) > 0.01f32, "{}", peak(&right));
+}
+
+fn short_delay_level_at(freq: f32, wet_invert: i32) -> f32 {
+    let mut otters = create_board(&mono_board_config(
+        "Delay/Basic",
+        &format!(
+            r#"[
+                {{"name": "delay_time_ms", "value": {{"F": 1.0}}}},
+                {{"name": "wet_dry_pct", "value": {{"F": 0.5}}}},
+                {{"name": "wet_invert?", "value": {{"N": {}}}}}
+            ]"#,
+            wet_invert
+        ),
+    ));
+
+    let output = process_mono(&mut otters, &sine(freq, 1.0f32, 8192));
+    magnitude_at(&output[TEST_BLOCK_SIZE..], freq)
+}
+
+#[test]
+fn test_delay_wet_invert_notches_low_frequencies() {
+    // a 1ms delay summed with the dry signal only notches odd multiples of 500Hz,
+    // flipping the wet polarity moves the notches to dc and multiples of 1kHz
+    let normal_low = short_delay_level_at(50.0f32, 0);
+    let inverted_low = short_delay_level_at(50.0f32, 1);
+    assert!(normal_low > 0.9f32, "{}", normal_low);
+    assert!(inverted_low < 0.2f32, "{}", inverted_low);
+
+    let normal_notch = short_delay_level_at(500.0f32, 0);
+    let inverted_peak = short_delay_level_at(500.0f32, 1);
+    assert!(normal_notch < 0.1f32, "{}", normal_notch);
+    assert!(inverted_peak > 0.9f32, "{}", inverted_peak);
+}
+
+#[test]
+fn test_delay_visualization_copies_recent_samples() {
+    let mut otters = create_board(&mono_board_config(
+        "Delay/Basic",
+        r#"[{"name": "wet_dry_pct", "value": {"F": 0.0}}]"#,
+    ));
+
+    let ramp: Vec<f32> = (0..1000).map(|n| n as f32).collect();
+    process_mono(&mut otters, &ramp);
+
+    let mut recent = [0.0f32; 16];
+    assert_eq!(otters.copy_effect_visualization("fx", &mut recent), 16);
+    assert_eq!(&recent[..], &ramp[984..]);
+
+    assert_eq!(otters.copy_effect_visualization("nope", &mut recent), 0);
+}
+
+fn granular_peak_on_dc(max_voices: i32) -> f32 {
+    let mut otters = create_board(&mono_board_config(
+        "Delay/Granular",
+        &format!(
+            r#"[
+                {{"name": "grain_size_ms", "value": {{"F": 100.0}}}},
+                {{"name": "density_hz", "value": {{"F": 200.0}}}},
+                {{"name": "spread_ms", "value": {{"F": 0.0}}}},
+                {{"name": "wet_dry_pct", "value": {{"F": 1.0}}}},
+                {{"name": "max_voices", "value": {{"N": {}}}}}
+            ]"#,
+            max_voices
+        ),
+    ));
+
+    // every grain of a constant input contributes at most its window peak of 1
+    let output = process_mono(&mut otters, &vec![1.0f32; TEST_SAMPLE_RATE as usize]);
+    peak(&output)
+}
+
+#[test]
+fn test_granular_voice_cap_limits_overlapping_grains() {
+    // 200 grains/s of 100 ms would overlap ~20 deep without a cap. the sum is scaled by
+    // 1 / sqrt(overlap), so 4 voices of at most 1 peak at 2
+    let capped = granular_peak_on_dc(4);
+    assert!(capped <= 2.0f32 + 1e-4f32, "capped peak {}", capped);
+    assert!(capped > 0.0f32, "capped peak {}", capped);
+
+    // ~20 hann windows sum to ~10 on dc, which comes out around sqrt(20) / 2
+    let uncapped = granular_peak_on_dc(64);
+    assert!(uncapped > 1.5f32, "uncapped peak {}", uncapped);
+    assert!(uncapped < 3.0f32, "uncapped peak {}", uncapped);
+}
+
+fn monitored_delay_impulse_response(monitor_mode: i32) -> Vec<f32> {
+    let mut otters = create_board(&mono_board_config(
+        "Delay/Basic",
+        &format!(
+            r#"[
+                {{"name": "delay_time_ms", "value": {{"F": 10.0}}}},
+                {{"name": "feedback_pct", "value": {{"F": 0.5}}}},
+                {{"name": "wet_dry_pct", "value": {{"F": 0.5}}}},
+                {{"name": "monitor_mode", "value": {{"N": {}}}}}
+            ]"#,
+            monitor_mode
+        ),
+    ));
+
+    process_mono(&mut otters, &impulse(4096))
+}
+
+#[test]
+fn test_delay_monitor_modes_isolate_wet_and_dry() {
+    // the full mix has the dry impulse and echoes at half level
+    let full = monitored_delay_impulse_response(0);
+    assert_eq!(full[0], 0.5f32);
+
+    let first_echo = 1 + full[1..].iter().position(|x| *x != 0.0f32).unwrap();
+    assert!((first_echo as f32 - 0.01f32 * TEST_SAMPLE_RATE).abs() <= 1.0f32);
+    assert!((full[first_echo] - 0.5f32).abs() < 1e-3f32);
+
+    // wet only drops the dry impulse and plays the echoes at full level
+    let wet_only = monitored_delay_impulse_response(1);
+    assert!(wet_only[..first_echo].iter().all(|x| *x == 0.0f32));
+    assert!((wet_only[first_echo] - 1.0f32).abs() < 1e-3f32);
+    assert!((peak(&wet_only[first_echo + 1..]) - 0.5f32).abs() < 1e-3f32);
+
+    // dry only is the input untouched
+    let dry_only = monitored_delay_impulse_response(2);
+    assert_eq!(dry_only, impulse(4096));
+}
+
+// the part of the output holding the second echo of an impulse (the first one that went
+// through the feedback path), up to just before the third
+fn second_echo(diffusion: f32) -> Vec<f32> {
+    let mut otters = create_board(&mono_board_config(
+        "Delay/Basic",
+        &format!(
+            r#"[
+                {{"name": "delay_time_ms", "value": {{"F": 100.0}}}},
+                {{"name": "feedback_pct", "value": {{"F": 0.7}}}},
+                {{"name": "wet_dry_pct", "value": {{"F": 1.0}}}},
+                {{"name": "diffusion", "value": {{"F": {}}}}}
+            ]"#,
+            diffusion
+        ),
+    ));
+
+    let echo_len = 4410;
+    let output = process_mono(&mut otters, &impulse(3 * echo_len));
+    output[2 * echo_len - 10..3 * echo_len - 10].to_vec()
+}
+
+// how much of the echo's energy sits in its single loudest sample
+fn peak_energy_fraction(signal: &[f32]) -> f32 {
+    let energy: f32 = signal.iter().map(|x| x * x).sum();
+    peak(signal).powi(2) / energy
+}
+
+#[test]
+fn test_diffusion_smears_repeats() {
+    let clean = second_echo(0.0f32);
+    let half = second_echo(0.5f32);
+    let full = second_echo(1.0f32);
+
+    // without diffusion the repeat is still a single click
+    assert!(peak_energy_fraction(&clean) > 0.999f32);
+    assert!((peak(&clean) - 0.7f32).abs() < 1e-3f32);
+
+    // more diffusion, broader burst
+    let half_fraction = peak_energy_fraction(&half);
+    let full_fraction = peak_energy_fraction(&full);
+    assert!(half_fraction < 0.9f32, "{}", half_fraction);
+    assert!(full_fraction < 0.1f32, "{}", full_fraction);
+
+    // the diffusers are all pass, so smearing moves the energy around without losing much of it
+    let energy = |signal: &[f32]| signal.iter().map(|x| x * x).sum::<f32>();
+    let kept = energy(&full) / energy(&clean);
+    assert!(kept > 0.9f32 && kept < 1.01f32, "{}", kept);
+}
+
+#[test]
+fn test_multi_tap_delay_sums_taps_from_one_line() {
+    let mut otters = create_board(&mono_board_config(
+        "Delay/MultiTap",
+        r#"[
+            {"name": "tap1_ms", "value": {"F": 10.0}},
+            {"name": "tap2_ms", "value": {"F": 30.0}},
+            {"name": "tap3_ms", "value": {"F": 20.0}},
+            {"name": "tap4_ms", "value": {"F": 40.0}},
+            {"name": "tap1_gain", "value": {"F": 0.8}},
+            {"name": "tap2_gain", "value": {"F": 0.25}},
+            {"name": "tap3_gain", "value": {"F": -0.5}},
+            {"name": "tap4_gain", "value": {"F": 0.0}},
+            {"name": "wet_dry_pct", "value": {"F": 1.0}}
+        ]"#,
+    ));
+
+    let num_samples = 4096;
+    let output = process_mono(&mut otters, &impulse(num_samples));
+
+    // 10 ms is a whole number of samples, so every tap is a single clean spike
+    let first_tap = (0..num_samples).find(|i| output[*i] != 0.0f32).unwrap();
+    assert!(first_tap >= 441 && first_tap <= 442);
+
+    let tap_spacing = 441;
+    let expected = [(0, 0.8f32), (1, -0.5f32), (2, 0.25f32)];
+    for (tap, gain) in expected.iter() {
+        let idx = first_tap + tap * tap_spacing;
+        assert!(
+            (output[idx] - gain).abs() < 1e-6f32,
+            "tap at {}: {} expected {}",
+            idx,
+            output[idx],
+            gain
+        );
+    }
+
+    // the fourth tap is off, and nothing lands between the taps
+    let num_spikes = output.iter().filter(|x| **x != 0.0f32).count();
+    assert_eq!(num_spikes, expected.len());
+}
+
+#[test]
+fn test_delay_buffer_reads_taps_without_moving_the_delay() {
+    let mut delay_buf = DelayBuffer::with_sample_rate(TEST_SAMPLE_RATE);
+    delay_buf.set_delay_time_ms(1.0f32, true);
+
+    for i in 0..1000 {
+        delay_buf.write_sample(i as f32);
+    }
+
+    // 2 ms = 88.2 samples back from the newest sample (999)
+    let tap = delay_buf.read_delayed_sample_at_ms(2.0f32);
+    assert!((tap - (999.0f32 - 88.2f32)).abs() < 1e-3f32, "tap {}", tap);
+
+    let configured = delay_buf.read_delayed_sample();
+    assert!(
+        (configured - (999.0f32 - 44.1f32)).abs() < 1e-3f32,
+        "configured {}",
+        configured
+    );
+}
+
+#[test]
+fn test_note_divisions_at_tempo() {
+    let cases = [
+        (NoteDivision::Quarter, 500.0f32),
+        (NoteDivision::Eighth, 250.0f32),
+        (NoteDivision::DottedEighth, 375.0f32),
+        (NoteDivision::EighthTriplet, 500.0f32 / 3.0f32),
+        (NoteDivision::Whole, 2000.0f32),
+    ];
+
+    for (division, expected_ms) in cases.iter() {
+        let ms = note_division_to_ms(120.0f32, *division).unwrap();
+        assert!((ms - expected_ms).abs() < 1e-3f32, "{:?}: {}", division, ms);
+    }
+
+    assert_eq!(note_division_to_ms(120.0f32, NoteDivision::Free), None);
+}
+
+fn first_echo(otters: &mut Otters) -> usize {
+    let output = process_mono(otters, &impulse(32768));
+    (0..output.len()).find(|i| output[*i] != 0.0f32).unwrap()
+}
+
+#[test]
+fn test_synced_delay_follows_the_tempo() {
+    let mut otters = create_board(&mono_board_config(
+        "Delay/Basic",
+        r#"[
+            {"name": "delay_time_ms", "value": {"F": 100.0}},
+            {"name": "sync_division", "value": {"N": 3}},
+            {"name": "wet_dry_pct", "value": {"F": 1.0}}
+        ]"#,
+    ));
+
+    // a quarter note at 120 bpm, not the 100 ms delay time
+    let echo = first_echo(&mut otters);
+    assert!(echo >= 22050 && echo <= 22051, "echo at {}", echo);
+
+    otters
+        .update_audio_config(AudioConfig {
+            tempo_bpm: 150.0f32,
+            ..test_audio_config()
+        })
+        .unwrap();
+
+    let echo = first_echo(&mut otters);
+    assert!(echo >= 17640 && echo <= 17641, "echo at {}", echo);
+}
@@ -0,0 +1,52 @@
+#![cfg(test)]
+
+use super::test_utils::*;
+use crate::host::dither::OutputQuantizer;
+
+// quantization error (in LSBs) of a quiet sine at 8 bit
+fn quantization_error(noise_shaping: bool) -> Vec<f32> {
+    let input = sine(440.0f32, 0.25f32, 16384);
+    let mut output = vec![0i32; input.len()];
+
+    let mut quantizer = OutputQuantizer::new(8, true, noise_shaping, 0x0773_7265);
+    quantizer.quantize_block(&input, &mut output);
+
+    let scale = quantizer.get_scale();
+    input
+        .iter()
+        .zip(output.iter())
+        .map(|(x, y)| *y as f32 - x * scale)
+        .collect()
+}
+
+// mean power of the bins in [low_hz, high_hz)
+fn band_energy(signal: &[f32], low_hz: u32, high_hz: u32) -> f32 {
+    let bins: Vec<f32> = (low_hz..high_hz)
+        .step_by(100)
+        .map(|f| magnitude_at(signal, f as f32).powi(2))
+        .collect();
+
+    bins.iter().sum::<f32>() / bins.len() as f32
+}
+
+#[test]
+fn test_noise_shaping_moves_error_to_high_frequencies() {
+    let flat = quantization_error(false);
+    let shaped = quantization_error(true);
+
+    // dither alone stays within +-1.5 LSB, shaping can add up to another 2
+    assert!(peak(&flat) <= 1.5f32);
+    assert!(peak(&shaped) <= 3.5f32);
+
+    let flat_low = band_energy(&flat, 100, 2000);
+    let flat_high = band_energy(&flat, 16000, 20000);
+    let shaped_low = band_energy(&shaped, 100, 2000);
+    let shaped_high = band_energy(&shaped, 16000, 20000);
+
+    // tpdf error is white
+    assert!(flat_high / flat_low > 0.5f32 && flat_high / flat_low < 2.0f32);
+
+    // shaping drops the low band and raises the high band
+    assert!(shaped_low < flat_low * 0.25f32);
+    assert!(shaped_high > flat_high * 2.0f32);
+}
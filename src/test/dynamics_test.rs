@@ -0,0 +1,673 @@
+#![cfg(test)]
+
+use super::test_utils::*;
+use crate::conf::{AudioConfig, BoardEffectConfigParameterValue};
+use crate::effects::Dynamics;
+use crate::traits::AudioEffect;
+use crate::utils::envelope::{EnvelopeDetectMode, EnvelopeDetector};
+use crate::utils::fast_rand::WyHashPRNG;
+use crate::utils::peak_window::PeakWindow;
+
+#[test]
+fn test_envelope_detector_unclamped_reports_above_0_db() {
+    let mut detector = EnvelopeDetector::new(TEST_SAMPLE_RATE);
+    detector.set_attack_time_ms(1.0f32);
+    detector.set_release_time_ms(100.0f32);
+
+    let mut clamped_db = 0.0f32;
+    for _ in 0..4410 {
+        clamped_db = detector.process(2.0f32);
+    }
+    assert!(clamped_db <= 0.0f32);
+
+    detector.should_clamp = false;
+    let mut unclamped_db = 0.0f32;
+    for _ in 0..4410 {
+        unclamped_db = detector.process(2.0f32);
+    }
+
+    // 20 * log10(2)
+    assert!(
+        (unclamped_db - 6.0206f32).abs() < 0.05f32,
+        "{}",
+        unclamped_db
+    );
+}
+
+fn compress_dc(clamp_envelope: i32) -> f32 {
+    let mut otters = create_board(&mono_board_config(
+        "Dynamics/BasicCompressor",
+        &format!(
+            r#"[
+                {{"name": "threshold_db", "value": {{"F": 0.0}}}},
+                {{"name": "ratio", "value": {{"F": 4.0}}}},
+                {{"name": "soft_knee?", "value": {{"N": 0}}}},
+                {{"name": "clamp_envelope?", "value": {{"N": {}}}}}
+            ]"#,
+            clamp_envelope
+        ),
+    ));
+
+    let output = process_mono(&mut otters, &vec![2.0f32; TEST_SAMPLE_RATE as usize]);
+    output[output.len() - 1]
+}
+
+#[test]
+fn test_compressor_acts_above_0_dbfs_without_clamp() {
+    // clamped, the detector never exceeds the 0 dB threshold so nothing happens
+    assert!((compress_dc(1) - 2.0f32).abs() < 1e-4f32);
+
+    // unclamped, +6 dB over threshold at 4:1 comes out at +1.5 dB
+    let expected = 10.0f32.powf(1.505f32 / 20.0f32);
+    let unclamped = compress_dc(0);
+    assert!((unclamped - expected).abs() < 0.01f32, "{}", unclamped);
+}
+
+fn compress_transient_meter(meter_ballistics: i32) -> Vec<f32> {
+    let mut otters = create_board(&format!(
+        r#"{{
+            "buffers": ["@SOURCE_0", "@SINK_0", "@SINK_1"],
+            "effects": [{{
+                "bind_name": "fx",
+                "effect_name": "Dynamics/BasicCompressor",
+                "config": [
+                    {{"name": "threshold_db", "value": {{"F": -20.0}}}},
+                    {{"name": "ratio", "value": {{"F": 10.0}}}},
+                    {{"name": "attack_time_ms", "value": {{"F": 1.0}}}},
+                    {{"name": "release_time_ms", "value": {{"F": 20.0}}}},
+                    {{"name": "meter_ballistics", "value": {{"N": {}}}}}
+                ],
+                "enabled": true
+            }}],
+            "connections": [{{"effect": "fx", "reads": ["@SOURCE_0"], "writes": ["@SINK_0", "@SINK_1"]}}]
+        }}"#,
+        meter_ballistics
+    ));
+
+    // 5 ms burst well above threshold, then silence
+    let mut input = vec![0.0f32; TEST_SAMPLE_RATE as usize / 2];
+    for x in input.iter_mut().take(TEST_SAMPLE_RATE as usize / 200) {
+        *x = 0.9f32;
+    }
+
+    let mut output = vec![0.0f32; input.len()];
+    let mut meter = vec![0.0f32; input.len()];
+    let mut offset = 0;
+    while offset < input.len() {
+        let block_size = TEST_BLOCK_SIZE.min(input.len() - offset);
+        otters.bind_input(0, input[offset..].as_ptr());
+        otters.bind_output(0, output[offset..].as_mut_ptr());
+        otters.bind_output(1, meter[offset..].as_mut_ptr());
+        otters.frolic(block_size);
+
+        offset += block_size;
+    }
+
+    meter
+}
+
+#[test]
+fn test_vu_gain_reduction_meter_is_slower_than_instant() {
+    let instant = compress_transient_meter(0);
+    let vu = compress_transient_meter(1);
+
+    let deepest = |meter: &Vec<f32>| meter.iter().cloned().fold(0.0f32, f32::min);
+    let instant_deepest = deepest(&instant);
+    let vu_deepest = deepest(&vu);
+
+    // the burst is ~19 dB over threshold so the detector gets well past 10 dB of reduction
+    assert!(instant_deepest < -10.0f32, "{}", instant_deepest);
+    assert!(vu_deepest < 0.0f32);
+    assert!(
+        vu_deepest > 0.5f32 * instant_deepest,
+        "{} vs {}",
+        vu_deepest,
+        instant_deepest
+    );
+
+    // and the vu meter is still showing reduction after the instant one has let go
+    let late = TEST_SAMPLE_RATE as usize / 5;
+    assert!(
+        vu[late] < instant[late],
+        "{} vs {}",
+        vu[late],
+        instant[late]
+    );
+}
+
+#[test]
+fn test_envelope_detector_holds_before_release() {
+    let hold_ms = 20.0f32;
+    let hold_samples = (hold_ms * TEST_SAMPLE_RATE * 0.001f32) as usize;
+
+    let mut detector = EnvelopeDetector::new(TEST_SAMPLE_RATE);
+    detector.should_return_db = false;
+    detector.set_attack_time_ms(1.0f32);
+    detector.set_release_time_ms(5.0f32);
+    detector.set_hold_time_ms(hold_ms);
+
+    let mut peak_envelope = 0.0f32;
+    for _ in 0..441 {
+        peak_envelope = detector.process(1.0f32);
+    }
+
+    // the envelope sits at its peak through the hold time...
+    for i in 0..hold_samples {
+        let envelope = detector.process(0.0f32);
+        assert_eq!(envelope, peak_envelope, "sample {}", i);
+    }
+
+    // ...then releases
+    let released: Vec<f32> = (0..441).map(|_| detector.process(0.0f32)).collect();
+    assert!(released[0] < peak_envelope);
+    assert!(released[440] < 0.5f32 * peak_envelope, "{}", released[440]);
+}
+
+#[test]
+fn test_gate_trigger_output_follows_threshold() {
+    let mut otters = create_board(
+        r#"{
+            "buffers": ["@SOURCE_0", "@SINK_0", "@SINK_1", "@SINK_2"],
+            "effects": [{
+                "bind_name": "fx",
+                "effect_name": "Dynamics/BasicNoiseGate",
+                "config": [
+                    {"name": "threshold_db", "value": {"F": -20.0}},
+                    {"name": "attack_time_ms", "value": {"F": 1.0}},
+                    {"name": "release_time_ms", "value": {"F": 10.0}}
+                ],
+                "enabled": true
+            }],
+            "connections": [{"effect": "fx", "reads": ["@SOURCE_0"], "writes": ["@SINK_0", "@SINK_1", "@SINK_2"]}]
+        }"#,
+    );
+
+    // a quarter second well over the threshold, then a quarter second well under it
+    let half = TEST_SAMPLE_RATE as usize / 4;
+    let mut input = sine(440.0f32, 0.5f32, 2 * half);
+    for x in input[half..].iter_mut() {
+        *x *= 0.01f32;
+    }
+
+    let mut output = vec![0.0f32; input.len()];
+    let mut meter = vec![0.0f32; input.len()];
+    let mut trigger = vec![0.0f32; input.len()];
+    let mut offset = 0;
+    while offset < input.len() {
+        let block_size = TEST_BLOCK_SIZE.min(input.len() - offset);
+        otters.bind_input(0, input[offset..].as_ptr());
+        otters.bind_output(0, output[offset..].as_mut_ptr());
+        otters.bind_output(1, meter[offset..].as_mut_ptr());
+        otters.bind_output(2, trigger[offset..].as_mut_ptr());
+        otters.frolic(block_size);
+
+        offset += block_size;
+    }
+
+    // the settled parts of each half, past the detector and the 5 ms trigger smoothing
+    let open = &trigger[half / 2..half];
+    let closed = &trigger[3 * half / 2..];
+    assert!(
+        open.iter().all(|x| *x > 0.95f32),
+        "{}",
+        open.iter().cloned().fold(1.0f32, f32::min)
+    );
+    assert!(closed.iter().all(|x| *x < 0.05f32), "{}", peak(closed));
+}
+
+fn compress_dc_with_knee(level: f32, threshold_db: f32, knee_mode: i32) -> f32 {
+    let mut otters = create_board(&mono_board_config(
+        "Dynamics/BasicCompressor",
+        &format!(
+            r#"[
+                {{"name": "threshold_db", "value": {{"F": {}}}}},
+                {{"name": "knee_width_db", "value": {{"F": 10.0}}}},
+                {{"name": "knee_mode", "value": {{"N": {}}}}},
+                {{"name": "ratio", "value": {{"F": 4.0}}}},
+                {{"name": "attack_time_ms", "value": {{"F": 1.0}}}}
+            ]"#,
+            threshold_db, knee_mode
+        ),
+    ));
+
+    let output = process_mono(&mut otters, &vec![level; TEST_SAMPLE_RATE as usize / 2]);
+    20.0f32 * output[output.len() - 1].log10()
+}
+
+#[test]
+fn test_relative_knee_widens_with_threshold_depth() {
+    let levels_db = [-40.0f32, -32.0f32, -30.0f32, -25.0f32, -20.0f32];
+    let curve = |threshold_db: f32, knee_mode: i32| -> Vec<f32> {
+        levels_db
+            .iter()
+            .map(|db| compress_dc_with_knee(10.0f32.powf(db / 20.0f32), threshold_db, knee_mode))
+            .collect()
+    };
+
+    // at a -10 dB threshold the relative knee is the same 10 dB wide as the absolute one
+    let absolute = curve(-10.0f32, 0);
+    let relative = curve(-10.0f32, 1);
+    for (a, r) in absolute.iter().zip(relative.iter()) {
+        assert!((a - r).abs() < 0.01f32, "{} vs {}", a, r);
+    }
+
+    // at -30 dB it's 30 dB wide, so it starts bending further below the threshold and
+    // hasn't reached the full ratio yet at -20 dB
+    let expected_absolute = [-40.0f32, -32.3375f32, -30.9375f32, -28.75f32, -27.5f32];
+    let expected_relative = [-40.3125f32, -34.1125f32, -32.8125f32, -30.0f32, -27.8125f32];
+    let absolute = curve(-30.0f32, 0);
+    let relative = curve(-30.0f32, 1);
+    for i in 0..levels_db.len() {
+        assert!(
+            (absolute[i] - expected_absolute[i]).abs() < 0.01f32,
+            "{} dB in: {}",
+            levels_db[i],
+            absolute[i]
+        );
+        assert!(
+            (relative[i] - expected_relative[i]).abs() < 0.01f32,
+            "{} dB in: {}",
+            levels_db[i],
+            relative[i]
+        );
+    }
+}
+
+#[test]
+fn test_ms_compressor_leaves_side_alone() {
+    let mut otters = create_board(&stereo_board_config(
+        "Dynamics/MSCompressor",
+        r#"[
+            {"name": "mid_threshold_db", "value": {"F": -30.0}},
+            {"name": "mid_ratio", "value": {"F": 8.0}},
+            {"name": "mid_attack_time_ms", "value": {"F": 1.0}},
+            {"name": "mid_release_time_ms", "value": {"F": 50.0}}
+        ]"#,
+    ));
+
+    // the mid drops by 24 dB halfway through, the side stays put
+    let half = TEST_SAMPLE_RATE as usize / 2;
+    let mut mid = sine(220.0f32, 0.8f32, 2 * half);
+    for x in mid[half..].iter_mut() {
+        *x *= 1.0f32 / 16.0f32;
+    }
+    let side = sine(1000.0f32, 0.1f32, 2 * half);
+
+    let left: Vec<f32> = mid.iter().zip(side.iter()).map(|(m, s)| m + s).collect();
+    let right: Vec<f32> = mid.iter().zip(side.iter()).map(|(m, s)| m - s).collect();
+    let (out_left, out_right) = process_stereo(&mut otters, &left, &right);
+
+    let out_mid: Vec<f32> = out_left
+        .iter()
+        .zip(out_right.iter())
+        .map(|(l, r)| 0.5f32 * (l + r))
+        .collect();
+    let out_side: Vec<f32> = out_left
+        .iter()
+        .zip(out_right.iter())
+        .map(|(l, r)| 0.5f32 * (l - r))
+        .collect();
+
+    // the compressors' delay lines are read before they're written, so everything is a sample late
+    let side_error: Vec<f32> = out_side[1..]
+        .iter()
+        .zip(side.iter())
+        .map(|(y, x)| y - x)
+        .collect();
+    assert!(peak(&side_error) < 1e-4f32, "{}", peak(&side_error));
+
+    // the settled part of each half. the level difference in the mid has been squashed
+    let loud = rms(&out_mid[half / 2..half]);
+    let quiet = rms(&out_mid[3 * half / 2..]);
+    assert!(loud < 0.5f32 * rms(&mid[half / 2..half]), "{}", loud);
+    assert!(loud / quiet < 4.0f32, "{} vs {}", loud, quiet);
+}
+
+#[test]
+fn test_sidechain_drives_the_detector() {
+    let config = r#"[
+        {"name": "threshold_db", "value": {"F": -20.0}},
+        {"name": "ratio", "value": {"F": 10.0}},
+        {"name": "attack_time_ms", "value": {"F": 1.0}},
+        {"name": "release_time_ms", "value": {"F": 20.0}}
+    ]"#;
+
+    // the signal sits over the threshold the whole time, the sidechain only for the first half
+    let half = TEST_SAMPLE_RATE as usize / 2;
+    let signal = sine(220.0f32, 0.25f32, 2 * half);
+    let mut sidechain = sine(60.0f32, 0.9f32, 2 * half);
+    for x in sidechain[half..].iter_mut() {
+        *x = 0.0f32;
+    }
+
+    // reads = [signal, sidechain]. writes[1] is the gain reduction meter
+    let mut ducker = create_board(&stereo_board_config("Dynamics/BasicCompressor", config));
+    let (ducked, _) = process_stereo(&mut ducker, &signal, &sidechain);
+
+    let signal_rms = rms(&signal[half / 2..half]);
+    let loud_rms = rms(&ducked[half / 2..half]);
+    let quiet_rms = rms(&ducked[3 * half / 2..]);
+    assert!(loud_rms < 0.25f32 * signal_rms, "{}", loud_rms);
+    assert!(
+        (quiet_rms / signal_rms - 1.0f32).abs() < 0.01f32,
+        "{}",
+        quiet_rms
+    );
+
+    // without the sidechain the same signal is compressed all the way through
+    let mut compressor = create_board(&mono_board_config("Dynamics/BasicCompressor", config));
+    let compressed = process_mono(&mut compressor, &signal);
+    assert!(rms(&compressed[3 * half / 2..]) < 0.75f32 * signal_rms);
+}
+
+fn gate_note_onset(lookahead_ms: f32) -> (Vec<f32>, Vec<f32>) {
+    let mut otters = create_board(&mono_board_config(
+        "Dynamics/BasicNoiseGate",
+        &format!(
+            r#"[
+                {{"name": "threshold_db", "value": {{"F": -20.0}}}},
+                {{"name": "soft_knee?", "value": {{"N": 0}}}},
+                {{"name": "attack_time_ms", "value": {{"F": 5.0}}}},
+                {{"name": "lookahead_ms", "value": {{"F": {}}}}}
+            ]"#,
+            lookahead_ms
+        ),
+    ));
+
+    // silence, then a note that starts at full level
+    let onset = TEST_SAMPLE_RATE as usize / 10;
+    let mut input = vec![0.0f32; onset];
+    input.extend(sine(440.0f32, 0.5f32, onset));
+
+    let output = process_mono(&mut otters, &input);
+    (input, output)
+}
+
+#[test]
+fn test_gate_lookahead_keeps_the_attack() {
+    let onset = TEST_SAMPLE_RATE as usize / 10;
+    let attack = TEST_SAMPLE_RATE as usize / 500;
+
+    // the detector takes a few ms to cross the threshold, which chops the first 2 ms of the note
+    let (input, late) = gate_note_onset(0.0f32);
+    let note_rms = rms(&input[onset..onset + attack]);
+    assert!(
+        rms(&late[onset + 1..onset + 1 + attack]) < 0.5f32 * note_rms,
+        "{}",
+        rms(&late[onset + 1..onset + 1 + attack])
+    );
+
+    // with 10 ms of lookahead the gate is already open when the note comes out 10 ms later
+    let lookahead = TEST_SAMPLE_RATE as usize / 100;
+    let (input, early) = gate_note_onset(10.0f32);
+    let start = onset + lookahead + 1;
+    let mut error = vec![0.0f32; attack];
+    for i in 0..attack {
+        error[i] = early[start + i] - input[onset + i];
+    }
+    assert!(peak(&error) < 1e-3f32, "{}", peak(&error));
+    assert!(peak(&early[..start]) < 1e-6f32, "{}", peak(&early[..start]));
+}
+
+fn compress_sine_with_detect_mode(detect_mode: i32) -> f32 {
+    let mut otters = create_board(&mono_board_config(
+        "Dynamics/BasicCompressor",
+        &format!(
+            r#"[
+                {{"name": "threshold_db", "value": {{"F": -12.0}}}},
+                {{"name": "ratio", "value": {{"F": 20.0}}}},
+                {{"name": "soft_knee?", "value": {{"N": 0}}}},
+                {{"name": "attack_time_ms", "value": {{"F": 50.0}}}},
+                {{"name": "release_time_ms", "value": {{"F": 50.0}}}},
+                {{"name": "detect_mode", "value": {{"N": {}}}}}
+            ]"#,
+            detect_mode
+        ),
+    ));
+
+    let input = sine(441.0f32, 0.5f32, TEST_SAMPLE_RATE as usize);
+    let output = process_mono(&mut otters, &input);
+
+    // gain reduction in dB over the settled half
+    let half = input.len() / 2;
+    20.0f32 * (rms(&output[half..]) / rms(&input[half..])).log10()
+}
+
+#[test]
+fn test_detect_mode_changes_what_the_compressor_hears() {
+    // with equal attack and release the detector averages. a 0.5 sine averages to 0.318
+    // (-9.94 dB) rectified, and its RMS is 0.354 (-9.03 dB), 2.06 dB and 2.97 dB over
+    let peak_reduction_db = compress_sine_with_detect_mode(0);
+    let rms_reduction_db = compress_sine_with_detect_mode(2);
+
+    assert!(
+        (peak_reduction_db + 2.06f32 * 0.95f32).abs() < 0.2f32,
+        "{}",
+        peak_reduction_db
+    );
+    assert!(
+        (rms_reduction_db + 2.97f32 * 0.95f32).abs() < 0.2f32,
+        "{}",
+        rms_reduction_db
+    );
+}
+
+fn dynamics_param_idx(name: &str) -> usize {
+    Dynamics::dynamics_info()
+        .iter()
+        .position(|p| p.name == name)
+        .unwrap()
+}
+
+#[test]
+fn test_set_audio_parameters_keeps_detector_settings() {
+    let mut compressor = Dynamics::new_compressor(test_audio_config());
+    compressor.set_effect_parameter(
+        dynamics_param_idx("attack_time_ms"),
+        BoardEffectConfigParameterValue::F(50.0f32),
+    );
+    compressor.set_effect_parameter(
+        dynamics_param_idx("release_time_ms"),
+        BoardEffectConfigParameterValue::F(200.0f32),
+    );
+    compressor.set_effect_parameter(
+        dynamics_param_idx("detect_mode"),
+        BoardEffectConfigParameterValue::N(EnvelopeDetectMode::RootMeanSquare as i32),
+    );
+
+    compressor.set_audio_parameters(&AudioConfig {
+        sample_rate: 48000.0f32,
+        ..test_audio_config()
+    });
+
+    // the same times, worked out again at the new rate
+    let mut expected = EnvelopeDetector::new(48000.0f32);
+    expected.set_attack_time_ms(50.0f32);
+    expected.set_release_time_ms(200.0f32);
+
+    let detector = compressor.get_envelope_detector();
+    assert_eq!(detector.get_sample_rate(), 48000.0f32);
+    assert!(detector.get_attack_time_coefficient() > 0.0f32);
+    assert_eq!(
+        detector.get_attack_time_coefficient(),
+        expected.get_attack_time_coefficient()
+    );
+    assert_eq!(
+        detector.get_release_time_coefficient(),
+        expected.get_release_time_coefficient()
+    );
+    assert!(detector.detect_mode == EnvelopeDetectMode::RootMeanSquare);
+}
+
+fn limit_spike(lookahead_ms: f32) -> f32 {
+    let mut otters = create_board(&mono_board_config(
+        "Dynamics/BasicLimiter",
+        &format!(
+            r#"[
+                {{"name": "threshold_db", "value": {{"F": -10.0}}}},
+                {{"name": "attack_time_ms", "value": {{"F": 1.0}}}},
+                {{"name": "release_time_ms", "value": {{"F": 50.0}}}},
+                {{"name": "lookahead_ms", "value": {{"F": {}}}}}
+            ]"#,
+            lookahead_ms
+        ),
+    ));
+
+    // a single full scale sample in the middle of some quiet tone
+    let num_samples = TEST_SAMPLE_RATE as usize / 10;
+    let mut input = sine(100.0f32, 0.1f32, num_samples);
+    input[num_samples / 2] = 1.0f32;
+
+    peak(&process_mono(&mut otters, &input))
+}
+
+#[test]
+fn test_limiter_lookahead_catches_a_single_spike() {
+    let threshold = 10.0f32.powf(-10.0f32 / 20.0f32);
+
+    // the detector only has the one sample to react, far too short for a 1 ms attack
+    let late = limit_spike(0.0f32);
+    assert!(late > 0.9f32, "{}", late);
+
+    // 5 attack times of warning is enough to be within 0.1 dB
+    let early = limit_spike(5.0f32);
+    assert!(early < threshold * 1.012f32, "{} vs {}", early, threshold);
+}
+
+#[test]
+fn test_peak_window_matches_brute_force() {
+    let prng = WyHashPRNG::new(7);
+    let input: Vec<f32> = (0..2000)
+        .map(|_| (prng.next() % 2001) as f32 / 1000.0f32 - 1.0f32)
+        .collect();
+
+    for length in &[1usize, 5, 64] {
+        let mut window = PeakWindow::new(64);
+        window.set_length(*length);
+
+        for (i, x) in input.iter().enumerate() {
+            let start = (i + 1).saturating_sub(*length);
+            let expected = peak(&input[start..=i]);
+            assert_eq!(window.process(*x), expected, "length {} at {}", length, i);
+        }
+    }
+}
+
+// a burst well over threshold, then a quiet tone under it. returns the quiet part
+fn compress_burst_then_release(burst_ms: f32, auto_release: Option<i32>) -> Vec<f32> {
+    let auto_release_param = match auto_release {
+        Some(auto_release) => format!(
+            r#", {{"name": "auto_release", "value": {{"N": {}}}}}"#,
+            auto_release
+        ),
+        None => String::new(),
+    };
+
+    let mut otters = create_board(&mono_board_config(
+        "Dynamics/BasicCompressor",
+        &format!(
+            r#"[
+                {{"name": "threshold_db", "value": {{"F": -20.0}}}},
+                {{"name": "ratio", "value": {{"F": 10.0}}}},
+                {{"name": "attack_time_ms", "value": {{"F": 1.0}}}},
+                {{"name": "release_time_ms", "value": {{"F": 20.0}}}}{}
+            ]"#,
+            auto_release_param
+        ),
+    ));
+
+    let burst_samples = (burst_ms * TEST_SAMPLE_RATE * 0.001f32) as usize;
+    let release_samples = TEST_SAMPLE_RATE as usize / 2;
+
+    let mut input = vec![0.9f32; burst_samples];
+    input.extend(std::iter::repeat(0.05f32).take(release_samples));
+
+    let output = process_mono(&mut otters, &input);
+    output[burst_samples..].to_vec()
+}
+
+#[test]
+fn test_auto_release_slows_down_after_sustained_reduction() {
+    let after_ms = |output: &Vec<f32>, ms: f32| output[(ms * TEST_SAMPLE_RATE * 0.001f32) as usize];
+
+    // off is exactly what the compressor did before there was a switch
+    for &burst_ms in [5.0f32, 1000.0f32].iter() {
+        assert_eq!(
+            compress_burst_then_release(burst_ms, None),
+            compress_burst_then_release(burst_ms, Some(0))
+        );
+    }
+
+    // a short burst lets go at the regular release either way
+    let short_off = compress_burst_then_release(5.0f32, Some(0));
+    let short_on = compress_burst_then_release(5.0f32, Some(1));
+    assert!(
+        (after_ms(&short_on, 40.0f32) - after_ms(&short_off, 40.0f32)).abs() < 0.005f32,
+        "{} {}",
+        after_ms(&short_on, 40.0f32),
+        after_ms(&short_off, 40.0f32)
+    );
+
+    // after a second of gain reduction the release is much slower
+    let long_off = compress_burst_then_release(1000.0f32, Some(0));
+    let long_on = compress_burst_then_release(1000.0f32, Some(1));
+    assert!(
+        after_ms(&long_off, 100.0f32) > 0.045f32,
+        "{}",
+        after_ms(&long_off, 100.0f32)
+    );
+    assert!(
+        after_ms(&long_on, 100.0f32) < 0.035f32,
+        "{}",
+        after_ms(&long_on, 100.0f32)
+    );
+
+    // but it still gets there
+    let recovered = long_on[long_on.len() - 1];
+    assert!(recovered > 0.045f32, "{}", recovered);
+}
+
+fn dc_through(effect_name: &str, level: f32, auto_makeup: i32) -> f32 {
+    let mut otters = create_board(&mono_board_config(
+        effect_name,
+        &format!(
+            r#"[
+                {{"name": "threshold_db", "value": {{"F": -20.0}}}},
+                {{"name": "ratio", "value": {{"F": 4.0}}}},
+                {{"name": "soft_knee?", "value": {{"N": 0}}}},
+                {{"name": "output_gain_db", "value": {{"F": 2.0}}}},
+                {{"name": "auto_makeup", "value": {{"N": {}}}}}
+            ]"#,
+            auto_makeup
+        ),
+    ));
+
+    let output = process_mono(&mut otters, &vec![level; TEST_SAMPLE_RATE as usize / 2]);
+    output[output.len() - 1]
+}
+
+#[test]
+fn test_auto_makeup_brings_full_scale_back_up() {
+    let db = |x: f32| 20.0f32 * x.log10();
+
+    // 20 dB over a -20 dB threshold at 4:1 loses 15 dB. made up, 0 dBFS comes out at
+    // output_gain_db
+    let off = dc_through("Dynamics/BasicCompressor", 1.0f32, 0);
+    let on = dc_through("Dynamics/BasicCompressor", 1.0f32, 1);
+    assert!((db(off) + 13.0f32).abs() < 0.05f32, "{}", db(off));
+    assert!((db(on) - 2.0f32).abs() < 0.05f32, "{}", db(on));
+
+    // the limiter holds everything at the threshold, so it makes up all 20 dB
+    let limited = dc_through("Dynamics/BasicLimiter", 1.0f32, 1);
+    assert!((db(limited) - 2.0f32).abs() < 0.05f32, "{}", db(limited));
+
+    // the gate and expander leave their output gain alone
+    for &effect_name in ["Dynamics/BasicNoiseGate", "Dynamics/BasicDownwardExpander"].iter() {
+        assert_eq!(
+            dc_through(effect_name, 0.5f32, 1),
+            dc_through(effect_name, 0.5f32, 0),
+            "{}",
+            effect_name
+        );
+    }
+}
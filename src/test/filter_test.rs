@@ -0,0 +1,583 @@
+#![cfg(test)]
+
+use super::test_utils::*;
+use crate::conf::{AudioConfig, BoardEffectConfigParameterValue};
+use crate::otters::Otters;
+use crate::utils::biquad::{Biquad, BiquadCoefficients, IIRFilterType};
+use crate::utils::smoothed_param::ParamSmoother;
+
+#[test]
+fn test_formant_filter_vowel_a_peaks() {
+    let mut otters = create_board(&mono_board_config(
+        "Filter/Formant",
+        r#"[{"name": "vowel", "value": {"F": 0.0}}]"#,
+    ));
+
+    let response = process_mono(&mut otters, &impulse(8192));
+
+    // scan the magnitude response in 10 Hz steps and collect the local maxima
+    let freqs: Vec<f32> = (10..320).map(|f| f as f32 * 10.0f32).collect();
+    let mags: Vec<f32> = freqs.iter().map(|f| magnitude_at(&response, *f)).collect();
+    let peaks: Vec<f32> = (1..freqs.len() - 1)
+        .filter(|i| mags[*i] > mags[*i - 1] && mags[*i] > mags[*i + 1])
+        .map(|i| freqs[i])
+        .collect();
+
+    // first three bass "A" formants
+    for formant in &[600.0f32, 1040.0f32, 2250.0f32] {
+        assert!(
+            peaks.iter().any(|p| (p - formant).abs() <= 20.0f32),
+            "no peak near {} in {:?}",
+            formant,
+            peaks
+        );
+    }
+
+    // the first formant is the loudest
+    let first = magnitude_at(&response, 600.0f32);
+    assert!(mags.iter().all(|m| *m <= first * 1.01f32));
+}
+
+// the settled response of a filter to a constant (dc) or an alternating (nyquist) input
+fn settled_gain(biquad: &mut Biquad, nyquist: bool) -> f32 {
+    let mut output = 0.0f32;
+    for n in 0..4096 {
+        let x = if nyquist && n % 2 == 1 { -1.0f32 } else { 1.0f32 };
+        output = biquad.filter(x) * x;
+    }
+
+    output
+}
+
+#[test]
+fn test_first_order_shelves_settle_at_their_gain() {
+    let boost = 10.0f32.powf(6.0f32 / 20.0f32);
+
+    let mut low_shelf =
+        Biquad::new(BiquadCoefficients::first_order_low_shelf(1000.0f32, 44100.0f32, 6.0f32));
+    assert!((settled_gain(&mut low_shelf, false) - boost).abs() < 1e-3f32);
+    let mut low_shelf =
+        Biquad::new(BiquadCoefficients::first_order_low_shelf(1000.0f32, 44100.0f32, 6.0f32));
+    assert!((settled_gain(&mut low_shelf, true) - 1.0f32).abs() < 1e-3f32);
+
+    let mut high_shelf =
+        Biquad::new(BiquadCoefficients::first_order_high_shelf(1000.0f32, 44100.0f32, 6.0f32));
+    assert!((settled_gain(&mut high_shelf, false) - 1.0f32).abs() < 1e-3f32);
+    let mut high_shelf =
+        Biquad::new(BiquadCoefficients::first_order_high_shelf(1000.0f32, 44100.0f32, 6.0f32));
+    assert!((settled_gain(&mut high_shelf, true) - boost).abs() < 1e-3f32);
+}
+
+#[test]
+fn test_linkwitz_riley_bands_sum_flat() {
+    use crate::utils::linkwitz_riley::LinkwitzRileyCrossover;
+
+    for freq in [125.0f32, 500.0f32, 800.0f32, 1000.0f32, 1250.0f32, 2000.0f32, 8000.0f32].iter() {
+        let mut crossover = LinkwitzRileyCrossover::new(1000.0f32, TEST_SAMPLE_RATE);
+        let input = sine(*freq, 0.5f32, TEST_SAMPLE_RATE as usize / 2);
+
+        let (mut low, mut high, mut sum) = (Vec::new(), Vec::new(), Vec::new());
+        for x in input.iter() {
+            let (l, h) = crossover.split(*x);
+            low.push(l);
+            high.push(h);
+            sum.push(l + h);
+        }
+
+        // skip the start up transient
+        let settled = input.len() / 2;
+        let gain = rms(&sum[settled..]) / rms(&input[settled..]);
+        assert!((gain - 1.0f32).abs() < 0.01f32, "{} Hz sums to {}", freq, gain);
+
+        // and the bands do actually split
+        let (low, high) = (rms(&low[settled..]), rms(&high[settled..]));
+        if *freq < 500.0f32 {
+            assert!(low > 10.0f32 * high, "{} Hz: {} {}", freq, low, high);
+        } else if *freq > 2000.0f32 {
+            assert!(high > 10.0f32 * low, "{} Hz: {} {}", freq, low, high);
+        }
+    }
+}
+
+// a linear filter should satisfy y(a + b) = y(a) + y(b). whatever is left over is rounding noise
+fn resonant_lpf_superposition_error(double_precision: bool) -> f32 {
+    let create_filter = || {
+        let mut filter = Biquad::new(BiquadCoefficients::second_order_lpf(
+            30.0f32,
+            TEST_SAMPLE_RATE,
+            Some(20.0f32),
+        ));
+        filter.set_double_precision(double_precision);
+        filter
+    };
+
+    let a = sine(30.0f32, 0.25f32, 44100);
+    let b = sine(1234.5f32, 0.25f32, 44100);
+
+    let (mut filter_a, mut filter_b, mut filter_sum) =
+        (create_filter(), create_filter(), create_filter());
+    let residual: Vec<f32> = a
+        .iter()
+        .zip(b.iter())
+        .map(|(a, b)| filter_sum.filter(a + b) - (filter_a.filter(*a) + filter_b.filter(*b)))
+        .collect();
+
+    rms(&residual)
+}
+
+#[test]
+fn test_double_precision_biquad_has_lower_noise_floor() {
+    let single = resonant_lpf_superposition_error(false);
+    let double = resonant_lpf_superposition_error(true);
+
+    assert!(double < 0.1f32 * single, "f64 {} vs f32 {}", double, single);
+}
+
+fn biquad_effect_response(filter_type: IIRFilterType, freq: f32, gain_db: f32, q: f32) -> Vec<f32> {
+    let mut otters = create_board(&mono_board_config(
+        "Filter/Biquad",
+        &format!(
+            r#"[
+                {{"name": "filter_type", "value": {{"N": {}}}}},
+                {{"name": "corner_freq_hz", "value": {{"F": {}}}}},
+                {{"name": "boost_cut_db", "value": {{"F": {}}}}},
+                {{"name": "q", "value": {{"F": {}}}}}
+            ]"#,
+            filter_type as i32, freq, gain_db, q
+        ),
+    ));
+
+    process_mono(&mut otters, &impulse(16384))
+}
+
+fn peaking_eq_response(gain_db: f32, q: f32) -> Vec<f32> {
+    biquad_effect_response(
+        IIRFilterType::SecondOrderParametricEQ,
+        1000.0f32,
+        gain_db,
+        q,
+    )
+}
+
+// level relative to a frequency far below the bell, where the filter is flat
+fn relative_db(response: &[f32], freq: f32) -> f32 {
+    20.0f32 * (magnitude_at(response, freq) / magnitude_at(response, 10.0f32)).log10()
+}
+
+#[test]
+fn test_peaking_eq_peaks_at_center_frequency() {
+    let boost = peaking_eq_response(12.0f32, 2.0f32);
+
+    // scan from 100 Hz to 10 kHz in 1/24 octave steps
+    let freqs: Vec<f32> = (0..160)
+        .map(|i| 100.0f32 * 2.0f32.powf(i as f32 / 24.0f32))
+        .collect();
+    let loudest = freqs
+        .iter()
+        .cloned()
+        .fold((0.0f32, std::f32::MIN), |(best_f, best_db), f| {
+            let db = relative_db(&boost, f);
+            if db > best_db {
+                (f, db)
+            } else {
+                (best_f, best_db)
+            }
+        });
+
+    assert!((loudest.0 - 1000.0f32).abs() < 30.0f32, "{:?}", loudest);
+    assert!((relative_db(&boost, 1000.0f32) - 12.0f32).abs() < 0.1f32);
+    assert!(relative_db(&boost, 10000.0f32).abs() < 0.5f32);
+
+    // a cut is the mirror image of the boost
+    let cut = peaking_eq_response(-12.0f32, 2.0f32);
+    assert!((relative_db(&cut, 1000.0f32) + 12.0f32).abs() < 0.1f32);
+    assert!((relative_db(&cut, 1500.0f32) + relative_db(&boost, 1500.0f32)).abs() < 0.1f32);
+
+    // a higher q keeps the boost closer to the center
+    let wide = peaking_eq_response(12.0f32, 0.707f32);
+    assert!((relative_db(&wide, 1000.0f32) - 12.0f32).abs() < 0.1f32);
+    assert!(relative_db(&wide, 2000.0f32) > relative_db(&boost, 2000.0f32) + 3.0f32);
+}
+
+// gain in dB, relative to the (flat) spectrum of the impulse that was filtered
+fn db_at(response: &[f32], freq: f32) -> f32 {
+    let flat = magnitude_at(&impulse(response.len()), freq);
+    20.0f32 * (magnitude_at(response, freq) / flat).log10()
+}
+
+#[test]
+fn test_second_order_shelves() {
+    let low = biquad_effect_response(
+        IIRFilterType::SecondOrderLowShelf,
+        200.0f32,
+        12.0f32,
+        0.707f32,
+    );
+    assert!((db_at(&low, 20.0f32) - 12.0f32).abs() < 0.3f32);
+    assert!((db_at(&low, 200.0f32) - 6.0f32).abs() < 0.2f32);
+    assert!(db_at(&low, 10000.0f32).abs() < 0.1f32);
+
+    let high = biquad_effect_response(
+        IIRFilterType::SecondOrderHighShelf,
+        2000.0f32,
+        -12.0f32,
+        0.707f32,
+    );
+    assert!((db_at(&high, 18000.0f32) + 12.0f32).abs() < 0.3f32);
+    assert!((db_at(&high, 2000.0f32) + 6.0f32).abs() < 0.2f32);
+    assert!(db_at(&high, 20.0f32).abs() < 0.1f32);
+
+    // twice the slope of the first order shelf, so it's closer to flat two octaves out
+    let mut first_order = Biquad::new(BiquadCoefficients::first_order_low_shelf(
+        200.0f32,
+        TEST_SAMPLE_RATE,
+        12.0f32,
+    ));
+    let first_order_response: Vec<f32> = impulse(16384)
+        .iter()
+        .map(|x| first_order.filter(*x))
+        .collect();
+    assert!(db_at(&low, 800.0f32) + 1.0f32 < db_at(&first_order_response, 800.0f32));
+
+    // raising q makes the shelf ring: it overshoots past 0 dB just above the corner
+    let resonant = biquad_effect_response(
+        IIRFilterType::SecondOrderLowShelf,
+        200.0f32,
+        12.0f32,
+        3.0f32,
+    );
+    let dip = |response: &[f32]| {
+        (0..48)
+            .map(|i| db_at(response, 200.0f32 * 2.0f32.powf(i as f32 / 24.0f32)))
+            .fold(std::f32::MAX, f32::min)
+    };
+    assert!(dip(&resonant) < -1.0f32, "{}", dip(&resonant));
+    assert!(dip(&low) > -0.1f32, "{}", dip(&low));
+}
+
+#[test]
+fn test_biquad_filter_keeps_state_per_channel() {
+    let config = stereo_board_config(
+        "Filter/Biquad",
+        r#"[
+            {"name": "filter_type", "value": {"N": 1}},
+            {"name": "corner_freq_hz", "value": {"F": 1000.0}}
+        ]"#,
+    );
+    let left = impulse(1024);
+    let right = sine(3000.0f32, 0.5f32, 1024);
+
+    let filtered = |input: &[f32]| -> Vec<f32> {
+        let mut biquad = Biquad::new(BiquadCoefficients::second_order_lpf(
+            1000.0f32,
+            TEST_SAMPLE_RATE,
+            Some(0.707f32),
+        ));
+        input.iter().map(|x| biquad.filter(*x)).collect()
+    };
+
+    // two channels of state, so each side is filtered as if it were alone
+    let stereo_config = AudioConfig {
+        num_channels: 2,
+        ..test_audio_config()
+    };
+    let mut otters = Otters::create_default_from_string(stereo_config, &config).unwrap();
+    assert_eq!(otters.num_channels(), 2);
+
+    let (out_left, out_right) = process_stereo(&mut otters, &left, &right);
+    assert_eq!(out_left, filtered(&left));
+    assert_eq!(out_right, filtered(&right));
+
+    // a mono config only has state for the first channel. the other one passes through
+    let mut otters = create_board(&config);
+    let (out_left, out_right) = process_stereo(&mut otters, &left, &right);
+    assert_eq!(out_left, filtered(&left));
+    assert_eq!(out_right, right);
+}
+
+#[test]
+fn test_notch_depth() {
+    let freq = 1000.0f32;
+    let input = impulse(16384);
+
+    // no depth, no filter
+    let untouched = biquad_effect_response(IIRFilterType::SecondOrderNotch, freq, 0.0f32, 2.0f32);
+    for (y, x) in untouched.iter().zip(input.iter()) {
+        assert!((y - x).abs() < 1e-6f32);
+    }
+
+    let partial = biquad_effect_response(IIRFilterType::SecondOrderNotch, freq, -20.0f32, 2.0f32);
+    assert!((db_at(&partial, freq) + 20.0f32).abs() < 0.2f32);
+    assert!(db_at(&partial, 100.0f32).abs() < 0.1f32);
+    assert!(db_at(&partial, 10000.0f32).abs() < 0.1f32);
+
+    // a very deep notch is the band stop
+    let response = |coeff: BiquadCoefficients| -> Vec<f32> {
+        let mut biquad = Biquad::new(coeff);
+        input.iter().map(|x| biquad.filter(*x)).collect()
+    };
+    let deep = response(BiquadCoefficients::second_order_notch(
+        freq,
+        TEST_SAMPLE_RATE,
+        Some(2.0f32),
+        -120.0f32,
+    ));
+    let band_stop = response(BiquadCoefficients::second_order_bsf(
+        freq,
+        TEST_SAMPLE_RATE,
+        Some(2.0f32),
+    ));
+    for (d, b) in deep.iter().zip(band_stop.iter()) {
+        assert!((d - b).abs() < 1e-5f32);
+    }
+}
+
+// (low, high) impulse responses of a 1 kHz crossover in the given mode
+fn crossover_impulse_responses(mode: i32) -> (Vec<f32>, Vec<f32>) {
+    let mut otters = create_board(&format!(
+        r#"{{
+            "buffers": ["@SOURCE_0", "@SINK_0", "@SINK_1"],
+            "effects": [{{"bind_name": "fx", "effect_name": "Filter/Crossover", "enabled": true, "config": [
+                {{"name": "crossover_freq_hz", "value": {{"F": 1000.0}}}},
+                {{"name": "crossover_mode", "value": {{"N": {}}}}}
+            ]}}],
+            "connections": [{{"effect": "fx", "reads": ["@SOURCE_0"], "writes": ["@SINK_0", "@SINK_1"]}}]
+        }}"#,
+        mode
+    ));
+
+    let num_samples = 8192;
+    process_stereo(
+        &mut otters,
+        &impulse(num_samples),
+        &vec![0.0f32; num_samples],
+    )
+}
+
+#[test]
+fn test_crossover_latency_follows_the_mode() {
+    let mut otters = create_board(&mono_board_config("Filter/Crossover", "[]"));
+    assert_eq!(otters.total_latency_samples(), 0);
+
+    otters.set_effect_parameter(1, BoardEffectConfigParameterValue::N(1));
+    let fir_latency = otters.total_latency_samples();
+    assert!(fir_latency > 0);
+
+    // the low band of a click peaks where the reported latency says it will
+    let output = process_mono(&mut otters, &impulse(4 * fir_latency));
+    let peak_at = (0..output.len())
+        .max_by(|a, b| output[*a].partial_cmp(&output[*b]).unwrap())
+        .unwrap();
+    assert_eq!(peak_at, fir_latency);
+
+    otters.set_effect_parameter(1, BoardEffectConfigParameterValue::N(0));
+    assert_eq!(otters.total_latency_samples(), 0);
+}
+
+// complex response of an impulse response at freq, with delay_samples of pure delay taken out
+fn response_at(impulse_response: &[f32], freq: f32, delay_samples: usize) -> (f32, f32) {
+    let omega = 2.0f64 * std::f64::consts::PI * freq as f64 / TEST_SAMPLE_RATE as f64;
+    let (mut re, mut im) = (0.0f64, 0.0f64);
+    for (n, h) in impulse_response.iter().enumerate() {
+        let phase = omega * (n as f64 - delay_samples as f64);
+        re += *h as f64 * phase.cos();
+        im -= *h as f64 * phase.sin();
+    }
+
+    ((re * re + im * im).sqrt() as f32, im.atan2(re) as f32)
+}
+
+#[test]
+fn test_crossover_modes_phase_and_transient_response() {
+    let (iir_low, iir_high) = crossover_impulse_responses(0);
+    let (fir_low, fir_high) = crossover_impulse_responses(1);
+
+    let otters = create_board(&mono_board_config(
+        "Filter/Crossover",
+        r#"[{"name": "crossover_mode", "value": {"N": 1}}]"#,
+    ));
+    let fir_latency = otters.total_latency_samples();
+    assert!(fir_latency > 0);
+
+    // both are 6 dB down at the crossover, but only the iir low band is phase shifted there
+    // (a 4th order low pass is half a turn behind at its corner)
+    let (iir_magnitude, iir_phase) = response_at(&iir_low, 1000.0f32, 0);
+    let (fir_magnitude, _) = response_at(&fir_low, 1000.0f32, fir_latency);
+    assert!(
+        (iir_magnitude - 0.5f32).abs() < 0.02f32,
+        "iir {}",
+        iir_magnitude
+    );
+    assert!(
+        (fir_magnitude - 0.5f32).abs() < 0.02f32,
+        "fir {}",
+        fir_magnitude
+    );
+    assert!(iir_phase.abs() > 3.0f32, "iir phase {}", iir_phase);
+
+    // once the latency is taken out, the linear phase bands have no phase shift anywhere
+    for freq in [100.0f32, 500.0f32, 1000.0f32, 2000.0f32, 5000.0f32].iter() {
+        let (_, low_phase) = response_at(&fir_low, *freq, fir_latency);
+        let (_, high_phase) = response_at(&fir_high, *freq, fir_latency);
+        assert!(
+            low_phase.abs() < 0.01f32,
+            "low phase at {}: {}",
+            freq,
+            low_phase
+        );
+        assert!(
+            high_phase.abs() < 0.01f32 || (high_phase.abs() - std::f32::consts::PI).abs() < 0.01f32,
+            "high phase at {}: {}",
+            freq,
+            high_phase
+        );
+    }
+
+    // linear phase: the bands add back up to the same (delayed) impulse
+    for n in 0..fir_low.len() {
+        let expected = if n == fir_latency { 1.0f32 } else { 0.0f32 };
+        let sum = fir_low[n] + fir_high[n];
+        assert!((sum - expected).abs() < 1e-4f32, "sum at {}: {}", n, sum);
+    }
+
+    // minimum phase: they add up to an all pass, so the energy is all there but the click is smeared
+    let iir_sum: Vec<f32> = iir_low
+        .iter()
+        .zip(iir_high.iter())
+        .map(|(l, h)| l + h)
+        .collect();
+    let energy: f32 = iir_sum.iter().map(|x| x * x).sum();
+    assert!((energy - 1.0f32).abs() < 0.02f32, "energy {}", energy);
+    assert!(peak(&iir_sum) < 0.9f32, "peak {}", peak(&iir_sum));
+}
+
+#[test]
+fn test_biquad_keeps_its_memory_across_a_sample_rate_change() {
+    let coefficients = BiquadCoefficients::second_order_lpf(1000.0f32, 44100.0f32, None);
+    let mut switched = Biquad::new(coefficients.clone());
+    let mut reference = Biquad::new(coefficients.clone());
+
+    let input = sine(440.0f32, 0.5f32, 512);
+    for x in input[..256].iter() {
+        switched.filter(*x);
+        reference.filter(*x);
+    }
+
+    switched.change_sample_rate(48000.0f32);
+    assert!(*switched.coefficients() != coefficients);
+
+    // back and forth re-derives the same set, and the filter carries on as if nothing happened
+    switched.change_sample_rate(44100.0f32);
+    assert!(*switched.coefficients() == coefficients);
+    for x in input[256..].iter() {
+        assert_eq!(switched.filter(*x), reference.filter(*x));
+    }
+}
+
+#[test]
+fn test_filter_block_matches_filter_bit_for_bit() {
+    let coefficient_sets = vec![
+        BiquadCoefficients::second_order_lpf(800.0f32, TEST_SAMPLE_RATE, Some(4.0f32)),
+        BiquadCoefficients::first_order_hpf(200.0f32, TEST_SAMPLE_RATE),
+        // the shelves are the ones that use c_0 and d_0
+        BiquadCoefficients::first_order_low_shelf(300.0f32, TEST_SAMPLE_RATE, 6.0f32),
+        BiquadCoefficients::second_order_high_shelf(3000.0f32, TEST_SAMPLE_RATE, -9.0f32, None),
+    ];
+
+    let mut input = sine(440.0f32, 0.5f32, 2048);
+    for (x, y) in input.iter_mut().zip(sine(5123.0f32, 0.3f32, 2048).iter()) {
+        *x += y;
+    }
+
+    // blocks of 0, 1 and 2 have to carry the state through the feedforward taps properly
+    let block_sizes = [64, 1, 0, 2, 3, 128, 1, 1, 500];
+
+    for double_precision in &[false, true] {
+        for coefficients in coefficient_sets.iter() {
+            let mut per_sample = Biquad::new(coefficients.clone());
+            let mut blocked = Biquad::new(coefficients.clone());
+            per_sample.set_double_precision(*double_precision);
+            blocked.set_double_precision(*double_precision);
+
+            let expected: Vec<f32> = input.iter().map(|x| per_sample.filter(*x)).collect();
+
+            let mut output = vec![0.0f32; input.len()];
+            let mut start = 0;
+            for block_size in block_sizes.iter().cycle() {
+                if start >= input.len() {
+                    break;
+                }
+
+                let end = (start + block_size).min(input.len());
+                blocked.filter_block(&input[start..end], &mut output[start..end]);
+                start = end;
+            }
+
+            assert_eq!(output, expected);
+
+            // and both carry on from the same state
+            assert_eq!(blocked.filter(0.25f32), per_sample.filter(0.25f32));
+        }
+    }
+}
+
+#[test]
+fn test_param_smoother_step_reaches_63_percent_after_one_time_constant() {
+    let mut smoother = ParamSmoother::new(0.0f32, 10.0f32, TEST_SAMPLE_RATE);
+    smoother.set_target(1.0f32);
+
+    // 10 ms
+    for _ in 0..440 {
+        smoother.next();
+    }
+    let after_one_time_constant = smoother.next();
+    assert!(
+        (after_one_time_constant - 0.632f32).abs() < 0.01f32,
+        "{}",
+        after_one_time_constant
+    );
+
+    // and it does get there eventually
+    for _ in 0..TEST_SAMPLE_RATE as usize {
+        smoother.next();
+    }
+    assert_eq!(smoother.current(), 1.0f32);
+
+    // a 0 ms smoother doesn't smooth
+    let mut instant = ParamSmoother::new(0.0f32, 0.0f32, TEST_SAMPLE_RATE);
+    instant.set_target(1.0f32);
+    assert!(!instant.is_smoothing());
+}
+
+#[test]
+fn test_biquad_cutoff_changes_glide() {
+    let lowpass = |corner_freq_hz: f32| {
+        create_board(&mono_board_config(
+            "Filter/Biquad",
+            &format!(
+                r#"[
+                    {{"name": "filter_type", "value": {{"N": {}}}}},
+                    {{"name": "corner_freq_hz", "value": {{"F": {}}}}}
+                ]"#,
+                IIRFilterType::SecondOrderLowPass as i32,
+                corner_freq_hz
+            ),
+        ))
+    };
+    let input = sine(2000.0f32, 0.5f32, TEST_SAMPLE_RATE as usize / 2);
+    let change_at = 64 * TEST_BLOCK_SIZE;
+
+    let mut otters = lowpass(200.0f32);
+    let mut output = process_mono(&mut otters, &input[..change_at]);
+    otters.set_effect_parameter(1, BoardEffectConfigParameterValue::F(5000.0f32));
+    output.extend(process_mono(&mut otters, &input[change_at..]));
+
+    // the 2 kHz sine comes up over a few ms rather than all at once
+    let opened = rms(&process_mono(&mut lowpass(5000.0f32), &input)[input.len() - 4410..]);
+    let just_after = rms(&output[change_at..change_at + 32]);
+    assert!(just_after < 0.5f32 * opened, "{} {}", just_after, opened);
+
+    // once the glide is done it's the same filter as one that started there
+    let settled = rms(&output[output.len() - 4410..]);
+    assert!((settled - opened).abs() < 0.01f32 * opened, "{} {}", settled, opened);
+}
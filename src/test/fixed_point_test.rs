@@ -0,0 +1,81 @@
+#![cfg(all(test, feature = "fixed_point"))]
+
+use super::test_utils::*;
+use crate::utils::biquad::{Biquad, BiquadCoefficients};
+use crate::utils::delay_buf::DelayBuffer;
+use crate::utils::fixed_point::*;
+
+// two tones so the filter has something in both its pass and stop band
+// kept quiet enough that +12 dB of shelf still fits under Q31 full scale
+fn test_input(num_samples: usize) -> Vec<f32> {
+    let low = sine(220.0f32, 0.1f32, num_samples);
+    let high = sine(7040.0f32, 0.1f32, num_samples);
+
+    low.iter().zip(high.iter()).map(|(l, h)| l + h).collect()
+}
+
+#[test]
+fn test_fixed_point_biquad_matches_float_within_quantization() {
+    let input = test_input(8192);
+
+    for coeff in &[
+        BiquadCoefficients::second_order_lpf(1000.0f32, TEST_SAMPLE_RATE, Some(0.707f32)),
+        BiquadCoefficients::second_order_hpf(1000.0f32, TEST_SAMPLE_RATE, Some(2.0f32)),
+        BiquadCoefficients::first_order_low_shelf(300.0f32, TEST_SAMPLE_RATE, 12.0f32),
+    ] {
+        let mut reference = Biquad::new(coeff.clone());
+        let mut fixed = FixedBiquad::new(coeff);
+
+        // Q31 rounding is ~5e-10 per step. the float reference itself is only good to ~1e-7
+        let mut max_error = 0.0f32;
+        for x in input.iter() {
+            let expected = reference.filter(*x);
+            let actual = q31_to_f32(fixed.filter(f32_to_q31(*x)));
+
+            max_error = max_error.max((expected - actual).abs());
+        }
+
+        assert!(max_error < 1e-5f32, "max error {}", max_error);
+
+        // through 16 bit i/o the output is within an lsb of the (quantized) float output
+        let mut reference = Biquad::new(coeff.clone());
+        let mut fixed = FixedBiquad::new(coeff);
+        for x in input.iter() {
+            let x_q15 = f32_to_q15(*x);
+            let expected = reference.filter(q15_to_f32(x_q15));
+            let actual = q31_to_q15(fixed.filter(q15_to_q31(x_q15)));
+
+            assert!((f32_to_q15(expected) as i32 - actual as i32).abs() <= 1);
+        }
+    }
+}
+
+#[test]
+fn test_fixed_point_delay_and_mix_match_float() {
+    let input = test_input(4096);
+    let delay_ms = 3.7f32;
+
+    let mut reference = DelayBuffer::with_sample_rate_and_max_delay(TEST_SAMPLE_RATE, 100.0f32);
+    reference.set_delay_time_ms(delay_ms, true);
+    let mut fixed = FixedDelayBuffer::with_sample_rate_and_max_delay(TEST_SAMPLE_RATE, 100.0f32);
+    fixed.set_delay_time_ms(delay_ms);
+
+    let (dry_gain, wet_gain) = (0.3f32, 0.6f32);
+    for x in input.iter() {
+        let expected_delayed = reference.read_delayed_sample();
+        reference.write_sample(*x);
+        let expected = dry_gain * x + wet_gain * expected_delayed;
+
+        let x_q31 = f32_to_q31(*x);
+        let delayed = fixed.read_delayed_sample();
+        fixed.write_sample(x_q31);
+        let actual = q31_mix(x_q31, f32_to_q31(dry_gain), delayed, f32_to_q31(wet_gain));
+
+        assert!((q31_to_f32(delayed) - expected_delayed).abs() < 1e-6f32);
+        assert!((q31_to_f32(actual) - expected).abs() < 1e-6f32);
+    }
+
+    // mixing saturates instead of wrapping
+    assert_eq!(q31_mix(i32::MAX, i32::MAX, i32::MAX, i32::MAX), i32::MAX);
+    assert_eq!(q31_mul(i32::MIN, i32::MIN), i32::MAX);
+}
@@ -0,0 +1,53 @@
+#![cfg(test)]
+
+use super::test_utils::*;
+use crate::utils::lfo::{LFOCurve, LFOWaveForm, LowFrequencyOscillator};
+
+// fraction of one second of a 5 Hz triangle spent within 0.1 of +-1
+fn time_near_extremes(curve: LFOCurve) -> f32 {
+    let mut lfo = LowFrequencyOscillator::new(LFOWaveForm::Triangle, 5.0f32, TEST_SAMPLE_RATE);
+    lfo.set_curve(curve);
+
+    let num_samples = TEST_SAMPLE_RATE as usize;
+    let mut num_near_extremes = 0;
+    for _ in 0..num_samples {
+        let y = lfo.current_sample();
+        assert!(y >= -1.0f32 && y <= 1.0f32);
+
+        if y.abs() > 0.9f32 {
+            num_near_extremes += 1;
+        }
+
+        lfo.oscillate();
+    }
+
+    num_near_extremes as f32 / num_samples as f32
+}
+
+#[test]
+fn test_exponential_lfo_curve_dwells_at_extremes() {
+    let linear = time_near_extremes(LFOCurve::Linear);
+    let exponential = time_near_extremes(LFOCurve::Exponential);
+    let logarithmic = time_near_extremes(LFOCurve::Logarithmic);
+
+    // a linear triangle is uniformly distributed, so 10% of the time
+    assert!((linear - 0.1f32).abs() < 0.005f32, "{}", linear);
+    assert!(
+        exponential > 2.0f32 * linear,
+        "{} vs {}",
+        exponential,
+        linear
+    );
+    assert!(logarithmic < linear, "{} vs {}", logarithmic, linear);
+
+    // the curves only bend the shape, the peaks and zero crossings stay put
+    let mut lfo = LowFrequencyOscillator::new(LFOWaveForm::Triangle, 1.0f32, 4.0f32);
+    lfo.set_curve(LFOCurve::Exponential);
+    let mut one_cycle = [0.0f32; 4];
+    for y in one_cycle.iter_mut() {
+        *y = lfo.current_sample();
+        lfo.oscillate();
+    }
+
+    assert_eq!(one_cycle, [1.0f32, 0.0f32, -1.0f32, 0.0f32]);
+}
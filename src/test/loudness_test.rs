@@ -0,0 +1,58 @@
+#![cfg(test)]
+
+use super::test_utils::*;
+use crate::host::loudness::{
+    integrated_loudness_lufs, normalize_loudness, normalize_peak, peak_db,
+};
+
+// k-weighting adds +0.691 dB at 997 Hz, which the -0.691 offset cancels, so a mono
+// sine of amplitude A reads 10 * log10(A^2 / 2) LUFS there (EBU Tech 3341's reference tone)
+fn expected_sine_lufs(amplitude: f32) -> f32 {
+    10.0f32 * (amplitude * amplitude / 2.0f32).log10()
+}
+
+#[test]
+fn test_loudness_of_known_level_sine() {
+    let signal = sine(997.0f32, 0.5f32, 5 * TEST_SAMPLE_RATE as usize);
+
+    let lufs = integrated_loudness_lufs(&signal, TEST_SAMPLE_RATE);
+    assert!(
+        (lufs - expected_sine_lufs(0.5f32)).abs() < 0.05f32,
+        "{}",
+        lufs
+    );
+
+    // silence is gated out rather than dragging the measurement down
+    // (only the few blocks straddling the edge count)
+    let mut with_silence = signal.clone();
+    with_silence.extend(vec![0.0f32; 5 * TEST_SAMPLE_RATE as usize]);
+    let gated_lufs = integrated_loudness_lufs(&with_silence, TEST_SAMPLE_RATE);
+    assert!(
+        (gated_lufs - lufs).abs() < 0.2f32,
+        "{} {}",
+        gated_lufs,
+        lufs
+    );
+
+    assert_eq!(
+        integrated_loudness_lufs(&vec![0.0f32; 44100], TEST_SAMPLE_RATE),
+        f32::NEG_INFINITY
+    );
+}
+
+#[test]
+fn test_normalize_loudness_and_peak() {
+    let mut signal = sine(200.0f32, 0.5f32, 5 * TEST_SAMPLE_RATE as usize);
+    let before = integrated_loudness_lufs(&signal, TEST_SAMPLE_RATE);
+
+    let gain_db = normalize_loudness(&mut signal, TEST_SAMPLE_RATE, -23.0f32);
+    assert!((gain_db - (-23.0f32 - before)).abs() < 1e-4f32);
+
+    let after = integrated_loudness_lufs(&signal, TEST_SAMPLE_RATE);
+    assert!((after + 23.0f32).abs() < 0.01f32, "{}", after);
+
+    let peak_before = peak_db(&signal);
+    let gain_db = normalize_peak(&mut signal, -1.0f32);
+    assert!((gain_db - (-1.0f32 - peak_before)).abs() < 1e-4f32);
+    assert!((peak_db(&signal) + 1.0f32).abs() < 0.01f32);
+}
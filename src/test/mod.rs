@@ -1 +1,22 @@
-mod otters_test;
\ No newline at end of file
+mod ab_compare_test;
+mod automation_test;
+mod boundary_fades_test;
+mod buf_rw_test;
+mod conf_test;
+mod delay_test;
+mod dither_test;
+mod dynamics_test;
+mod filter_test;
+mod fixed_point_test;
+mod lfo_test;
+mod loudness_test;
+mod nonlinear_test;
+mod otters_test;
+mod pcm_test;
+mod pitch_test;
+mod reverb_test;
+mod synth_test;
+mod test_signal_test;
+mod test_utils;
+mod utility_test;
+mod vocoder_test;
@@ -0,0 +1,145 @@
+#![cfg(test)]
+
+use super::test_utils::*;
+
+#[test]
+fn test_wavefold_folds_sine_and_adds_odd_harmonics() {
+    let freq = 441.0f32;
+    let mut otters = create_board(&mono_board_config(
+        "NonLinear/WaveShaper",
+        r#"[
+            {"name": "waveshaper_function", "value": {"N": 12}},
+            {"name": "gain", "value": {"F": 2.0}}
+        ]"#,
+    ));
+
+    // 100 samples per period
+    let input = sine(freq, 1.0f32, 8820);
+    let output = process_mono(&mut otters, &input);
+
+    // driven to 2.0 the crest folds all the way back down to 0,
+    // while the signal still reaches the 1.0 boundary on the way up (where the input is 0.5)
+    assert!(output[25].abs() < 1e-3f32, "{}", output[25]);
+    assert!(peak(&output) > 0.9f32, "{}", peak(&output));
+    assert!(output.iter().all(|y| y.abs() <= 1.0f32));
+
+    // the fold is odd symmetric, so only odd harmonics show up
+    let third = magnitude_at(&output, 3.0f32 * freq);
+    let fifth = magnitude_at(&output, 5.0f32 * freq);
+    let second = magnitude_at(&output, 2.0f32 * freq);
+    assert!(third > 0.1f32, "{}", third);
+    assert!(fifth > 0.05f32, "{}", fifth);
+    assert!(second < 0.01f32, "{}", second);
+}
+
+// energy in the bins that aren't harmonics of fundamental_bin, relative to the energy that is
+// the input is periodic in the dft length, so everything else is aliasing (or the dc it leaves)
+fn aliasing_to_harmonic_ratio(signal: &[f32], fundamental_bin: usize) -> f64 {
+    let n = signal.len();
+    let twiddles: Vec<(f64, f64)> = (0..n)
+        .map(|k| {
+            let w = 2.0f64 * std::f64::consts::PI * k as f64 / n as f64;
+            (w.cos(), w.sin())
+        })
+        .collect();
+
+    let (mut harmonic, mut aliased) = (0.0f64, 0.0f64);
+    for bin in 1..n / 2 {
+        let (mut re, mut im) = (0.0f64, 0.0f64);
+        for (i, x) in signal.iter().enumerate() {
+            let (c, s) = twiddles[(bin * i) % n];
+            re += *x as f64 * c;
+            im -= *x as f64 * s;
+        }
+
+        if bin % fundamental_bin == 0 {
+            harmonic += re * re + im * im;
+        } else {
+            aliased += re * re + im * im;
+        }
+    }
+
+    aliased / harmonic
+}
+
+#[test]
+fn test_adaa_hard_clip_aliases_less_than_hard_clip() {
+    // ~4 kHz, so most of the clipper's harmonics are past nyquist
+    let dft_len = 4096;
+    let fundamental_bin = 373;
+    let input: Vec<f32> = (0..2 * dft_len)
+        .map(|i| {
+            let phase = ((fundamental_bin * i) % dft_len) as f32 / dft_len as f32;
+            0.9f32 * (2.0f32 * std::f32::consts::PI * phase).sin()
+        })
+        .collect();
+
+    let clipped_ratio = |function: i32| {
+        let mut otters = create_board(&mono_board_config(
+            "NonLinear/WaveShaper",
+            &format!(
+                r#"[
+                    {{"name": "waveshaper_function", "value": {{"N": {}}}}},
+                    {{"name": "gain", "value": {{"F": 8.0}}}}
+                ]"#,
+                function
+            ),
+        ));
+
+        // the first period is warm up
+        let output = process_mono(&mut otters, &input);
+        aliasing_to_harmonic_ratio(&output[dft_len..], fundamental_bin)
+    };
+
+    let plain = clipped_ratio(9);
+    let adaa = clipped_ratio(14);
+    let improvement_db = 10.0f64 * (plain / adaa).log10();
+    assert!(improvement_db > 6.0f64, "{} vs {}", plain, adaa);
+}
+
+fn bass_enhance(input: &[f32], blend: f32) -> Vec<f32> {
+    let mut otters = create_board(&mono_board_config(
+        "NonLinear/BassEnhance",
+        &format!(
+            r#"[
+                {{"name": "crossover_hz", "value": {{"F": 120.0}}}},
+                {{"name": "harmonics", "value": {{"N": 4}}}},
+                {{"name": "blend", "value": {{"F": {}}}}}
+            ]"#,
+            blend
+        ),
+    ));
+
+    process_mono(&mut otters, input)
+}
+
+#[test]
+fn test_bass_enhance_adds_harmonics_that_follow_the_bass() {
+    // 700 samples per period. the last 30 periods are well past the filters settling
+    let fundamental = 63.0f32;
+    let num_samples = 2 * TEST_SAMPLE_RATE as usize;
+    let settled = num_samples - 30 * 700;
+
+    let harmonic_levels = |amplitude: f32| -> Vec<f32> {
+        let output = bass_enhance(&sine(fundamental, amplitude, num_samples), 1.0f32);
+        (3..6)
+            .map(|k| magnitude_at(&output[settled..], k as f32 * fundamental))
+            .collect()
+    };
+
+    let quiet = harmonic_levels(0.1f32);
+    let loud = harmonic_levels(0.4f32);
+    for (q, l) in quiet.iter().zip(loud.iter()) {
+        // the input is a pure sine, so all of it is synthesized, and it scales with the bass
+        assert!(*q > 0.005f32, "{}", q);
+        assert!((l / q - 4.0f32).abs() < 0.2f32, "{} vs {}", l, q);
+    }
+
+    // with nothing blended in the input comes out untouched
+    let input = sine(fundamental, 0.4f32, num_samples);
+    assert_eq!(bass_enhance(&input, 0.0f32), input);
+
+    // nothing to do for a tone the speaker can already play
+    let output = bass_enhance(&sine(1000.0f32, 0.4f32, num_samples), 1.0f32);
+    assert!(magnitude_at(&output[settled..], 2000.0f32) < 1e-3f32);
+}
@@ -1,7 +1,9 @@
 #![cfg(test)]
 
+use super::test_utils::*;
 use crate::otters::Otters;
-use crate::conf::AudioConfig;
+use crate::conf::{AudioConfig, BoardEffectConfigParameterValue, DEFAULT_TEMPO_BPM};
+use crate::host::test_signal::TestSignal;
 
 use std::path::PathBuf;
 
@@ -21,6 +23,8 @@ fn test_load_basic() {
         AudioConfig {
             sample_rate: 44100.0f32,
             max_block_size: 32,
+            num_channels: 1,
+            tempo_bpm: DEFAULT_TEMPO_BPM,
         },
         &config_file.display().to_string(),
     );
@@ -29,3 +33,790 @@ fn test_load_basic() {
         assert!(false);
     }
 }
+
+#[test]
+fn test_wet_amount_blends_processed_and_dry() {
+    let input = sine(440.0f32, 0.8f32, 4 * TEST_BLOCK_SIZE + 17);
+
+    // a coarse crush is far enough from the input that a bad blend would show up
+    let crusher_config = r#"[{"name": "quantized_bit_depth", "value": {"N": 2}}]"#;
+    let mut processed_board =
+        create_board(&mono_board_config("NonLinear/BitCrusher", crusher_config));
+    let processed = process_mono(&mut processed_board, &input);
+
+    let half_wet_config = r#"[
+        {"name": "quantized_bit_depth", "value": {"N": 2}},
+        {"name": "wet_amount", "value": {"F": 0.5}}
+    ]"#;
+    let mut half_wet_board =
+        create_board(&mono_board_config("NonLinear/BitCrusher", half_wet_config));
+    let half_wet = process_mono(&mut half_wet_board, &input);
+
+    let difference: Vec<f32> = processed
+        .iter()
+        .zip(input.iter())
+        .map(|(p, x)| p - x)
+        .collect();
+    assert!(rms(&difference) > 0.01f32);
+
+    for i in 0..input.len() {
+        let expected = 0.5f32 * processed[i] + 0.5f32 * input[i];
+        assert!(
+            (half_wet[i] - expected).abs() < 1e-6f32,
+            "sample {}: {} != {}",
+            i,
+            half_wet[i],
+            expected
+        );
+    }
+
+    // wet_amount is the parameter right after the effect's own parameters
+    // the change ramps in over one block
+    half_wet_board.set_effect_parameter(1, BoardEffectConfigParameterValue::F(0.0f32));
+    process_mono(&mut half_wet_board, &input[..TEST_BLOCK_SIZE]);
+    let dry = process_mono(&mut half_wet_board, &input);
+    assert_eq!(dry, input);
+}
+
+#[test]
+fn test_wet_amount_lines_the_dry_signal_up_with_a_latent_effect() {
+    let mut otters = create_board(&mono_board_config(
+        "Utility/Delay",
+        r#"[
+            {"name": "delay_samples", "value": {"N": 100}},
+            {"name": "wet_amount", "value": {"F": 0.5}}
+        ]"#,
+    ));
+
+    // both halves of the impulse land together, so there's no comb filtering
+    let output = process_mono(&mut otters, &impulse(4 * TEST_BLOCK_SIZE));
+    for (i, x) in output.iter().enumerate() {
+        let expected = if i == 100 { 1.0f32 } else { 0.0f32 };
+        assert!((x - expected).abs() < 1e-6f32, "sample {}: {}", i, x);
+    }
+}
+
+#[test]
+fn test_wet_amount_changes_ramp_across_a_block() {
+    let crusher_config = r#"[
+        {"name": "quantized_bit_depth", "value": {"N": 2}},
+        {"name": "wet_amount", "value": {"F": 0.0}}
+    ]"#;
+    let input = sine(440.0f32, 0.8f32, TEST_BLOCK_SIZE);
+
+    let mut reference_board = create_board(&mono_board_config(
+        "NonLinear/BitCrusher",
+        r#"[{"name": "quantized_bit_depth", "value": {"N": 2}}]"#,
+    ));
+    let processed = process_mono(&mut reference_board, &input);
+
+    let mut otters = create_board(&mono_board_config("NonLinear/BitCrusher", crusher_config));
+    assert_eq!(process_mono(&mut otters, &input), input);
+
+    // going fully wet fades in over the next block instead of jumping
+    otters.set_effect_parameter(1, BoardEffectConfigParameterValue::F(1.0f32));
+    let faded = process_mono(&mut otters, &input);
+    for i in 0..TEST_BLOCK_SIZE {
+        let wet_gain = (i + 1) as f32 / TEST_BLOCK_SIZE as f32;
+        let expected = wet_gain * processed[i] + (1.0f32 - wet_gain) * input[i];
+        assert!(
+            (faded[i] - expected).abs() < 1e-5f32,
+            "sample {}: {} != {}",
+            i,
+            faded[i],
+            expected
+        );
+    }
+
+    assert_eq!(process_mono(&mut otters, &input), processed);
+}
+
+#[test]
+fn test_output_routing_sends_one_sink_to_two_outputs() {
+    let input = sine(440.0f32, 0.8f32, 3 * TEST_BLOCK_SIZE + 5);
+    let crusher_config = r#"[{"name": "quantized_bit_depth", "value": {"N": 3}}]"#;
+
+    let mut reference_board =
+        create_board(&mono_board_config("NonLinear/BitCrusher", crusher_config));
+    let processed = process_mono(&mut reference_board, &input);
+
+    // @SINK_0 goes to output 0 at unity and to output 1 at half level
+    let mut otters = create_board(&mono_board_config("NonLinear/BitCrusher", crusher_config));
+    otters.set_output_routing(vec![vec![1.0f32], vec![0.5f32]]);
+
+    let mut out_a = vec![0.0f32; input.len()];
+    let mut out_b = vec![0.0f32; input.len()];
+    let mut offset = 0;
+    while offset < input.len() {
+        let block_size = TEST_BLOCK_SIZE.min(input.len() - offset);
+        otters.bind_input(0, input[offset..].as_ptr());
+        otters.bind_output(0, out_a[offset..].as_mut_ptr());
+        otters.bind_output(1, out_b[offset..].as_mut_ptr());
+        otters.frolic(block_size);
+
+        offset += block_size;
+    }
+
+    assert_eq!(out_a, processed);
+    for i in 0..input.len() {
+        assert_eq!(out_b[i], 0.5f32 * processed[i]);
+    }
+}
+
+#[test]
+fn test_output_invert_and_swap() {
+    let left = sine(440.0f32, 0.5f32, 8 * TEST_BLOCK_SIZE);
+    let right = sine(1000.0f32, 0.25f32, 8 * TEST_BLOCK_SIZE);
+    let negated = |signal: &Vec<f32>| signal.iter().map(|x| -x).collect::<Vec<f32>>();
+
+    // a 0 degree rotation passes both channels through untouched
+    let mut otters = create_board(&stereo_board_config("Utility/StereoRotate", "[]"));
+
+    otters.set_output_invert(0, true);
+    let (out_left, out_right) = process_stereo(&mut otters, &left, &right);
+    assert_eq!(out_left, negated(&left));
+    assert_eq!(out_right, right);
+
+    // the swap happens first, the invert stays on output 0
+    otters.set_output_swap(true);
+    let (out_left, out_right) = process_stereo(&mut otters, &left, &right);
+    assert_eq!(out_left, negated(&right));
+    assert_eq!(out_right, left);
+
+    otters.set_output_invert(0, false);
+    let (out_left, out_right) = process_stereo(&mut otters, &left, &right);
+    assert_eq!(out_left, right);
+    assert_eq!(out_right, left);
+
+    otters.set_output_swap(false);
+    let (out_left, out_right) = process_stereo(&mut otters, &left, &right);
+    assert_eq!(out_left, left);
+    assert_eq!(out_right, right);
+}
+
+#[test]
+fn test_frolic_with_impulse_test_signal_gives_impulse_response() {
+    let num_samples = 4 * TEST_BLOCK_SIZE;
+
+    let mut reference_board = create_board(&mono_board_config("Filter/Biquad", "[]"));
+    let impulse_response = process_mono(&mut reference_board, &impulse(num_samples));
+    assert!(impulse_response[1..].iter().any(|x| *x != 0.0f32));
+
+    // nothing bound to @SOURCE_0, the test signal stands in for it
+    let mut otters = create_board(&mono_board_config("Filter/Biquad", "[]"));
+    let mut output = vec![0.0f32; num_samples];
+    let mut offset = 0;
+    while offset < num_samples {
+        otters.bind_output(0, output[offset..].as_mut_ptr());
+        otters.frolic_with_test_signal(TestSignal::Impulse, TEST_BLOCK_SIZE);
+
+        offset += TEST_BLOCK_SIZE;
+    }
+
+    assert_eq!(output, impulse_response);
+}
+
+#[test]
+fn test_binding_high_source_and_sink_indices() {
+    let mut otters = create_board(
+        r#"{
+            "buffers": ["@SOURCE_8", "@SINK_9"],
+            "effects": [
+                {"bind_name": "fx", "effect_name": "Bypass/Mono", "config": [], "enabled": true}
+            ],
+            "connections": [
+                {"effect": "fx", "reads": ["@SOURCE_8"], "writes": ["@SINK_9"]}
+            ]
+        }"#,
+    );
+
+    let input = sine(440.0f32, 0.5f32, TEST_BLOCK_SIZE);
+    let mut output = vec![0.0f32; TEST_BLOCK_SIZE];
+    otters.bind_input(8, input.as_ptr());
+    otters.bind_output(9, output.as_mut_ptr());
+    otters.frolic(TEST_BLOCK_SIZE);
+
+    assert_eq!(output, input);
+}
+
+#[test]
+fn test_disabled_effect_zeroes_only_its_own_extra_outputs() {
+    // the compressor is off, so it runs as a generic bypass: its meter output has no input
+    // to copy and should come out silent. "b" is internal buffer 1, the meter's output slot
+    let mut otters = create_board(
+        r#"{
+            "buffers": ["@SOURCE_0", "a", "b", "@SINK_0", "@SINK_1", "@SINK_2"],
+            "effects": [
+                {"bind_name": "to_a", "effect_name": "Bypass/Mono", "config": [], "enabled": true},
+                {"bind_name": "to_b", "effect_name": "Bypass/Mono", "config": [], "enabled": true},
+                {"bind_name": "fx", "effect_name": "Dynamics/BasicCompressor", "config": [], "enabled": false},
+                {"bind_name": "from_b", "effect_name": "Bypass/Mono", "config": [], "enabled": true}
+            ],
+            "connections": [
+                {"effect": "to_a", "reads": ["@SOURCE_0"], "writes": ["a"]},
+                {"effect": "to_b", "reads": ["a"], "writes": ["b"]},
+                {"effect": "fx", "reads": ["@SOURCE_0"], "writes": ["@SINK_0", "@SINK_1"]},
+                {"effect": "from_b", "reads": ["b"], "writes": ["@SINK_2"]}
+            ]
+        }"#,
+    );
+
+    let input = sine(440.0f32, 0.5f32, TEST_BLOCK_SIZE);
+    let mut outputs = vec![vec![1.0f32; TEST_BLOCK_SIZE]; 3];
+    otters.bind_input(0, input.as_ptr());
+    for (i, output) in outputs.iter_mut().enumerate() {
+        otters.bind_output(i, output.as_mut_ptr());
+    }
+    otters.frolic(TEST_BLOCK_SIZE);
+
+    assert_eq!(outputs[0], input);
+    assert!(outputs[1].iter().all(|x| *x == 0.0f32));
+    assert_eq!(outputs[2], input);
+}
+
+fn const_board_config(read_target: &str, write_target: &str) -> String {
+    format!(
+        r#"{{
+            "buffers": ["@CONST_0.5", "@CONST_2", "@SINK_0"],
+            "effects": [
+                {{"bind_name": "fx", "effect_name": "Bypass/Mono", "config": [], "enabled": true}}
+            ],
+            "connections": [
+                {{"effect": "fx", "reads": ["{}"], "writes": ["{}"]}}
+            ]
+        }}"#,
+        read_target, write_target
+    )
+}
+
+#[test]
+fn test_const_buffer_reads_as_constant() {
+    // odd block sizes so reads don't line up with the buffer size
+    let mut otters = create_board(&const_board_config("@CONST_0.5", "@SINK_0"));
+    let output = process_mono(&mut otters, &vec![0.0f32; 5 * TEST_BLOCK_SIZE + 3]);
+
+    assert!(output.iter().all(|x| *x == 0.5f32));
+}
+
+#[test]
+fn test_const_buffer_rejects_writes_and_bad_values() {
+    let config = const_board_config("@CONST_0.5", "@CONST_2");
+    let err = match Otters::create_default_from_string(test_audio_config(), &config) {
+        Ok(_) => panic!("writing a constant buffer should fail"),
+        Err(err) => format!("{:?}", err),
+    };
+    assert!(err.contains("@CONST_2"), "{}", err);
+
+    let config = const_board_config("@CONST_0.5", "@SINK_0").replace("0.5", "abc");
+    assert!(Otters::create_default_from_string(test_audio_config(), &config).is_err());
+}
+
+#[test]
+fn test_manual_latency_overrides_computed_latency() {
+    // two vocoders in series, a bypass next to them doesn't add anything
+    let config = r#"{
+        "buffers": ["@SOURCE_0", "@SINK_0", "@SINK_1", "mid"],
+        "effects": [
+            {"bind_name": "voc1", "effect_name": "Vocoder/Bypass", "config": [], "enabled": true},
+            {"bind_name": "voc2", "effect_name": "Vocoder/Bypass", "config": [], "enabled": true},
+            {"bind_name": "dry", "effect_name": "Bypass/Mono", "config": [], "enabled": true}
+        ],
+        "connections": [
+            {"effect": "voc1", "reads": ["@SOURCE_0"], "writes": ["mid"]},
+            {"effect": "voc2", "reads": ["mid"], "writes": ["@SINK_0"]},
+            {"effect": "dry", "reads": ["@SOURCE_0"], "writes": ["@SINK_1"]}
+        ]
+    }"#;
+
+    let mut otters = create_board(config);
+    assert_eq!(otters.total_latency_samples(), 2 * 1024);
+
+    otters.set_manual_latency(Some(512));
+    assert_eq!(otters.total_latency_samples(), 512);
+
+    otters.set_manual_latency(None);
+    assert_eq!(otters.total_latency_samples(), 2 * 1024);
+
+    // and through the c interface
+    let otters_ptr = Box::into_raw(Box::new(otters));
+    crate::ffi::otters_set_manual_latency(otters_ptr, 300);
+    assert_eq!(crate::ffi::otters_get_latency_samples(otters_ptr), 300);
+
+    crate::ffi::otters_set_manual_latency(otters_ptr, -1);
+    assert_eq!(crate::ffi::otters_get_latency_samples(otters_ptr), 2 * 1024);
+    crate::ffi::otters_bye(otters_ptr);
+}
+
+#[test]
+fn test_configured_parameters_survive_an_audio_config_change() {
+    let mut otters = create_board(&mono_board_config(
+        "Delay/Basic",
+        r#"[
+            {"name": "delay_time_ms", "value": {"F": 500.0}},
+            {"name": "wet_dry_pct", "value": {"F": 1.0}},
+            {"name": "wet_amount", "value": {"F": 0.5}}
+        ]"#,
+    ));
+
+    otters
+        .update_audio_config(AudioConfig {
+            sample_rate: TEST_SAMPLE_RATE,
+            max_block_size: 2 * TEST_BLOCK_SIZE,
+            num_channels: 1,
+            tempo_bpm: DEFAULT_TEMPO_BPM,
+        })
+        .unwrap();
+
+    // the default delay is a second, and with a wet_amount of 1 there'd be no dry impulse
+    let output = process_mono(&mut otters, &impulse(32768));
+    assert!((output[0] - 0.5f32).abs() < 1e-6f32);
+    let echo = (1..output.len())
+        .find(|i| output[*i].abs() > 0.25f32)
+        .unwrap();
+    assert!(echo >= 22049 && echo <= 22051, "echo at {}", echo);
+
+    // the rebuilt parameter manager still knows each bind name's effect type
+    let session_info = otters.setup_async_param_updater().get_session_info_json();
+    assert!(session_info.contains("Delay/Basic"), "{}", session_info);
+}
+
+#[test]
+fn test_runtime_parameters_survive_an_audio_config_change() {
+    let crusher = mono_board_config("NonLinear/BitCrusher", "[]");
+    let mut otters = create_board(&crusher);
+    otters.set_effect_parameter(0, BoardEffectConfigParameterValue::N(2));
+    otters.set_effect_parameter(1, BoardEffectConfigParameterValue::F(0.25f32));
+
+    // a tempo change goes through here too
+    otters
+        .update_audio_config(AudioConfig {
+            tempo_bpm: 2.0f32 * DEFAULT_TEMPO_BPM,
+            ..test_audio_config()
+        })
+        .unwrap();
+
+    assert_eq!(otters.get_effect_parameter(0).unwrap().as_int(), 2);
+    assert_eq!(otters.get_effect_parameter(1).unwrap().as_flt(), 0.25f32);
+
+    let mut expected = create_board(&mono_board_config(
+        "NonLinear/BitCrusher",
+        r#"[
+            {"name": "quantized_bit_depth", "value": {"N": 2}},
+            {"name": "wet_amount", "value": {"F": 0.25}}
+        ]"#,
+    ));
+    let input = sine(440.0f32, 0.5f32, 4 * TEST_BLOCK_SIZE);
+    assert_eq!(process_mono(&mut otters, &input), process_mono(&mut expected, &input));
+}
+
+#[test]
+fn test_restoring_a_snapshot_continues_where_it_left_off() {
+    let config = r#"{
+        "buffers": ["@SOURCE_0", "@SINK_0", "a", "b"],
+        "effects": [
+            {"bind_name": "delay", "effect_name": "Delay/Basic", "config": [
+                {"name": "delay_time_ms", "value": {"F": 7.3}},
+                {"name": "feedback_pct", "value": {"F": 0.7}}
+            ], "enabled": true},
+            {"bind_name": "filter", "effect_name": "Filter/Biquad", "config": [
+                {"name": "filter_type", "value": {"N": 3}}
+            ], "enabled": true},
+            {"bind_name": "comp", "effect_name": "Dynamics/BasicCompressor", "config": [
+                {"name": "threshold_db", "value": {"F": -20.0}}
+            ], "enabled": true}
+        ],
+        "connections": [
+            {"effect": "delay", "reads": ["@SOURCE_0"], "writes": ["a"]},
+            {"effect": "filter", "reads": ["a"], "writes": ["b"]},
+            {"effect": "comp", "reads": ["b"], "writes": ["@SINK_0"]}
+        ]
+    }"#;
+
+    let input = sine(330.0f32, 0.8f32, 40 * TEST_BLOCK_SIZE);
+    let (first_half, second_half) = input.split_at(input.len() / 2);
+
+    let mut uninterrupted = create_board(config);
+    process_mono(&mut uninterrupted, first_half);
+    let expected = process_mono(&mut uninterrupted, second_half);
+
+    let mut otters = create_board(config);
+    process_mono(&mut otters, first_half);
+    let state = otters.serialize_state().unwrap();
+
+    for bind_name in &["delay", "filter", "comp"] {
+        assert!(otters.reset_effect(bind_name));
+    }
+
+    assert!(otters.deserialize_state(&state));
+    assert_eq!(process_mono(&mut otters, second_half), expected);
+
+    // a fresh board from the same config picks up the snapshot too
+    let mut fresh = create_board(config);
+    assert!(fresh.deserialize_state(&state));
+    assert_eq!(process_mono(&mut fresh, second_half), expected);
+
+    // truncated snapshots are rejected
+    assert!(!fresh.deserialize_state(&state[..state.len() - 1]));
+
+    // and boards with an effect that can't snapshot don't produce one
+    let vocoder = create_board(&mono_board_config("Vocoder/Bypass", "[]"));
+    assert!(vocoder.serialize_state().is_none());
+}
+
+#[test]
+fn test_async_param_updates_are_applied_on_next_frolic() {
+    let input = sine(440.0f32, 0.8f32, 4 * TEST_BLOCK_SIZE);
+
+    let crusher_config = r#"[{"name": "quantized_bit_depth", "value": {"N": 2}}]"#;
+    let mut crushed_board =
+        create_board(&mono_board_config("NonLinear/BitCrusher", crusher_config));
+    let crushed = process_mono(&mut crushed_board, &input);
+
+    let mut otters = create_board(&mono_board_config("NonLinear/BitCrusher", "[]"));
+    let updater = otters.setup_async_param_updater();
+
+    // wet_amount (global idx 1) to dry. an index past the end is dropped, not a panic
+    updater.set_flt_param_value(1, 0.0f32);
+    updater.set_flt_param_value(999, 0.0f32);
+    assert_eq!(process_mono(&mut otters, &input), input);
+
+    // quantized_bit_depth is global idx 0
+    updater.set_int_param_value(0, 2);
+    updater.set_flt_param_value(1, 1.0f32);
+    assert_eq!(process_mono(&mut otters, &input), crushed);
+}
+
+#[test]
+fn test_parameter_values_can_be_read_back() {
+    let crusher_config = r#"[{"name": "quantized_bit_depth", "value": {"N": 4}}]"#;
+    let mut otters = create_board(&mono_board_config("NonLinear/BitCrusher", crusher_config));
+
+    // quantized_bit_depth (global idx 0) comes from the config, wet_amount (1) is the default
+    assert_eq!(otters.get_effect_parameter(0).unwrap().as_int(), 4);
+    assert_eq!(otters.get_effect_parameter(1).unwrap().as_flt(), 1.0f32);
+    assert!(otters.get_effect_parameter(2).is_none());
+
+    // out of range array writes, and array writes to wet_amount, are ignored
+    otters.set_effect_parameter_array(2, &[0.5f32]);
+    otters.set_effect_parameter_array(1, &[0.5f32]);
+    assert_eq!(otters.get_effect_parameter(1).unwrap().as_flt(), 1.0f32);
+
+    // wet_amount reads back clamped, like it's applied
+    otters.set_effect_parameter(1, BoardEffectConfigParameterValue::F(1.5f32));
+    assert_eq!(otters.get_effect_parameter(1).unwrap().as_flt(), 1.0f32);
+
+    let updater = otters.setup_async_param_updater();
+    assert_eq!(updater.get_int_param_value(0), 4);
+
+    updater.set_int_param_value(0, 2);
+    updater.set_flt_param_value(1, 0.25f32);
+    assert_eq!(updater.get_int_param_value(0), 2);
+    assert_eq!(updater.get_flt_param_value(1), 0.25f32);
+    assert_eq!(updater.get_flt_param_value(999), 0.0f32);
+
+    // the board catches up on the next block
+    process_mono(&mut otters, &sine(440.0f32, 0.5f32, TEST_BLOCK_SIZE));
+    assert_eq!(otters.get_effect_parameter(0).unwrap().as_int(), 2);
+    assert_eq!(otters.get_effect_parameter(1).unwrap().as_flt(), 0.25f32);
+}
+
+#[test]
+fn test_session_info_names_each_effects_type() {
+    let mut otters = create_board(&format!(
+        r#"{{"buffers": ["@SOURCE_0", "filtered", "@SINK_0"], {}}}"#,
+        FILTER_THEN_DELAY
+    ));
+
+    let session_info: serde_json::Value =
+        serde_json::from_str(&otters.setup_async_param_updater().get_session_info_json()).unwrap();
+    let echo = &session_info["infos"]["echo"];
+    assert_eq!(echo["effect_name"], "Delay/Basic");
+    assert_eq!(session_info["infos"]["lpf"]["effect_name"], "Filter/Biquad");
+
+    // ["name", global idx] pairs, ending with the board's wet_amount
+    let global_idxs = echo["global_idxs"].as_array().unwrap();
+    assert_eq!(global_idxs.last().unwrap()[0], "wet_amount");
+}
+
+#[test]
+fn test_disabling_an_effect_bypasses_it_and_drops_its_tail() {
+    let mut otters = create_board(&mono_board_config(
+        "Delay/Basic",
+        r#"[
+            {"name": "delay_time_ms", "value": {"F": 10.0}},
+            {"name": "feedback_pct", "value": {"F": 0.5}}
+        ]"#,
+    ));
+    assert!(!otters.set_effect_enabled("nope", false));
+
+    // leave some echoes in the delay line, then switch it off
+    process_mono(&mut otters, &impulse(256));
+    assert!(otters.set_effect_enabled("fx", false));
+
+    let input = sine(440.0f32, 0.5f32, 4 * TEST_BLOCK_SIZE);
+    assert_eq!(process_mono(&mut otters, &input), input);
+
+    // back on, there's nothing left of the old impulse
+    assert!(otters.set_effect_enabled("fx", true));
+    let silence = vec![0.0f32; 2048];
+    assert_eq!(peak(&process_mono(&mut otters, &silence)), 0.0f32);
+}
+
+const FILTER_THEN_DELAY: &str = r#"
+    "effects": [
+        {"bind_name": "lpf", "effect_name": "Filter/Biquad", "enabled": true, "config": [
+            {"name": "corner_freq_hz", "value": {"F": 800.0}}
+        ]},
+        {"bind_name": "echo", "effect_name": "Delay/Basic", "enabled": true, "config": [
+            {"name": "delay_time_ms", "value": {"F": 5.0}},
+            {"name": "feedback_pct", "value": {"F": 0.4}}
+        ]}
+    ],
+    "connections": [
+        {"effect": "lpf", "reads": ["@SOURCE_0"], "writes": ["filtered"]},
+        {"effect": "echo", "reads": ["filtered"], "writes": ["@SINK_0"]}
+    ]
+"#;
+
+#[test]
+fn test_sub_board_processes_like_the_flat_board() {
+    let mut flat = create_board(&format!(
+        r#"{{"buffers": ["@SOURCE_0", "filtered", "@SINK_0"], {}}}"#,
+        FILTER_THEN_DELAY
+    ));
+
+    let mut nested = create_board(&format!(
+        r#"{{
+            "buffers": ["@SOURCE_0", "@SINK_0"],
+            "sub_boards": {{
+                "chain": {{"buffers": ["@SOURCE_0", "filtered", "@SINK_0"], {}}}
+            }},
+            "effects": [
+                {{"bind_name": "fx", "effect_name": "SubBoard/chain", "config": [], "enabled": true}}
+            ],
+            "connections": [
+                {{"effect": "fx", "reads": ["@SOURCE_0"], "writes": ["@SINK_0"]}}
+            ]
+        }}"#,
+        FILTER_THEN_DELAY
+    ));
+
+    let input = sine(1000.0f32, 0.5f32, 8 * TEST_BLOCK_SIZE);
+    let flat_output = process_mono(&mut flat, &input);
+    let nested_output = process_mono(&mut nested, &input);
+
+    assert!(rms(&flat_output) > 0.01f32);
+    assert_eq!(nested_output, flat_output);
+    assert_eq!(nested.total_latency_samples(), flat.total_latency_samples());
+}
+
+#[test]
+fn test_snapshot_covers_a_phaser_and_a_sub_board() {
+    let config = r#"{
+        "buffers": ["@SOURCE_0", "phased", "@SINK_0"],
+        "sub_boards": {
+            "inner": {
+                "buffers": ["@SOURCE_0", "@SINK_0"],
+                "effects": [
+                    {"bind_name": "phaser", "effect_name": "Modulation/Phaser", "config": [
+                        {"name": "mod_rate_hz", "value": {"F": 3.0}}
+                    ], "enabled": true}
+                ],
+                "connections": [
+                    {"effect": "phaser", "reads": ["@SOURCE_0"], "writes": ["@SINK_0"]}
+                ]
+            }
+        },
+        "effects": [
+            {"bind_name": "phaser", "effect_name": "Modulation/Phaser", "config": [], "enabled": true},
+            {"bind_name": "fx", "effect_name": "SubBoard/inner", "config": [], "enabled": true}
+        ],
+        "connections": [
+            {"effect": "phaser", "reads": ["@SOURCE_0"], "writes": ["phased"]},
+            {"effect": "fx", "reads": ["phased"], "writes": ["@SINK_0"]}
+        ]
+    }"#;
+
+    let input = sine(440.0f32, 0.5f32, 40 * TEST_BLOCK_SIZE);
+    let (first_half, second_half) = input.split_at(input.len() / 2);
+
+    let mut uninterrupted = create_board(config);
+    process_mono(&mut uninterrupted, first_half);
+    let expected = process_mono(&mut uninterrupted, second_half);
+
+    let mut otters = create_board(config);
+    process_mono(&mut otters, first_half);
+    let state = otters.serialize_state().unwrap();
+
+    // the lfos are mid sweep, so a fresh board only matches once it has the snapshot
+    let mut fresh = create_board(config);
+    assert!(process_mono(&mut create_board(config), second_half) != expected);
+    assert!(fresh.deserialize_state(&state));
+    assert_eq!(process_mono(&mut fresh, second_half), expected);
+}
+
+#[test]
+fn test_unknown_sub_board_fails_to_load() {
+    let result = Otters::create_default_from_string(
+        test_audio_config(),
+        r#"{
+            "buffers": ["@SOURCE_0", "@SINK_0"],
+            "effects": [
+                {"bind_name": "fx", "effect_name": "SubBoard/missing", "config": [], "enabled": true}
+            ],
+            "connections": [
+                {"effect": "fx", "reads": ["@SOURCE_0"], "writes": ["@SINK_0"]}
+            ]
+        }"#,
+    );
+
+    assert!(result.is_err());
+}
+
+fn side_by_side_board_config(first: &str, second: &str) -> String {
+    let connection = |bind_name: &str| {
+        let (read, write) = if bind_name == "lpf" {
+            ("@SOURCE_0", "@SINK_0")
+        } else {
+            ("@SOURCE_1", "@SINK_1")
+        };
+        format!(
+            r#"{{"effect": "{}", "reads": ["{}"], "writes": ["{}"]}}"#,
+            bind_name, read, write
+        )
+    };
+
+    format!(
+        r#"{{
+            "buffers": ["@SOURCE_0", "@SOURCE_1", "@SINK_0", "@SINK_1"],
+            "effects": [
+                {{"bind_name": "lpf", "effect_name": "Filter/Biquad", "enabled": true, "config": []}},
+                {{"bind_name": "echo", "effect_name": "Delay/Basic", "enabled": true, "config": []}}
+            ],
+            "connections": [{}, {}]
+        }}"#,
+        connection(first),
+        connection(second)
+    )
+}
+
+#[test]
+fn test_processing_order_override() {
+    let left = sine(440.0f32, 0.5f32, 8 * TEST_BLOCK_SIZE);
+    let right = sine(1000.0f32, 0.25f32, 8 * TEST_BLOCK_SIZE);
+
+    // the two effects don't share any buffers, so they can go in either order
+    let mut reversed = create_board(&side_by_side_board_config("echo", "lpf"));
+    let expected = process_stereo(&mut reversed, &left, &right);
+
+    let mut otters = create_board(&side_by_side_board_config("lpf", "echo"));
+    assert!(otters.set_processing_order(&["echo", "lpf"]));
+    assert_eq!(process_stereo(&mut otters, &left, &right), expected);
+
+    // unknown, repeated or missing effects are turned away
+    assert!(!otters.set_processing_order(&["echo", "nope"]));
+    assert!(!otters.set_processing_order(&["echo", "echo"]));
+    assert!(!otters.set_processing_order(&["echo"]));
+    assert!(otters.set_processing_order(&[]));
+
+    // the echo reads what the filter writes, so it can't go first
+    let mut chain = create_board(&format!(
+        r#"{{"buffers": ["@SOURCE_0", "filtered", "@SINK_0"], {}}}"#,
+        FILTER_THEN_DELAY
+    ));
+    let mut reference = create_board(&format!(
+        r#"{{"buffers": ["@SOURCE_0", "filtered", "@SINK_0"], {}}}"#,
+        FILTER_THEN_DELAY
+    ));
+    assert!(!chain.set_processing_order(&["echo", "lpf"]));
+    assert!(chain.set_processing_order(&["lpf", "echo"]));
+    assert_eq!(
+        process_mono(&mut chain, &left),
+        process_mono(&mut reference, &left)
+    );
+}
+
+fn ducking_board_config(ducking: &str) -> String {
+    format!(
+        r#"{{
+            "buffers": ["@SOURCE_0", "@SOURCE_1", "@SINK_0", "@SINK_1"],
+            "effects": [
+                {{"bind_name": "music", "effect_name": "Bypass/Mono", "config": [], "enabled": true}},
+                {{"bind_name": "voice", "effect_name": "Bypass/Mono", "config": [], "enabled": true}}
+            ],
+            "connections": [
+                {{"effect": "music", "reads": ["@SOURCE_0"], "writes": ["@SINK_0"]}},
+                {{"effect": "voice", "reads": ["@SOURCE_1"], "writes": ["@SINK_1"]}}
+            ],
+            "ducking": [{}]
+        }}"#,
+        ducking
+    )
+}
+
+#[test]
+fn test_ducking_turns_the_target_down_while_the_key_is_loud() {
+    let half = TEST_SAMPLE_RATE as usize;
+    let music = sine(440.0f32, 0.5f32, 2 * half);
+
+    // the voice talks over the first half, then goes quiet
+    let mut voice = sine(200.0f32, 0.5f32, 2 * half);
+    for x in voice[half..].iter_mut() {
+        *x = 0.0f32;
+    }
+
+    let mut otters = create_board(&ducking_board_config(
+        r#"{"key": "@SOURCE_1", "target": "music", "amount_db": 12.0, "release_ms": 100.0}"#,
+    ));
+    let (ducked, voice_out) = process_stereo(&mut otters, &music, &voice);
+
+    // the key itself goes through untouched
+    assert_eq!(voice_out, voice);
+
+    let music_rms = rms(&music[half / 2..half]);
+    let ducked_db = 20.0f32 * (rms(&ducked[half / 2..half]) / music_rms).log10();
+    assert!((ducked_db + 12.0f32).abs() < 0.1f32, "{}", ducked_db);
+
+    // and the music comes back once the voice stops
+    let released_db = 20.0f32 * (rms(&ducked[3 * half / 2..]) / music_rms).log10();
+    assert!(released_db.abs() < 0.1f32, "{}", released_db);
+
+    // only real buffers and effects can be used
+    assert!(Otters::create_default_from_string(
+        test_audio_config(),
+        &ducking_board_config(r#"{"key": "@SINK_1", "target": "music", "amount_db": 12.0}"#),
+    )
+    .is_err());
+    assert!(Otters::create_default_from_string(
+        test_audio_config(),
+        &ducking_board_config(r#"{"key": "@SOURCE_1", "target": "nope", "amount_db": 12.0}"#),
+    )
+    .is_err());
+}
+
+#[test]
+fn test_reset_all_sounds_like_a_fresh_board() {
+    let stale = sine(220.0f32, 0.9f32, 64 * TEST_BLOCK_SIZE);
+    let input = sine(1234.0f32, 0.3f32, 64 * TEST_BLOCK_SIZE);
+
+    for effect_name in &[
+        "Filter/Biquad",
+        "Filter/Formant",
+        "Dynamics/BasicCompressor",
+        "Vocoder/Bypass",
+        "Vocoder/SpectralCompressor",
+        "Vocoder/Freeze",
+        "PitchShifter/Ocean",
+    ] {
+        let config = mono_board_config(effect_name, "[]");
+
+        let mut fresh = create_board(&config);
+        let expected = process_mono(&mut fresh, &input);
+
+        // whatever the first signal left behind is gone after the reset
+        let mut otters = create_board(&config);
+        process_mono(&mut otters, &stale);
+        otters.reset_all();
+        assert_eq!(process_mono(&mut otters, &input), expected, "{}", effect_name);
+    }
+}
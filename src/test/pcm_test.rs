@@ -0,0 +1,21 @@
+#![cfg(test)]
+
+use crate::host::pcm::int_samples_to_float;
+
+#[test]
+fn test_int_to_float_full_scale_and_clip_warning() {
+    let clean = int_samples_to_float(&[0, 16384, -16384, 32000], 16);
+    assert_eq!(clean.clipped_samples, 0);
+    assert!(clean.clip_warning().is_none());
+
+    let clipped = int_samples_to_float(&[32767, -32768, 12, 32767, 40000], 16);
+    assert_eq!(clipped.samples[0], 1.0f32);
+    assert_eq!(clipped.samples[1], -1.0f32);
+
+    // out of range input saturates (and counts as clipped)
+    assert_eq!(clipped.samples[4], 1.0f32);
+    assert_eq!(clipped.clipped_samples, 4);
+
+    let warning = clipped.clip_warning().expect("expected a clip warning");
+    assert!(warning.contains("4 of 5"), "{}", warning);
+}
@@ -0,0 +1,301 @@
+#![cfg(test)]
+
+use super::test_utils::*;
+use crate::utils::fast_rand::WyHashPRNG;
+
+fn cents_to_ratio(cents: f32) -> f32 {
+    2.0f32.powf(cents / 1200.0f32)
+}
+
+#[test]
+fn test_unison_adds_detuned_peaks_around_each_partial() {
+    let mut otters = create_board(&mono_board_config(
+        "Pitch/Unison",
+        r#"[
+            {"name": "voices", "value": {"N": 2}},
+            {"name": "detune_cents", "value": {"F": 20.0}},
+            {"name": "mix", "value": {"F": 0.5}}
+        ]"#,
+    ));
+
+    let num_samples = 2 * TEST_SAMPLE_RATE as usize;
+    let low = sine(1000.0f32, 0.5f32, num_samples);
+    let high = sine(3000.0f32, 0.5f32, num_samples);
+    let input: Vec<f32> = low.iter().zip(high.iter()).map(|(l, h)| l + h).collect();
+
+    let output = process_mono(&mut otters, &input);
+    let settled = &output[TEST_SAMPLE_RATE as usize / 2..];
+
+    for partial in &[1000.0f32, 3000.0f32] {
+        let sharp = partial * cents_to_ratio(20.0f32);
+        let flat = partial * cents_to_ratio(-20.0f32);
+
+        // the dry partial plus one peak on either side of it. each voice gets half the wet level
+        let dry = magnitude_at(settled, *partial);
+        let sharp_level = magnitude_at(settled, sharp);
+        let flat_level = magnitude_at(settled, flat);
+        assert!((dry - 0.25f32).abs() < 0.02f32, "{} dry {}", partial, dry);
+        assert!(sharp_level > 0.08f32, "{} sharp {}", partial, sharp_level);
+        assert!(flat_level > 0.08f32, "{} flat {}", partial, flat_level);
+
+        // and not much in the gaps between them
+        let between = magnitude_at(settled, partial * cents_to_ratio(10.0f32))
+            .max(magnitude_at(settled, partial * cents_to_ratio(-10.0f32)));
+        assert!(
+            between < 0.25f32 * sharp_level.min(flat_level),
+            "{} between {}",
+            partial,
+            between
+        );
+    }
+}
+
+fn ocean_shift_up_a_semitone(bin_mapping: i32, freq: f32) -> Vec<f32> {
+    let mut otters = create_board(&mono_board_config(
+        "PitchShifter/Ocean",
+        &format!(
+            r#"[
+                {{"name": "semitone_difference", "value": {{"N": 1}}}},
+                {{"name": "bin_mapping", "value": {{"N": {}}}}}
+            ]"#,
+            bin_mapping
+        ),
+    ));
+
+    let output = process_mono(&mut otters, &sine(freq, 0.5f32, TEST_SAMPLE_RATE as usize));
+    output[TEST_SAMPLE_RATE as usize / 4..].to_vec()
+}
+
+// magnitude weighted mean frequency within width Hz of freq
+fn spectral_centroid_near(signal: &[f32], freq: f32, width: f32) -> f32 {
+    let n = signal.len() as f32;
+    let windowed: Vec<f32> = signal
+        .iter()
+        .enumerate()
+        .map(|(i, x)| x * (0.5f32 - 0.5f32 * (2.0f32 * std::f32::consts::PI * i as f32 / n).cos()))
+        .collect();
+
+    let (mut weighted, mut total) = (0.0f32, 0.0f32);
+    let mut f = freq - width;
+    while f <= freq + width {
+        let magnitude = magnitude_at(&windowed, f);
+        weighted += magnitude * f;
+        total += magnitude;
+        f += 1.0f32;
+    }
+
+    weighted / total
+}
+
+#[test]
+fn test_ocean_shifted_bins_come_out_at_their_destination_frequency() {
+    // bin 20 a semitone up is bin 21.19, which nearest mapping rounds to 21. with the phase
+    // rotated the wrong way (or over the wrong cycle) the shifted tone came out flat of it
+    let bin_hz = TEST_SAMPLE_RATE / 1024.0f32;
+    let shifted = ocean_shift_up_a_semitone(0, 20.0f32 * bin_hz);
+
+    let centroid = spectral_centroid_near(&shifted, 21.0f32 * bin_hz, 60.0f32);
+    assert!(
+        (centroid - 21.0f32 * bin_hz).abs() < 1.0f32,
+        "{} vs {}",
+        centroid,
+        21.0f32 * bin_hz
+    );
+}
+
+#[test]
+fn test_ocean_interpolated_bin_mapping_is_closer_in_pitch() {
+    // right on bin 10, so a semitone up lands between bins 10 and 11
+    let freq = 10.0f32 * TEST_SAMPLE_RATE / 1024.0f32;
+    let target = freq * 2.0f32.powf(1.0f32 / 12.0f32);
+
+    let nearest = spectral_centroid_near(&ocean_shift_up_a_semitone(0, freq), target, 60.0f32);
+    let interpolated = spectral_centroid_near(&ocean_shift_up_a_semitone(1, freq), target, 60.0f32);
+
+    // rounding to bin 11 is 0.4 bins (17 Hz) sharp
+    assert!(
+        (nearest - 11.0f32 * TEST_SAMPLE_RATE / 1024.0f32).abs() < 1.0f32,
+        "{}",
+        nearest
+    );
+    assert!(
+        (interpolated - target).abs() < 2.0f32,
+        "{} vs {}",
+        interpolated,
+        target
+    );
+}
+
+// decaying noise bursts (a few ms, like a rimshot) every hit_spacing samples
+fn percussive_hits(num_samples: usize, hit_spacing: usize) -> Vec<f32> {
+    let prng = WyHashPRNG::new(0x6869_7473);
+    let decay = (-1.0f32 / (0.002f32 * TEST_SAMPLE_RATE)).exp();
+
+    let mut signal = vec![0.0f32; num_samples];
+    for start in (0..num_samples).step_by(hit_spacing) {
+        let mut level = 0.8f32;
+        for x in signal[start..].iter_mut().take(hit_spacing / 4) {
+            *x = level * ((prng.next() % 2001) as f32 / 1000.0f32 - 1.0f32);
+            level *= decay;
+        }
+    }
+
+    signal
+}
+
+fn ocean_shift_hits(phase_locking: i32, input: &[f32]) -> Vec<f32> {
+    let mut otters = create_board(&mono_board_config(
+        "PitchShifter/Ocean",
+        &format!(
+            r#"[
+                {{"name": "semitone_difference", "value": {{"N": 5}}}},
+                {{"name": "phase_locking", "value": {{"N": {}}}}}
+            ]"#,
+            phase_locking
+        ),
+    ));
+
+    process_mono(&mut otters, input)
+}
+
+// the share of each hit's energy that lands in its loudest window_size samples, averaged
+fn energy_concentration(signal: &[f32], hit_spacing: usize, window_size: usize) -> f32 {
+    let hits: Vec<f32> = signal
+        .chunks(hit_spacing)
+        .filter(|hit| hit.len() == hit_spacing)
+        .map(|hit| {
+            let energies: Vec<f32> = hit.iter().map(|x| x * x).collect();
+            let total: f32 = energies.iter().sum();
+            let loudest = energies
+                .windows(window_size)
+                .map(|window| window.iter().sum::<f32>())
+                .fold(0.0f32, f32::max);
+
+            loudest / total
+        })
+        .collect();
+
+    hits.iter().sum::<f32>() / hits.len() as f32
+}
+
+#[test]
+fn test_ocean_phase_locking_smears_transients_less() {
+    let hit_spacing = 4096;
+    let input = percussive_hits(16 * hit_spacing, hit_spacing);
+
+    // skip the first hit while the vocoder fills up. a hop (256) is about where the unlocked
+    // shifter starts smearing each hit out
+    let unlocked = ocean_shift_hits(0, &input);
+    let locked = ocean_shift_hits(1, &input);
+    let unlocked_concentration = energy_concentration(&unlocked[hit_spacing..], hit_spacing, 256);
+    let locked_concentration = energy_concentration(&locked[hit_spacing..], hit_spacing, 256);
+
+    assert!(
+        locked_concentration > unlocked_concentration + 0.1f32,
+        "locked {} unlocked {}",
+        locked_concentration,
+        unlocked_concentration
+    );
+}
+
+const VOICE_F0: f32 = 150.0f32;
+const VOICE_FORMANT_HZ: f32 = 900.0f32;
+const VOICE_MAX_FREQ: f32 = 3000.0f32;
+
+// a vowel-ish harmonic series: a single broad formant over a flat floor
+fn voice_envelope(freq: f32) -> f32 {
+    0.1f32 + (-((freq - VOICE_FORMANT_HZ) / 400.0f32).powi(2)).exp()
+}
+
+fn voice(num_samples: usize) -> Vec<f32> {
+    let mut signal = vec![0.0f32; num_samples];
+
+    let mut freq = VOICE_F0;
+    while freq < VOICE_MAX_FREQ {
+        let partial = sine(freq, 0.05f32 * voice_envelope(freq), num_samples);
+        for (x, p) in signal.iter_mut().zip(partial.iter()) {
+            *x += p;
+        }
+        freq += VOICE_F0;
+    }
+
+    signal
+}
+
+fn ocean_shift_voice(preserve_formants: i32, input: &[f32]) -> Vec<f32> {
+    let mut otters = create_board(&mono_board_config(
+        "PitchShifter/Ocean",
+        &format!(
+            r#"[
+                {{"name": "semitone_difference", "value": {{"N": 6}}}},
+                {{"name": "preserve_formants", "value": {{"N": {}}}}}
+            ]"#,
+            preserve_formants
+        ),
+    ));
+
+    let output = process_mono(&mut otters, input);
+    output[TEST_SAMPLE_RATE as usize / 4..].to_vec()
+}
+
+// magnitude weighted mean frequency of the whole spectrum below VOICE_MAX_FREQ. follows where the
+// formant is, wherever the partials under it landed
+fn envelope_centroid(signal: &[f32]) -> f32 {
+    spectral_centroid_near(signal, VOICE_MAX_FREQ / 2.0f32, VOICE_MAX_FREQ / 2.0f32)
+}
+
+#[test]
+fn test_ocean_preserve_formants_keeps_the_envelope_in_place() {
+    let input = voice(TEST_SAMPLE_RATE as usize / 2);
+
+    let original = envelope_centroid(&input[TEST_SAMPLE_RATE as usize / 4..]);
+    let unpreserved = ocean_shift_voice(0, &input);
+    let preserved = ocean_shift_voice(1, &input);
+
+    // half an octave up drags the formant up with the partials, unless it's preserved
+    let unpreserved_centroid = envelope_centroid(&unpreserved);
+    let preserved_centroid = envelope_centroid(&preserved);
+    assert!(
+        unpreserved_centroid > 1.2f32 * original,
+        "{} vs {}",
+        unpreserved_centroid,
+        original
+    );
+    assert!(
+        (preserved_centroid / original - 1.0f32).abs() < 0.1f32,
+        "{} vs {}",
+        preserved_centroid,
+        original
+    );
+
+    // off is exactly what the shifter did before the option existed
+    let mut otters = create_board(&mono_board_config(
+        "PitchShifter/Ocean",
+        r#"[{"name": "semitone_difference", "value": {"N": 6}}]"#,
+    ));
+    let default = process_mono(&mut otters, &input);
+    assert_eq!(&default[TEST_SAMPLE_RATE as usize / 4..], &unpreserved[..]);
+}
+
+#[test]
+fn test_ocean_latency_is_exact_from_the_first_frame() {
+    // the shifter's output hop counter starts negative. that only moves its phase cycle,
+    // so an impulse still comes out exactly the reported latency later, even in the first frame
+    for impulse_at in [0, 4096 + 37].iter() {
+        let mut otters = create_board(&mono_board_config(
+            "PitchShifter/Ocean",
+            r#"[{"name": "semitone_difference", "value": {"N": 0}}]"#,
+        ));
+        let latency = otters.total_latency_samples();
+        assert!(latency > 0);
+
+        let mut input = vec![0.0f32; 16384];
+        input[*impulse_at] = 1.0f32;
+        let output = process_mono(&mut otters, &input);
+
+        let loudest = (0..output.len())
+            .max_by(|a, b| output[*a].abs().partial_cmp(&output[*b].abs()).unwrap())
+            .unwrap();
+        assert_eq!(loudest, impulse_at + latency);
+    }
+}
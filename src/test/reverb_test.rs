@@ -0,0 +1,442 @@
+#![cfg(test)]
+
+use super::test_utils::*;
+use crate::conf::AudioConfig;
+use crate::effects::reverb::apf::DelayAPF;
+use crate::effects::reverb::comb::LPFCombFilter;
+use crate::effects::reverb::pre_delay::PreDelay;
+use crate::otters::Otters;
+
+#[test]
+fn test_comb_filter_stays_stable_at_extreme_rt60() {
+    // an hour long rt60 on a 1ms delay would need comb_g ~= 1.0
+    let mut comb = LPFCombFilter::new(1.0f32, TEST_SAMPLE_RATE, 3_600_000.0f32, 5000.0f32, 0.5f32);
+
+    let mut response = Vec::with_capacity(TEST_SAMPLE_RATE as usize * 10);
+    response.push(comb.process(1.0f32));
+    for _ in 1..TEST_SAMPLE_RATE as usize * 10 {
+        response.push(comb.process(0.0f32));
+    }
+
+    let early = rms(&response[..4410]);
+    let late = rms(&response[response.len() - 4410..]);
+
+    assert!(response.iter().all(|x| x.is_finite() && x.abs() <= 1.0f32));
+    assert!(early > 0.0f32);
+    assert!(late < early * 0.1f32, "early {} late {}", early, late);
+}
+
+// how much of the energy at freq is left between an early and a late window of the tail
+fn comb_decay_at(damping_amount: f32, freq: f32) -> f32 {
+    // 10ms delay puts the comb's peaks on multiples of 100Hz
+    let mut comb = LPFCombFilter::new(
+        10.0f32,
+        TEST_SAMPLE_RATE,
+        2000.0f32,
+        2000.0f32,
+        damping_amount,
+    );
+
+    let num_samples = TEST_SAMPLE_RATE as usize;
+    let response: Vec<f32> = impulse(num_samples)
+        .iter()
+        .map(|x| comb.process(*x))
+        .collect();
+
+    let window = 4410;
+    let early = magnitude_at(&response[..window], freq);
+    let late = magnitude_at(&response[num_samples - window..], freq);
+
+    late / early
+}
+
+#[test]
+fn test_comb_filter_damping_decays_highs_faster() {
+    let low_hz = 200.0f32;
+    let high_hz = 8000.0f32;
+
+    // compare against the undamped comb so only the damping's effect on the decay is left
+    let low_decay = comb_decay_at(1.0f32, low_hz) / comb_decay_at(0.0f32, low_hz);
+    let high_decay = comb_decay_at(1.0f32, high_hz) / comb_decay_at(0.0f32, high_hz);
+
+    // the lows barely notice a 2kHz damping filter, the highs are mostly gone by the end of the tail
+    assert!(low_decay > 0.5f32, "{}", low_decay);
+    assert!(
+        high_decay < 0.01f32 * low_decay,
+        "{} vs {}",
+        high_decay,
+        low_decay
+    );
+}
+
+// impulse -> pre-delay -> comb, the way a reverb wires it. returns where the tail starts
+fn tail_start_with_pre_delay(pre_delay_ms: f32) -> usize {
+    let mut pre_delay = PreDelay::new(pre_delay_ms, TEST_SAMPLE_RATE);
+    let mut comb = LPFCombFilter::new(5.0f32, TEST_SAMPLE_RATE, 500.0f32, 5000.0f32, 0.5f32);
+
+    impulse(16384)
+        .iter()
+        .map(|x| comb.process(pre_delay.process(*x)))
+        .position(|y| y.abs() > 1e-6f32)
+        .unwrap()
+}
+
+#[test]
+fn test_pre_delay_holds_off_the_tail() {
+    // the comb's own delay is there either way, the pre-delay comes on top
+    let undelayed_start = tail_start_with_pre_delay(0.0f32);
+    assert!(undelayed_start < 300);
+
+    for pre_delay_ms in &[10.0f32, 30.0f32, 250.0f32] {
+        let pre_delay_samples = (pre_delay_ms * TEST_SAMPLE_RATE / 1000.0f32) as usize;
+        assert_eq!(
+            tail_start_with_pre_delay(*pre_delay_ms),
+            undelayed_start + pre_delay_samples
+        );
+    }
+
+    // changing it later moves the start of the tail
+    let mut pre_delay = PreDelay::new(0.0f32, TEST_SAMPLE_RATE);
+    pre_delay.set_pre_delay_ms(10.0f32);
+    let delayed_start = impulse(1024)
+        .iter()
+        .map(|x| pre_delay.process(*x))
+        .position(|y| y.abs() > 1e-6f32);
+    assert_eq!(delayed_start, Some(441));
+}
+
+fn schroeder_impulse_response(rt60_ms: f32) -> Vec<f32> {
+    let mut otters = create_board(&mono_board_config(
+        "Reverb/Schroeder",
+        &format!(
+            r#"[
+                {{"name": "rt60_ms", "value": {{"F": {}}}}},
+                {{"name": "damping", "value": {{"F": 0.0}}}},
+                {{"name": "monitor_mode", "value": {{"N": 1}}}}
+            ]"#,
+            rt60_ms
+        ),
+    ));
+
+    process_mono(&mut otters, &impulse(2 * TEST_SAMPLE_RATE as usize))
+}
+
+#[test]
+fn test_schroeder_reverb_decays_over_rt60() {
+    assert!(Otters::get_available_effect_names().contains(&"Reverb/Schroeder".to_string()));
+
+    let rt60_ms = 1000.0f32;
+    let response = schroeder_impulse_response(rt60_ms);
+    assert!(response.iter().all(|x| x.is_finite()));
+
+    // 100 ms windows, one early in the tail and one rt60 later
+    let window = TEST_SAMPLE_RATE as usize / 10;
+    let rt60_samples = (rt60_ms * TEST_SAMPLE_RATE / 1000.0f32) as usize;
+    let early = rms(&response[window..2 * window]);
+    let late = rms(&response[window + rt60_samples..2 * window + rt60_samples]);
+    let decay_db = 20.0f32 * (late / early).log10();
+
+    assert!(early > 0.0f32);
+    assert!(decay_db < -50.0f32 && decay_db > -70.0f32, "{}", decay_db);
+
+    // a longer rt60 leaves more of the tail in the same window
+    let longer = schroeder_impulse_response(2.0f32 * rt60_ms);
+    let longer_late = rms(&longer[window + rt60_samples..2 * window + rt60_samples]);
+    assert!(longer_late > 10.0f32 * late, "{} vs {}", longer_late, late);
+}
+
+#[test]
+fn test_delay_apf_impulse_response_is_all_pass() {
+    let g = 0.5f32;
+    // 1 ms at 44.1k is 44.1 samples, rounded to 44
+    let d = 44;
+    let mut apf = DelayAPF::with_max_delay_ms(1.0f32, 10.0f32, g, TEST_SAMPLE_RATE);
+
+    let response: Vec<f32> = impulse(TEST_SAMPLE_RATE as usize / 10)
+        .iter()
+        .map(|x| apf.process(*x))
+        .collect();
+
+    // y(n) = -g * x(n) + x(n - D) + g * y(n - D): -g at 0, then (1 - g^2) * g^(k-1) at kD
+    assert!((response[0] + g).abs() < 1e-6f32, "{}", response[0]);
+    for k in 1..6 {
+        let expected = (1.0f32 - g * g) * g.powi(k - 1);
+        let actual = response[k as usize * d];
+        assert!((actual - expected).abs() < 1e-6f32, "{}: {}", k, actual);
+    }
+
+    // nothing in between the echoes
+    for (n, y) in response.iter().enumerate() {
+        if n % d != 0 {
+            assert_eq!(*y, 0.0f32, "{}", n);
+        }
+    }
+
+    // an all pass keeps all of the impulse's energy
+    let energy: f32 = response.iter().map(|y| y * y).sum();
+    assert!((energy - 1.0f32).abs() < 1e-4f32, "{}", energy);
+
+    // a longer delay (up to what the line was built for) spaces the echoes out to match
+    apf.set_delay_time_ms(10.0f32);
+    apf.clear();
+    let response: Vec<f32> = impulse(2048).iter().map(|x| apf.process(*x)).collect();
+    assert!((response[441] - (1.0f32 - g * g)).abs() < 1e-6f32);
+
+    // and anything longer is held at the longest it can do
+    apf.set_delay_time_ms(100.0f32);
+    apf.clear();
+    let response: Vec<f32> = impulse(2048).iter().map(|x| apf.process(*x)).collect();
+    assert!((response[441] - (1.0f32 - g * g)).abs() < 1e-6f32);
+}
+
+fn freeverb_impulse_response(sample_rate: f32, room_size: f32, width: f32) -> (Vec<f32>, Vec<f32>) {
+    let mut otters = Otters::create_default_from_string(
+        AudioConfig {
+            sample_rate,
+            ..test_audio_config()
+        },
+        &stereo_board_config(
+            "Reverb/Freeverb",
+            &format!(
+                r#"[
+                    {{"name": "room_size", "value": {{"F": {}}}}},
+                    {{"name": "width", "value": {{"F": {}}}}},
+                    {{"name": "monitor_mode", "value": {{"N": 1}}}}
+                ]"#,
+                room_size, width
+            ),
+        ),
+    )
+    .unwrap();
+
+    let num_samples = 2 * sample_rate as usize;
+    process_stereo(
+        &mut otters,
+        &impulse(num_samples),
+        &vec![0.0f32; num_samples],
+    )
+}
+
+#[test]
+fn test_freeverb_room_size_and_width() {
+    assert!(Otters::get_available_effect_names().contains(&"Reverb/Freeverb".to_string()));
+
+    let (left, right) = freeverb_impulse_response(TEST_SAMPLE_RATE, 0.5f32, 1.0f32);
+    assert!(left.iter().chain(right.iter()).all(|x| x.is_finite()));
+    assert_ne!(left, right);
+
+    // the tail dies away
+    let window = TEST_SAMPLE_RATE as usize / 10;
+    let early = rms(&left[window..2 * window]);
+    let late = rms(&left[left.len() - window..]);
+    assert!(early > 0.0f32);
+    assert!(late < 0.01f32 * early, "{} vs {}", late, early);
+
+    // a bigger room rings longer
+    let (bigger_left, _) = freeverb_impulse_response(TEST_SAMPLE_RATE, 1.0f32, 1.0f32);
+    let bigger_late = rms(&bigger_left[bigger_left.len() - window..]);
+    assert!(bigger_late > 10.0f32 * late, "{} vs {}", bigger_late, late);
+
+    // no width puts the same tail on both sides
+    let (mono_left, mono_right) = freeverb_impulse_response(TEST_SAMPLE_RATE, 0.5f32, 0.0f32);
+    assert_eq!(mono_left, mono_right);
+}
+
+#[test]
+fn test_freeverb_delays_scale_with_sample_rate() {
+    // the shortest comb is 1116 samples at 44.1k, and nothing comes out before it
+    let first_output = |sample_rate: f32| {
+        let (left, _) = freeverb_impulse_response(sample_rate, 0.5f32, 1.0f32);
+        left.iter().position(|x| *x != 0.0f32).unwrap()
+    };
+
+    assert_eq!(first_output(44100.0f32), 1116);
+    assert_eq!(first_output(88200.0f32), 2232);
+    assert_eq!(first_output(22050.0f32), 558);
+}
+
+fn plate_impulse_response(decay: f32, modulation: f32) -> (Vec<f32>, Vec<f32>) {
+    let mut otters = create_board(&stereo_board_config(
+        "Reverb/Plate",
+        &format!(
+            r#"[
+                {{"name": "decay", "value": {{"F": {}}}}},
+                {{"name": "modulation", "value": {{"F": {}}}}},
+                {{"name": "monitor_mode", "value": {{"N": 1}}}}
+            ]"#,
+            decay, modulation
+        ),
+    ));
+
+    let num_samples = 2 * TEST_SAMPLE_RATE as usize;
+    process_stereo(
+        &mut otters,
+        &impulse(num_samples),
+        &vec![0.0f32; num_samples],
+    )
+}
+
+// abel and huang's normalized echo density: the share of samples more than a standard deviation
+// out, over what gaussian noise would have. ~1 is a smooth wash, near 0 is a few clean echoes
+fn echo_density(window: &[f32]) -> f32 {
+    let std_dev = rms(window);
+    let outliers = window.iter().filter(|x| x.abs() > std_dev).count();
+
+    outliers as f32 / window.len() as f32 / 0.3173f32
+}
+
+// how far the level jumps between neighboring 10 ms windows of the first second of the tail.
+// discrete echoes show up as big jumps, a smooth decay barely moves
+fn max_envelope_jump(response: &[f32]) -> f32 {
+    let window = TEST_SAMPLE_RATE as usize / 100;
+    let start = response.iter().position(|x| *x != 0.0f32).unwrap();
+    let levels: Vec<f32> = response[start..start + 100 * window]
+        .chunks(window)
+        .map(rms)
+        .collect();
+
+    levels
+        .windows(2)
+        .map(|pair| (pair[1] / pair[0]).max(pair[0] / pair[1]))
+        .fold(0.0f32, f32::max)
+}
+
+// how much of the response's energy is in its single loudest sample
+fn peak_energy_share(response: &[f32]) -> f32 {
+    let energy: f32 = response.iter().map(|x| x * x).sum();
+    peak(response).powi(2) / energy
+}
+
+#[test]
+fn test_plate_reverb_is_dense_and_smooth() {
+    assert!(Otters::get_available_effect_names().contains(&"Reverb/Plate".to_string()));
+
+    let (left, right) = plate_impulse_response(0.7f32, 0.5f32);
+    assert!(left.iter().chain(right.iter()).all(|x| x.is_finite()));
+    assert_ne!(left, right);
+
+    // the schroeder reverb's combs are the discrete echoes the plate shouldn't have
+    let schroeder = schroeder_impulse_response(1000.0f32);
+
+    // no early echo stands out of the tail
+    assert!(
+        peak_energy_share(&left) < 0.015f32,
+        "{}",
+        peak_energy_share(&left)
+    );
+    assert!(peak_energy_share(&schroeder) > 0.03f32);
+    assert!(
+        max_envelope_jump(&left) < 2.0f32,
+        "{}",
+        max_envelope_jump(&left)
+    );
+    assert!(max_envelope_jump(&schroeder) > 3.0f32);
+
+    // the tail builds up to noise-like density and stays there
+    let window = TEST_SAMPLE_RATE as usize / 50;
+    let dense_from = 3 * TEST_SAMPLE_RATE as usize / 10;
+    let mean_density = |response: &[f32]| {
+        let windows = &response[dense_from..dense_from + 20 * window];
+        windows.chunks(window).map(echo_density).sum::<f32>() / 20.0f32
+    };
+    assert!(mean_density(&left) > 0.9f32, "{}", mean_density(&left));
+    assert!(
+        mean_density(&schroeder) < 0.7f32,
+        "{}",
+        mean_density(&schroeder)
+    );
+
+    // the modulation moves the tail without changing how fast it dies
+    let tail = TEST_SAMPLE_RATE as usize;
+    let late = rms(&left[tail..tail + 10 * window]);
+    let (unmodulated, _) = plate_impulse_response(0.7f32, 0.0f32);
+    let unmodulated_late = rms(&unmodulated[tail..tail + 10 * window]);
+    assert_ne!(&left[tail..], &unmodulated[tail..]);
+    assert!(
+        (unmodulated_late / late).log2().abs() < 0.5f32,
+        "{} vs {}",
+        unmodulated_late,
+        late
+    );
+
+    // more decay rings longer
+    let (longer, _) = plate_impulse_response(0.9f32, 0.5f32);
+    let longer_late = rms(&longer[tail..tail + 10 * window]);
+    assert!(longer_late > 2.0f32 * late, "{} vs {}", longer_late, late);
+}
+
+// third harmonic over fundamental in the plate's wet output, once a steady sine has filled it up
+fn plate_third_harmonic_ratio(drive: f32, amplitude: f32) -> f32 {
+    let mut otters = create_board(&stereo_board_config(
+        "Reverb/Plate",
+        &format!(
+            r#"[
+                {{"name": "decay", "value": {{"F": 0.7}}}},
+                {{"name": "damping", "value": {{"F": 0.0}}}},
+                {{"name": "modulation", "value": {{"F": 0.0}}}},
+                {{"name": "drive", "value": {{"F": {}}}}},
+                {{"name": "monitor_mode", "value": {{"N": 1}}}}
+            ]"#,
+            drive
+        ),
+    ));
+
+    // 441 Hz is exactly 100 samples a cycle, so the window below holds whole cycles of both
+    let freq = 441.0f32;
+    let num_samples = TEST_SAMPLE_RATE as usize;
+    let input = sine(freq, amplitude, num_samples);
+    let (left, _) = process_stereo(&mut otters, &input, &input);
+    assert!(left.iter().all(|x| x.is_finite()));
+
+    let window = &left[num_samples - 4410..];
+    magnitude_at(window, 3.0f32 * freq) / magnitude_at(window, freq)
+}
+
+#[test]
+fn test_plate_drive_saturates_only_loud_tails() {
+    // without drive the tank is linear, so there's nothing at the harmonic
+    let clean = plate_third_harmonic_ratio(0.0f32, 1.0f32);
+    assert!(clean < 1e-3f32, "{}", clean);
+
+    let loud = plate_third_harmonic_ratio(1.0f32, 1.0f32);
+    assert!(loud > 0.01f32, "{}", loud);
+
+    let quiet = plate_third_harmonic_ratio(1.0f32, 0.0005f32);
+    assert!(quiet < 1e-3f32, "{}", quiet);
+}
+
+fn reverb_wet_response(effect_name: &str, is_stereo: bool, wet_invert: i32) -> Vec<f32> {
+    let config = format!(
+        r#"[
+            {{"name": "monitor_mode", "value": {{"N": 1}}}},
+            {{"name": "wet_invert?", "value": {{"N": {}}}}}
+        ]"#,
+        wet_invert
+    );
+
+    let input = impulse(TEST_SAMPLE_RATE as usize / 2);
+    if is_stereo {
+        let mut otters = create_board(&stereo_board_config(effect_name, &config));
+        process_stereo(&mut otters, &input, &input).0
+    } else {
+        let mut otters = create_board(&mono_board_config(effect_name, &config));
+        process_mono(&mut otters, &input)
+    }
+}
+
+#[test]
+fn test_reverb_wet_invert_flips_the_tail() {
+    for (effect_name, is_stereo) in &[
+        ("Reverb/Schroeder", false),
+        ("Reverb/Freeverb", true),
+        ("Reverb/Plate", true),
+    ] {
+        let normal = reverb_wet_response(effect_name, *is_stereo, 0);
+        let inverted = reverb_wet_response(effect_name, *is_stereo, 1);
+
+        assert!(rms(&normal) > 1e-4f32, "{} {}", effect_name, rms(&normal));
+        for (n, i) in normal.iter().zip(inverted.iter()) {
+            assert_eq!(*i, -*n, "{}", effect_name);
+        }
+    }
+}
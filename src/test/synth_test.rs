@@ -0,0 +1,96 @@
+#![cfg(test)]
+
+use super::test_utils::*;
+
+#[test]
+fn test_karplus_string_pluck_pitch_and_decay() {
+    let pitch_hz = 220.0f32;
+    let mut otters = create_board(&mono_board_config(
+        "Synth/KarplusString",
+        r#"[
+            {"name": "pitch_hz", "value": {"F": 220.0}},
+            {"name": "pluck", "value": {"N": 1}}
+        ]"#,
+    ));
+
+    let output = process_mono(&mut otters, &vec![0.0f32; TEST_SAMPLE_RATE as usize]);
+
+    // the strongest autocorrelation lag is the period of the string
+    let analysis = &output[4410..8820];
+    let expected_period = TEST_SAMPLE_RATE / pitch_hz;
+    let mut best_lag = 0;
+    let mut best_corr = 0.0f32;
+    for lag in 100..400 {
+        let corr = analysis[..analysis.len() - lag]
+            .iter()
+            .zip(&analysis[lag..])
+            .fold(0.0f32, |acc, (a, b)| acc + a * b);
+        if corr > best_corr {
+            best_corr = corr;
+            best_lag = lag;
+        }
+    }
+
+    assert!(
+        (best_lag as f32 - expected_period).abs() <= 1.0f32,
+        "period {} expected {}",
+        best_lag,
+        expected_period
+    );
+
+    let early = rms(&output[4410..8820]);
+    let late = rms(&output[35280..39690]);
+    assert!(early > 0.01f32);
+    assert!(late < early * 0.5f32);
+}
+
+fn pluck_with_seed(seed: i32) -> Vec<f32> {
+    let mut otters = create_board(&mono_board_config(
+        "Synth/KarplusString",
+        &format!(
+            r#"[
+                {{"name": "seed", "value": {{"N": {}}}}},
+                {{"name": "pluck", "value": {{"N": 1}}}}
+            ]"#,
+            seed
+        ),
+    ));
+
+    process_mono(&mut otters, &vec![0.0f32; 4410])
+}
+
+#[test]
+fn test_karplus_string_pluck_is_reproducible() {
+    assert_eq!(pluck_with_seed(0), pluck_with_seed(0));
+    assert_ne!(pluck_with_seed(0), pluck_with_seed(1));
+}
+
+// rms of the first difference over the rms, so louder doesn't count as brighter
+fn pluck_high_frequency_ratio(brightness: f32) -> f32 {
+    let mut otters = create_board(&mono_board_config(
+        "Synth/KarplusString",
+        &format!(
+            r#"[
+                {{"name": "brightness", "value": {{"F": {}}}}},
+                {{"name": "pluck", "value": {{"N": 1}}}}
+            ]"#,
+            brightness
+        ),
+    ));
+
+    let output = process_mono(&mut otters, &vec![0.0f32; 8820]);
+    let analysis = &output[4410..];
+    let diff: Vec<f32> = analysis.windows(2).map(|w| w[1] - w[0]).collect();
+
+    rms(&diff) / rms(analysis)
+}
+
+#[test]
+fn test_karplus_string_brightness_keeps_the_highs() {
+    let dark = pluck_high_frequency_ratio(0.0f32);
+    let bright = pluck_high_frequency_ratio(0.5f32);
+
+    // with no loop filter the noise burst keeps its top end
+    assert!(bright > 2.0f32 * dark, "bright {} dark {}", bright, dark);
+    assert!(bright > 0.5f32, "bright {}", bright);
+}
@@ -0,0 +1,133 @@
+#![cfg(test)]
+
+use super::test_utils::*;
+use crate::host::test_signal::{
+    deconvolve_sweep, log_sweep_inverse_filter, SweepType, TestSignal, TestSignalGenerator,
+};
+use crate::utils::biquad::IIRFilterType;
+
+fn generate(kind: TestSignal, num_samples: usize) -> Vec<f32> {
+    let mut generator = TestSignalGenerator::new(TEST_SAMPLE_RATE, TEST_BLOCK_SIZE);
+    let mut signal = Vec::with_capacity(num_samples);
+    while signal.len() < num_samples {
+        let block_size = TEST_BLOCK_SIZE.min(num_samples - signal.len());
+        let block = generator.generate(kind, block_size);
+        signal.extend_from_slice(unsafe { std::slice::from_raw_parts(block, block_size) });
+    }
+
+    signal
+}
+
+// seconds spent in each octave above start_hz, going by the spacing of zero crossings
+fn seconds_per_octave(signal: &[f32], start_hz: f32, num_octaves: usize) -> Vec<f32> {
+    let mut crossings = Vec::new();
+    for i in 1..signal.len() {
+        if (signal[i - 1] < 0.0f32) != (signal[i] < 0.0f32) {
+            let fract = signal[i - 1] / (signal[i - 1] - signal[i]);
+            crossings.push((i - 1) as f32 + fract);
+        }
+    }
+
+    let mut seconds = vec![0.0f32; num_octaves];
+    for pair in crossings.windows(2) {
+        let half_period = (pair[1] - pair[0]) / TEST_SAMPLE_RATE;
+        let octave = (0.5f32 / half_period / start_hz).log2().floor();
+        if octave >= 0.0f32 && (octave as usize) < num_octaves {
+            seconds[octave as usize] += half_period;
+        }
+    }
+
+    seconds
+}
+
+#[test]
+fn test_log_sweep_spends_equal_time_per_octave() {
+    let (start_hz, num_octaves, duration_secs) = (40.0f32, 8, 2.0f32);
+    let sweep = |sweep_type| TestSignal::Sweep {
+        start_hz,
+        end_hz: start_hz * (1 << num_octaves) as f32,
+        duration_secs,
+        sweep_type,
+    };
+    let num_samples = (duration_secs * TEST_SAMPLE_RATE) as usize;
+
+    // the outer octaves lose a crossing or two at the ends, so only the inner ones are checked
+    let log_sweep = generate(sweep(SweepType::Logarithmic), num_samples);
+    let per_octave = duration_secs / num_octaves as f32;
+    let seconds = seconds_per_octave(&log_sweep, start_hz, num_octaves);
+    for octave in 1..num_octaves - 1 {
+        assert!(
+            (seconds[octave] - per_octave).abs() < 0.03f32 * per_octave,
+            "octave {}: {} s",
+            octave,
+            seconds[octave]
+        );
+    }
+
+    // a linear sweep spends twice as long on each octave as on the one below
+    let linear_sweep = generate(sweep(SweepType::Linear), num_samples);
+    let seconds = seconds_per_octave(&linear_sweep, start_hz, num_octaves);
+    assert!(seconds[6] > 16.0f32 * seconds[2], "{:?}", seconds);
+
+    // and once it's done the generator goes quiet
+    let tail = generate(sweep(SweepType::Logarithmic), num_samples + 256);
+    assert!(tail[num_samples..].iter().all(|x| *x == 0.0f32));
+}
+
+#[test]
+fn test_log_sweep_deconvolves_to_impulse_response() {
+    let (start_hz, end_hz, duration_secs) = (20.0f32, 20000.0f32, 1.0f32);
+    let ir_len = 256;
+
+    let board_config = mono_board_config(
+        "Filter/Biquad",
+        &format!(
+            r#"[
+                {{"name": "filter_type", "value": {{"N": {}}}}},
+                {{"name": "corner_freq_hz", "value": {{"F": 1000.0}}}},
+                {{"name": "q", "value": {{"F": 1.0}}}}
+            ]"#,
+            IIRFilterType::SecondOrderBandPass as i32
+        ),
+    );
+
+    let mut reference_board = create_board(&board_config);
+    let impulse_response = process_mono(&mut reference_board, &impulse(ir_len));
+
+    // the sweep stands in for the board's input, and the recording runs on past its end
+    // so the tail of the response is captured too
+    let mut otters = create_board(&board_config);
+    let num_samples = (duration_secs * TEST_SAMPLE_RATE) as usize + ir_len;
+    let mut recording = vec![0.0f32; num_samples];
+    let mut offset = 0;
+    while offset < num_samples {
+        let block_size = TEST_BLOCK_SIZE.min(num_samples - offset);
+        otters.bind_output(0, recording[offset..].as_mut_ptr());
+        otters.frolic_with_test_signal(
+            TestSignal::Sweep {
+                start_hz,
+                end_hz,
+                duration_secs,
+                sweep_type: SweepType::Logarithmic,
+            },
+            block_size,
+        );
+
+        offset += block_size;
+    }
+
+    let inverse = log_sweep_inverse_filter(start_hz, end_hz, duration_secs, TEST_SAMPLE_RATE);
+    let recovered = deconvolve_sweep(&recording, &inverse, ir_len);
+
+    let error: Vec<f32> = recovered
+        .iter()
+        .zip(impulse_response.iter())
+        .map(|(a, b)| a - b)
+        .collect();
+    assert!(
+        rms(&error) < 0.05f32 * rms(&impulse_response),
+        "error {} response {}",
+        rms(&error),
+        rms(&impulse_response)
+    );
+}
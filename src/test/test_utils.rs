@@ -0,0 +1,121 @@
+#![cfg(test)]
+
+use crate::conf::{AudioConfig, DEFAULT_TEMPO_BPM};
+use crate::otters::Otters;
+
+pub const TEST_SAMPLE_RATE: f32 = 44100.0f32;
+pub const TEST_BLOCK_SIZE: usize = 64;
+
+pub fn test_audio_config() -> AudioConfig {
+    AudioConfig {
+        sample_rate: TEST_SAMPLE_RATE,
+        max_block_size: TEST_BLOCK_SIZE,
+        num_channels: 1,
+        tempo_bpm: DEFAULT_TEMPO_BPM,
+    }
+}
+
+// a board with a single effect reading @SOURCE_0 and writing @SINK_0
+// config is the json list of parameters, e.g. [{"name": "gain", "value": {"F": 1.0}}]
+pub fn mono_board_config(effect_name: &str, config: &str) -> String {
+    format!(
+        r#"{{
+            "buffers": ["@SOURCE_0", "@SINK_0"],
+            "effects": [{{"bind_name": "fx", "effect_name": "{}", "config": {}, "enabled": true}}],
+            "connections": [{{"effect": "fx", "reads": ["@SOURCE_0"], "writes": ["@SINK_0"]}}]
+        }}"#,
+        effect_name, config
+    )
+}
+
+// same as above but with two sources and two sinks (L = 0, R = 1)
+pub fn stereo_board_config(effect_name: &str, config: &str) -> String {
+    format!(
+        r#"{{
+            "buffers": ["@SOURCE_0", "@SOURCE_1", "@SINK_0", "@SINK_1"],
+            "effects": [{{"bind_name": "fx", "effect_name": "{}", "config": {}, "enabled": true}}],
+            "connections": [{{"effect": "fx", "reads": ["@SOURCE_0", "@SOURCE_1"], "writes": ["@SINK_0", "@SINK_1"]}}]
+        }}"#,
+        effect_name, config
+    )
+}
+
+pub fn create_board(config: &str) -> Otters {
+    match Otters::create_default_from_string(test_audio_config(), config) {
+        Ok(otters) => otters,
+        Err(err) => panic!("Load Failed! {:?}", err),
+    }
+}
+
+pub fn process_mono(otters: &mut Otters, input: &[f32]) -> Vec<f32> {
+    let mut output = vec![0.0f32; input.len()];
+
+    let mut offset = 0;
+    while offset < input.len() {
+        let block_size = TEST_BLOCK_SIZE.min(input.len() - offset);
+        otters.bind_input(0, input[offset..].as_ptr());
+        otters.bind_output(0, output[offset..].as_mut_ptr());
+        otters.frolic(block_size);
+
+        offset += block_size;
+    }
+
+    output
+}
+
+pub fn process_stereo(otters: &mut Otters, left: &[f32], right: &[f32]) -> (Vec<f32>, Vec<f32>) {
+    let mut out_left = vec![0.0f32; left.len()];
+    let mut out_right = vec![0.0f32; right.len()];
+
+    let mut offset = 0;
+    while offset < left.len() {
+        let block_size = TEST_BLOCK_SIZE.min(left.len() - offset);
+        otters.bind_input(0, left[offset..].as_ptr());
+        otters.bind_input(1, right[offset..].as_ptr());
+        otters.bind_output(0, out_left[offset..].as_mut_ptr());
+        otters.bind_output(1, out_right[offset..].as_mut_ptr());
+        otters.frolic(block_size);
+
+        offset += block_size;
+    }
+
+    (out_left, out_right)
+}
+
+pub fn sine(freq: f32, amplitude: f32, num_samples: usize) -> Vec<f32> {
+    (0..num_samples)
+        .map(|n| {
+            amplitude * (2.0f32 * std::f32::consts::PI * freq * n as f32 / TEST_SAMPLE_RATE).sin()
+        })
+        .collect()
+}
+
+pub fn impulse(num_samples: usize) -> Vec<f32> {
+    let mut v = vec![0.0f32; num_samples];
+    v[0] = 1.0f32;
+    v
+}
+
+pub fn rms(signal: &[f32]) -> f32 {
+    let sum_sq = signal
+        .iter()
+        .fold(0.0f64, |acc, x| acc + (*x as f64) * (*x as f64));
+    (sum_sq / signal.len() as f64).sqrt() as f32
+}
+
+pub fn peak(signal: &[f32]) -> f32 {
+    signal.iter().fold(0.0f32, |acc, x| acc.max(x.abs()))
+}
+
+// magnitude of a single dft bin at an arbitrary frequency, normalized so a
+// full scale sine reads ~1.0
+pub fn magnitude_at(signal: &[f32], freq: f32) -> f32 {
+    let w = 2.0f64 * std::f64::consts::PI * freq as f64 / TEST_SAMPLE_RATE as f64;
+    let (mut re, mut im) = (0.0f64, 0.0f64);
+    for (n, x) in signal.iter().enumerate() {
+        re += *x as f64 * (w * n as f64).cos();
+        im -= *x as f64 * (w * n as f64).sin();
+    }
+
+    (2.0f64 * (re * re + im * im).sqrt() / signal.len() as f64) as f32
+}
@@ -0,0 +1,343 @@
+#![cfg(test)]
+
+use super::test_utils::*;
+use crate::conf::BoardEffectConfigParameterValue;
+use crate::utils::fast_rand::WyHashPRNG;
+use crate::utils::mathutils::{pan_gains, PanLaw};
+
+fn crossfeed_board() -> crate::otters::Otters {
+    create_board(&stereo_board_config(
+        "Utility/Crossfeed",
+        r#"[
+            {"name": "amount", "value": {"F": 0.5}},
+            {"name": "cutoff_hz", "value": {"F": 700.0}},
+            {"name": "delay_us", "value": {"F": 300.0}}
+        ]"#,
+    ))
+}
+
+#[test]
+fn test_crossfeed_left_low_frequencies_reach_right() {
+    let num_samples = 16384;
+    let silence = vec![0.0f32; num_samples];
+
+    // nothing reaches the right channel before the crossfeed delay
+    let mut otters = crossfeed_board();
+    let (left, right) = process_stereo(&mut otters, &impulse(num_samples), &silence);
+    let delay_samples = (300.0e-6f32 * TEST_SAMPLE_RATE) as usize;
+    assert_eq!(left[0], 1.0f32);
+    assert!(right[..delay_samples].iter().all(|x| *x == 0.0f32));
+    assert!(right[delay_samples..delay_samples + 64]
+        .iter()
+        .any(|x| *x != 0.0f32));
+
+    // low frequencies come through at ~amount, highs are filtered out
+    let mut otters = crossfeed_board();
+    let (left, right) = process_stereo(&mut otters, &sine(100.0f32, 1.0f32, num_samples), &silence);
+    assert!((magnitude_at(&left[4096..], 100.0f32) - 1.0f32).abs() < 0.01f32);
+
+    let low = magnitude_at(&right[4096..], 100.0f32);
+    assert!((low - 0.5f32).abs() < 0.05f32, "{}", low);
+
+    let mut otters = crossfeed_board();
+    let (_, right) = process_stereo(&mut otters, &sine(8000.0f32, 1.0f32, num_samples), &silence);
+    assert!(magnitude_at(&right[4096..], 8000.0f32) < 0.01f32);
+}
+
+#[test]
+fn test_pan_laws_center_levels() {
+    let expected_center_db = [
+        (PanLaw::Linear, 0.0f32),
+        (PanLaw::ConstantPower, -3.01f32),
+        (PanLaw::Compromise, -4.52f32),
+        (PanLaw::LinearTaper, -6.02f32),
+    ];
+
+    for (law, expected_db) in expected_center_db.iter() {
+        let (left, right) = pan_gains(0.0f32, *law);
+        assert_eq!(left, right);
+
+        let center_db = 20.0f32 * left.log10();
+        assert!(
+            (center_db - expected_db).abs() < 0.01f32,
+            "{:?}: {}",
+            law,
+            center_db
+        );
+
+        // every law is fully on one side at the extremes
+        assert_eq!(pan_gains(-1.0f32, *law), (1.0f32, 0.0f32));
+        let (hard_right_left, hard_right_right) = pan_gains(1.0f32, *law);
+        assert!(hard_right_left.abs() < 1e-6f32 && (hard_right_right - 1.0f32).abs() < 1e-6f32);
+    }
+}
+
+#[test]
+fn test_pan_effect_places_mono_source_with_selected_law() {
+    let mut otters = create_board(
+        r#"{
+            "buffers": ["@SOURCE_0", "@SINK_0", "@SINK_1"],
+            "effects": [{"bind_name": "fx", "effect_name": "Utility/Pan", "config": [
+                {"name": "pan", "value": {"F": 0.0}},
+                {"name": "pan_law", "value": {"N": 3}}
+            ], "enabled": true}],
+            "connections": [{"effect": "fx", "reads": ["@SOURCE_0"], "writes": ["@SINK_0", "@SINK_1"]}]
+        }"#,
+    );
+
+    let input = sine(440.0f32, 1.0f32, 1024);
+    let silence = vec![0.0f32; input.len()];
+    let (left, right) = process_stereo(&mut otters, &input, &silence);
+
+    for i in 0..input.len() {
+        assert_eq!(left[i], 0.5f32 * input[i]);
+        assert_eq!(right[i], 0.5f32 * input[i]);
+    }
+}
+
+// returns (output, mean meter, rms meter)
+fn calibrate(remove_dc: i32, input: &[f32]) -> (Vec<f32>, Vec<f32>, Vec<f32>) {
+    let mut otters = create_board(&format!(
+        r#"{{
+            "buffers": ["@SOURCE_0", "@SINK_0", "@SINK_1", "@SINK_2"],
+            "effects": [{{
+                "bind_name": "fx",
+                "effect_name": "Utility/Calibrate",
+                "config": [
+                    {{"name": "remove_dc?", "value": {{"N": {}}}}},
+                    {{"name": "averaging_ms", "value": {{"F": 100.0}}}}
+                ],
+                "enabled": true
+            }}],
+            "connections": [{{"effect": "fx", "reads": ["@SOURCE_0"], "writes": ["@SINK_0", "@SINK_1", "@SINK_2"]}}]
+        }}"#,
+        remove_dc
+    ));
+
+    let mut output = vec![0.0f32; input.len()];
+    let mut mean = vec![0.0f32; input.len()];
+    let mut rms = vec![0.0f32; input.len()];
+    let mut offset = 0;
+    while offset < input.len() {
+        let block_size = TEST_BLOCK_SIZE.min(input.len() - offset);
+        otters.bind_input(0, input[offset..].as_ptr());
+        otters.bind_output(0, output[offset..].as_mut_ptr());
+        otters.bind_output(1, mean[offset..].as_mut_ptr());
+        otters.bind_output(2, rms[offset..].as_mut_ptr());
+        otters.frolic(block_size);
+
+        offset += block_size;
+    }
+
+    (output, mean, rms)
+}
+
+#[test]
+fn test_calibrate_reports_and_removes_dc_offset() {
+    let num_samples = 2 * TEST_SAMPLE_RATE as usize;
+    let dc_offset = 0.25f32;
+    let input: Vec<f32> = sine(1000.0f32, 0.5f32, num_samples)
+        .iter()
+        .map(|x| x + dc_offset)
+        .collect();
+
+    // metering only: the output is untouched
+    let (output, mean, rms_meter) = calibrate(0, &input);
+    assert_eq!(output, input);
+
+    let last = num_samples - 1;
+    assert!((mean[last] - dc_offset).abs() < 0.005f32, "{}", mean[last]);
+
+    let expected_rms = (dc_offset * dc_offset + 0.5f32 * 0.5f32 / 2.0f32).sqrt();
+    assert!(
+        (rms_meter[last] - expected_rms).abs() < 0.005f32,
+        "{}",
+        rms_meter[last]
+    );
+
+    // with removal on, the meters still read the input but the output settles around 0
+    let (output, mean, _) = calibrate(1, &input);
+    assert!((mean[last] - dc_offset).abs() < 0.005f32);
+
+    let settled = &output[num_samples / 2..];
+    let output_mean = settled.iter().sum::<f32>() / settled.len() as f32;
+    assert!(output_mean.abs() < 0.001f32, "{}", output_mean);
+    assert!((rms(settled) - 0.5f32 / 2.0f32.sqrt()).abs() < 0.01f32);
+}
+
+#[test]
+fn test_level_histogram_of_sine_is_arcsine_shaped() {
+    let mut otters = create_board(
+        r#"{
+            "buffers": ["@SOURCE_0", "@SINK_0", "@SINK_1"],
+            "effects": [{
+                "bind_name": "fx",
+                "effect_name": "Utility/LevelHistogram",
+                "config": [{"name": "bin_width_db", "value": {"F": 1.0}}],
+                "enabled": true
+            }],
+            "connections": [{"effect": "fx", "reads": ["@SOURCE_0"], "writes": ["@SINK_0", "@SINK_1"]}]
+        }"#,
+    );
+
+    let input = sine(997.0f32, 1.0f32, 2 * TEST_SAMPLE_RATE as usize);
+    let mut output = vec![0.0f32; input.len()];
+    let mut crest_db = vec![0.0f32; input.len()];
+    let mut offset = 0;
+    while offset < input.len() {
+        let block_size = TEST_BLOCK_SIZE.min(input.len() - offset);
+        otters.bind_input(0, input[offset..].as_ptr());
+        otters.bind_output(0, output[offset..].as_mut_ptr());
+        otters.bind_output(1, crest_db[offset..].as_mut_ptr());
+        otters.frolic(block_size);
+
+        offset += block_size;
+    }
+
+    assert_eq!(output, input);
+
+    // a sine spends most of its time near its peaks: P(|x| <= a) = 2/pi * asin(a)
+    let mut histogram = [0.0f32; 128];
+    let num_bins = otters.copy_effect_visualization("fx", &mut histogram);
+    assert!(num_bins > 20);
+
+    let level = |bin: usize| 10.0f32.powf(-(bin as f32) / 20.0f32);
+    for bin in 0..20 {
+        let expected = 2.0f32 / std::f32::consts::PI * (level(bin).asin() - level(bin + 1).asin());
+        assert!(
+            (histogram[bin] - expected).abs() < 0.005f32,
+            "bin {}: {} vs {}",
+            bin,
+            histogram[bin],
+            expected
+        );
+    }
+
+    assert!(histogram[0] > histogram[1] && histogram[1] > histogram[2]);
+    let total: f32 = histogram[..num_bins].iter().sum();
+    assert!((total - 1.0f32).abs() < 1e-4f32);
+
+    let last = crest_db[crest_db.len() - 1];
+    assert!((last - 3.01f32).abs() < 0.05f32, "{}", last);
+
+    // and reset starts over
+    assert!(otters.reset_effect("fx"));
+    assert_eq!(
+        otters.copy_effect_visualization("fx", &mut histogram),
+        num_bins
+    );
+    assert!(histogram[..num_bins].iter().all(|x| *x == 0.0f32));
+}
+
+fn rotate(angle_deg: f32, left: &[f32], right: &[f32]) -> (Vec<f32>, Vec<f32>) {
+    let mut otters = create_board(&stereo_board_config(
+        "Utility/StereoRotate",
+        &format!(
+            r#"[{{"name": "angle_deg", "value": {{"F": {}}}}}]"#,
+            angle_deg
+        ),
+    ));
+
+    process_stereo(&mut otters, left, right)
+}
+
+#[test]
+fn test_stereo_rotate_mixes_channels_by_angle() {
+    let left = sine(440.0f32, 0.8f32, 4096);
+    let right = sine(1000.0f32, 0.3f32, 4096);
+
+    let (out_left, out_right) = rotate(0.0f32, &left, &right);
+    assert_eq!(out_left, left);
+    assert_eq!(out_right, right);
+
+    // 45 degrees: each output gets cos(45) of its own channel and sin(45) of the other
+    let (out_left, out_right) = rotate(45.0f32, &left, &right);
+    let g = std::f32::consts::FRAC_1_SQRT_2;
+    for i in 0..left.len() {
+        assert!((out_left[i] - g * (left[i] + right[i])).abs() < 1e-6f32);
+        assert!((out_right[i] - g * (right[i] - left[i])).abs() < 1e-6f32);
+    }
+
+    assert!((magnitude_at(&out_right, 440.0f32) - 0.8f32 * g).abs() < 0.01f32);
+    assert!((magnitude_at(&out_right, 1000.0f32) - 0.3f32 * g).abs() < 0.01f32);
+
+    // a rotation doesn't change the total power
+    let power = |l: &[f32], r: &[f32]| rms(l).powi(2) + rms(r).powi(2);
+    assert!((power(&out_left, &out_right) - power(&left, &right)).abs() < 1e-4f32);
+}
+
+#[test]
+fn test_utility_delay_is_a_whole_number_of_samples() {
+    let mut otters = create_board(&mono_board_config(
+        "Utility/Delay",
+        r#"[{"name": "delay_samples", "value": {"N": 64}}]"#,
+    ));
+    assert_eq!(otters.total_latency_samples(), 64);
+
+    // noise has energy everywhere, so any filtering would show up as a mismatch
+    let prng = WyHashPRNG::new(0x7574_696c_6974_79);
+    let input: Vec<f32> = (0..4096)
+        .map(|_| (prng.next() % 2001) as f32 / 1000.0f32 - 1.0f32)
+        .collect();
+    let output = process_mono(&mut otters, &input);
+
+    assert!(output[..64].iter().all(|x| *x == 0.0f32));
+    assert_eq!(&output[64..], &input[..input.len() - 64]);
+}
+
+#[test]
+fn test_changing_delay_samples_updates_the_board_latency() {
+    let mut otters = create_board(&mono_board_config(
+        "Utility/Delay",
+        r#"[{"name": "delay_samples", "value": {"N": 64}}]"#,
+    ));
+    assert_eq!(otters.total_latency_samples(), 64);
+
+    otters.set_effect_parameter(0, BoardEffectConfigParameterValue::N(300));
+    assert_eq!(otters.total_latency_samples(), 300);
+
+    let output = process_mono(&mut otters, &impulse(4 * TEST_BLOCK_SIZE + 100));
+    assert_eq!(output[300], 1.0f32);
+}
+
+#[test]
+fn test_spectrum_analyzer_finds_a_tone() {
+    let mut otters = create_board(&mono_board_config(
+        "Utility/SpectrumAnalyzer",
+        r#"[
+            {"name": "fft_size", "value": {"N": 1000}},
+            {"name": "smoothing", "value": {"F": 0.0}}
+        ]"#,
+    ));
+
+    // right in the middle of bin 40 of the 1024 point fft 1000 rounds up to
+    let freq = 40.0f32 * TEST_SAMPLE_RATE / 1024.0f32;
+    let input = sine(freq, 0.5f32, TEST_SAMPLE_RATE as usize / 4);
+    assert_eq!(process_mono(&mut otters, &input), input);
+
+    let mut spectrum = [0.0f32; 1024];
+    assert_eq!(otters.copy_effect_visualization("fx", &mut spectrum), 513);
+
+    let loudest = (0..513)
+        .max_by(|a, b| spectrum[*a].partial_cmp(&spectrum[*b]).unwrap())
+        .unwrap();
+    assert_eq!(loudest, 40);
+    assert!((spectrum[40] - 0.5f32).abs() < 0.01f32, "{}", spectrum[40]);
+
+    // the hann window keeps it from spreading much past the neighbouring bins
+    let leakage = peak(&spectrum[..38]).max(peak(&spectrum[43..513]));
+    assert!(leakage < 0.01f32, "{}", leakage);
+
+    // a new size switches to the plan made for it up front and starts over
+    otters.set_effect_parameter(0, BoardEffectConfigParameterValue::N(4096));
+    let mut spectrum = [0.0f32; 4096];
+    assert_eq!(otters.copy_effect_visualization("fx", &mut spectrum), 2049);
+    assert_eq!(peak(&spectrum), 0.0f32);
+
+    process_mono(&mut otters, &input);
+    otters.copy_effect_visualization("fx", &mut spectrum);
+    let loudest = (0..2049)
+        .max_by(|a, b| spectrum[*a].partial_cmp(&spectrum[*b]).unwrap())
+        .unwrap();
+    assert_eq!(loudest, 160);
+}
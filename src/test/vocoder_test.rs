@@ -0,0 +1,670 @@
+#![cfg(test)]
+
+use super::test_utils::*;
+use crate::conf::{
+    BoardConfig, BoardEffectConfigParameterArrayValue, BoardEffectConfigParameterValue,
+    BoardEffectConfigValue,
+};
+use crate::effects::bypass::VocoderBypass;
+use crate::effects::misc_vocoder::spectral_eq::{SpectralEQ, NUM_EQ_BANDS};
+use crate::effects::misc_vocoder::{SpectralCompressor, SpectralGate};
+use crate::effects::vocoder2::{create_window, FFTPlanning, FFTWindowType, PhaseVocoder};
+use crate::effects::VocoderContext;
+use crate::traits::{AudioEffect, FrequencyDomainAudioEffect};
+use crate::utils::fast_rand::WyHashPRNG;
+use crate::utils::mathutils::db_to_linear;
+
+use fftw::array::AlignedVec;
+use fftw::types::c32;
+
+#[test]
+fn test_vocoder_rejects_hop_that_does_not_divide_frame() {
+    let result = PhaseVocoder::new(1024, 300, FFTWindowType::Hamming, VocoderBypass::new());
+
+    match result {
+        Ok(_) => panic!("hop of 300 should not be accepted for a frame of 1024"),
+        Err(err) => {
+            assert!(err.0.contains("300"));
+            assert!(err.0.contains("1024"));
+            assert!(err.0.contains("does not evenly divide"));
+        }
+    }
+
+    assert!(PhaseVocoder::new(1024, 256, FFTWindowType::Hamming, VocoderBypass::new()).is_ok());
+}
+
+const EQ_CONFIG: &str = r#"[
+    {"name": "band_gains_db", "value": {"FArray": [-9.0, -7.0, -5.0, -3.0, -1.0, 1.0, 3.0, 5.0, -12.0, 7.0]}}
+]"#;
+
+#[test]
+fn test_spectral_eq_band_gains_from_config() {
+    let board: BoardConfig =
+        serde_json::from_str(&mono_board_config("Vocoder/SpectralEQ", EQ_CONFIG)).unwrap();
+    let values = match &board.effects[0].config[0].value {
+        BoardEffectConfigValue::Array(BoardEffectConfigParameterArrayValue::FArray(values)) => {
+            values.clone()
+        }
+        _ => panic!("band_gains_db should parse as an array"),
+    };
+    assert_eq!(values.len(), NUM_EQ_BANDS);
+
+    let frame_size = 4096;
+    let mut eq = SpectralEQ::new(TEST_SAMPLE_RATE);
+    eq.post_initialize(&VocoderContext {
+        frame_size,
+        hop_size: frame_size / 4,
+        analysis_window: AlignedVec::new(frame_size),
+        synthesis_window: AlignedVec::new(frame_size),
+    });
+    eq.set_effect_parameter_array(0, &values);
+
+    let mut fft = AlignedVec::new(frame_size);
+    let mut output = AlignedVec::new(frame_size);
+    for i in 0..frame_size {
+        fft[i] = c32::new(1.0f32, 0.0f32);
+    }
+    eq.execute(&fft, &mut output);
+
+    // the bin nearest each band's center (and its mirror) gets that band's gain
+    for band in 0..NUM_EQ_BANDS {
+        let center_hz = 31.25f32 * (1 << band) as f32;
+        let bin = (center_hz * frame_size as f32 / TEST_SAMPLE_RATE).round() as usize;
+        let expected = db_to_linear(values[band]);
+
+        assert_eq!(SpectralEQ::band_for_freq(center_hz), band);
+        assert!((output[bin].re - expected).abs() < 1e-5f32);
+        assert!((output[frame_size - bin].re - expected).abs() < 1e-5f32);
+    }
+
+    // and the same config through a whole board. 8 kHz is in the -12 dB band
+    let mut otters = create_board(&mono_board_config("Vocoder/SpectralEQ", EQ_CONFIG));
+    let output = process_mono(&mut otters, &sine(8000.0f32, 0.5f32, 16384));
+    let level = magnitude_at(&output[4096..], 8000.0f32);
+    assert!(
+        (level - 0.5f32 * db_to_linear(-12.0f32)).abs() < 0.01f32,
+        "{}",
+        level
+    );
+}
+
+#[test]
+fn test_spectral_compressor_only_reduces_loud_bin() {
+    // both tones sit exactly on a bin of the 1024 point frame
+    let loud_hz = 20.0f32 * TEST_SAMPLE_RATE / 1024.0f32;
+    let quiet_hz = 150.0f32 * TEST_SAMPLE_RATE / 1024.0f32;
+    let num_samples = 32768;
+
+    let loud = sine(loud_hz, 0.5f32, num_samples);
+    let quiet = sine(quiet_hz, 0.02f32, num_samples);
+    let input: Vec<f32> = loud.iter().zip(quiet.iter()).map(|(l, q)| l + q).collect();
+
+    let mut otters = create_board(&mono_board_config(
+        "Vocoder/SpectralCompressor",
+        r#"[
+            {"name": "threshold_db", "value": {"F": -20.0}},
+            {"name": "ratio", "value": {"F": 10.0}},
+            {"name": "attack_time_ms", "value": {"F": 1.0}},
+            {"name": "release_time_ms", "value": {"F": 50.0}}
+        ]"#,
+    ));
+    let output = process_mono(&mut otters, &input);
+    let settled = &output[8192..];
+
+    // -6 dB in, so about -18.6 dB out
+    let loud_level = magnitude_at(settled, loud_hz);
+    assert!(
+        loud_level < 0.5f32 * db_to_linear(-10.0f32),
+        "loud level {}",
+        loud_level
+    );
+
+    // -34 dB is under the threshold and passes through
+    let quiet_level = magnitude_at(settled, quiet_hz);
+    assert!(
+        (quiet_level - 0.02f32).abs() < 0.002f32,
+        "quiet level {}",
+        quiet_level
+    );
+}
+
+// per bin gain (in dB) a spectral compressor applies to each of num_frames frames of noise.
+// the lower half of the spectrum hovers around the threshold, the upper half is well under it
+fn spectral_compressor_noise_gains_db(smoothing_bins: i32, num_frames: usize) -> Vec<Vec<f32>> {
+    let frame_size = 1024;
+    let mut compressor = SpectralCompressor::new(TEST_SAMPLE_RATE);
+    compressor.post_initialize(&VocoderContext {
+        frame_size,
+        hop_size: frame_size / 4,
+        analysis_window: AlignedVec::new(frame_size),
+        synthesis_window: AlignedVec::new(frame_size),
+    });
+
+    // instant envelopes, so every frame's gains only depend on that frame
+    let params = [(0, -20.0f32), (1, 10.0f32), (2, 0.0f32), (3, 0.0f32)];
+    for (idx, value) in params.iter() {
+        compressor.set_effect_parameter(*idx, BoardEffectConfigParameterValue::F(*value));
+    }
+    compressor.set_effect_parameter(5, BoardEffectConfigParameterValue::N(smoothing_bins));
+
+    let prng = WyHashPRNG::new(0x6d75_7369_6361_6c);
+    let mut fft = AlignedVec::new(frame_size);
+    let mut output = AlignedVec::new(frame_size);
+
+    (0..num_frames)
+        .map(|_| {
+            for i in 0..=frame_size / 2 {
+                let base = if i < frame_size / 4 { 0.3f32 } else { 0.01f32 };
+                let u = (prng.next() >> 40) as f32 / (1u64 << 24) as f32;
+                fft[i] = c32::new(base * (0.2f32 + 1.6f32 * u), 0.0f32);
+                fft[(frame_size - i) % frame_size] = fft[i];
+            }
+            compressor.execute(&fft, &mut output);
+
+            (0..=frame_size / 2)
+                .map(|i| 20.0f32 * (output[i].re / fft[i].re).log10())
+                .collect()
+        })
+        .collect()
+}
+
+#[test]
+fn test_spectral_smoothing_steadies_per_bin_gains() {
+    let num_frames = 200;
+    let raw = spectral_compressor_noise_gains_db(0, num_frames);
+    let smoothed = spectral_compressor_noise_gains_db(4, num_frames);
+
+    let mean = |values: &mut dyn Iterator<Item = f32>| {
+        let (sum, count) = values.fold((0.0f32, 0), |(s, c), v| (s + v, c + 1));
+        sum / count as f32
+    };
+    // frame to frame variance of each bin's gain, averaged over the bins
+    let flicker = |gains: &Vec<Vec<f32>>, bins: std::ops::Range<usize>| {
+        mean(&mut bins.map(|bin| {
+            let bin_mean = mean(&mut gains.iter().map(|frame| frame[bin]));
+            mean(&mut gains.iter().map(|frame| (frame[bin] - bin_mean).powi(2)))
+        }))
+    };
+    let average_gain = |gains: &Vec<Vec<f32>>, bins: std::ops::Range<usize>| {
+        mean(&mut gains.iter().flat_map(|frame| frame[bins.clone()].to_vec()))
+    };
+
+    // away from the edge between the two halves
+    let (loud, quiet) = (16..240, 272..496);
+
+    let raw_flicker = flicker(&raw, loud.clone());
+    let smoothed_flicker = flicker(&smoothed, loud.clone());
+    assert!(
+        smoothed_flicker < 0.3f32 * raw_flicker,
+        "flicker {} vs {}",
+        smoothed_flicker,
+        raw_flicker
+    );
+
+    // the loud half is still turned down by about as much, and the quiet half is still left alone
+    let (raw_loud, smoothed_loud) = (
+        average_gain(&raw, loud.clone()),
+        average_gain(&smoothed, loud),
+    );
+    assert!(raw_loud < -3.0f32, "{}", raw_loud);
+    assert!(
+        (smoothed_loud - raw_loud).abs() < 1.5f32,
+        "{} vs {}",
+        smoothed_loud,
+        raw_loud
+    );
+    assert!(average_gain(&smoothed, quiet).abs() < 0.01f32);
+}
+
+fn tone_with_clicks(num_samples: usize) -> Vec<f32> {
+    let tone_hz = 20.0f32 * TEST_SAMPLE_RATE / 1024.0f32;
+    let mut signal = sine(tone_hz, 0.5f32, num_samples);
+    for i in (0..num_samples).step_by(2048) {
+        signal[i] += 0.8f32;
+    }
+
+    signal
+}
+
+fn crest_factor(signal: &[f32]) -> f32 {
+    peak(signal) / rms(signal)
+}
+
+#[test]
+fn test_spectral_limiter_keeps_transients_better_than_broadband() {
+    let num_samples = 32768;
+    let input = tone_with_clicks(num_samples);
+    let settle = 8192;
+
+    let mut spectral = create_board(&mono_board_config(
+        "Vocoder/SpectralCompressor",
+        r#"[
+            {"name": "threshold_db", "value": {"F": -20.0}},
+            {"name": "release_time_ms", "value": {"F": 50.0}},
+            {"name": "limiter?", "value": {"N": 1}}
+        ]"#,
+    ));
+    let spectral_out = process_mono(&mut spectral, &input);
+    let spectral_out = &spectral_out[settle..];
+    let spectral_rms = rms(spectral_out);
+
+    // find the broadband threshold that takes the same amount of level off
+    let mut broadband_out = Vec::new();
+    let mut best_rms_error = f32::MAX;
+    for threshold_db in -40..=0 {
+        let mut broadband = create_board(&mono_board_config(
+            "Dynamics/BasicLimiter",
+            &format!(
+                r#"[
+                    {{"name": "threshold_db", "value": {{"F": {}}}}},
+                    {{"name": "attack_time_ms", "value": {{"F": 1.0}}}},
+                    {{"name": "release_time_ms", "value": {{"F": 50.0}}}},
+                    {{"name": "soft_knee?", "value": {{"N": 0}}}}
+                ]"#,
+                threshold_db as f32
+            ),
+        ));
+        let output = process_mono(&mut broadband, &input);
+        let rms_error = (rms(&output[settle..]) - spectral_rms).abs();
+        if rms_error < best_rms_error {
+            best_rms_error = rms_error;
+            broadband_out = output[settle..].to_vec();
+        }
+    }
+
+    let broadband_rms = rms(&broadband_out);
+    assert!(
+        (20.0f32 * (broadband_rms / spectral_rms).log10()).abs() < 1.0f32,
+        "spectral rms {} broadband rms {}",
+        spectral_rms,
+        broadband_rms
+    );
+
+    let spectral_crest = crest_factor(spectral_out);
+    let broadband_crest = crest_factor(&broadband_out);
+    assert!(
+        spectral_crest > 1.5f32 * broadband_crest,
+        "spectral crest {} broadband crest {}",
+        spectral_crest,
+        broadband_crest
+    );
+}
+
+#[test]
+fn test_band_bank_vocoder_shapes_carrier_with_low_latency() {
+    let (low_freq, high_freq, num_bands, band_q) = (100.0f32, 8000.0f32, 16, 8.0f32);
+    let band_center =
+        |band: i32| low_freq * (high_freq / low_freq).powf(band as f32 / (num_bands - 1) as f32);
+    let (quiet_low, voiced, quiet_high) = (band_center(2), band_center(8), band_center(14));
+
+    // carrier has equal tones in three bands, the modulator only speaks in the middle one
+    // and stays silent for a while first
+    let num_samples = 32768;
+    let onset = 8192;
+    let mut carrier = vec![0.0f32; num_samples];
+    for freq in &[quiet_low, voiced, quiet_high] {
+        for (c, x) in carrier.iter_mut().zip(sine(*freq, 0.3f32, num_samples)) {
+            *c += x;
+        }
+    }
+    let mut modulator = sine(voiced, 0.5f32, num_samples);
+    for x in modulator[..onset].iter_mut() {
+        *x = 0.0f32;
+    }
+
+    let mut otters = create_board(&stereo_board_config(
+        "Vocoder/BandBank",
+        &format!(
+            r#"[
+                {{"name": "num_bands", "value": {{"N": {}}}}},
+                {{"name": "low_freq_hz", "value": {{"F": {}}}}},
+                {{"name": "high_freq_hz", "value": {{"F": {}}}}},
+                {{"name": "band_q", "value": {{"F": {}}}}}
+            ]"#,
+            num_bands, low_freq, high_freq, band_q
+        ),
+    ));
+    let (output, _) = process_stereo(&mut otters, &modulator, &carrier);
+
+    assert!(output[..onset].iter().all(|x| x.abs() < 1e-6f32));
+
+    // the other bands only pick up the skirts of a 2nd order band pass (~-25 dB here)
+    let settled = &output[onset + 8192..];
+    let voiced_level = magnitude_at(settled, voiced);
+    assert!(voiced_level > 0.05f32, "voiced {}", voiced_level);
+    for freq in &[quiet_low, quiet_high] {
+        let level = magnitude_at(settled, *freq);
+        assert!(
+            level < 0.1f32 * voiced_level,
+            "{} Hz at {} voiced {}",
+            freq,
+            level,
+            voiced_level
+        );
+    }
+
+    // a 2nd order band pass settles with time constant 2Q / w0, its group delay at the center
+    let group_delay_samples = band_q / (std::f32::consts::PI * voiced) * TEST_SAMPLE_RATE;
+    let steady_peak = peak(settled);
+    let latency = output[onset..]
+        .iter()
+        .position(|x| x.abs() > 0.5f32 * steady_peak)
+        .unwrap();
+    assert!(
+        (latency as f32) < group_delay_samples,
+        "latency {} group delay {}",
+        latency,
+        group_delay_samples
+    );
+}
+
+#[test]
+fn test_vocoder_degrades_when_over_cpu_budget() {
+    let input = sine(1000.0f32, 0.5f32, 16384);
+
+    let mut clean = create_board(&mono_board_config("Vocoder/Bypass", "[]"));
+    clean.set_cpu_budget(Some(1.0e6f32));
+    let clean_output = process_mono(&mut clean, &input);
+    assert!(!clean.is_degraded());
+
+    // no block can finish in zero time, so every block after the first runs degraded
+    let mut starved = create_board(&mono_board_config("Vocoder/Bypass", "[]"));
+    starved.set_cpu_budget(Some(0.0f32));
+    let starved_output = process_mono(&mut starved, &input);
+    assert!(starved.is_degraded());
+
+    // every other frame is a repeat: worse, but still finite and still audio
+    let settled = &starved_output[8192..];
+    assert!(settled.iter().all(|x| x.is_finite()));
+    assert!(rms(settled) > 0.05f32, "rms {}", rms(settled));
+
+    let difference: Vec<f32> = clean_output[8192..]
+        .iter()
+        .zip(settled.iter())
+        .map(|(a, b)| a - b)
+        .collect();
+    assert!(rms(&difference) > 0.01f32, "rms {}", rms(&difference));
+
+    starved.set_cpu_budget(None);
+    assert!(!starved.is_degraded());
+}
+
+// steady state output of a bypassed vocoder fed a constant 1.0
+fn bypass_vocoder_dc_output(hop_size: usize, synthesis_windowing: bool) -> (Vec<f32>, f32) {
+    let frame_size = 1024;
+    let vocoder = PhaseVocoder::with_windowing(
+        frame_size,
+        hop_size,
+        FFTWindowType::Hamming,
+        true,
+        synthesis_windowing,
+        VocoderBypass::new(),
+    )
+    .unwrap();
+
+    let output: Vec<f32> = (0..8 * frame_size)
+        .map(|_| vocoder.execute_one(1.0f32, false))
+        .collect();
+
+    // overlap-add predicts a gain of hop * sum(a * s over overlapping frames) / sum(a * s)
+    let (window, _) = create_window(FFTWindowType::Hamming, 0.0f32, frame_size);
+    let product: Vec<f32> = (0..frame_size)
+        .map(|i| {
+            if synthesis_windowing {
+                window[i] * window[i]
+            } else {
+                window[i]
+            }
+        })
+        .collect();
+    let product_sum: f32 = product.iter().sum();
+    let mut predicted_ripple = 0.0f32;
+    for offset in 0..hop_size {
+        let overlapped: f32 = product[offset..].iter().step_by(hop_size).sum();
+        let gain = overlapped * hop_size as f32 / product_sum;
+        predicted_ripple = predicted_ripple.max((gain - 1.0f32).abs());
+    }
+
+    (output[4 * frame_size..].to_vec(), predicted_ripple)
+}
+
+#[test]
+fn test_vocoder_synthesis_windowing_changes_overlap_add() {
+    let frame_size = 1024;
+    let (analysis_only, analysis_only_ripple) = bypass_vocoder_dc_output(frame_size / 4, false);
+    let (both, both_ripple) = bypass_vocoder_dc_output(frame_size / 4, true);
+    let (both_half_overlap, both_half_overlap_ripple) =
+        bypass_vocoder_dc_output(frame_size / 2, true);
+
+    // hamming and hamming squared both add up flat at 75% overlap
+    for (output, ripple) in [(&analysis_only, analysis_only_ripple), (&both, both_ripple)].iter() {
+        assert!(*ripple < 1.0e-3f32, "predicted ripple {}", ripple);
+        for x in output.iter() {
+            assert!((x - 1.0f32).abs() < 1.0e-3f32, "sample {}", x);
+        }
+    }
+
+    // hamming squared at 50% overlap doesn't, and the output wobbles the way overlap-add says
+    assert!(
+        both_half_overlap_ripple > 0.05f32,
+        "predicted ripple {}",
+        both_half_overlap_ripple
+    );
+    let measured_ripple = both_half_overlap
+        .iter()
+        .fold(0.0f32, |acc, x| acc.max((x - 1.0f32).abs()));
+    assert!(
+        (measured_ripple - both_half_overlap_ripple).abs() < 1.0e-3f32,
+        "measured {} predicted {}",
+        measured_ripple,
+        both_half_overlap_ripple
+    );
+}
+
+#[test]
+fn test_vocoders_of_the_same_size_run_independently() {
+    let first = PhaseVocoder::new(512, 128, FFTWindowType::Hann, VocoderBypass::new()).unwrap();
+    let second = PhaseVocoder::new(512, 128, FFTWindowType::Hann, VocoderBypass::new()).unwrap();
+
+    // running one after the other doesn't change what either puts out
+    let prng = WyHashPRNG::new(7);
+    for _ in 0..4096 {
+        let x = (prng.next() % 2001) as f32 / 1000.0f32 - 1.0f32;
+        assert_eq!(first.execute_one(x, false), second.execute_one(x, false));
+    }
+}
+
+#[test]
+fn test_vocoders_plan_cheaply_unless_asked_to_measure() {
+    let estimated = PhaseVocoder::new(512, 128, FFTWindowType::Hann, VocoderBypass::new()).unwrap();
+    assert_eq!(estimated.planning(), FFTPlanning::Estimate);
+
+    let measured = PhaseVocoder::new(512, 128, FFTWindowType::Hann, VocoderBypass::new())
+        .unwrap()
+        .with_planning(FFTPlanning::Measure)
+        .unwrap();
+    assert_eq!(measured.planning(), FFTPlanning::Measure);
+
+    // the plans only differ in how they get there
+    let prng = WyHashPRNG::new(11);
+    for _ in 0..4096 {
+        let x = (prng.next() % 2001) as f32 / 1000.0f32 - 1.0f32;
+        let difference = estimated.execute_one(x, false) - measured.execute_one(x, false);
+        assert!(difference.abs() < 1e-5f32, "{}", difference);
+    }
+}
+
+fn vocoder_mix_output(effect_name: &str, mix_pct: f32, input: &[f32]) -> Vec<f32> {
+    let mut otters = create_board(&mono_board_config(
+        effect_name,
+        &format!(r#"[{{"name": "mix_pct", "value": {{"F": {}}}}}]"#, mix_pct),
+    ));
+
+    process_mono(&mut otters, input)
+}
+
+#[test]
+fn test_vocoder_mix_lines_the_dry_up_with_the_wet() {
+    let frame_size = 1024;
+    let prng = WyHashPRNG::new(0x6d69_78);
+    let input: Vec<f32> = (0..8 * frame_size)
+        .map(|_| 0.5f32 * ((prng.next() % 2001) as f32 / 1000.0f32 - 1.0f32))
+        .collect();
+
+    // every vocoder effect advertises it, after its own parameters
+    let info = crate::otters::Otters::get_effect_info_json(false);
+    assert_eq!(info.matches("mix_pct").count(), 8, "{}", info);
+
+    // straight from the processor's static list, nothing is built (or leaked) to add it
+    let vocoder = PhaseVocoder::new(512, 128, FFTWindowType::Hann, VocoderBypass::new()).unwrap();
+    assert!(std::ptr::eq(
+        AudioEffect::advertise_parameters(&vocoder),
+        VocoderBypass::info()
+    ));
+
+    // all dry is the input, delayed by the vocoder's latency and nothing else
+    let dry = vocoder_mix_output("Vocoder/Robotize", 0.0f32, &input);
+    assert_eq!(&dry[frame_size..], &input[..input.len() - frame_size]);
+    assert!(dry[..frame_size].iter().all(|x| *x == 0.0f32));
+
+    // the bypass vocoder's wet is the same delayed input, so half and half only comb filters if
+    // the two are out of line
+    let wet = vocoder_mix_output("Vocoder/Bypass", 1.0f32, &input);
+    let half = vocoder_mix_output("Vocoder/Bypass", 0.5f32, &input);
+    let settled = 2 * frame_size;
+    let residual: Vec<f32> = half[settled..]
+        .iter()
+        .zip(wet[settled..].iter())
+        .map(|(h, w)| h - w)
+        .collect();
+    assert!(
+        rms(&residual) < 1.0e-3f32 * rms(&wet[settled..]),
+        "{} vs {}",
+        rms(&residual),
+        rms(&wet[settled..])
+    );
+}
+
+#[test]
+fn test_spectral_gate_removes_noise_under_a_tone() {
+    let num_samples = 16384;
+    let tone_hz = 1000.0f32;
+    let tone = sine(tone_hz, 0.5f32, num_samples);
+
+    // about -67 dB per bin
+    let prng = WyHashPRNG::new(0x6761_7465);
+    let noise: Vec<f32> = (0..num_samples)
+        .map(|_| 0.01f32 * ((prng.next() % 2001) as f32 / 1000.0f32 - 1.0f32))
+        .collect();
+    let input: Vec<f32> = tone.iter().zip(noise.iter()).map(|(t, n)| t + n).collect();
+
+    let config = r#"[{"name": "threshold_db", "value": {"F": -50.0}}]"#;
+
+    let mut otters = create_board(&mono_board_config("Vocoder/SpectralGate", config));
+    let gated_noise = process_mono(&mut otters, &noise);
+    assert!(
+        rms(&gated_noise[4096..]) < 0.01f32 * rms(&noise),
+        "{}",
+        rms(&gated_noise[4096..])
+    );
+
+    let mut otters = create_board(&mono_board_config("Vocoder/SpectralGate", config));
+    let output = process_mono(&mut otters, &input);
+    let settled = &output[4096..];
+    assert!(
+        (magnitude_at(settled, tone_hz) - magnitude_at(&tone[4096..], tone_hz)).abs() < 0.02f32,
+        "{}",
+        magnitude_at(settled, tone_hz)
+    );
+}
+
+#[test]
+fn test_spectral_gate_keeps_bins_with_their_mirrors() {
+    let frame_size = 16;
+    let mut analysis_window = AlignedVec::new(frame_size);
+    for i in 0..frame_size {
+        analysis_window[i] = 1.0f32;
+    }
+
+    let mut gate = SpectralGate::new();
+    gate.post_initialize(&VocoderContext {
+        frame_size,
+        hop_size: frame_size / 4,
+        analysis_window,
+        synthesis_window: AlignedVec::new(frame_size),
+    });
+    gate.set_effect_parameter(0, BoardEffectConfigParameterValue::F(-6.0f32));
+
+    // with a flat window a sine of amplitude a shows up as a * 16 / 2 in its bin and the mirror,
+    // while dc / nyquist of amplitude a show up as a * 16 in their one bin
+    let mut fft = AlignedVec::new(frame_size);
+    fft[0] = c32::new(0.4f32 * 16.0f32, 0.0f32);
+    fft[8] = c32::new(0.6f32 * 16.0f32, 0.0f32);
+    fft[3] = c32::new(0.0f32, 0.6f32 * 8.0f32);
+    fft[13] = fft[3].conj();
+    fft[5] = c32::new(0.4f32 * 8.0f32, 0.0f32);
+    fft[11] = fft[5].conj();
+
+    let mut output = AlignedVec::new(frame_size);
+    for i in 0..frame_size {
+        output[i] = c32::new(1.0f32, 1.0f32);
+    }
+    gate.execute(&fft, &mut output);
+
+    // -6 dB is ~0.5. dc reads 0.4 and goes, nyquist reads 0.6 and stays
+    assert_eq!(output[0], c32::new(0.0f32, 0.0f32));
+    assert_eq!(output[8], fft[8]);
+    assert_eq!(output[3], fft[3]);
+    assert_eq!(output[13], fft[13]);
+    for &i in [5, 11, 1, 15].iter() {
+        assert_eq!(output[i], c32::new(0.0f32, 0.0f32), "bin {}", i);
+    }
+}
+
+#[test]
+fn test_freeze_sustains_a_note_after_it_stops() {
+    let tone_hz = 440.0f32;
+    let note = sine(tone_hz, 0.5f32, TEST_SAMPLE_RATE as usize / 2);
+    let silence = vec![0.0f32; TEST_SAMPLE_RATE as usize];
+
+    let mut otters = create_board(&mono_board_config("Vocoder/Freeze", "[]"));
+
+    // unfrozen it passes the note through like the bypass does
+    let mut bypass = create_board(&mono_board_config("Vocoder/Bypass", "[]"));
+    let played = process_mono(&mut otters, &note);
+    let reference = process_mono(&mut bypass, &note);
+    let settled = TEST_SAMPLE_RATE as usize / 4;
+    assert!(
+        (rms(&played[settled..]) - rms(&reference[settled..])).abs() < 0.01f32,
+        "{} {}",
+        rms(&played[settled..]),
+        rms(&reference[settled..])
+    );
+
+    // the frozen param is the first (and only) one on the board
+    otters.set_effect_parameter(0, BoardEffectConfigParameterValue::N(1));
+    let held = process_mono(&mut otters, &silence);
+
+    // a second after the input went silent the note is still there, at its pitch
+    let tail = &held[held.len() / 2..];
+    assert!(
+        rms(tail) > 0.5f32 * rms(&reference[settled..]),
+        "{}",
+        rms(tail)
+    );
+    assert!(
+        magnitude_at(tail, tone_hz) > 4.0f32 * magnitude_at(tail, 2.0f32 * tone_hz),
+        "{} {}",
+        magnitude_at(tail, tone_hz),
+        magnitude_at(tail, 2.0f32 * tone_hz)
+    );
+
+    // and unfreezing lets the silence through again
+    otters.set_effect_parameter(0, BoardEffectConfigParameterValue::N(0));
+    let released = process_mono(&mut otters, &silence);
+    assert!(
+        peak(&released[settled..]) < 1e-4f32,
+        "{}",
+        peak(&released[settled..])
+    );
+}
@@ -1,28 +1,63 @@
 use crate::conf::{AdvertisedParameter, AudioConfig, BoardEffectConfigParameterValue};
 use crate::context::BoardContext;
 use crate::effects::VocoderContext;
+use crate::utils::state::{StateReader, StateWriter};
 use fftw::array::AlignedVec;
 use fftw::types::c32;
 
 pub trait AudioEffect {
     fn advertise_parameters(&self) -> &'static [AdvertisedParameter];
     fn set_audio_parameters(&mut self, new_config: &AudioConfig);
+    // queued parameter updates are applied from frolic, so this has to be rt-safe:
+    // no allocating, freeing or locking. size anything a parameter can grow up front
     fn set_effect_parameter(
         &mut self,
         param_idx: usize,
         param_value: BoardEffectConfigParameterValue,
     );
+    // only effects advertising a ParameterRange::FArray need to implement this
+    fn set_effect_parameter_array(&mut self, _param_idx: usize, _param_values: &[f32]) {}
+    // clear any internal state (delay lines, tails, envelopes). parameters are left alone
+    fn reset(&mut self) {}
+    // for visualizers: copy recent internal signal (e.g. a delay line) into output
+    // must not allocate or affect processing. returns the number of samples copied
+    fn copy_visualization_samples(&self, _output: &mut [f32]) -> usize {
+        0
+    }
+    // how many samples late the output is relative to the input, for latency compensation
+    fn latency_samples(&self) -> usize {
+        0
+    }
+    // the most latency_samples can be under any parameter settings. the board sizes the
+    // delay lines that line the dry signal up with the effect by it, once, at load
+    fn max_latency_samples(&self) -> usize {
+        self.latency_samples()
+    }
+    // for snapshots: everything execute() depends on besides the parameters
+    // both return false if the effect doesn't support it (or the data doesn't fit this instance)
+    fn serialize_state(&self, _writer: &mut StateWriter) -> bool {
+        false
+    }
+    fn deserialize_state(&mut self, _reader: &mut StateReader) -> bool {
+        false
+    }
     fn execute(&self, context: &BoardContext, connection_idx: usize, num_samples: usize);
 }
 
 pub trait FrequencyDomainAudioEffect {
+    // has to end with vocoder2::MIX_PARAM, which the vocoder takes care of
     fn advertise_parameters(&self) -> &'static [AdvertisedParameter];
     fn post_initialize(&mut self, vocoder_context: &VocoderContext);
+    // clear any state carried from frame to frame. runs on the audio thread, so it has to
+    // work on what post_initialize already sized rather than allocating again
+    fn reset(&mut self) {}
+    // called from the vocoder's set_effect_parameter, so the same rt-safety rules apply
     fn set_effect_parameter(
         &mut self,
         param_idx: usize,
         param_value: BoardEffectConfigParameterValue,
     );
+    fn set_effect_parameter_array(&mut self, _param_idx: usize, _param_values: &[f32]) {}
     fn execute(&self, fft: &AlignedVec<c32>, output: &mut AlignedVec<c32>);
     fn post_process(&self, ifft: &mut AlignedVec<c32>);
 }
\ No newline at end of file
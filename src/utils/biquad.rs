@@ -1,12 +1,14 @@
 use super::mathutils::{db_to_linear, vcosf, vsinf, vtanf};
 use super::ringbuf::TinyFloatBuffer;
+use super::state::{StateReader, StateWriter};
 
 use num_derive::FromPrimitive;
 use std::mem;
 
 const DEFAULT_Q: f32 = 0.707f32;
+const BUTTERWORTH_Q: f32 = std::f32::consts::FRAC_1_SQRT_2;
 
-#[derive(Clone, FromPrimitive)]
+#[derive(Clone, PartialEq, FromPrimitive)]
 #[allow(non_camel_case_types)]
 pub enum IIRFilterType {
     FirstOrderLowPass = 0,
@@ -19,11 +21,15 @@ pub enum IIRFilterType {
     SecondOrderAllPass,
     FirstOrderLowShelf,
     FirstOrderHighShelf,
+    SecondOrderParametricEQ,
+    SecondOrderLowShelf,
+    SecondOrderHighShelf,
+    SecondOrderNotch,
 
     __NUM_IIR_FILTER_TYPES,
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub struct BiquadCoefficients {
     a0: f32,
     a1: f32,
@@ -43,6 +49,12 @@ pub struct Biquad {
     coefficients: BiquadCoefficients,
     x: TinyFloatBuffer,
     y: TinyFloatBuffer,
+
+    // opt-in f64 state for long/resonant filters where f32 rounding builds up
+    // [z^-1, z^-2]
+    double_precision: bool,
+    x64: [f64; 2],
+    y64: [f64; 2],
 }
 
 impl Default for IIRFilterType {
@@ -52,6 +64,12 @@ impl Default for IIRFilterType {
 }
 
 impl BiquadCoefficients {
+    // [a0, a1, a2, b1, b2, c0, d0], for filters that keep their own copy (e.g. FixedBiquad)
+    #[cfg(feature = "fixed_point")]
+    pub fn raw_coefficients(&self) -> [f32; 7] {
+        [self.a0, self.a1, self.a2, self.b1, self.b2, self.c0, self.d0]
+    }
+
     pub fn set_cutoff(mut self, new_cutoff: f32) -> BiquadCoefficients {
         self.cutoff = new_cutoff;
         self.recreate()
@@ -109,6 +127,30 @@ impl BiquadCoefficients {
             IIRFilterType::FirstOrderHighShelf => {
                 BiquadCoefficients::first_order_high_shelf(self.cutoff, self.sample_rate, self.shelf_gain_db)
             }
+            IIRFilterType::SecondOrderParametricEQ => BiquadCoefficients::second_order_peaking(
+                self.cutoff,
+                self.sample_rate,
+                Some(self.q),
+                self.shelf_gain_db,
+            ),
+            IIRFilterType::SecondOrderLowShelf => BiquadCoefficients::second_order_low_shelf(
+                self.cutoff,
+                self.sample_rate,
+                self.shelf_gain_db,
+                Some(self.q),
+            ),
+            IIRFilterType::SecondOrderHighShelf => BiquadCoefficients::second_order_high_shelf(
+                self.cutoff,
+                self.sample_rate,
+                self.shelf_gain_db,
+                Some(self.q),
+            ),
+            IIRFilterType::SecondOrderNotch => BiquadCoefficients::second_order_notch(
+                self.cutoff,
+                self.sample_rate,
+                Some(self.q),
+                self.shelf_gain_db,
+            ),
 
             IIRFilterType::__NUM_IIR_FILTER_TYPES => panic!("Should never get here"),
         }
@@ -282,6 +324,41 @@ impl BiquadCoefficients {
         }
     }
 
+    // 1 - (1 - depth) * bpf: a band stop that only takes the band down by depth_db
+    // 0 dB passes everything, very negative depths approach second_order_bsf
+    pub fn second_order_notch(
+        corner: f32,
+        sample_rate: f32,
+        q: Option<f32>,
+        depth_db: f32,
+    ) -> BiquadCoefficients {
+        let q = q.unwrap_or(DEFAULT_Q);
+        let k = vtanf(std::f32::consts::PI * corner / sample_rate);
+        let delta = k * k * q + k + q;
+        let removed = 1.0f32 - db_to_linear(depth_db);
+
+        let a0 = (delta - removed * k) / delta;
+        let a1 = (2.0f32 * q * (k * k - 1.0f32)) / delta;
+        let a2 = (k * k * q - k + q + removed * k) / delta;
+        let b1 = a1;
+        let b2 = (k * k * q - k + q) / delta;
+
+        BiquadCoefficients {
+            a0,
+            a1,
+            a2,
+            b1,
+            b2,
+            c0: 1.0f32,
+            d0: 0.0f32,
+            cutoff: corner,
+            sample_rate,
+            q,
+            shelf_gain_db: depth_db,
+            iir_type: IIRFilterType::SecondOrderNotch,
+        }
+    }
+
     pub fn first_order_apf(corner: f32, sample_rate: f32) -> BiquadCoefficients {
         // TODO: corner MUST be less than 0.5* sample_rate (nyquist freq)
         // tan is undefined
@@ -403,6 +480,135 @@ impl BiquadCoefficients {
             iir_type: IIRFilterType::FirstOrderHighShelf,
         }
     }
+
+    // constant-q bell (rbj cookbook). gain_db at center_freq, 0 dB far away from it
+    // the bandwidth is the same for a boost and the matching cut
+    pub fn second_order_peaking(
+        center_freq: f32,
+        sample_rate: f32,
+        q: Option<f32>,
+        gain_db: f32,
+    ) -> BiquadCoefficients {
+        let q = q.unwrap_or(DEFAULT_Q);
+
+        let theta_c = super::TWO_PI * center_freq / sample_rate;
+        let big_a = 10.0f32.powf(gain_db / 40.0f32);
+        let alpha = vsinf(theta_c) / (2.0f32 * q);
+        let cos_theta_c = vcosf(theta_c);
+        let norm = 1.0f32 + alpha / big_a;
+
+        let a0 = (1.0f32 + alpha * big_a) / norm;
+        let a1 = -2.0f32 * cos_theta_c / norm;
+        let a2 = (1.0f32 - alpha * big_a) / norm;
+        let b1 = a1;
+        let b2 = (1.0f32 - alpha / big_a) / norm;
+
+        BiquadCoefficients {
+            a0,
+            a1,
+            a2,
+            b1,
+            b2,
+            c0: 1.0f32,
+            d0: 0.0f32,
+            cutoff: center_freq,
+            sample_rate,
+            q,
+            shelf_gain_db: gain_db,
+            iir_type: IIRFilterType::SecondOrderParametricEQ,
+        }
+    }
+
+    // 12 dB/oct shelves (rbj cookbook). half the gain lands at shelf_freq
+    // q above ~0.707 adds a bump on the shelf side and a dip just past it
+    pub fn second_order_low_shelf(
+        shelf_freq: f32,
+        sample_rate: f32,
+        gain_db: f32,
+        q: Option<f32>,
+    ) -> BiquadCoefficients {
+        let q = q.unwrap_or(DEFAULT_Q);
+
+        let theta_c = super::TWO_PI * shelf_freq / sample_rate;
+        let big_a = 10.0f32.powf(gain_db / 40.0f32);
+        let alpha = vsinf(theta_c) / (2.0f32 * q);
+        let cos_theta_c = vcosf(theta_c);
+        let two_sqrt_a_alpha = 2.0f32 * big_a.sqrt() * alpha;
+        let norm = (big_a + 1.0f32) + (big_a - 1.0f32) * cos_theta_c + two_sqrt_a_alpha;
+
+        let a0 =
+            big_a * ((big_a + 1.0f32) - (big_a - 1.0f32) * cos_theta_c + two_sqrt_a_alpha) / norm;
+        let a1 = 2.0f32 * big_a * ((big_a - 1.0f32) - (big_a + 1.0f32) * cos_theta_c) / norm;
+        let a2 =
+            big_a * ((big_a + 1.0f32) - (big_a - 1.0f32) * cos_theta_c - two_sqrt_a_alpha) / norm;
+        let b1 = -2.0f32 * ((big_a - 1.0f32) + (big_a + 1.0f32) * cos_theta_c) / norm;
+        let b2 = ((big_a + 1.0f32) + (big_a - 1.0f32) * cos_theta_c - two_sqrt_a_alpha) / norm;
+
+        BiquadCoefficients {
+            a0,
+            a1,
+            a2,
+            b1,
+            b2,
+            c0: 1.0f32,
+            d0: 0.0f32,
+            cutoff: shelf_freq,
+            sample_rate,
+            q,
+            shelf_gain_db: gain_db,
+            iir_type: IIRFilterType::SecondOrderLowShelf,
+        }
+    }
+
+    pub fn second_order_high_shelf(
+        shelf_freq: f32,
+        sample_rate: f32,
+        gain_db: f32,
+        q: Option<f32>,
+    ) -> BiquadCoefficients {
+        let q = q.unwrap_or(DEFAULT_Q);
+
+        let theta_c = super::TWO_PI * shelf_freq / sample_rate;
+        let big_a = 10.0f32.powf(gain_db / 40.0f32);
+        let alpha = vsinf(theta_c) / (2.0f32 * q);
+        let cos_theta_c = vcosf(theta_c);
+        let two_sqrt_a_alpha = 2.0f32 * big_a.sqrt() * alpha;
+        let norm = (big_a + 1.0f32) - (big_a - 1.0f32) * cos_theta_c + two_sqrt_a_alpha;
+
+        let a0 =
+            big_a * ((big_a + 1.0f32) + (big_a - 1.0f32) * cos_theta_c + two_sqrt_a_alpha) / norm;
+        let a1 = -2.0f32 * big_a * ((big_a - 1.0f32) + (big_a + 1.0f32) * cos_theta_c) / norm;
+        let a2 =
+            big_a * ((big_a + 1.0f32) + (big_a - 1.0f32) * cos_theta_c - two_sqrt_a_alpha) / norm;
+        let b1 = 2.0f32 * ((big_a - 1.0f32) - (big_a + 1.0f32) * cos_theta_c) / norm;
+        let b2 = ((big_a + 1.0f32) - (big_a - 1.0f32) * cos_theta_c - two_sqrt_a_alpha) / norm;
+
+        BiquadCoefficients {
+            a0,
+            a1,
+            a2,
+            b1,
+            b2,
+            c0: 1.0f32,
+            d0: 0.0f32,
+            cutoff: shelf_freq,
+            sample_rate,
+            q,
+            shelf_gain_db: gain_db,
+            iir_type: IIRFilterType::SecondOrderHighShelf,
+        }
+    }
+
+    // one of the two butterworth sections of a 4th order linkwitz-riley low pass.
+    // run the signal through two of these (see LinkwitzRileyCrossover)
+    pub fn linkwitz_riley_lpf(crossover_freq: f32, sample_rate: f32) -> BiquadCoefficients {
+        BiquadCoefficients::second_order_lpf(crossover_freq, sample_rate, Some(BUTTERWORTH_Q))
+    }
+
+    // the high pass half of linkwitz_riley_lpf. also cascaded twice
+    pub fn linkwitz_riley_hpf(crossover_freq: f32, sample_rate: f32) -> BiquadCoefficients {
+        BiquadCoefficients::second_order_hpf(crossover_freq, sample_rate, Some(BUTTERWORTH_Q))
+    }
 }
 
 impl Biquad {
@@ -411,10 +617,18 @@ impl Biquad {
             coefficients: coeff,
             x: TinyFloatBuffer::new(),
             y: TinyFloatBuffer::new(),
+            double_precision: false,
+            x64: [0.0f64; 2],
+            y64: [0.0f64; 2],
         }
     }
 
+    // the filter memory is kept, so a running filter carries on at the new rate
     pub fn change_sample_rate(&mut self, new_sample_rate: f32) {
+        if new_sample_rate == self.coefficients.sample_rate {
+            return;
+        }
+
         let mut temp = self.coefficients.clone();
 
         mem::swap(&mut self.coefficients, &mut temp);
@@ -458,21 +672,144 @@ impl Biquad {
         self.coefficients = new_params;
     }
 
+    pub fn coefficients(&self) -> &BiquadCoefficients {
+        &self.coefficients
+    }
+
+    // switching modes starts the new state from silence
+    pub fn set_double_precision(&mut self, double_precision: bool) {
+        if self.double_precision == double_precision {
+            return;
+        }
+
+        self.double_precision = double_precision;
+        self.x = TinyFloatBuffer::new();
+        self.y = TinyFloatBuffer::new();
+        self.x64 = [0.0f64; 2];
+        self.y64 = [0.0f64; 2];
+    }
+
+    // forgets the filter memory, in whichever precision is in use. coefficients stay
+    pub fn clear(&mut self) {
+        self.x = TinyFloatBuffer::new();
+        self.y = TinyFloatBuffer::new();
+        self.x64 = [0.0f64; 2];
+        self.y64 = [0.0f64; 2];
+    }
+
+    // only the filter memory. coefficients come from parameters
+    pub fn save_state(&self, writer: &mut StateWriter) {
+        self.x.save_state(writer);
+        self.y.save_state(writer);
+        for v in self.x64.iter().chain(self.y64.iter()) {
+            writer.write_f64(*v);
+        }
+    }
+
+    pub fn load_state(&mut self, reader: &mut StateReader) -> Option<()> {
+        self.x.load_state(reader)?;
+        self.y.load_state(reader)?;
+        for v in self.x64.iter_mut().chain(self.y64.iter_mut()) {
+            *v = reader.read_f64()?;
+        }
+
+        Some(())
+    }
+
     pub fn filter(&mut self, input: f32) -> f32 {
+        if self.double_precision {
+            return self.filter_f64(input);
+        }
+
         // y(n) = c_0 * (a_0 * x(n) + a_1 * x(n - 1) + a_2 * x(n - 2) - b_1 * y(n - 1) - b_2 * y (n - 2)) + d_0 * x(n)
-        // TODO: low hanging fruit for vectorization
-        let result = self.coefficients.c0
-            * (self.coefficients.a0 * input
-                + self.coefficients.a1 * self.x.z1()
-                + self.coefficients.a2 * self.x.z2()
-                - self.coefficients.b1 * self.y.z1()
-                - self.coefficients.b2 * self.y.z2())
-            + self.coefficients.d0 * input;
+        // see filter_block for a whole block at a time
+        let filtered = self.coefficients.a0 * input
+            + self.coefficients.a1 * self.x.z1()
+            + self.coefficients.a2 * self.x.z2()
+            - self.coefficients.b1 * self.y.z1()
+            - self.coefficients.b2 * self.y.z2();
 
+        // the feedback path only sees the filter itself, not the shelf mix (c_0 / d_0)
         self.x.write(input);
-        self.y.write(result);
+        self.y.write(filtered);
+
+        self.coefficients.c0 * filtered + self.coefficients.d0 * input
+    }
+
+    // same as calling filter on every input in order, down to the last bit, state included.
+    // only the feedback has to go a sample at a time. the feedforward sum and the shelf mix are
+    // done in their own passes over the block, which the compiler can vectorize.
+    // the sums are added up in the same order filter does, so rounding doesn't change
+    pub fn filter_block(&mut self, input: &[f32], output: &mut [f32]) {
+        let num_samples = input.len().min(output.len());
+        let input = &input[..num_samples];
+        let output = &mut output[..num_samples];
+
+        if self.double_precision {
+            for (x_n, y_n) in input.iter().zip(output.iter_mut()) {
+                *y_n = self.filter_f64(*x_n);
+            }
+
+            return;
+        }
+
+        if num_samples == 0 {
+            return;
+        }
+
+        let c = &self.coefficients;
+        let (mut x1, mut x2) = (self.x.z1(), self.x.z2());
+        let (mut y1, mut y2) = (self.y.z1(), self.y.z2());
+
+        // a_0 * x(n) + a_1 * x(n - 1) + a_2 * x(n - 2). the first two samples reach into the
+        // previous block, the rest only look at this one
+        for n in 0..num_samples.min(2) {
+            let x_n1 = if n == 0 { x1 } else { input[0] };
+            let x_n2 = if n == 0 { x2 } else { x1 };
+            output[n] = c.a0 * input[n] + c.a1 * x_n1 + c.a2 * x_n2;
+        }
+        for n in 2..num_samples {
+            output[n] = c.a0 * input[n] + c.a1 * input[n - 1] + c.a2 * input[n - 2];
+        }
+
+        // - b_1 * y(n - 1) - b_2 * y(n - 2)
+        for y_n in output.iter_mut() {
+            let filtered = *y_n - c.b1 * y1 - c.b2 * y2;
+            y2 = y1;
+            y1 = filtered;
+            *y_n = filtered;
+        }
+
+        if num_samples >= 2 {
+            x2 = input[num_samples - 2];
+        } else {
+            x2 = x1;
+        }
+        x1 = input[num_samples - 1];
+
+        for (y_n, x_n) in output.iter_mut().zip(input.iter()) {
+            *y_n = c.c0 * *y_n + c.d0 * *x_n;
+        }
+
+        self.x.write(x2);
+        self.x.write(x1);
+        self.y.write(y2);
+        self.y.write(y1);
+    }
+
+    // same as filter, but the math and the state are f64. only the input and output are f32
+    fn filter_f64(&mut self, input: f32) -> f32 {
+        let c = &self.coefficients;
+        let input = input as f64;
+
+        let filtered = c.a0 as f64 * input + c.a1 as f64 * self.x64[0] + c.a2 as f64 * self.x64[1]
+            - c.b1 as f64 * self.y64[0]
+            - c.b2 as f64 * self.y64[1];
+
+        self.x64 = [input, self.x64[0]];
+        self.y64 = [filtered, self.y64[0]];
 
-        result
+        (c.c0 as f64 * filtered + c.d0 as f64 * input) as f32
     }
 
     pub fn g(&self) -> f32 {
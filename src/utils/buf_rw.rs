@@ -26,6 +26,23 @@ impl<'a> AudioBufferReader<'a> {
             AudioBufferReader::External(ptr) => unsafe_buf_read(ptr, idx),
         }
     }
+
+    // the whole block at once, so a loop over it doesn't go through the match every sample.
+    // None for Null, and for an internal buffer whose block wraps round its end.
+    // an external source is assumed to be at least num_samples long, same as for buf_read
+    pub fn read_slice(&self, num_samples: usize) -> Option<&[f32]> {
+        match *self {
+            AudioBufferReader::Null => None,
+            AudioBufferReader::Internal(ref flt_buf) => flt_buf.read_slice(num_samples),
+            AudioBufferReader::External(ptr) => {
+                if ptr.is_null() {
+                    return None;
+                }
+
+                Some(unsafe { std::slice::from_raw_parts(ptr, num_samples) })
+            }
+        }
+    }
 }
 
 impl<'a> AudioBufferWriter<'a> {
@@ -36,6 +53,84 @@ impl<'a> AudioBufferWriter<'a> {
             AudioBufferWriter::External(ptr) => unsafe_buf_write(ptr, idx, value),
         }
     }
+
+    // same as calling buf_write(idx + i, values[i]) for every value in order, with one match
+    // for the lot. an internal buffer is written in order anyway, so it doesn't need idx
+    pub fn buf_write_slice(&mut self, idx: usize, values: &[f32]) {
+        match *self {
+            AudioBufferWriter::Null => (),
+            AudioBufferWriter::Internal(ref mut flt_buf) => {
+                for value in values.iter() {
+                    flt_buf.write(*value);
+                }
+            }
+            AudioBufferWriter::External(ptr) => {
+                if ptr.is_null() {
+                    return;
+                }
+
+                // a host may hand the same memory in as a source and out as a sink
+                unsafe { std::ptr::copy(values.as_ptr(), ptr.add(idx), values.len()) }
+            }
+        }
+    }
+
+    // crossfades a sample written earlier in this block with dry_value
+    // out = wet_amount * out + (1 - wet_amount) * dry_value
+    pub fn buf_blend(&mut self, idx: usize, num_samples: usize, wet_amount: f32, dry_value: f32) {
+        match *self {
+            AudioBufferWriter::Null => (),
+            AudioBufferWriter::Internal(ref mut flt_buf) => {
+                flt_buf.blend_written(idx, num_samples, wet_amount, dry_value)
+            }
+            AudioBufferWriter::External(ptr) => {
+                let wet_value = unsafe_buf_read(ptr, idx);
+                unsafe_buf_write(
+                    ptr,
+                    idx,
+                    wet_amount * wet_value + (1.0f32 - wet_amount) * dry_value,
+                )
+            }
+        }
+    }
+}
+
+// how much of a block process_block works out before writing it
+const PROCESS_BLOCK_CHUNK_LEN: usize = 256;
+
+// write_buf[i] = f(read_buf[i]) for the whole block. reads straight off a slice when
+// read_buf has one, and falls back to buf_read when it doesn't.
+// a host may bind the same memory as a source and a sink, so a chunk is worked out into
+// scratch and the input slice is gone before the chunk is written back over it
+pub fn process_block<F: FnMut(f32) -> f32>(
+    read_buf: &AudioBufferReader,
+    write_buf: &mut AudioBufferWriter,
+    num_samples: usize,
+    mut f: F,
+) {
+    let mut scratch = [0.0f32; PROCESS_BLOCK_CHUNK_LEN];
+    let mut start = 0;
+
+    while start < num_samples {
+        let end = (start + PROCESS_BLOCK_CHUNK_LEN).min(num_samples);
+        let chunk = &mut scratch[..end - start];
+
+        match read_buf.read_slice(end) {
+            Some(input) => {
+                for (y_n, x_n) in chunk.iter_mut().zip(input[start..end].iter()) {
+                    *y_n = f(*x_n);
+                }
+            }
+            None => {
+                for (i, y_n) in chunk.iter_mut().enumerate() {
+                    *y_n = f(read_buf.buf_read(start + i));
+                }
+            }
+        }
+
+        write_buf.buf_write_slice(start, chunk);
+        start = end;
+    }
 }
 
 impl<'a> Default for AudioBufferReader<'a> {
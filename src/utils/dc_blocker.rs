@@ -0,0 +1,56 @@
+use super::state::{StateReader, StateWriter};
+use super::TWO_PI;
+
+// one-pole, one-zero high pass: y[n] = x[n] - x[n-1] + r * y[n-1]
+// the zero sits right on dc, the pole sets how fast an offset is forgotten
+pub struct DcBlocker {
+    r: f32,
+    x_1: f32,
+    y_1: f32,
+}
+
+impl DcBlocker {
+    pub fn new(cutoff_hz: f32, sample_rate: f32) -> DcBlocker {
+        let mut blocker = DcBlocker {
+            r: 0.0f32,
+            x_1: 0.0f32,
+            y_1: 0.0f32,
+        };
+
+        blocker.set_cutoff(cutoff_hz, sample_rate);
+        blocker
+    }
+
+    // keeps the filter state
+    pub fn set_cutoff(&mut self, cutoff_hz: f32, sample_rate: f32) {
+        self.r = (-TWO_PI * cutoff_hz / sample_rate).exp();
+    }
+
+    pub fn clear(&mut self) {
+        self.x_1 = 0.0f32;
+        self.y_1 = 0.0f32;
+    }
+
+    pub fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_f32(self.x_1);
+        writer.write_f32(self.y_1);
+    }
+
+    pub fn load_state(&mut self, reader: &mut StateReader) -> Option<()> {
+        let x_1 = reader.read_f32()?;
+        let y_1 = reader.read_f32()?;
+
+        self.x_1 = x_1;
+        self.y_1 = y_1;
+
+        Some(())
+    }
+
+    pub fn process(&mut self, x_n: f32) -> f32 {
+        let y_n = x_n - self.x_1 + self.r * self.y_1;
+
+        self.x_1 = x_n;
+        self.y_1 = y_n;
+        y_n
+    }
+}
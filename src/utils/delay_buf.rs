@@ -1,5 +1,6 @@
 use super::mathutils;
 use super::ringbuf::SimpleFloatBuffer;
+use super::state::{StateReader, StateWriter};
 use crate::consts;
 
 pub struct DelayBuffer {
@@ -79,20 +80,80 @@ impl DelayBuffer {
     }
 
     pub fn read_delayed_sample(&self) -> f32 {
+        self.read_at(
+            self.whole_delay_time_samples as usize,
+            self.fract_delay_time_samples,
+        )
+    }
+
+    // reads a tap at any delay without touching the configured one, so several taps can share
+    // one line. delays past the end of the line are clamped
+    pub fn read_delayed_sample_at_ms(&self, delay_time_ms: f32) -> f32 {
+        let max_delay_samples = (self.buf.get_capacity() - 2) as f32;
+        let delay_samples =
+            (delay_time_ms.max(0.0f32) * self.sample_rate / 1000.0f32).min(max_delay_samples);
+        let (ipart, fpart) = mathutils::vmodf(delay_samples);
+
+        self.read_at(ipart as usize, fpart)
+    }
+
+    fn read_at(&self, whole_delay_samples: usize, fract_delay_samples: f32) -> f32 {
         let sample_1 = self
             .buf
-            .read(self.buf.get_limit() - self.whole_delay_time_samples as usize - 1);
+            .read(self.buf.get_limit() - whole_delay_samples - 1);
         let sample_2 = self
             .buf
-            .read(self.buf.get_limit() - self.whole_delay_time_samples as usize - 2);
+            .read(self.buf.get_limit() - whole_delay_samples - 2);
 
-        mathutils::lerp(sample_1, sample_2, self.fract_delay_time_samples)
+        mathutils::lerp(sample_1, sample_2, fract_delay_samples)
     }
 
     pub fn write_sample(&mut self, sample: f32) {
         self.buf.write(sample);
     }
 
+    // copies the most recently written samples (oldest first) into output
+    // returns how many samples were copied, which is less than output.len() if the line is shorter
+    pub fn copy_recent_samples(&self, output: &mut [f32]) -> usize {
+        let limit = self.buf.get_limit();
+        let num_samples = output.len().min(limit);
+
+        for i in 0..num_samples {
+            output[i] = self.buf.read(limit - num_samples + i);
+        }
+
+        num_samples
+    }
+
+    pub fn clear(&mut self) {
+        self.buf.clear();
+    }
+
+    // the delay time is included since gliding effects move it while processing
+    // it's stored in samples too, since some users set the sample count directly
+    pub fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_f32(self.delay_time_ms);
+        writer.write_usize(self.whole_delay_time_samples as usize);
+        writer.write_f32(self.fract_delay_time_samples);
+        self.buf.save_state(writer);
+    }
+
+    pub fn load_state(&mut self, reader: &mut StateReader) -> Option<()> {
+        let delay_time_ms = reader.read_f32()?;
+        let whole_delay_time_samples = reader.read_usize()?;
+        let fract_delay_time_samples = reader.read_f32()?;
+        if whole_delay_time_samples + 1 >= self.buf.get_capacity() {
+            return None;
+        }
+
+        self.buf.load_state(reader)?;
+        self.delay_time_ms = delay_time_ms;
+        self.whole_delay_time_samples = whole_delay_time_samples as i32;
+        self.fract_delay_time_samples = fract_delay_time_samples;
+
+        Some(())
+    }
+
     fn clamp_delay_sample_count(&mut self) {
         if self.whole_delay_time_samples == self.buf.get_capacity() as i32 - 1 {
             self.whole_delay_time_samples = self.buf.get_capacity() as i32 - 2;
@@ -1,15 +1,27 @@
 use super::mathutils;
+use super::state::{StateReader, StateWriter};
+
+use num_derive::FromPrimitive;
 
 use std::cell::Cell;
 
 // TODO: when const fns stabilize, should be replaced with ln(0.368)
-const ANALOG_RC_TIME_CONSTANT: f32 = -0.999672340813206123f32;
+pub const ANALOG_RC_TIME_CONSTANT: f32 = -0.999672340813206123f32;
 
-#[derive(PartialEq)]
+#[derive(Clone, Copy, PartialEq, FromPrimitive)]
+#[allow(non_camel_case_types)]
 pub enum EnvelopeDetectMode {
-    Peak,
+    Peak = 0,
     MeanSquare,
     RootMeanSquare,
+
+    __NUM_DETECT_MODES,
+}
+
+impl Default for EnvelopeDetectMode {
+    fn default() -> Self {
+        EnvelopeDetectMode::Peak
+    }
 }
 
 pub struct EnvelopeDetector {
@@ -20,6 +32,19 @@ pub struct EnvelopeDetector {
     pub should_return_db: bool,
     attack_time_coefficient: f32,
     release_time_coefficient: f32,
+
+    // after the input stops rising, the envelope is held for this many samples before releasing
+    hold_time_samples: usize,
+    hold_samples_remaining: Cell<usize>,
+
+    // auto release: the release slides from release_time_coefficient to the slow one the longer
+    // the envelope has been at or over the threshold (linear, after the square root for RMS)
+    // so short peaks let go quickly and long stretches of gain reduction don't pump
+    pub auto_release: bool,
+    slow_release_time_coefficient: f32,
+    auto_release_threshold: f32,
+    sustain_coefficient: f32,
+    sustain: Cell<f32>,
 }
 
 impl EnvelopeDetector {
@@ -32,9 +57,28 @@ impl EnvelopeDetector {
             should_return_db: true,
             attack_time_coefficient: 0.0f32,
             release_time_coefficient: 0.0f32,
+            hold_time_samples: 0,
+            hold_samples_remaining: Cell::new(0),
+            auto_release: false,
+            slow_release_time_coefficient: 0.0f32,
+            auto_release_threshold: 1.0f32,
+            sustain_coefficient: 0.0f32,
+            sustain: Cell::new(0.0f32),
         }
     }
 
+    pub fn get_sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+
+    pub fn get_attack_time_coefficient(&self) -> f32 {
+        self.attack_time_coefficient
+    }
+
+    pub fn get_release_time_coefficient(&self) -> f32 {
+        self.release_time_coefficient
+    }
+
     pub fn set_attack_time_ms(&mut self, attack_time_ms: f32) {
         if attack_time_ms <= 0.0f32 {
             return;
@@ -55,6 +99,74 @@ impl EnvelopeDetector {
         );
     }
 
+    pub fn set_slow_release_time_ms(&mut self, release_time_ms: f32) {
+        if release_time_ms <= 0.0f32 {
+            return;
+        }
+
+        self.slow_release_time_coefficient = mathutils::vexpf(
+            ANALOG_RC_TIME_CONSTANT / (release_time_ms * self.sample_rate * 0.001f32),
+        );
+    }
+
+    // how long the envelope has to stay over the threshold to get most of the way to the slow
+    // release
+    pub fn set_sustain_time_ms(&mut self, sustain_time_ms: f32) {
+        if sustain_time_ms <= 0.0f32 {
+            return;
+        }
+
+        self.sustain_coefficient = mathutils::vexpf(
+            ANALOG_RC_TIME_CONSTANT / (sustain_time_ms * self.sample_rate * 0.001f32),
+        );
+    }
+
+    pub fn set_auto_release_threshold_db(&mut self, threshold_db: f32) {
+        self.auto_release_threshold = mathutils::db_to_linear(threshold_db);
+    }
+
+    pub fn set_hold_time_ms(&mut self, hold_time_ms: f32) {
+        self.hold_time_samples = (hold_time_ms.max(0.0f32) * self.sample_rate * 0.001f32) as usize;
+        let hold_samples_remaining = self.hold_samples_remaining.get();
+        self.hold_samples_remaining
+            .set(hold_samples_remaining.min(self.hold_time_samples));
+    }
+
+    // back to silence. the attack, release and hold settings stay
+    pub fn clear(&mut self) {
+        self.last_envelope.set(0.0f32);
+        self.hold_samples_remaining.set(0);
+        self.sustain.set(0.0f32);
+    }
+
+    pub fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_f32(self.last_envelope.get());
+        writer.write_usize(self.hold_samples_remaining.get());
+        writer.write_f32(self.sustain.get());
+    }
+
+    pub fn load_state(&mut self, reader: &mut StateReader) -> Option<()> {
+        let last_envelope = reader.read_f32()?;
+        let hold_samples_remaining = reader.read_usize()?;
+        let sustain = reader.read_f32()?;
+
+        self.last_envelope.set(last_envelope);
+        self.hold_samples_remaining.set(hold_samples_remaining);
+        self.sustain.set(sustain);
+
+        Some(())
+    }
+
+    fn release_coefficient(&self) -> f32 {
+        if !self.auto_release {
+            return self.release_time_coefficient;
+        }
+
+        let sustain = self.sustain.get();
+        (1.0f32 - sustain) * self.release_time_coefficient
+            + sustain * self.slow_release_time_coefficient
+    }
+
     pub fn process(&self, x: f32) -> f32 {
         let mut abs_x = x.abs();
 
@@ -65,10 +177,15 @@ impl EnvelopeDetector {
         }
 
         let last_envelope = self.last_envelope.get();
+        let hold_samples_remaining = self.hold_samples_remaining.get();
         let mut current_envelope = if abs_x > last_envelope {
+            self.hold_samples_remaining.set(self.hold_time_samples);
             self.attack_time_coefficient * (last_envelope - abs_x) + abs_x
+        } else if hold_samples_remaining > 0 {
+            self.hold_samples_remaining.set(hold_samples_remaining - 1);
+            last_envelope
         } else {
-            self.release_time_coefficient * (last_envelope - abs_x) + abs_x
+            self.release_coefficient() * (last_envelope - abs_x) + abs_x
         };
 
         if self.should_clamp {
@@ -83,6 +200,17 @@ impl EnvelopeDetector {
             current_envelope = mathutils::vsqrtf(current_envelope);
         }
 
+        if self.auto_release {
+            let over = if current_envelope >= self.auto_release_threshold {
+                1.0f32
+            } else {
+                0.0f32
+            };
+            let sustain = self.sustain.get();
+            self.sustain
+                .set(self.sustain_coefficient * (sustain - over) + over);
+        }
+
         return if self.should_return_db {
             20.0f32 * current_envelope.log10()
         } else {
@@ -26,7 +26,7 @@ impl WyHashPRNG {
     pub fn next(&self) -> u64 {
         let mut state = self.state.get();
 
-        state += 0x60bee2bee120fc15;
+        state = state.wrapping_add(0x60bee2bee120fc15);
 
         self.state.set(state);
         let mut tmp: u128 = state as u128;
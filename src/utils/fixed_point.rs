@@ -0,0 +1,190 @@
+#![allow(dead_code)]
+
+// integer-only versions of the core building blocks for targets without an fpu
+// enabled with the fixed_point feature. samples are Q31 (Q15 for 16 bit i/o)
+// floats only show up when setting things up (coefficients, delay times), never per sample
+// Q31 can't go over full scale, so anything that boosts needs headroom left in the input
+
+use super::biquad::BiquadCoefficients;
+
+pub type Q31 = i32;
+pub type Q15 = i16;
+
+const Q31_SCALE: f32 = 2147483648.0f32;
+const Q15_SCALE: f32 = 32768.0f32;
+
+// biquad feedback coefficients fit in +-4, the shelf mix (c0 / d0) can reach ~10 at +20 dB
+const FILTER_COEFF_FRAC_BITS: u32 = 29;
+const MIX_COEFF_FRAC_BITS: u32 = 27;
+
+fn f32_to_fixed(x: f32, frac_bits: u32) -> i32 {
+    let scaled = (x as f64 * (1u64 << frac_bits) as f64).round();
+    scaled.max(i32::MIN as f64).min(i32::MAX as f64) as i32
+}
+
+fn saturate_to_i32(x: i64) -> i32 {
+    x.max(i32::MIN as i64).min(i32::MAX as i64) as i32
+}
+
+// rounds off the low frac_bits of a wide accumulator
+fn round_shift(acc: i64, frac_bits: u32) -> i32 {
+    saturate_to_i32(acc.saturating_add(1i64 << (frac_bits - 1)) >> frac_bits)
+}
+
+pub fn f32_to_q31(x: f32) -> Q31 {
+    f32_to_fixed(x, 31)
+}
+
+pub fn q31_to_f32(x: Q31) -> f32 {
+    x as f32 / Q31_SCALE
+}
+
+pub fn f32_to_q15(x: f32) -> Q15 {
+    (x * Q15_SCALE)
+        .round()
+        .max(i16::MIN as f32)
+        .min(i16::MAX as f32) as i16
+}
+
+pub fn q15_to_f32(x: Q15) -> f32 {
+    x as f32 / Q15_SCALE
+}
+
+pub fn q31_to_q15(x: Q31) -> Q15 {
+    (round_shift(x as i64, 16) as i32)
+        .max(i16::MIN as i32)
+        .min(i16::MAX as i32) as i16
+}
+
+pub fn q15_to_q31(x: Q15) -> Q31 {
+    (x as i32) << 16
+}
+
+pub fn q31_mul(a: Q31, b: Q31) -> Q31 {
+    round_shift(a as i64 * b as i64, 31)
+}
+
+// a * gain_a + b * gain_b, saturating
+pub fn q31_mix(a: Q31, gain_a: Q31, b: Q31, gain_b: Q31) -> Q31 {
+    round_shift(a as i64 * gain_a as i64 + b as i64 * gain_b as i64, 31)
+}
+
+// same structure as Biquad::filter. the accumulator is 64 bit so only the output rounds
+pub struct FixedBiquad {
+    // [a0, a1, a2, b1, b2] in Q2.29
+    filter_coeffs: [i32; 5],
+    // [c0, d0] in Q4.27
+    mix_coeffs: [i32; 2],
+
+    // [z^-1, z^-2]
+    x: [Q31; 2],
+    y: [Q31; 2],
+}
+
+impl FixedBiquad {
+    pub fn new(coeff: &BiquadCoefficients) -> FixedBiquad {
+        let mut biquad = FixedBiquad {
+            filter_coeffs: [0; 5],
+            mix_coeffs: [0; 2],
+            x: [0; 2],
+            y: [0; 2],
+        };
+
+        biquad.change_params(coeff);
+        biquad
+    }
+
+    // keeps the filter state, like Biquad::change_params
+    pub fn change_params(&mut self, coeff: &BiquadCoefficients) {
+        let [a0, a1, a2, b1, b2, c0, d0] = coeff.raw_coefficients();
+
+        self.filter_coeffs = [
+            f32_to_fixed(a0, FILTER_COEFF_FRAC_BITS),
+            f32_to_fixed(a1, FILTER_COEFF_FRAC_BITS),
+            f32_to_fixed(a2, FILTER_COEFF_FRAC_BITS),
+            f32_to_fixed(b1, FILTER_COEFF_FRAC_BITS),
+            f32_to_fixed(b2, FILTER_COEFF_FRAC_BITS),
+        ];
+        self.mix_coeffs = [
+            f32_to_fixed(c0, MIX_COEFF_FRAC_BITS),
+            f32_to_fixed(d0, MIX_COEFF_FRAC_BITS),
+        ];
+    }
+
+    pub fn filter(&mut self, input: Q31) -> Q31 {
+        let [a0, a1, a2, b1, b2] = self.filter_coeffs;
+
+        let acc = (a0 as i64 * input as i64)
+            .saturating_add(a1 as i64 * self.x[0] as i64)
+            .saturating_add(a2 as i64 * self.x[1] as i64)
+            .saturating_sub(b1 as i64 * self.y[0] as i64)
+            .saturating_sub(b2 as i64 * self.y[1] as i64);
+        let filtered = round_shift(acc, FILTER_COEFF_FRAC_BITS);
+
+        self.x = [input, self.x[0]];
+        self.y = [filtered, self.y[0]];
+
+        let [c0, d0] = self.mix_coeffs;
+        round_shift(
+            (c0 as i64 * filtered as i64).saturating_add(d0 as i64 * input as i64),
+            MIX_COEFF_FRAC_BITS,
+        )
+    }
+}
+
+// DelayBuffer with Q31 samples. the fractional part of the delay is Q31 too
+pub struct FixedDelayBuffer {
+    buf: Vec<Q31>,
+    write_idx: usize,
+    sample_rate: f32,
+    whole_delay_samples: usize,
+    fract_delay: Q31,
+}
+
+impl FixedDelayBuffer {
+    pub fn with_sample_rate_and_max_delay(sample_rate: f32, max_delay_ms: f32) -> FixedDelayBuffer {
+        let capacity = ((sample_rate * max_delay_ms / 1000.0f32) as usize).max(2);
+
+        FixedDelayBuffer {
+            buf: vec![0; capacity],
+            write_idx: 0,
+            sample_rate,
+            whole_delay_samples: 0,
+            fract_delay: 0,
+        }
+    }
+
+    // clamped to what the buffer can hold
+    pub fn set_delay_time_ms(&mut self, delay_time_ms: f32) {
+        let max_delay = (self.buf.len() - 2) as f32;
+        let delay_samples =
+            (delay_time_ms.max(0.0f32) * self.sample_rate / 1000.0f32).min(max_delay);
+
+        self.whole_delay_samples = delay_samples.trunc() as usize;
+        self.fract_delay = f32_to_q31(delay_samples.fract());
+    }
+
+    pub fn read_delayed_sample(&self) -> Q31 {
+        let len = self.buf.len();
+        let idx_1 = (self.write_idx + len - self.whole_delay_samples - 1) % len;
+        let idx_2 = (idx_1 + len - 1) % len;
+
+        let sample_1 = self.buf[idx_1];
+        let sample_2 = self.buf[idx_2];
+
+        // lerp in 64 bit so the difference can't overflow
+        let diff = sample_2 as i64 - sample_1 as i64;
+        saturate_to_i32(sample_1 as i64 + ((diff * self.fract_delay as i64) >> 31))
+    }
+
+    pub fn write_sample(&mut self, sample: Q31) {
+        self.buf[self.write_idx] = sample;
+        self.write_idx = (self.write_idx + 1) % self.buf.len();
+    }
+
+    pub fn clear(&mut self) {
+        for sample in self.buf.iter_mut() {
+            *sample = 0;
+        }
+    }
+}
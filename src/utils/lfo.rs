@@ -1,3 +1,5 @@
+use super::state::{StateReader, StateWriter};
+
 use num_derive::FromPrimitive;
 
 #[derive(FromPrimitive)]
@@ -13,6 +15,28 @@ impl Default for LFOWaveForm {
     }
 }
 
+// bends the waveform's output between its center and its peaks
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, PartialEq, FromPrimitive)]
+pub enum LFOCurve {
+    Linear = 0,
+    // like an rc charging: leaves the center quickly and slows down towards the peaks
+    Exponential,
+    // the opposite: lingers around the center and rushes through the peaks
+    Logarithmic,
+
+    __NUM_LFO_CURVES,
+}
+
+impl Default for LFOCurve {
+    fn default() -> Self {
+        LFOCurve::Linear
+    }
+}
+
+// how far the curves bend. the output still hits 0 and +-1 at the same points
+const CURVE_STEEPNESS: f32 = 4.0f32;
+
 pub struct LowFrequencyOscillator {
     modulo_counter: f32,
     modulo_inc: f32,
@@ -20,6 +44,7 @@ pub struct LowFrequencyOscillator {
     oscillation_freq: f32,
     sample_rate: f32,
     waveform: LFOWaveForm,
+    curve: LFOCurve,
 }
 
 impl LowFrequencyOscillator {
@@ -34,6 +59,7 @@ impl LowFrequencyOscillator {
             oscillation_freq,
             sample_rate,
             waveform,
+            curve: LFOCurve::Linear,
         }
     }
 
@@ -48,6 +74,21 @@ impl LowFrequencyOscillator {
         self.modulo_counter = 0.0f32;
     }
 
+    pub fn set_curve(&mut self, curve: LFOCurve) {
+        self.curve = curve;
+    }
+
+    // only the phase. rate, waveform and curve come from parameters
+    pub fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_f32(self.modulo_counter);
+    }
+
+    pub fn load_state(&mut self, reader: &mut StateReader) -> Option<()> {
+        self.modulo_counter = reader.read_f32()?;
+
+        Some(())
+    }
+
     pub fn oscillate(&mut self) {
         self.modulo_counter += self.modulo_inc;
         if self.modulo_counter >= 1.0f32 {
@@ -56,11 +97,13 @@ impl LowFrequencyOscillator {
     }
 
     pub fn current_sample(&mut self) -> f32 {
-        match self.waveform {
+        let y = match self.waveform {
             LFOWaveForm::Triangle => triangle_wave(self.modulo_counter),
             LFOWaveForm::Sawtooth => sawtooth_wave(self.modulo_counter),
             LFOWaveForm::Sine => sine_wave(self.modulo_counter),
-        }
+        };
+
+        apply_curve(y, self.curve)
     }
 }
 
@@ -69,6 +112,22 @@ pub fn bipolar_to_unipolar(v: f32) -> f32 {
     (v + 1.0f32) / 2.0f32
 }
 
+// shapes the magnitude of a bipolar value, keeping its sign
+fn apply_curve(y: f32, curve: LFOCurve) -> f32 {
+    let magnitude = y.abs();
+    let shaped = match curve {
+        LFOCurve::Exponential => {
+            (1.0f32 - (-CURVE_STEEPNESS * magnitude).exp()) / (1.0f32 - (-CURVE_STEEPNESS).exp())
+        }
+        LFOCurve::Logarithmic => {
+            ((CURVE_STEEPNESS * magnitude).exp() - 1.0f32) / (CURVE_STEEPNESS.exp() - 1.0f32)
+        }
+        _ => return y,
+    };
+
+    shaped.copysign(y)
+}
+
 const B: f32 = 4.0f32 / std::f32::consts::PI;
 const C: f32 = -4.0f32 / (std::f32::consts::PI * std::f32::consts::PI);
 const P: f32 = 0.225f32;
@@ -0,0 +1,69 @@
+use super::biquad::{Biquad, BiquadCoefficients};
+use super::state::{StateReader, StateWriter};
+
+// a 4th order linkwitz-riley band split: two cascaded butterworths per band.
+// the bands are -6 dB at the crossover and sum back to an all pass (flat magnitude)
+pub struct LinkwitzRileyCrossover {
+    low: [Biquad; 2],
+    high: [Biquad; 2],
+
+    crossover_freq: f32,
+    sample_rate: f32,
+}
+
+impl LinkwitzRileyCrossover {
+    pub fn new(crossover_freq: f32, sample_rate: f32) -> LinkwitzRileyCrossover {
+        let low = BiquadCoefficients::linkwitz_riley_lpf(crossover_freq, sample_rate);
+        let high = BiquadCoefficients::linkwitz_riley_hpf(crossover_freq, sample_rate);
+
+        LinkwitzRileyCrossover {
+            low: [Biquad::new(low.clone()), Biquad::new(low)],
+            high: [Biquad::new(high.clone()), Biquad::new(high)],
+            crossover_freq,
+            sample_rate,
+        }
+    }
+
+    // keeps the filter state
+    pub fn change_crossover_freq(&mut self, crossover_freq: f32) {
+        self.crossover_freq = crossover_freq;
+
+        for biquad in self.low.iter_mut().chain(self.high.iter_mut()) {
+            biquad.change_cutoff(crossover_freq);
+        }
+    }
+
+    pub fn change_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+
+        for biquad in self.low.iter_mut().chain(self.high.iter_mut()) {
+            biquad.change_sample_rate(sample_rate);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        *self = LinkwitzRileyCrossover::new(self.crossover_freq, self.sample_rate);
+    }
+
+    pub fn save_state(&self, writer: &mut StateWriter) {
+        for biquad in self.low.iter().chain(self.high.iter()) {
+            biquad.save_state(writer);
+        }
+    }
+
+    pub fn load_state(&mut self, reader: &mut StateReader) -> Option<()> {
+        for biquad in self.low.iter_mut().chain(self.high.iter_mut()) {
+            biquad.load_state(reader)?;
+        }
+
+        Some(())
+    }
+
+    // (low, high)
+    pub fn split(&mut self, x_n: f32) -> (f32, f32) {
+        let low = self.low[0].filter(x_n);
+        let high = self.high[0].filter(x_n);
+
+        (self.low[1].filter(low), self.high[1].filter(high))
+    }
+}
@@ -3,6 +3,7 @@
 #![allow(non_snake_case)]
 #![allow(dead_code)]
 
+use num_derive::FromPrimitive;
 use std::ops::BitAnd;
 
 #[cfg(target_arch = "arm")]
@@ -37,6 +38,140 @@ pub fn db_to_linear(db: f32) -> f32 {
     10.0f32.powf(db / 20.0f32)
 }
 
+// named by how loud a centered signal ends up in each channel
+#[derive(Clone, Copy, PartialEq, Debug, FromPrimitive)]
+pub enum PanLaw {
+    // 0 dB. a balance control: the far side fades out, the near side stays at unity
+    Linear = 0,
+    // -3 dB. sin/cos, the summed power stays constant across the field
+    ConstantPower,
+    // -4.5 dB. geometric mean of constant power and linear taper
+    Compromise,
+    // -6 dB. gains sum to 1, so a mono fold-down stays at unity
+    LinearTaper,
+
+    __NUM_PAN_LAWS,
+}
+
+impl Default for PanLaw {
+    fn default() -> Self {
+        PanLaw::ConstantPower
+    }
+}
+
+// position is [-1 (hard left), 1 (hard right)]. returns (left gain, right gain)
+pub fn pan_gains(position: f32, law: PanLaw) -> (f32, f32) {
+    let p = num::clamp(position, -1.0f32, 1.0f32);
+    let theta = (p + 1.0f32) * std::f32::consts::FRAC_PI_4;
+
+    match law {
+        PanLaw::Linear => ((1.0f32 - p).min(1.0f32), (1.0f32 + p).min(1.0f32)),
+        PanLaw::ConstantPower => (theta.cos(), theta.sin()),
+        PanLaw::Compromise => (
+            (0.5f32 * (1.0f32 - p) * theta.cos()).sqrt(),
+            (0.5f32 * (1.0f32 + p) * theta.sin()).sqrt(),
+        ),
+        _ => (0.5f32 * (1.0f32 - p), 0.5f32 * (1.0f32 + p)),
+    }
+}
+
+// mid = (L + R) / 2, side = (L - R) / 2. decoding is exact: L = mid + side, R = mid - side
+pub fn mid_side_encode(left: f32, right: f32) -> (f32, f32) {
+    (0.5f32 * (left + right), 0.5f32 * (left - right))
+}
+
+pub fn mid_side_decode(mid: f32, side: f32) -> (f32, f32) {
+    (mid + side, mid - side)
+}
+
+// what a wet/dry effect lets through. for monitoring, the wet_dry_pct setting is left alone
+#[derive(Clone, Copy, PartialEq, Debug, FromPrimitive)]
+pub enum MonitorMode {
+    // the wet_dry_pct blend
+    Full = 0,
+    // just the effect, at full level
+    WetOnly,
+    // just the input, as if the effect weren't there
+    DryOnly,
+
+    __NUM_MONITOR_MODES,
+}
+
+impl Default for MonitorMode {
+    fn default() -> Self {
+        MonitorMode::Full
+    }
+}
+
+// returns (dry gain, wet gain)
+pub fn wet_dry_gains(wet_dry_pct: f32, mode: MonitorMode) -> (f32, f32) {
+    match mode {
+        MonitorMode::WetOnly => (0.0f32, 1.0f32),
+        MonitorMode::DryOnly => (1.0f32, 0.0f32),
+        _ => (1.0f32 - wet_dry_pct, wet_dry_pct),
+    }
+}
+
+// note lengths a tempo synced time can be set to. Free = not synced
+#[derive(Clone, Copy, PartialEq, Debug, FromPrimitive)]
+pub enum NoteDivision {
+    Free = 0,
+    Whole,
+    Half,
+    Quarter,
+    Eighth,
+    Sixteenth,
+    ThirtySecond,
+    DottedHalf,
+    DottedQuarter,
+    DottedEighth,
+    DottedSixteenth,
+    HalfTriplet,
+    QuarterTriplet,
+    EighthTriplet,
+    SixteenthTriplet,
+
+    __NUM_NOTE_DIVISIONS,
+}
+
+impl Default for NoteDivision {
+    fn default() -> Self {
+        NoteDivision::Free
+    }
+}
+
+impl NoteDivision {
+    // length in quarter notes (beats)
+    pub fn beats(&self) -> Option<f32> {
+        match *self {
+            NoteDivision::Whole => Some(4.0f32),
+            NoteDivision::Half => Some(2.0f32),
+            NoteDivision::Quarter => Some(1.0f32),
+            NoteDivision::Eighth => Some(0.5f32),
+            NoteDivision::Sixteenth => Some(0.25f32),
+            NoteDivision::ThirtySecond => Some(0.125f32),
+            NoteDivision::DottedHalf => Some(3.0f32),
+            NoteDivision::DottedQuarter => Some(1.5f32),
+            NoteDivision::DottedEighth => Some(0.75f32),
+            NoteDivision::DottedSixteenth => Some(0.375f32),
+            NoteDivision::HalfTriplet => Some(4.0f32 / 3.0f32),
+            NoteDivision::QuarterTriplet => Some(2.0f32 / 3.0f32),
+            NoteDivision::EighthTriplet => Some(1.0f32 / 3.0f32),
+            NoteDivision::SixteenthTriplet => Some(1.0f32 / 6.0f32),
+            _ => None,
+        }
+    }
+}
+
+// None for NoteDivision::Free (or a tempo that isn't positive)
+pub fn note_division_to_ms(bpm: f32, division: NoteDivision) -> Option<f32> {
+    if bpm <= 0.0f32 {
+        return None;
+    }
+
+    division.beats().map(|beats| beats * 60000.0f32 / bpm)
+}
+
 // wow this is both really ugly and kinda nice at the same time somehow
 pub fn is_power_of_2<S, T>(v: T) -> bool
 where
@@ -1,11 +1,18 @@
 pub mod async_utils;
 pub mod biquad;
 pub mod buf_rw;
+pub mod dc_blocker;
 pub mod delay_buf;
 pub mod envelope;
 pub mod fast_rand;
+#[cfg(feature = "fixed_point")]
+pub mod fixed_point;
 pub mod lfo;
+pub mod linkwitz_riley;
 pub mod mathutils;
+pub mod peak_window;
 pub mod ringbuf;
+pub mod smoothed_param;
+pub mod state;
 
 pub const TWO_PI: f32 = 2.0f32 * std::f32::consts::PI;
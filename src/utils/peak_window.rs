@@ -0,0 +1,94 @@
+use super::state::{StateReader, StateWriter};
+
+// the loudest |x| over the last `length` samples
+// keeps a queue of (sample number, |x|) candidates that only get quieter front to back, so the
+// front is always the peak. a new sample knocks out every quieter candidate behind it, since
+// those leave the window first and can never be the peak again
+pub struct PeakWindow {
+    // ring, room for max_length + 1 since a sample goes in before the oldest one falls out
+    candidates: Vec<(usize, f32)>,
+    head: usize,
+    num_candidates: usize,
+    length: usize,
+    sample_count: usize,
+}
+
+impl PeakWindow {
+    pub fn new(max_length: usize) -> PeakWindow {
+        PeakWindow {
+            candidates: vec![(0, 0.0f32); max_length.max(1) + 1],
+            head: 0,
+            num_candidates: 0,
+            length: 1,
+            sample_count: 0,
+        }
+    }
+
+    // clamped to [1, max_length]. a shorter window drops the older candidates on the next sample
+    pub fn set_length(&mut self, length: usize) {
+        self.length = num::clamp(length, 1, self.candidates.len() - 1);
+    }
+
+    pub fn clear(&mut self) {
+        self.head = 0;
+        self.num_candidates = 0;
+        self.sample_count = 0;
+    }
+
+    pub fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_usize(self.sample_count);
+        writer.write_usize(self.num_candidates);
+        for i in 0..self.num_candidates {
+            let (sample_number, level) = self.candidates[self.candidate_idx(i)];
+            writer.write_usize(sample_number);
+            writer.write_f32(level);
+        }
+    }
+
+    pub fn load_state(&mut self, reader: &mut StateReader) -> Option<()> {
+        let sample_count = reader.read_usize()?;
+        let num_candidates = reader.read_usize()?;
+        if num_candidates >= self.candidates.len() {
+            return None;
+        }
+
+        let mut candidates = Vec::with_capacity(num_candidates);
+        for _ in 0..num_candidates {
+            candidates.push((reader.read_usize()?, reader.read_f32()?));
+        }
+
+        self.candidates[..num_candidates].copy_from_slice(&candidates);
+        self.head = 0;
+        self.num_candidates = num_candidates;
+        self.sample_count = sample_count;
+
+        Some(())
+    }
+
+    pub fn process(&mut self, x: f32) -> f32 {
+        let level = x.abs();
+
+        while self.num_candidates > 0
+            && self.candidates[self.candidate_idx(self.num_candidates - 1)].1 <= level
+        {
+            self.num_candidates -= 1;
+        }
+
+        let back = self.candidate_idx(self.num_candidates);
+        self.candidates[back] = (self.sample_count, level);
+        self.num_candidates += 1;
+
+        while self.sample_count.wrapping_sub(self.candidates[self.head].0) >= self.length {
+            self.head = self.candidate_idx(1);
+            self.num_candidates -= 1;
+        }
+
+        self.sample_count = self.sample_count.wrapping_add(1);
+
+        self.candidates[self.head].1
+    }
+
+    fn candidate_idx(&self, i: usize) -> usize {
+        (self.head + i) % self.candidates.len()
+    }
+}
@@ -1,4 +1,5 @@
 use super::mathutils::is_power_of_2;
+use super::state::{StateReader, StateWriter};
 
 use std::cmp;
 
@@ -49,6 +50,15 @@ impl FFTCollectionBuffer {
         Some(buf)
     }
 
+    pub fn clear(&mut self) {
+        for i in 0..self.data.len() {
+            self.data[i] = 0_f32;
+        }
+
+        self.read_idx = 0;
+        self.write_idx = 0;
+    }
+
     pub fn get_read_idx(&self) -> usize {
         self.read_idx
     }
@@ -104,6 +114,11 @@ impl FFTCollectionBuffer {
         self.advance_write_idx();
     }
 
+    // what was written count samples before whatever goes in at the write idx next
+    pub fn get_behind_write_idx(&self, count: usize) -> f32 {
+        self.data[self.write_idx.wrapping_sub(count) & self.index_wrap_mask]
+    }
+
     pub fn get_at_idx(&self, idx: usize) -> f32 {
         self.data[idx]
     }
@@ -155,6 +170,12 @@ impl SimpleFloatBuffer {
         self.write_idx = (self.write_idx + 1) % self.limit;
     }
 
+    pub fn fill(&mut self, value: f32) {
+        for i in 0..self.capacity {
+            self.data[i] = value;
+        }
+    }
+
     pub fn clear(&mut self) {
         for i in 0..self.capacity {
             self.data[i] = 0.0f32;
@@ -167,6 +188,49 @@ impl SimpleFloatBuffer {
         // TODO
         self.data[(self.write_idx + idx) % self.limit]
     }
+
+    // the samples read would give for 0..num_samples, as long as they don't wrap round the end
+    pub fn read_slice(&self, num_samples: usize) -> Option<&[f32]> {
+        let end_idx = self.write_idx + num_samples;
+        if end_idx > self.limit {
+            return None;
+        }
+
+        Some(&self.data[self.write_idx..end_idx])
+    }
+
+    // mixes dry_value into a sample that was already written this block
+    // idx is relative to the first of the last num_written samples
+    pub fn blend_written(
+        &mut self,
+        idx: usize,
+        num_written: usize,
+        wet_amount: f32,
+        dry_value: f32,
+    ) {
+        let start_idx = (self.write_idx + self.limit - num_written % self.limit) % self.limit;
+        let data_idx = (start_idx + idx) % self.limit;
+
+        self.data[data_idx] = wet_amount * self.data[data_idx] + (1.0f32 - wet_amount) * dry_value;
+    }
+
+    pub fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_usize(self.write_idx);
+        writer.write_f32_slice(&self.data);
+    }
+
+    // the capacity has to match, so the sample rate (and max delay) has to be the same
+    pub fn load_state(&mut self, reader: &mut StateReader) -> Option<()> {
+        let write_idx = reader.read_usize()?;
+        if write_idx >= self.limit {
+            return None;
+        }
+
+        reader.read_f32_slice_into(&mut self.data)?;
+        self.write_idx = write_idx;
+
+        Some(())
+    }
 }
 
 impl TinyFloatBuffer {
@@ -189,4 +253,19 @@ impl TinyFloatBuffer {
         self.x[self.next_idx] = v;
         self.next_idx = (self.next_idx + 1) % 2;
     }
+
+    pub fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_f32(self.z1());
+        writer.write_f32(self.z2());
+    }
+
+    pub fn load_state(&mut self, reader: &mut StateReader) -> Option<()> {
+        let z1 = reader.read_f32()?;
+        let z2 = reader.read_f32()?;
+
+        self.write(z2);
+        self.write(z1);
+
+        Some(())
+    }
 }
@@ -0,0 +1,168 @@
+use crate::utils::envelope::ANALOG_RC_TIME_CONSTANT;
+use crate::utils::mathutils;
+
+// how a SmoothedParam moves from its current value to a new target
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum RampCurve {
+    // hold the old value, then jump to the target on the last sample of the ramp
+    Step,
+    Linear,
+    // constant ratio per sample. falls back to linear if the ramp would cross or touch 0
+    Exponential,
+}
+
+// a value that ramps to its target over a fixed number of samples instead of jumping
+// used to take the zipper noise out of per-block parameter changes
+pub struct SmoothedParam {
+    current: f32,
+    target: f32,
+    curve: RampCurve,
+
+    // added for linear ramps, multiplied for exponential ones
+    increment: f32,
+    samples_remaining: usize,
+}
+
+impl SmoothedParam {
+    pub fn new(initial_value: f32) -> SmoothedParam {
+        SmoothedParam {
+            current: initial_value,
+            target: initial_value,
+            curve: RampCurve::Linear,
+            increment: 0.0f32,
+            samples_remaining: 0,
+        }
+    }
+
+    pub fn is_ramping(&self) -> bool {
+        self.samples_remaining > 0
+    }
+
+    pub fn set_immediate(&mut self, value: f32) {
+        self.current = value;
+        self.target = value;
+        self.samples_remaining = 0;
+    }
+
+    // the target is reached on the num_samples'th call to next()
+    pub fn ramp_to(&mut self, target: f32, num_samples: usize, curve: RampCurve) {
+        if num_samples == 0 {
+            self.set_immediate(target);
+            return;
+        }
+
+        self.target = target;
+        self.samples_remaining = num_samples;
+
+        let can_be_exponential = self.current * target > 0.0f32;
+        self.curve = if curve == RampCurve::Exponential && !can_be_exponential {
+            RampCurve::Linear
+        } else {
+            curve
+        };
+
+        self.increment = match self.curve {
+            RampCurve::Step => 0.0f32,
+            RampCurve::Linear => (target - self.current) / num_samples as f32,
+            RampCurve::Exponential => (target / self.current).powf(1.0f32 / num_samples as f32),
+        };
+    }
+
+    pub fn next(&mut self) -> f32 {
+        if self.samples_remaining == 0 {
+            return self.current;
+        }
+
+        self.samples_remaining -= 1;
+        if self.samples_remaining == 0 {
+            // land exactly on the target, whatever rounding happened on the way
+            self.current = self.target;
+            return self.current;
+        }
+
+        match self.curve {
+            RampCurve::Step => (),
+            RampCurve::Linear => self.current += self.increment,
+            RampCurve::Exponential => self.current *= self.increment,
+        }
+
+        self.current
+    }
+}
+
+// once a ParamSmoother is this close to its target, it snaps to it
+const SMOOTHER_SETTLE_THRESHOLD: f32 = 1e-4f32;
+
+// a one pole lowpass on a parameter, for values that get set at any time rather than ramped
+// each next() closes the same fraction of the gap, so after one time constant it's ~63% there
+#[derive(Clone, Copy)]
+pub struct ParamSmoother {
+    current: f32,
+    target: f32,
+
+    // 0 = no smoothing
+    coefficient: f32,
+}
+
+impl ParamSmoother {
+    pub fn new(initial_value: f32, time_constant_ms: f32, sample_rate: f32) -> ParamSmoother {
+        let mut smoother = ParamSmoother {
+            current: initial_value,
+            target: initial_value,
+            coefficient: 0.0f32,
+        };
+        smoother.set_time_constant(time_constant_ms, sample_rate);
+
+        smoother
+    }
+
+    // 0 ms jumps straight to every new target
+    pub fn set_time_constant(&mut self, time_constant_ms: f32, sample_rate: f32) {
+        self.coefficient = if time_constant_ms <= 0.0f32 {
+            0.0f32
+        } else {
+            mathutils::vexpf(ANALOG_RC_TIME_CONSTANT / (time_constant_ms * sample_rate * 0.001f32))
+        };
+    }
+
+    pub fn is_instant(&self) -> bool {
+        self.coefficient == 0.0f32
+    }
+
+    pub fn is_smoothing(&self) -> bool {
+        self.current != self.target
+    }
+
+    pub fn current(&self) -> f32 {
+        self.current
+    }
+
+    pub fn target(&self) -> f32 {
+        self.target
+    }
+
+    pub fn set_target(&mut self, target: f32) {
+        self.target = target;
+        if self.is_instant() {
+            self.current = target;
+        }
+    }
+
+    pub fn set_immediate(&mut self, value: f32) {
+        self.current = value;
+        self.target = value;
+    }
+
+    pub fn next(&mut self) -> f32 {
+        if !self.is_smoothing() {
+            return self.current;
+        }
+
+        self.current = self.coefficient * (self.current - self.target) + self.target;
+        if (self.current - self.target).abs() < SMOOTHER_SETTLE_THRESHOLD {
+            self.current = self.target;
+        }
+
+        self.current
+    }
+}
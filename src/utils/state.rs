@@ -0,0 +1,113 @@
+// flat little endian encoding for effect state snapshots
+// there's no versioning: a snapshot is only meant to be restored into the board (and audio
+// config) it was taken from, so readers just check that lengths line up
+pub struct StateWriter {
+    bytes: Vec<u8>,
+}
+
+pub struct StateReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl StateWriter {
+    pub fn new() -> StateWriter {
+        StateWriter { bytes: Vec::new() }
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    pub fn write_f32(&mut self, value: f32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn write_f64(&mut self, value: f64) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn write_usize(&mut self, value: usize) {
+        self.write_u64(value as u64);
+    }
+
+    pub fn write_u64(&mut self, value: u64) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn write_bool(&mut self, value: bool) {
+        self.bytes.push(value as u8);
+    }
+
+    // length prefixed, so the reader can tell when the buffer sizes don't match
+    pub fn write_f32_slice(&mut self, values: &[f32]) {
+        self.write_usize(values.len());
+        for v in values {
+            self.write_f32(*v);
+        }
+    }
+}
+
+impl<'a> StateReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> StateReader<'a> {
+        StateReader { bytes, pos: 0 }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pos == self.bytes.len()
+    }
+
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.bytes.len() - self.pos < len {
+            return None;
+        }
+
+        let taken = &self.bytes[self.pos..self.pos + len];
+        self.pos += len;
+
+        Some(taken)
+    }
+
+    pub fn read_f32(&mut self) -> Option<f32> {
+        let mut raw = [0u8; 4];
+        raw.copy_from_slice(self.take(4)?);
+
+        Some(f32::from_le_bytes(raw))
+    }
+
+    pub fn read_f64(&mut self) -> Option<f64> {
+        let mut raw = [0u8; 8];
+        raw.copy_from_slice(self.take(8)?);
+
+        Some(f64::from_le_bytes(raw))
+    }
+
+    pub fn read_usize(&mut self) -> Option<usize> {
+        Some(self.read_u64()? as usize)
+    }
+
+    pub fn read_u64(&mut self) -> Option<u64> {
+        let mut raw = [0u8; 8];
+        raw.copy_from_slice(self.take(8)?);
+
+        Some(u64::from_le_bytes(raw))
+    }
+
+    pub fn read_bool(&mut self) -> Option<bool> {
+        Some(self.take(1)?[0] != 0)
+    }
+
+    // fails (without touching output) unless the stored slice is exactly output.len() long
+    pub fn read_f32_slice_into(&mut self, output: &mut [f32]) -> Option<()> {
+        if self.read_usize()? != output.len() {
+            return None;
+        }
+
+        let raw = self.take(4 * output.len())?;
+        for (v, chunk) in output.iter_mut().zip(raw.chunks_exact(4)) {
+            *v = f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        }
+
+        Some(())
+    }
+}